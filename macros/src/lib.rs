@@ -0,0 +1,368 @@
+//! Procedural macros for defining functions in `type_rust`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    Attribute, Error, FnArg, GenericArgument, ItemFn, LitStr, PathArguments, ReturnType, Type,
+    parse_macro_input,
+};
+
+/// Defines a vectorized function from a scalar function.
+///
+/// ```ignore
+/// #[function("substr(varchar, int32, int32) -> varchar")]
+/// fn substr(s: &str, start: i32, count: i32) -> Result<String> {
+///     ...
+/// }
+/// ```
+///
+/// The scalar function takes the reference type of each argument (e.g. `&str` for `varchar`),
+/// and returns the owned type of the return value (e.g. `String` for `varchar`). It may also
+/// return an `Option` to produce `NULL`, or a `Result` to report an error for the whole batch.
+/// If any argument is `NULL`, the function is not called and `NULL` is produced, unless the
+/// argument is taken as an `Option`.
+///
+/// A function returning a string may instead take a writer as its last parameter, and write the
+/// result into it piece by piece. The result is then written directly into the output array
+/// without allocating a `String` for each row. Such a function returns `()`, `Option<()>`,
+/// `Result<()>` or `Result<Option<()>>`.
+///
+/// ```ignore
+/// #[function("upper(varchar) -> varchar")]
+/// fn upper(s: &str, writer: &mut StringWriter) {
+///     ...
+/// }
+/// ```
+///
+/// Besides the function itself, this macro generates a `register_<name>` function, which
+/// registers a vectorized version of the function with every signature into a
+/// `FunctionRegistry`. A function may have several `#[function]` attributes, e.g. a generic
+/// function may be registered with one signature for each type.
+#[proc_macro_attribute]
+pub fn function(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let signature = parse_macro_input!(attr as LitStr);
+    let mut item = parse_macro_input!(item as ItemFn);
+    match generate(signature, &mut item) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Whether `attr` is another `#[function]` attribute.
+fn is_function_attr(attr: &Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "function")
+}
+
+fn generate(signature: LitStr, item: &mut ItemFn) -> syn::Result<TokenStream2> {
+    // Stacked `#[function]` attributes are all consumed by the outermost one, so that we only
+    // generate one registration function.
+    let mut signatures = vec![signature];
+    let mut attrs = vec![];
+    for attr in item.attrs.drain(..) {
+        if is_function_attr(&attr) {
+            signatures.push(attr.parse_args()?);
+        } else {
+            attrs.push(attr);
+        }
+    }
+    item.attrs = attrs;
+
+    let registrations = signatures
+        .iter()
+        .map(|signature| generate_registration(signature, item))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let vis = &item.vis;
+    let name = &item.sig.ident;
+    let register = format_ident!("register_{}", name);
+    let doc = format!("Register all signatures of [`{name}`] into `registry`.");
+    Ok(quote! {
+        #item
+
+        #[doc = #doc]
+        #vis fn #register(
+            registry: &mut ::type_rust::expr::FunctionRegistry,
+        ) -> ::type_rust::__private::anyhow::Result<()> {
+            #(#registrations)*
+            Ok(())
+        }
+    })
+}
+
+/// How the return value of the scalar function is pushed into the builder.
+enum ReturnKind {
+    Value,
+    Option,
+    Result,
+    ResultOption,
+}
+
+impl ReturnKind {
+    fn of(output: &ReturnType) -> Self {
+        let ReturnType::Type(_, ty) = output else {
+            return Self::Value;
+        };
+        match outer_type(ty) {
+            Some(("Option", _)) => Self::Option,
+            Some(("Result", inner)) => match inner.and_then(outer_type) {
+                Some(("Option", _)) => Self::ResultOption,
+                _ => Self::Result,
+            },
+            _ => Self::Value,
+        }
+    }
+}
+
+/// Get the name of `Option<T>` or `Result<T, E>` together with `T`.
+fn outer_type(ty: &Type) -> Option<(&'static str, Option<&Type>)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    let name = match segment.ident.to_string().as_str() {
+        "Option" => "Option",
+        "Result" => "Result",
+        _ => return None,
+    };
+    let inner = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    };
+    Some((name, inner))
+}
+
+/// Parse `name(type, ...) -> type` into argument types and return type.
+fn parse_signature(signature: &LitStr) -> syn::Result<(Vec<String>, String)> {
+    let value = signature.value();
+    let invalid = || {
+        Error::new(
+            signature.span(),
+            "expect a signature like `f(int32) -> int32`",
+        )
+    };
+    let (call, ret) = value.split_once("->").ok_or_else(invalid)?;
+    let (_, args) = call.trim().split_once('(').ok_or_else(invalid)?;
+    let args = args.strip_suffix(')').ok_or_else(invalid)?;
+
+    let mut types = vec![];
+    let (mut depth, mut start) = (0, 0);
+    for (idx, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                types.push(args[start..idx].trim().to_string());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if !args.trim().is_empty() {
+        types.push(args[start..].trim().to_string());
+    }
+    Ok((types, ret.trim().to_string()))
+}
+
+/// Get the array type storing values of type `name`.
+fn array_type(name: &str, signature: &LitStr) -> syn::Result<TokenStream2> {
+    let base = name.split('(').next().unwrap_or_default().trim();
+    let array = match base.to_ascii_lowercase().as_str() {
+        "smallint" | "int2" | "int16" => quote! { I16Array },
+        "integer" | "int" | "int4" | "int32" => quote! { I32Array },
+        "bigint" | "int8" | "int64" => quote! { I64Array },
+        "real" | "float4" | "float32" => quote! { F32Array },
+        "double precision" | "double" | "float8" | "float64" => quote! { F64Array },
+        "boolean" | "bool" => quote! { BoolArray },
+        "varchar" | "text" | "string" | "char" | "character" => quote! { StringArray },
+        _ => {
+            return Err(Error::new(
+                signature.span(),
+                format!("type `{name}` does not have a physical array"),
+            ));
+        }
+    };
+    Ok(quote! { ::type_rust::array::#array })
+}
+
+/// Whether the parameter is taken as an `Option`, i.e. `NULL` is passed to the function.
+fn is_nullable(arg: &FnArg) -> bool {
+    match arg {
+        FnArg::Typed(arg) => matches!(outer_type(&arg.ty), Some(("Option", _))),
+        FnArg::Receiver(_) => false,
+    }
+}
+
+/// Generate the code registering `item` with one signature.
+fn generate_registration(signature: &LitStr, item: &ItemFn) -> syn::Result<TokenStream2> {
+    let (args, ret) = parse_signature(signature)?;
+    if args.is_empty() {
+        return Err(Error::new(
+            signature.span(),
+            "function must have at least one argument",
+        ));
+    }
+    let num_params = item.sig.inputs.len();
+    let with_writer = num_params == args.len() + 1;
+    if num_params != args.len() && !with_writer {
+        return Err(Error::new(
+            signature.span(),
+            format!(
+                "signature has {} arguments, but the function takes {}",
+                args.len(),
+                num_params
+            ),
+        ));
+    }
+
+    let name = &item.sig.ident;
+    let num_args = args.len();
+    let arrays = (0..num_args)
+        .map(|idx| format_ident!("a{}", idx))
+        .collect::<Vec<_>>();
+    let values = (0..num_args)
+        .map(|idx| format_ident!("v{}", idx))
+        .collect::<Vec<_>>();
+    let array_types = args
+        .iter()
+        .map(|ty| array_type(ty, signature))
+        .collect::<syn::Result<Vec<_>>>()?;
+    let ret_array_type = array_type(&ret, signature)?;
+    if with_writer && ret_array_type.to_string() != array_type("varchar", signature)?.to_string() {
+        return Err(Error::new(
+            signature.span(),
+            "only functions returning strings can take a writer",
+        ));
+    }
+
+    // Non-nullable arguments are destructured together, and we produce `NULL` if any of them is
+    // `NULL`.
+    let (mut strict_arrays, mut strict_values) = (vec![], vec![]);
+    let (mut nullable_arrays, mut nullable_values) = (vec![], vec![]);
+    for ((arg, array), value) in item.sig.inputs.iter().zip(&arrays).zip(&values) {
+        if is_nullable(arg) {
+            nullable_arrays.push(array);
+            nullable_values.push(value);
+        } else {
+            strict_arrays.push(array);
+            strict_values.push(value);
+        }
+    }
+    let strict = match strict_arrays.as_slice() {
+        [] => quote! {},
+        [array] => {
+            let value = &strict_values[0];
+            quote! {
+                let Some(#value) = #array.get(row) else {
+                    builder.push(None);
+                    continue;
+                };
+            }
+        }
+        _ => quote! {
+            let (#(Some(#strict_values)),*) = (#(#strict_arrays.get(row)),*) else {
+                builder.push(None);
+                continue;
+            };
+        },
+    };
+
+    let call_and_push = match (with_writer, ReturnKind::of(&item.sig.output)) {
+        (false, _) if matches!(item.sig.output, ReturnType::Default) => {
+            return Err(Error::new_spanned(
+                &item.sig,
+                "function must return a value or take a writer",
+            ));
+        }
+        (false, ReturnKind::Value) => quote! {
+            let output = #name(#(#values),*);
+            builder.push(Some(output.as_scalar_ref()));
+        },
+        (false, ReturnKind::Option) => quote! {
+            let output = #name(#(#values),*);
+            builder.push(output.as_ref().map(|v| v.as_scalar_ref()));
+        },
+        (false, ReturnKind::Result) => quote! {
+            let output = #name(#(#values),*)?;
+            builder.push(Some(output.as_scalar_ref()));
+        },
+        (false, ReturnKind::ResultOption) => quote! {
+            let output = #name(#(#values),*)?;
+            builder.push(output.as_ref().map(|v| v.as_scalar_ref()));
+        },
+        (true, ReturnKind::Value) => quote! {
+            let mut writer = builder.writer();
+            #name(#(#values,)* &mut writer);
+            writer.finish();
+        },
+        (true, ReturnKind::Result) => quote! {
+            let mut writer = builder.writer();
+            #name(#(#values,)* &mut writer)?;
+            writer.finish();
+        },
+        (true, kind) => {
+            let output = match kind {
+                ReturnKind::ResultOption => quote! { #name(#(#values,)* &mut writer)? },
+                _ => quote! { #name(#(#values,)* &mut writer) },
+            };
+            quote! {
+                let mut writer = builder.writer();
+                if #output.is_some() {
+                    writer.finish();
+                } else {
+                    drop(writer);
+                    builder.push(None);
+                }
+            }
+        }
+    };
+
+    let fn_name = name.to_string();
+    Ok(quote! {
+        {
+            struct Function;
+
+            impl ::type_rust::expr::Expression for Function {
+                fn eval_expr(
+                    &self,
+                    data: &[&::type_rust::array::ArrayImpl],
+                ) -> ::type_rust::__private::anyhow::Result<::type_rust::array::ArrayImpl> {
+                    #[allow(unused_imports)]
+                    use ::type_rust::array::{Array, ArrayBuilder};
+                    #[allow(unused_imports)]
+                    use ::type_rust::scalar::Scalar;
+
+                    let [#(#arrays),*] = data else {
+                        ::type_rust::__private::anyhow::bail!(
+                            "Expect {} inputs for function {}", #num_args, #fn_name
+                        );
+                    };
+                    #(
+                        let #arrays: &#array_types = (*#arrays).try_into()?;
+                    )*
+                    let len = a0.len();
+                    if #(#arrays.len() != len)||* {
+                        ::type_rust::__private::anyhow::bail!("array length mismatch");
+                    }
+                    let mut builder = <#ret_array_type as Array>::Builder::with_capacity(len);
+                    for row in 0..len {
+                        #(
+                            let #nullable_values = #nullable_arrays.get(row);
+                        )*
+                        #strict
+                        #call_and_push
+                    }
+                    Ok(builder.finish().into())
+                }
+            }
+
+            registry.register(#signature.parse()?, || Box::new(Function))?;
+        }
+    })
+}
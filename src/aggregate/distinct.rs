@@ -0,0 +1,239 @@
+//! Implements `DISTINCT` aggregates like `count(DISTINCT v)`.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hasher};
+
+use anyhow::{Result, bail};
+
+use crate::TypeMismatch;
+use crate::aggregate::{
+    AggState, AggregateFunction, downcast_state, into_state, scalar_heap_size, state_ref,
+};
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::expr::cmp::{SqlHash, SqlOrd};
+use crate::scalar::{ScalarImpl, ScalarRefImpl};
+
+/// A set of distinct rows of argument values. Rows are compared with [`SqlOrd`] and hashed with
+/// [`SqlHash`] as [`ScalarRefImpl`]s, so that a row is only copied when it is inserted.
+#[derive(Default, Clone)]
+struct DistinctSet {
+    /// Rows grouped by their hashes.
+    buckets: HashMap<u64, Vec<Box<[Option<ScalarImpl>]>>>,
+    /// Number of bytes allocated for the values of rows.
+    heap_size: usize,
+}
+
+fn hash_row(row: &[Option<ScalarRefImpl<'_>>]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for value in row {
+        match value {
+            Some(value) => {
+                hasher.write_u8(1);
+                value.sql_hash(&mut hasher);
+            }
+            None => hasher.write_u8(0),
+        }
+    }
+    hasher.finish()
+}
+
+fn row_eq(stored: &[Option<ScalarImpl>], row: &[Option<ScalarRefImpl<'_>>]) -> bool {
+    stored.iter().zip(row).all(|(l, r)| match (l, r) {
+        (Some(l), Some(r)) => l.as_scalar_ref().sql_cmp(r).is_eq(),
+        (None, None) => true,
+        _ => false,
+    })
+}
+
+impl DistinctSet {
+    /// Insert `row`, and return whether it is new.
+    fn insert(&mut self, row: &[Option<ScalarRefImpl<'_>>]) -> bool {
+        let bucket = self.buckets.entry(hash_row(row)).or_default();
+        if bucket.iter().any(|stored| row_eq(stored, row)) {
+            return false;
+        }
+        bucket.push(row.iter().map(|v| v.map(|v| v.to_owned_scalar())).collect());
+        let values = row.iter().flatten().map(|v| scalar_heap_size(*v));
+        self.heap_size += row.len() * size_of::<Option<ScalarImpl>>() + values.sum::<usize>();
+        true
+    }
+}
+
+/// State of [`DistinctAggregate`].
+struct DistinctState {
+    seen: DistinctSet,
+    inner: AggState,
+}
+
+/// `agg(DISTINCT args)`, which passes each distinct row of arguments to the aggregate function
+/// `inner` only once.
+pub struct DistinctAggregate {
+    inner: Box<dyn AggregateFunction>,
+    arg_types: Vec<DataType>,
+}
+
+impl DistinctAggregate {
+    /// Create `inner(DISTINCT args)`, where arguments are of `arg_types`.
+    pub fn new(inner: Box<dyn AggregateFunction>, arg_types: Vec<DataType>) -> Result<Self> {
+        if arg_types.is_empty() {
+            bail!("DISTINCT requires arguments");
+        }
+        Ok(Self { inner, arg_types })
+    }
+
+    fn check_args(&self, args: &[&ArrayImpl]) -> Result<()> {
+        if args.len() != self.arg_types.len() {
+            bail!(
+                "Expect {} inputs for DistinctAggregate, get {}",
+                self.arg_types.len(),
+                args.len()
+            );
+        }
+        // Rows are copied into arrays of `arg_types` when states are merged.
+        for (arg, ty) in args.iter().zip(&self.arg_types) {
+            if arg.identifier() != ty.physical_identifier() {
+                return Err(TypeMismatch(ty.physical_identifier(), arg.identifier()).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get row `row` of `args`.
+fn get_row<'a>(args: &[&'a ArrayImpl], row: usize, buf: &mut Vec<Option<ScalarRefImpl<'a>>>) {
+    buf.clear();
+    buf.extend(args.iter().map(|arg| arg.get(row)));
+}
+
+impl AggregateFunction for DistinctAggregate {
+    fn return_type(&self) -> DataType {
+        self.inner.return_type()
+    }
+
+    fn create_state(&self) -> AggState {
+        Box::new(DistinctState {
+            seen: DistinctSet::default(),
+            inner: self.inner.create_state(),
+        })
+    }
+
+    fn update(&self, state: &mut AggState, args: &[&ArrayImpl], num_rows: usize) -> Result<()> {
+        self.check_args(args)?;
+        let state = downcast_state::<DistinctState>(state)?;
+        let mut row = vec![];
+        let visibility = (0..num_rows)
+            .map(|i| {
+                get_row(args, i, &mut row);
+                state.seen.insert(&row)
+            })
+            .collect::<Vec<_>>();
+        let filtered = args
+            .iter()
+            .map(|arg| arg.filter(&visibility))
+            .collect::<Vec<_>>();
+        let filtered = filtered.iter().collect::<Vec<_>>();
+        let num_rows = visibility.iter().filter(|v| **v).count();
+        self.inner.update(&mut state.inner, &filtered, num_rows)
+    }
+
+    fn update_grouped(
+        &self,
+        states: &mut [AggState],
+        groups: &[usize],
+        args: &[&ArrayImpl],
+    ) -> Result<()> {
+        self.check_args(args)?;
+        let mut row = vec![];
+        let mut visibility = Vec::with_capacity(groups.len());
+        for (i, group) in groups.iter().enumerate() {
+            get_row(args, i, &mut row);
+            let state = downcast_state::<DistinctState>(&mut states[*group])?;
+            visibility.push(state.seen.insert(&row));
+        }
+        let filtered = args
+            .iter()
+            .map(|arg| arg.filter(&visibility))
+            .collect::<Vec<_>>();
+        let filtered = filtered.iter().collect::<Vec<_>>();
+        // Take the inner states of groups with new rows out, and put them back afterwards.
+        let mut touched = HashMap::new();
+        let mut inner_states = vec![];
+        let mut inner_groups = vec![];
+        for (group, _) in groups.iter().zip(&visibility).filter(|(_, v)| **v) {
+            let idx = *touched.entry(*group).or_insert_with(|| {
+                inner_states.push(*group);
+                inner_states.len() - 1
+            });
+            inner_groups.push(idx);
+        }
+        let mut taken = inner_states
+            .iter()
+            .map(|group| {
+                let state = downcast_state::<DistinctState>(&mut states[*group])?;
+                Ok(std::mem::replace(&mut state.inner, Box::new(())))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let result = self
+            .inner
+            .update_grouped(&mut taken, &inner_groups, &filtered);
+        for (group, inner) in inner_states.into_iter().zip(taken) {
+            downcast_state::<DistinctState>(&mut states[group])?.inner = inner;
+        }
+        result
+    }
+
+    /// Rows seen by both states must only be passed to the inner function once, so the rows
+    /// only seen by `other` are passed to the inner state of `state` again, instead of merging
+    /// the inner states.
+    fn merge(&self, state: &mut AggState, other: AggState) -> Result<()> {
+        let state = downcast_state::<DistinctState>(state)?;
+        let other = into_state::<DistinctState>(other)?;
+        let mut builders = self
+            .arg_types
+            .iter()
+            .map(|ty| ty.create_array_builder(0))
+            .collect::<Result<Vec<_>>>()?;
+        let mut num_rows = 0;
+        for stored in other.seen.buckets.into_values().flatten() {
+            let row = stored.iter().map(|v| v.as_ref().map(|v| v.as_scalar_ref()));
+            if state.seen.insert(&row.clone().collect::<Vec<_>>()) {
+                for (builder, value) in builders.iter_mut().zip(row) {
+                    builder.push(value);
+                }
+                num_rows += 1;
+            }
+        }
+        let args = builders.into_iter().map(|b| b.finish()).collect::<Vec<_>>();
+        let args = args.iter().collect::<Vec<_>>();
+        self.inner.update(&mut state.inner, &args, num_rows)
+    }
+
+    fn clone_state(&self, state: &AggState) -> Result<AggState> {
+        let state = state_ref::<DistinctState>(state)?;
+        Ok(Box::new(DistinctState {
+            seen: state.seen.clone(),
+            inner: self.inner.clone_state(&state.inner)?,
+        }))
+    }
+
+    fn finish(&self, state: &AggState) -> Result<Option<ScalarImpl>> {
+        self.inner.finish(&state_ref::<DistinctState>(state)?.inner)
+    }
+
+    fn estimated_state_size(&self, state: &AggState) -> Result<usize> {
+        let state = state_ref::<DistinctState>(state)?;
+        let buckets = state.seen.buckets.len() * size_of::<(u64, Vec<Box<[Option<ScalarImpl>]>>)>();
+        let rows = state
+            .seen
+            .buckets
+            .values()
+            .map(Vec::capacity)
+            .sum::<usize>();
+        let size = size_of::<DistinctState>()
+            + buckets
+            + rows * size_of::<Box<[Option<ScalarImpl>]>>()
+            + state.seen.heap_size;
+        Ok(size + self.inner.estimated_state_size(&state.inner)?)
+    }
+}
@@ -0,0 +1,87 @@
+//! Implements `FILTER` clauses like `count(v) FILTER (WHERE cond)`.
+
+use anyhow::{Result, bail};
+
+use crate::aggregate::{AggState, AggregateFunction};
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::scalar::ScalarImpl;
+
+/// `agg(args) FILTER (WHERE cond)`, which only passes rows where `cond` is true to the aggregate
+/// function `inner`. The condition is the last argument, and rows where it is `NULL` are
+/// filtered out as well.
+///
+/// `agg(DISTINCT args) FILTER (WHERE cond)` wraps a [`DistinctAggregate`] with this, so that
+/// filtered rows are not counted as seen.
+///
+/// [`DistinctAggregate`]: crate::aggregate::DistinctAggregate
+pub struct FilterAggregate {
+    inner: Box<dyn AggregateFunction>,
+}
+
+impl FilterAggregate {
+    pub fn new(inner: Box<dyn AggregateFunction>) -> Self {
+        Self { inner }
+    }
+}
+
+/// Split `args` into the arguments of the inner function and the visibility of rows.
+fn split_args<'a>(args: &[&'a ArrayImpl]) -> Result<(&'a BoolArray, Vec<ArrayImpl>)> {
+    let Some((cond, args)) = args.split_last() else {
+        bail!("FILTER requires a condition");
+    };
+    let cond: &BoolArray = (*cond).try_into()?;
+    let visibility = cond.iter().map(|v| v == Some(true)).collect::<Vec<_>>();
+    let args = args.iter().map(|arg| arg.filter(&visibility)).collect();
+    Ok((cond, args))
+}
+
+impl AggregateFunction for FilterAggregate {
+    fn return_type(&self) -> DataType {
+        self.inner.return_type()
+    }
+
+    fn create_state(&self) -> AggState {
+        self.inner.create_state()
+    }
+
+    fn update(&self, state: &mut AggState, args: &[&ArrayImpl], _num_rows: usize) -> Result<()> {
+        let (cond, filtered) = split_args(args)?;
+        let num_rows = cond.iter().filter(|v| *v == Some(true)).count();
+        let filtered = filtered.iter().collect::<Vec<_>>();
+        self.inner.update(state, &filtered, num_rows)
+    }
+
+    fn update_grouped(
+        &self,
+        states: &mut [AggState],
+        groups: &[usize],
+        args: &[&ArrayImpl],
+    ) -> Result<()> {
+        let (cond, filtered) = split_args(args)?;
+        let groups = groups
+            .iter()
+            .zip(cond.iter())
+            .filter(|(_, v)| *v == Some(true))
+            .map(|(group, _)| *group)
+            .collect::<Vec<_>>();
+        let filtered = filtered.iter().collect::<Vec<_>>();
+        self.inner.update_grouped(states, &groups, &filtered)
+    }
+
+    fn merge(&self, state: &mut AggState, other: AggState) -> Result<()> {
+        self.inner.merge(state, other)
+    }
+
+    fn clone_state(&self, state: &AggState) -> Result<AggState> {
+        self.inner.clone_state(state)
+    }
+
+    fn finish(&self, state: &AggState) -> Result<Option<ScalarImpl>> {
+        self.inner.finish(state)
+    }
+
+    fn estimated_state_size(&self, state: &AggState) -> Result<usize> {
+        self.inner.estimated_state_size(state)
+    }
+}
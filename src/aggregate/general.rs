@@ -0,0 +1,304 @@
+//! Implements general-purpose aggregate functions: `count`, `sum`, `avg`, `min`, `max`,
+//! `bool_and` and `bool_or`.
+
+use std::marker::PhantomData;
+
+use anyhow::{Result, anyhow};
+
+use crate::aggregate::{
+    Accumulator, AggState, AggregateFunction, downcast_state, into_state, scalar_heap_size,
+    state_ref,
+};
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::expr::arith::CheckedArith;
+use crate::expr::cmp::SqlOrd;
+use crate::scalar::{Scalar, ScalarImpl, ScalarRef, ScalarRefImpl};
+
+/// `count(v)`, the number of non-null values.
+#[derive(Default, Clone)]
+pub struct Count {
+    count: i64,
+}
+
+impl<A: Array> Accumulator<A> for Count {
+    fn accumulate(&mut self, _: A::RefItem<'_>) -> Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        self.count += other.count;
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(Some(self.count.into()))
+    }
+}
+
+/// `count(*)`, the number of rows.
+pub struct CountStar;
+
+impl AggregateFunction for CountStar {
+    fn return_type(&self) -> DataType {
+        DataType::BigInt
+    }
+
+    fn create_state(&self) -> AggState {
+        Box::new(0i64)
+    }
+
+    fn update(&self, state: &mut AggState, _: &[&ArrayImpl], num_rows: usize) -> Result<()> {
+        *downcast_state::<i64>(state)? += num_rows as i64;
+        Ok(())
+    }
+
+    fn update_grouped(
+        &self,
+        states: &mut [AggState],
+        groups: &[usize],
+        _: &[&ArrayImpl],
+    ) -> Result<()> {
+        for group in groups {
+            *downcast_state::<i64>(&mut states[*group])? += 1;
+        }
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut AggState, other: AggState) -> Result<()> {
+        *downcast_state::<i64>(state)? += into_state::<i64>(other)?;
+        Ok(())
+    }
+
+    fn clone_state(&self, state: &AggState) -> Result<AggState> {
+        Ok(Box::new(*state_ref::<i64>(state)?))
+    }
+
+    fn finish(&self, state: &AggState) -> Result<Option<ScalarImpl>> {
+        Ok(Some((*state_ref::<i64>(state)?).into()))
+    }
+
+    fn estimated_state_size(&self, _: &AggState) -> Result<usize> {
+        Ok(size_of::<i64>())
+    }
+}
+
+/// Types used to add up values. Integers are added up in `i128`, which cannot overflow with
+/// any practical number of `i64` inputs.
+pub trait SumType: Copy + Default + Send + 'static {
+    fn add(self, rhs: Self) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_sum_type {
+    ($($ty:ty),*) => {
+        $(
+            impl SumType for $ty {
+                fn add(self, rhs: Self) -> Self {
+                    self + rhs
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+impl_sum_type! { i128, f32, f64 }
+
+/// `sum(v)`, added up in `W` and returned as `O`. The result is `NULL` if there is no input.
+pub struct Sum<W, O> {
+    sum: Option<W>,
+    _phantom: PhantomData<fn() -> O>,
+}
+
+impl<W: Copy, O> Clone for Sum<W, O> {
+    fn clone(&self) -> Self {
+        Self {
+            sum: self.sum,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<W, O> Default for Sum<W, O> {
+    fn default() -> Self {
+        Self {
+            sum: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A: Array, W: SumType, O> Accumulator<A> for Sum<W, O>
+where
+    for<'a> A::RefItem<'a>: Into<W>,
+    O: CheckedArith + TryFrom<W> + Into<ScalarImpl> + 'static,
+{
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        self.sum = Some(self.sum.unwrap_or_default().add(v.into()));
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        if let Some(other) = other.sum {
+            self.sum = Some(self.sum.unwrap_or_default().add(other));
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        self.sum
+            .map(|sum| {
+                O::try_from(sum)
+                    .map(Into::into)
+                    .map_err(|_| anyhow!("{} out of range", O::TYPE_NAME))
+            })
+            .transpose()
+    }
+}
+
+/// `avg(v)`, added up in `W` and returned as `double precision`. The result is `NULL` if there
+/// is no input.
+#[derive(Default, Clone)]
+pub struct Avg<W> {
+    sum: W,
+    count: i64,
+}
+
+impl<A: Array, W: SumType> Accumulator<A> for Avg<W>
+where
+    for<'a> A::RefItem<'a>: Into<W>,
+{
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        self.sum = self.sum.add(v.into());
+        self.count += 1;
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        self.sum = self.sum.add(other.sum);
+        self.count += other.count;
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok((self.count > 0).then(|| (self.sum.to_f64() / self.count as f64).into()))
+    }
+}
+
+/// `max(v)` if `MAX` is set, or `min(v)` if not. Values are ordered by [`SqlOrd`].
+pub struct MinMax<A: Array, const MAX: bool> {
+    value: Option<A::OwnedItem>,
+}
+
+impl<A: Array, const MAX: bool> Clone for MinMax<A, MAX> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<A: Array, const MAX: bool> Default for MinMax<A, MAX> {
+    fn default() -> Self {
+        Self { value: None }
+    }
+}
+
+impl<A: Array, const MAX: bool> MinMax<A, MAX> {
+    /// Check if `v` should replace the current value.
+    fn is_better(&self, v: A::RefItem<'_>) -> bool {
+        match &self.value {
+            Some(current) => {
+                // Compare as `ScalarRefImpl`, which unifies the lifetimes of both sides.
+                let v: ScalarRefImpl<'_> = v.into();
+                let ordering = v.sql_cmp(&current.as_scalar_ref().into());
+                if MAX {
+                    ordering.is_gt()
+                } else {
+                    ordering.is_lt()
+                }
+            }
+            None => true,
+        }
+    }
+}
+
+impl<A: Array, const MAX: bool> Accumulator<A> for MinMax<A, MAX> {
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        if self.is_better(v) {
+            self.value = Some(v.to_owned_scalar());
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        if let Some(other) = other.value
+            && self.is_better(other.as_scalar_ref())
+        {
+            self.value = Some(other);
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(self.value.clone().map(Into::into))
+    }
+
+    fn estimated_heap_size(&self) -> usize {
+        let value = self.value.as_ref().map(|v| v.as_scalar_ref().into());
+        value.map_or(0, scalar_heap_size)
+    }
+}
+
+/// `bool_and(v)`, which is `true` if all values are `true`.
+#[derive(Default, Clone)]
+pub struct BoolAnd {
+    value: Option<bool>,
+}
+
+impl Accumulator<BoolArray> for BoolAnd {
+    fn accumulate(&mut self, v: bool) -> Result<()> {
+        self.value = Some(self.value.unwrap_or(true) && v);
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        if let Some(other) = other.value {
+            self.accumulate(other)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(self.value.map(Into::into))
+    }
+}
+
+/// `bool_or(v)`, which is `true` if any value is `true`.
+#[derive(Default, Clone)]
+pub struct BoolOr {
+    value: Option<bool>,
+}
+
+impl Accumulator<BoolArray> for BoolOr {
+    fn accumulate(&mut self, v: bool) -> Result<()> {
+        self.value = Some(self.value.unwrap_or(false) || v);
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        if let Some(other) = other.value {
+            self.accumulate(other)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(self.value.map(Into::into))
+    }
+}
@@ -0,0 +1,107 @@
+//! Implements `approx_count_distinct` with HyperLogLog.
+//!
+//! A HyperLogLog sketch splits hashes of values into `2^PRECISION` buckets by their leading bits,
+//! and keeps the longest run of leading zeros of the remaining bits seen in each bucket. The
+//! number of distinct values is estimated from these runs with a relative standard error of
+//! about `1.04 / sqrt(2^PRECISION)`, which is 0.8% here. Sketches are merged by taking the
+//! maximum of each bucket, so partial sketches built in parallel can be combined.
+
+use std::hash::{DefaultHasher, Hasher};
+
+use anyhow::Result;
+
+use crate::aggregate::Accumulator;
+use crate::array::*;
+use crate::expr::cmp::SqlHash;
+use crate::scalar::ScalarImpl;
+
+/// Number of bits of the hash used to choose the bucket.
+const PRECISION: u32 = 14;
+const NUM_REGISTERS: usize = 1 << PRECISION;
+
+/// A HyperLogLog sketch.
+#[derive(Default, Clone)]
+pub struct HyperLogLog {
+    /// Longest run of leading zeros plus one in each bucket, or empty if no value is added, so
+    /// that states of empty groups stay small.
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Add a value with `hash`.
+    pub fn add_hash(&mut self, hash: u64) {
+        if self.registers.is_empty() {
+            self.registers = vec![0; NUM_REGISTERS];
+        }
+        let bucket = (hash >> (64 - PRECISION)) as usize;
+        // Set a bit below the remaining bits, so that the run is at most `64 - PRECISION`.
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[bucket] = self.registers[bucket].max(rank);
+    }
+
+    /// Add all values added to `other`.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.registers.is_empty() {
+            return;
+        }
+        if self.registers.is_empty() {
+            self.registers = other.registers.clone();
+            return;
+        }
+        for (r, o) in self.registers.iter_mut().zip(&other.registers) {
+            *r = (*r).max(*o);
+        }
+    }
+
+    /// Estimate the number of distinct values.
+    pub fn estimate(&self) -> u64 {
+        if self.registers.is_empty() {
+            return 0;
+        }
+        let m = NUM_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum = self
+            .registers
+            .iter()
+            .map(|r| 2f64.powi(-(*r as i32)))
+            .sum::<f64>();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        // Use linear counting for small cardinalities, where it is more accurate.
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// `approx_count_distinct(v)`, which estimates the number of distinct non-null values.
+///
+/// Values are hashed with [`SqlHash`] by [`DefaultHasher::new`], whose keys are fixed, so that
+/// sketches built by different workers of the same build can be merged.
+impl<A: Array> Accumulator<A> for HyperLogLog
+where
+    for<'a> A::RefItem<'a>: SqlHash,
+{
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        let mut hasher = DefaultHasher::new();
+        v.sql_hash(&mut hasher);
+        self.add_hash(hasher.finish());
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        HyperLogLog::merge(self, &other);
+        Ok(())
+    }
+
+    fn estimated_heap_size(&self) -> usize {
+        self.registers.capacity()
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(Some((self.estimate() as i64).into()))
+    }
+}
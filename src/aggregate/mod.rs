@@ -0,0 +1,559 @@
+//! Implements aggregate functions.
+//!
+//! An aggregate function folds many rows into one value. Its intermediate result is kept in a
+//! state created by [`AggregateFunction::create_state`], which is updated with batches of input
+//! rows, and finally turned into the result by [`AggregateFunction::finish`]. States built on
+//! different parts of the input can be combined with [`AggregateFunction::merge`], so that the
+//! input can be aggregated in parallel.
+//!
+//! Most aggregate functions take one argument and ignore `NULL` inputs. They are written as
+//! [`Accumulator`]s over values of one array type, which are vectorized by [`UnaryAggregate`].
+//!
+//! `agg(DISTINCT v)` and `agg(v) FILTER (WHERE cond)` wrap any aggregate function with
+//! [`DistinctAggregate`] and [`FilterAggregate`].
+
+mod distinct;
+mod filter;
+mod general;
+mod hll;
+mod percentile;
+mod statistical;
+mod string_agg;
+mod tdigest;
+
+use std::any::Any;
+use std::marker::PhantomData;
+
+use anyhow::{Result, anyhow, bail};
+
+pub use self::distinct::*;
+pub use self::filter::*;
+pub use self::general::*;
+pub use self::hll::*;
+pub use self::percentile::*;
+pub use self::statistical::*;
+pub use self::string_agg::*;
+pub use self::tdigest::*;
+use crate::TypeMismatch;
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::expr::registry::DisplayArgs;
+use crate::macros::for_all_variants;
+use crate::scalar::{ScalarImpl, ScalarRefImpl};
+
+/// Intermediate state of an aggregate function, whose concrete type is only known to the
+/// function.
+pub type AggState = Box<dyn Any + Send>;
+
+/// A trait over all aggregate functions.
+pub trait AggregateFunction: Send + Sync {
+    /// The type of the result.
+    fn return_type(&self) -> DataType;
+
+    /// Create the state of an empty input.
+    fn create_state(&self) -> AggState;
+
+    /// Update `state` with rows of `args`, which has one array for each argument. `num_rows` is
+    /// the number of rows, which is needed by functions without arguments like `count(*)`.
+    fn update(&self, state: &mut AggState, args: &[&ArrayImpl], num_rows: usize) -> Result<()>;
+
+    /// Update `states[groups[row]]` with each row of `args`, which is used to aggregate rows of
+    /// many groups at once.
+    fn update_grouped(
+        &self,
+        states: &mut [AggState],
+        groups: &[usize],
+        args: &[&ArrayImpl],
+    ) -> Result<()>;
+
+    /// Merge `other` into `state`, as if `state` had been updated with the input of `other`.
+    fn merge(&self, state: &mut AggState, other: AggState) -> Result<()>;
+
+    /// Get a copy of `state`, which is needed to combine states without consuming them, like
+    /// evaluating framed window aggregates with a segment tree.
+    fn clone_state(&self, state: &AggState) -> Result<AggState>;
+
+    /// Get the result of `state`. `None` represents `NULL`.
+    fn finish(&self, state: &AggState) -> Result<Option<ScalarImpl>>;
+
+    /// Estimated number of bytes of `state`, including memory allocated by it, which is used to
+    /// keep states of many groups within a memory budget.
+    fn estimated_state_size(&self, state: &AggState) -> Result<usize>;
+}
+
+/// Get the concrete type of an [`AggState`] by reference.
+pub(crate) fn state_ref<S: 'static>(state: &AggState) -> Result<&S> {
+    state
+        .downcast_ref()
+        .ok_or_else(|| anyhow!("aggregate state is not created by this function"))
+}
+
+/// Get the concrete type of an [`AggState`].
+pub(crate) fn downcast_state<S: 'static>(state: &mut AggState) -> Result<&mut S> {
+    state
+        .downcast_mut()
+        .ok_or_else(|| anyhow!("aggregate state is not created by this function"))
+}
+
+/// Take the concrete value of an [`AggState`].
+pub(crate) fn into_state<S: 'static>(state: AggState) -> Result<S> {
+    state
+        .downcast()
+        .map(|state| *state)
+        .map_err(|_| anyhow!("aggregate state is not created by this function"))
+}
+
+/// A trait over the states of aggregate functions taking one argument of array type `A`. `NULL`
+/// inputs are skipped before reaching the accumulator.
+pub trait Accumulator<A: Array>: Clone + Send + 'static {
+    /// Add a non-null value.
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()>;
+
+    /// Add all values added to `other`.
+    fn merge(&mut self, other: Self) -> Result<()>;
+
+    /// Get the result. `None` represents `NULL`.
+    fn finish(&self) -> Result<Option<ScalarImpl>>;
+
+    /// Number of bytes allocated by the state besides itself, like values kept in a `Vec`.
+    fn estimated_heap_size(&self) -> usize {
+        0
+    }
+}
+
+/// Number of bytes allocated by `value` besides itself.
+pub(crate) fn scalar_heap_size(value: ScalarRefImpl<'_>) -> usize {
+    match value {
+        ScalarRefImpl::String(v) => v.len(),
+        _ => 0,
+    }
+}
+
+/// Creates empty states of an aggregate function.
+type CreateState<S> = Box<dyn Fn() -> S + Send + Sync>;
+
+/// Represents an aggregate function of one argument with state `S`.
+///
+/// Like [`UnaryExpression`](crate::expr::vectorize::UnaryExpression), [`UnaryAggregate`]
+/// vectorizes an accumulator and erases the concrete array type.
+pub struct UnaryAggregate<A: Array, S> {
+    return_type: DataType,
+    create_state: CreateState<S>,
+    _phantom: PhantomData<fn(A)>,
+}
+
+impl<A: Array, S: Accumulator<A>> UnaryAggregate<A, S> {
+    /// Create an aggregate function whose empty state is `S::default()`.
+    pub fn new(return_type: DataType) -> Self
+    where
+        S: Default,
+    {
+        Self::with_state(return_type, S::default)
+    }
+
+    /// Create an aggregate function whose empty states are created by `create_state`, which
+    /// passes parameters of the function to the states.
+    pub fn with_state(
+        return_type: DataType,
+        create_state: impl Fn() -> S + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            return_type,
+            create_state: Box::new(create_state),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A: Array, S: Accumulator<A>> AggregateFunction for UnaryAggregate<A, S>
+where
+    for<'a> &'a A: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+{
+    fn return_type(&self) -> DataType {
+        self.return_type
+    }
+
+    fn create_state(&self) -> AggState {
+        Box::new((self.create_state)())
+    }
+
+    fn update(&self, state: &mut AggState, args: &[&ArrayImpl], _num_rows: usize) -> Result<()> {
+        let [array] = args else {
+            bail!("Expect one input for UnaryAggregate");
+        };
+        let array: &A = (*array).try_into()?;
+        let state = downcast_state::<S>(state)?;
+        for v in array.iter().flatten() {
+            state.accumulate(v)?;
+        }
+        Ok(())
+    }
+
+    fn update_grouped(
+        &self,
+        states: &mut [AggState],
+        groups: &[usize],
+        args: &[&ArrayImpl],
+    ) -> Result<()> {
+        let [array] = args else {
+            bail!("Expect one input for UnaryAggregate");
+        };
+        let array: &A = (*array).try_into()?;
+        for (v, group) in array.iter().zip(groups) {
+            if let Some(v) = v {
+                downcast_state::<S>(&mut states[*group])?.accumulate(v)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut AggState, other: AggState) -> Result<()> {
+        downcast_state::<S>(state)?.merge(into_state(other)?)
+    }
+
+    fn clone_state(&self, state: &AggState) -> Result<AggState> {
+        Ok(Box::new(state_ref::<S>(state)?.clone()))
+    }
+
+    fn finish(&self, state: &AggState) -> Result<Option<ScalarImpl>> {
+        state_ref::<S>(state)?.finish()
+    }
+
+    fn estimated_state_size(&self, state: &AggState) -> Result<usize> {
+        Ok(size_of::<S>() + state_ref::<S>(state)?.estimated_heap_size())
+    }
+}
+
+/// A trait over the states of aggregate functions taking two arguments of array types `A1` and
+/// `A2`. Rows where any argument is `NULL` are skipped before reaching the accumulator.
+pub trait BinaryAccumulator<A1: Array, A2: Array>: Default + Clone + Send + 'static {
+    /// Add a row of non-null values.
+    fn accumulate(&mut self, v1: A1::RefItem<'_>, v2: A2::RefItem<'_>) -> Result<()>;
+
+    /// Add all rows added to `other`.
+    fn merge(&mut self, other: Self) -> Result<()>;
+
+    /// Get the result. `None` represents `NULL`.
+    fn finish(&self) -> Result<Option<ScalarImpl>>;
+}
+
+/// Represents an aggregate function of two arguments with state `S`, which vectorizes a
+/// [`BinaryAccumulator`] like [`UnaryAggregate`].
+pub struct BinaryAggregate<A1: Array, A2: Array, S> {
+    return_type: DataType,
+    _phantom: PhantomData<fn(A1, A2) -> S>,
+}
+
+impl<A1: Array, A2: Array, S: BinaryAccumulator<A1, A2>> BinaryAggregate<A1, A2, S> {
+    pub fn new(return_type: DataType) -> Self {
+        Self {
+            return_type,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A1: Array, A2: Array, S: BinaryAccumulator<A1, A2>> AggregateFunction
+    for BinaryAggregate<A1, A2, S>
+where
+    for<'a> &'a A1: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    for<'a> &'a A2: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+{
+    fn return_type(&self) -> DataType {
+        self.return_type
+    }
+
+    fn create_state(&self) -> AggState {
+        Box::new(S::default())
+    }
+
+    fn update(&self, state: &mut AggState, args: &[&ArrayImpl], _num_rows: usize) -> Result<()> {
+        let [a1, a2] = args else {
+            bail!("Expect two inputs for BinaryAggregate");
+        };
+        let (a1, a2): (&A1, &A2) = ((*a1).try_into()?, (*a2).try_into()?);
+        let state = downcast_state::<S>(state)?;
+        for (v1, v2) in a1.iter().zip(a2.iter()) {
+            if let (Some(v1), Some(v2)) = (v1, v2) {
+                state.accumulate(v1, v2)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn update_grouped(
+        &self,
+        states: &mut [AggState],
+        groups: &[usize],
+        args: &[&ArrayImpl],
+    ) -> Result<()> {
+        let [a1, a2] = args else {
+            bail!("Expect two inputs for BinaryAggregate");
+        };
+        let (a1, a2): (&A1, &A2) = ((*a1).try_into()?, (*a2).try_into()?);
+        for ((v1, v2), group) in a1.iter().zip(a2.iter()).zip(groups) {
+            if let (Some(v1), Some(v2)) = (v1, v2) {
+                downcast_state::<S>(&mut states[*group])?.accumulate(v1, v2)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut AggState, other: AggState) -> Result<()> {
+        downcast_state::<S>(state)?.merge(into_state(other)?)
+    }
+
+    fn clone_state(&self, state: &AggState) -> Result<AggState> {
+        Ok(Box::new(state_ref::<S>(state)?.clone()))
+    }
+
+    fn finish(&self, state: &AggState) -> Result<Option<ScalarImpl>> {
+        state_ref::<S>(state)?.finish()
+    }
+
+    /// States of binary accumulators have a fixed size.
+    fn estimated_state_size(&self, _: &AggState) -> Result<usize> {
+        Ok(size_of::<S>())
+    }
+}
+
+/// Create `Box<UnaryAggregate<A, S>>` as an aggregate function.
+fn unary<A: Array, S: Accumulator<A> + Default>(return_type: DataType) -> Box<dyn AggregateFunction>
+where
+    for<'a> &'a A: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+{
+    Box::new(UnaryAggregate::<A, S>::new(return_type))
+}
+
+/// Create `Box<BinaryAggregate<A1, A2, S>>` as an aggregate function.
+fn binary<A1: Array, A2: Array, S: BinaryAccumulator<A1, A2>>(
+    return_type: DataType,
+) -> Box<dyn AggregateFunction>
+where
+    for<'a> &'a A1: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    for<'a> &'a A2: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+{
+    Box::new(BinaryAggregate::<A1, A2, S>::new(return_type))
+}
+
+/// Implements [`build_count`] by dispatching on the physical type.
+macro_rules! impl_build_count {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        /// Build `count` over `data_type`.
+        fn build_count(data_type: DataType) -> Result<Box<dyn AggregateFunction>> {
+            match data_type.physical_identifier() {
+                $(
+                    stringify!($Abc) => Ok(unary::<$AbcArray, Count>(DataType::BigInt)),
+                )*
+                other => bail!("count is not supported for physical type {other}"),
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_build_count }
+
+/// Implements [`build_min_max`] by dispatching on the physical type.
+macro_rules! impl_build_min_max {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        /// Build `min` or `max` over `data_type`.
+        fn build_min_max<const MAX: bool>(data_type: DataType) -> Result<Box<dyn AggregateFunction>> {
+            match data_type.physical_identifier() {
+                $(
+                    stringify!($Abc) => Ok(unary::<$AbcArray, MinMax<$AbcArray, MAX>>(data_type)),
+                )*
+                other => bail!("min and max are not supported for physical type {other}"),
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_build_min_max }
+
+/// Implements [`build_percentile_disc`] by dispatching on the physical type.
+macro_rules! impl_build_percentile_disc {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        /// Build `percentile_disc(fraction)` over `data_type`.
+        fn build_percentile_disc(fraction: f64, data_type: DataType) -> Result<Box<dyn AggregateFunction>> {
+            match data_type.physical_identifier() {
+                $(
+                    stringify!($Abc) => Ok(Box::new(UnaryAggregate::<$AbcArray, _>::with_state(
+                        data_type,
+                        move || PercentileDisc::<$AbcArray>::new(fraction),
+                    ))),
+                )*
+                other => bail!("percentile_disc is not supported for physical type {other}"),
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_build_percentile_disc }
+
+/// Implements [`build_approx_count_distinct`] by dispatching on the physical type.
+macro_rules! impl_build_approx_count_distinct {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        /// Build `approx_count_distinct` over `data_type`.
+        fn build_approx_count_distinct(data_type: DataType) -> Result<Box<dyn AggregateFunction>> {
+            match data_type.physical_identifier() {
+                $(
+                    stringify!($Abc) => Ok(unary::<$AbcArray, HyperLogLog>(DataType::BigInt)),
+                )*
+                other => bail!("approx_count_distinct is not supported for physical type {other}"),
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_build_approx_count_distinct }
+
+/// Build an aggregate of one numeric argument of `data_type` with states created by
+/// `create_state`, which convert values to `double precision`.
+fn build_numeric<S>(
+    data_type: DataType,
+    create_state: impl Fn() -> S + Send + Sync + 'static,
+) -> Option<Box<dyn AggregateFunction>>
+where
+    S: Accumulator<I16Array>
+        + Accumulator<I32Array>
+        + Accumulator<I64Array>
+        + Accumulator<F32Array>
+        + Accumulator<F64Array>,
+{
+    use DataType::*;
+
+    Some(match data_type {
+        SmallInt => Box::new(UnaryAggregate::<I16Array, _>::with_state(
+            Double,
+            create_state,
+        )),
+        Integer => Box::new(UnaryAggregate::<I32Array, _>::with_state(
+            Double,
+            create_state,
+        )),
+        BigInt => Box::new(UnaryAggregate::<I64Array, _>::with_state(
+            Double,
+            create_state,
+        )),
+        Real => Box::new(UnaryAggregate::<F32Array, _>::with_state(
+            Double,
+            create_state,
+        )),
+        Double => Box::new(UnaryAggregate::<F64Array, _>::with_state(
+            Double,
+            create_state,
+        )),
+        _ => return None,
+    })
+}
+
+/// Build an aggregate computing statistic `S` of one numeric variable of `data_type`.
+fn build_univariate<S: Statistic>(data_type: DataType) -> Option<Box<dyn AggregateFunction>> {
+    build_numeric(data_type, Univariate::<S>::default)
+}
+
+/// Build an aggregate computing statistic `S` of two numeric variables of `y` and `x`.
+fn build_bivariate<S: BivariateStatistic>(
+    y: DataType,
+    x: DataType,
+) -> Option<Box<dyn AggregateFunction>> {
+    use DataType::*;
+
+    match y {
+        SmallInt => build_bivariate_x::<I16Array, S>(x),
+        Integer => build_bivariate_x::<I32Array, S>(x),
+        BigInt => build_bivariate_x::<I64Array, S>(x),
+        Real => build_bivariate_x::<F32Array, S>(x),
+        Double => build_bivariate_x::<F64Array, S>(x),
+        _ => None,
+    }
+}
+
+/// Build an aggregate computing statistic `S` of `y` of array type `A` and `x` of `x`.
+fn build_bivariate_x<A: Array, S: BivariateStatistic>(
+    x: DataType,
+) -> Option<Box<dyn AggregateFunction>>
+where
+    for<'a> &'a A: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    for<'a> A::RefItem<'a>: AsF64,
+{
+    use DataType::*;
+
+    Some(match x {
+        SmallInt => binary::<A, I16Array, Bivariate<S>>(Double),
+        Integer => binary::<A, I32Array, Bivariate<S>>(Double),
+        BigInt => binary::<A, I64Array, Bivariate<S>>(Double),
+        Real => binary::<A, F32Array, Bivariate<S>>(Double),
+        Double => binary::<A, F64Array, Bivariate<S>>(Double),
+        _ => return None,
+    })
+}
+
+/// Create the aggregate function `name` called with arguments of `args`. `count(*)` is `count`
+/// without arguments.
+pub fn build_aggregate(name: &str, args: &[DataType]) -> Result<Box<dyn AggregateFunction>> {
+    use DataType::*;
+
+    let not_exist = || anyhow!("function {name}({}) does not exist", DisplayArgs(args));
+
+    Ok(match (name, args) {
+        ("count", []) => Box::new(CountStar),
+        ("count", [arg]) => build_count(*arg)?,
+        ("sum", [SmallInt]) => unary::<I16Array, Sum<i128, i64>>(BigInt),
+        ("sum", [Integer]) => unary::<I32Array, Sum<i128, i64>>(BigInt),
+        ("sum", [BigInt]) => unary::<I64Array, Sum<i128, i64>>(BigInt),
+        ("sum", [Real]) => unary::<F32Array, Sum<f32, f32>>(Real),
+        ("sum", [Double]) => unary::<F64Array, Sum<f64, f64>>(Double),
+        ("avg", [SmallInt]) => unary::<I16Array, Avg<i128>>(Double),
+        ("avg", [Integer]) => unary::<I32Array, Avg<i128>>(Double),
+        ("avg", [BigInt]) => unary::<I64Array, Avg<i128>>(Double),
+        ("avg", [Real]) => unary::<F32Array, Avg<f64>>(Double),
+        ("avg", [Double]) => unary::<F64Array, Avg<f64>>(Double),
+        ("min", [arg]) => build_min_max::<false>(*arg)?,
+        ("max", [arg]) => build_min_max::<true>(*arg)?,
+        ("bool_and", [Boolean]) => unary::<BoolArray, BoolAnd>(Boolean),
+        ("bool_or", [Boolean]) => unary::<BoolArray, BoolOr>(Boolean),
+        ("string_agg", [Varchar | Char { .. }, Varchar | Char { .. }]) => Box::new(StringAgg),
+        ("var_pop", [arg]) => build_univariate::<VarPop>(*arg).ok_or_else(not_exist)?,
+        ("var_samp" | "variance", [arg]) => {
+            build_univariate::<VarSamp>(*arg).ok_or_else(not_exist)?
+        }
+        ("stddev_pop", [arg]) => build_univariate::<StddevPop>(*arg).ok_or_else(not_exist)?,
+        ("stddev_samp" | "stddev", [arg]) => {
+            build_univariate::<StddevSamp>(*arg).ok_or_else(not_exist)?
+        }
+        ("covar_pop", [y, x]) => build_bivariate::<CovarPop>(*y, *x).ok_or_else(not_exist)?,
+        ("covar_samp", [y, x]) => build_bivariate::<CovarSamp>(*y, *x).ok_or_else(not_exist)?,
+        ("corr", [y, x]) => build_bivariate::<Corr>(*y, *x).ok_or_else(not_exist)?,
+        ("regr_slope", [y, x]) => build_bivariate::<RegrSlope>(*y, *x).ok_or_else(not_exist)?,
+        ("median", [arg]) => {
+            build_numeric(*arg, || PercentileCont::new(0.5)).ok_or_else(not_exist)?
+        }
+        ("approx_count_distinct", [arg]) => build_approx_count_distinct(*arg)?,
+        _ => return Err(not_exist()),
+    })
+}
+
+/// Create the ordered-set aggregate function `name` with the constant parameter `fraction`,
+/// ordering values of `arg`, like `percentile_cont(fraction) WITHIN GROUP (ORDER BY arg)`.
+pub fn build_ordered_set_aggregate(
+    name: &str,
+    fraction: f64,
+    arg: DataType,
+) -> Result<Box<dyn AggregateFunction>> {
+    let not_exist = || {
+        anyhow!("function {name}(double precision) within group (order by {arg}) does not exist")
+    };
+
+    check_fraction(fraction)?;
+    Ok(match (name, arg) {
+        ("percentile_cont", arg) => {
+            build_numeric(arg, move || PercentileCont::new(fraction)).ok_or_else(not_exist)?
+        }
+        ("percentile_disc", arg) => build_percentile_disc(fraction, arg)?,
+        ("approx_percentile", arg) => {
+            build_numeric(arg, move || ApproxPercentile::new(fraction)).ok_or_else(not_exist)?
+        }
+        _ => return Err(not_exist()),
+    })
+}
@@ -0,0 +1,134 @@
+//! Implements exact percentiles: `percentile_cont`, `percentile_disc` and `median`.
+//!
+//! These are ordered-set aggregates in PostgreSQL, written as
+//! `percentile_cont(fraction) WITHIN GROUP (ORDER BY v)`, where `fraction` is a constant
+//! parameter of the function rather than an input column. States keep all non-null values, so
+//! that they can be sorted when the result is computed.
+
+use anyhow::{Result, bail};
+
+use crate::aggregate::{Accumulator, AsF64, scalar_heap_size};
+use crate::array::*;
+use crate::expr::cmp::SqlOrd;
+use crate::scalar::{Scalar, ScalarImpl, ScalarRef, ScalarRefImpl};
+
+/// Check that `fraction` is a valid parameter of percentiles.
+pub fn check_fraction(fraction: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&fraction) {
+        bail!("percentile value {fraction} is not between 0 and 1");
+    }
+    Ok(())
+}
+
+/// `percentile_cont(fraction) WITHIN GROUP (ORDER BY v)`, which interpolates between the values
+/// around position `fraction * (count - 1)` of the sorted values. `median(v)` is the same as
+/// `percentile_cont(0.5)`.
+#[derive(Clone)]
+pub struct PercentileCont {
+    fraction: f64,
+    values: Vec<f64>,
+}
+
+impl PercentileCont {
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction,
+            values: vec![],
+        }
+    }
+}
+
+impl<A: Array> Accumulator<A> for PercentileCont
+where
+    for<'a> A::RefItem<'a>: AsF64,
+{
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        self.values.push(v.as_f64());
+        Ok(())
+    }
+
+    fn merge(&mut self, mut other: Self) -> Result<()> {
+        self.values.append(&mut other.values);
+        Ok(())
+    }
+
+    fn estimated_heap_size(&self) -> usize {
+        self.values.capacity() * size_of::<f64>()
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        if self.values.is_empty() {
+            return Ok(None);
+        }
+        let mut values = self.values.clone();
+        let pos = self.fraction * (values.len() - 1) as f64;
+        let (lower, upper) = (pos.floor() as usize, pos.ceil() as usize);
+        let (_, lo, rest) = values.select_nth_unstable_by(lower, SqlOrd::sql_cmp);
+        let lo = *lo;
+        // The upper value is the smallest one after the lower one, if they differ.
+        let hi = match upper > lower {
+            true => *rest.iter().min_by(|a, b| a.sql_cmp(b)).unwrap(),
+            false => lo,
+        };
+        Ok(Some((lo + (hi - lo) * (pos - lower as f64)).into()))
+    }
+}
+
+/// `percentile_disc(fraction) WITHIN GROUP (ORDER BY v)`, which is the first of the sorted values
+/// whose position is not less than `fraction` of all values.
+pub struct PercentileDisc<A: Array> {
+    fraction: f64,
+    values: Vec<A::OwnedItem>,
+}
+
+impl<A: Array> Clone for PercentileDisc<A> {
+    fn clone(&self) -> Self {
+        Self {
+            fraction: self.fraction,
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<A: Array> PercentileDisc<A> {
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction,
+            values: vec![],
+        }
+    }
+}
+
+impl<A: Array> Accumulator<A> for PercentileDisc<A> {
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        self.values.push(v.to_owned_scalar());
+        Ok(())
+    }
+
+    fn merge(&mut self, mut other: Self) -> Result<()> {
+        self.values.append(&mut other.values);
+        Ok(())
+    }
+
+    fn estimated_heap_size(&self) -> usize {
+        let values = self
+            .values
+            .iter()
+            .map(|v| scalar_heap_size(v.as_scalar_ref().into()));
+        self.values.capacity() * size_of::<A::OwnedItem>() + values.sum::<usize>()
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        if self.values.is_empty() {
+            return Ok(None);
+        }
+        let n = self.values.len();
+        let nth = ((self.fraction * n as f64).ceil() as usize).clamp(1, n) - 1;
+        let mut indexes = (0..n).collect::<Vec<_>>();
+        let (_, nth, _) = indexes.select_nth_unstable_by(nth, |a, b| {
+            let a: ScalarRefImpl<'_> = self.values[*a].as_scalar_ref().into();
+            a.sql_cmp(&self.values[*b].as_scalar_ref().into())
+        });
+        Ok(Some(self.values[*nth].clone().into()))
+    }
+}
@@ -0,0 +1,255 @@
+//! Implements statistical aggregate functions: variances, standard deviations, covariances,
+//! correlation and regression slope.
+//!
+//! States keep the count, means and sums of squared deviations from the means, which are updated
+//! with Welford's algorithm and merged with the formulas of Chan et al. This is numerically
+//! stable, unlike keeping sums of squares, which loses precision when the mean is large
+//! compared to the deviation.
+
+use std::marker::PhantomData;
+
+use anyhow::Result;
+
+use crate::aggregate::{Accumulator, BinaryAccumulator};
+use crate::array::*;
+use crate::scalar::ScalarImpl;
+
+/// Numeric types converted to `double precision` for statistics.
+pub trait AsF64: Copy {
+    fn as_f64(self) -> f64;
+}
+
+macro_rules! impl_as_f64 {
+    ($($ty:ty),*) => {
+        $(
+            impl AsF64 for $ty {
+                fn as_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+impl_as_f64! { i16, i32, i64, f32, f64 }
+
+/// Count, mean and sum of squared deviations of one variable.
+#[derive(Default, Clone, Copy)]
+pub struct Moments {
+    pub count: f64,
+    pub mean: f64,
+    pub m2: f64,
+}
+
+impl Moments {
+    fn merge(&mut self, other: Moments) {
+        if other.count == 0.0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.count / count;
+        self.m2 += other.m2 + delta * delta * self.count * other.count / count;
+        self.count = count;
+    }
+}
+
+/// A statistic computed from [`Moments`].
+pub trait Statistic: Send + 'static {
+    fn compute(moments: &Moments) -> Option<f64>;
+}
+
+/// `var_pop(v)`, the population variance.
+pub struct VarPop;
+/// `var_samp(v)` or `variance(v)`, the sample variance.
+pub struct VarSamp;
+/// `stddev_pop(v)`, the population standard deviation.
+pub struct StddevPop;
+/// `stddev_samp(v)` or `stddev(v)`, the sample standard deviation.
+pub struct StddevSamp;
+
+impl Statistic for VarPop {
+    fn compute(m: &Moments) -> Option<f64> {
+        (m.count > 0.0).then(|| m.m2 / m.count)
+    }
+}
+
+impl Statistic for VarSamp {
+    fn compute(m: &Moments) -> Option<f64> {
+        (m.count > 1.0).then(|| m.m2 / (m.count - 1.0))
+    }
+}
+
+impl Statistic for StddevPop {
+    fn compute(m: &Moments) -> Option<f64> {
+        VarPop::compute(m).map(f64::sqrt)
+    }
+}
+
+impl Statistic for StddevSamp {
+    fn compute(m: &Moments) -> Option<f64> {
+        VarSamp::compute(m).map(f64::sqrt)
+    }
+}
+
+/// State of an aggregate computing statistic `S` of one variable.
+pub struct Univariate<S> {
+    moments: Moments,
+    _phantom: PhantomData<fn() -> S>,
+}
+
+impl<S> Clone for Univariate<S> {
+    fn clone(&self) -> Self {
+        Self {
+            moments: self.moments,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<S> Default for Univariate<S> {
+    fn default() -> Self {
+        Self {
+            moments: Moments::default(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A: Array, S: Statistic> Accumulator<A> for Univariate<S>
+where
+    for<'a> A::RefItem<'a>: AsF64,
+{
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        self.moments.merge(Moments {
+            count: 1.0,
+            mean: v.as_f64(),
+            m2: 0.0,
+        });
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        self.moments.merge(other.moments);
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(S::compute(&self.moments).map(Into::into))
+    }
+}
+
+/// Count, means, sums of squared deviations and the sum of co-deviations of two variables `y`
+/// and `x`.
+#[derive(Default, Clone, Copy)]
+pub struct CoMoments {
+    pub y: Moments,
+    pub x: Moments,
+    /// Sum of `(y - mean_y) * (x - mean_x)`.
+    pub c: f64,
+}
+
+impl CoMoments {
+    fn merge(&mut self, other: CoMoments) {
+        if other.x.count == 0.0 {
+            return;
+        }
+        let count = self.x.count + other.x.count;
+        let dy = other.y.mean - self.y.mean;
+        let dx = other.x.mean - self.x.mean;
+        self.c += other.c + dy * dx * self.x.count * other.x.count / count;
+        self.y.merge(other.y);
+        self.x.merge(other.x);
+    }
+}
+
+/// A statistic computed from [`CoMoments`].
+pub trait BivariateStatistic: Send + 'static {
+    fn compute(moments: &CoMoments) -> Option<f64>;
+}
+
+/// `covar_pop(y, x)`, the population covariance.
+pub struct CovarPop;
+/// `covar_samp(y, x)`, the sample covariance.
+pub struct CovarSamp;
+/// `corr(y, x)`, the correlation coefficient.
+pub struct Corr;
+/// `regr_slope(y, x)`, the slope of the least-squares-fit linear equation of `y` by `x`.
+pub struct RegrSlope;
+
+impl BivariateStatistic for CovarPop {
+    fn compute(m: &CoMoments) -> Option<f64> {
+        (m.x.count > 0.0).then(|| m.c / m.x.count)
+    }
+}
+
+impl BivariateStatistic for CovarSamp {
+    fn compute(m: &CoMoments) -> Option<f64> {
+        (m.x.count > 1.0).then(|| m.c / (m.x.count - 1.0))
+    }
+}
+
+impl BivariateStatistic for Corr {
+    fn compute(m: &CoMoments) -> Option<f64> {
+        (m.x.count > 0.0 && m.x.m2 != 0.0 && m.y.m2 != 0.0).then(|| m.c / (m.x.m2 * m.y.m2).sqrt())
+    }
+}
+
+impl BivariateStatistic for RegrSlope {
+    fn compute(m: &CoMoments) -> Option<f64> {
+        (m.x.count > 0.0 && m.x.m2 != 0.0).then(|| m.c / m.x.m2)
+    }
+}
+
+/// State of an aggregate computing statistic `S` of two variables.
+pub struct Bivariate<S> {
+    moments: CoMoments,
+    _phantom: PhantomData<fn() -> S>,
+}
+
+impl<S> Clone for Bivariate<S> {
+    fn clone(&self) -> Self {
+        Self {
+            moments: self.moments,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<S> Default for Bivariate<S> {
+    fn default() -> Self {
+        Self {
+            moments: CoMoments::default(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A1: Array, A2: Array, S: BivariateStatistic> BinaryAccumulator<A1, A2> for Bivariate<S>
+where
+    for<'a> A1::RefItem<'a>: AsF64,
+    for<'a> A2::RefItem<'a>: AsF64,
+{
+    fn accumulate(&mut self, y: A1::RefItem<'_>, x: A2::RefItem<'_>) -> Result<()> {
+        let single = |v| Moments {
+            count: 1.0,
+            mean: v,
+            m2: 0.0,
+        };
+        self.moments.merge(CoMoments {
+            y: single(y.as_f64()),
+            x: single(x.as_f64()),
+            c: 0.0,
+        });
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        self.moments.merge(other.moments);
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(S::compute(&self.moments).map(Into::into))
+    }
+}
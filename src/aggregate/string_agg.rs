@@ -0,0 +1,110 @@
+//! Implements `string_agg`.
+
+use anyhow::{Result, bail};
+
+use crate::aggregate::{AggState, AggregateFunction, downcast_state, into_state, state_ref};
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::scalar::ScalarImpl;
+
+/// `string_agg(value, delimiter)`, which concatenates non-null values with the delimiter of each
+/// value put before it, except the first one. `NULL` delimiters are treated as empty strings.
+pub struct StringAgg;
+
+/// State of [`StringAgg`], which keeps the delimiter of the first value as well, so that states
+/// can be concatenated when merged.
+#[derive(Default, Clone)]
+struct StringAggState {
+    /// Values with their delimiters, or `None` if there is no value yet.
+    buffer: Option<String>,
+    /// Length of the delimiter of the first value, which is removed from the result.
+    first_delimiter_len: usize,
+}
+
+impl StringAggState {
+    fn push(&mut self, value: &str, delimiter: &str) {
+        let buffer = self.buffer.get_or_insert_with(|| {
+            self.first_delimiter_len = delimiter.len();
+            String::new()
+        });
+        buffer.push_str(delimiter);
+        buffer.push_str(value);
+    }
+}
+
+impl AggregateFunction for StringAgg {
+    fn return_type(&self) -> DataType {
+        DataType::Varchar
+    }
+
+    fn create_state(&self) -> AggState {
+        Box::new(StringAggState::default())
+    }
+
+    fn update(&self, state: &mut AggState, args: &[&ArrayImpl], _: usize) -> Result<()> {
+        let [values, delimiters] = args else {
+            bail!("Expect two inputs for string_agg");
+        };
+        let values: &StringArray = (*values).try_into()?;
+        let delimiters: &StringArray = (*delimiters).try_into()?;
+        let state = downcast_state::<StringAggState>(state)?;
+        for (value, delimiter) in values.iter().zip(delimiters.iter()) {
+            if let Some(value) = value {
+                state.push(value, delimiter.unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+
+    fn update_grouped(
+        &self,
+        states: &mut [AggState],
+        groups: &[usize],
+        args: &[&ArrayImpl],
+    ) -> Result<()> {
+        let [values, delimiters] = args else {
+            bail!("Expect two inputs for string_agg");
+        };
+        let values: &StringArray = (*values).try_into()?;
+        let delimiters: &StringArray = (*delimiters).try_into()?;
+        for ((value, delimiter), group) in values.iter().zip(delimiters.iter()).zip(groups) {
+            if let Some(value) = value {
+                downcast_state::<StringAggState>(&mut states[*group])?
+                    .push(value, delimiter.unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut AggState, other: AggState) -> Result<()> {
+        let state = downcast_state::<StringAggState>(state)?;
+        let other = into_state::<StringAggState>(other)?;
+        match (&mut state.buffer, other.buffer) {
+            (Some(buffer), Some(other)) => buffer.push_str(&other),
+            (None, Some(buffer)) => {
+                state.buffer = Some(buffer);
+                state.first_delimiter_len = other.first_delimiter_len;
+            }
+            (_, None) => {}
+        }
+        Ok(())
+    }
+
+    fn clone_state(&self, state: &AggState) -> Result<AggState> {
+        Ok(Box::new(state_ref::<StringAggState>(state)?.clone()))
+    }
+
+    fn finish(&self, state: &AggState) -> Result<Option<ScalarImpl>> {
+        let state = state_ref::<StringAggState>(state)?;
+        Ok(state
+            .buffer
+            .as_ref()
+            .map(|buffer| buffer[state.first_delimiter_len..].to_string().into()))
+    }
+
+    fn estimated_state_size(&self, state: &AggState) -> Result<usize> {
+        let state = state_ref::<StringAggState>(state)?;
+        let buffer = state.buffer.as_ref().map_or(0, String::capacity);
+        Ok(size_of::<StringAggState>() + buffer)
+    }
+}
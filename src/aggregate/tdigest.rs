@@ -0,0 +1,205 @@
+//! Implements `approx_percentile` with t-digest.
+//!
+//! A t-digest summarizes values with a sorted list of centroids, each of which is the mean of
+//! some adjacent values with their count as the weight. Centroids near both ends hold fewer
+//! values than those in the middle, so that extreme percentiles are estimated accurately. The
+//! size of centroids is bounded by the scale function `k(q) = delta / (2 * pi) * asin(2q - 1)`:
+//! each centroid spans at most 1 in `k`, so there are at most about `delta` centroids.
+//!
+//! Digests are merged by combining their centroids and compressing them again, so partial
+//! digests built in parallel can be combined.
+
+use std::f64::consts::PI;
+
+use anyhow::Result;
+
+use crate::aggregate::{Accumulator, AsF64};
+use crate::array::*;
+use crate::scalar::ScalarImpl;
+
+/// The compression parameter `delta`.
+const COMPRESSION: f64 = 100.0;
+
+/// Number of values buffered before they are compressed into centroids.
+const BUFFER_SIZE: usize = 500;
+
+#[derive(Clone, Copy, Debug)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A t-digest over `double precision` values. `NaN`s are ignored.
+#[derive(Clone, Default)]
+pub struct TDigest {
+    /// Centroids sorted by mean.
+    centroids: Vec<Centroid>,
+    /// Values not compressed into centroids yet.
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+fn scale(q: f64) -> f64 {
+    COMPRESSION / (2.0 * PI) * (2.0 * q - 1.0).asin()
+}
+
+fn scale_inverse(k: f64) -> f64 {
+    if k >= COMPRESSION / 4.0 {
+        return 1.0;
+    }
+    ((2.0 * PI * k / COMPRESSION).sin() + 1.0) / 2.0
+}
+
+impl TDigest {
+    /// Number of bytes allocated for centroids and buffered values.
+    pub fn estimated_heap_size(&self) -> usize {
+        self.centroids.capacity() * size_of::<Centroid>()
+            + self.buffer.capacity() * size_of::<f64>()
+    }
+
+    /// Total weight of all values.
+    pub fn count(&self) -> f64 {
+        self.centroids.iter().map(|c| c.weight).sum::<f64>() + self.buffer.len() as f64
+    }
+
+    pub fn add(&mut self, v: f64) {
+        if v.is_nan() {
+            return;
+        }
+        if self.centroids.is_empty() && self.buffer.is_empty() {
+            (self.min, self.max) = (v, v);
+        } else {
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+        self.buffer.push(v);
+        if self.buffer.len() >= BUFFER_SIZE {
+            self.compress();
+        }
+    }
+
+    /// Add all values added to `other`.
+    pub fn merge(&mut self, other: &TDigest) {
+        if other.count() == 0.0 {
+            return;
+        }
+        if self.count() == 0.0 {
+            *self = other.clone();
+            return;
+        }
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.centroids.extend_from_slice(&other.centroids);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.compress();
+    }
+
+    /// Compress buffered values and all centroids into new centroids.
+    fn compress(&mut self) {
+        let mut all = std::mem::take(&mut self.centroids);
+        all.extend(
+            self.buffer
+                .drain(..)
+                .map(|mean| Centroid { mean, weight: 1.0 }),
+        );
+        if all.is_empty() {
+            return;
+        }
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total = all.iter().map(|c| c.weight).sum::<f64>();
+
+        let mut merged = Vec::with_capacity(COMPRESSION as usize);
+        let mut current = all[0];
+        // Weight of values before `current`.
+        let mut before = 0.0;
+        let mut limit = total * scale_inverse(scale(0.0) + 1.0);
+        for next in &all[1..] {
+            if before + current.weight + next.weight <= limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                before += current.weight;
+                merged.push(current);
+                limit = total * scale_inverse(scale(before / total) + 1.0);
+                current = *next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// Estimate the value at `fraction` of all values, or `None` if there is no value.
+    pub fn quantile(&self, fraction: f64) -> Option<f64> {
+        let mut digest = self.clone();
+        digest.compress();
+        let centroids = &digest.centroids;
+        let (first, last) = (centroids.first()?, centroids.last()?);
+        if centroids.len() == 1 {
+            return Some(first.mean);
+        }
+        let total = digest.count();
+        let target = fraction * total;
+        // Each centroid is placed at the middle of the values it holds, and values between
+        // centroids are interpolated.
+        if target < first.weight / 2.0 {
+            let t = target / (first.weight / 2.0);
+            return Some(digest.min + (first.mean - digest.min) * t);
+        }
+        if target > total - last.weight / 2.0 {
+            let t = (target - (total - last.weight / 2.0)) / (last.weight / 2.0);
+            return Some(last.mean + (digest.max - last.mean) * t);
+        }
+        let mut center = first.weight / 2.0;
+        for pair in centroids.windows(2) {
+            let next_center = center + (pair[0].weight + pair[1].weight) / 2.0;
+            if target <= next_center {
+                let t = (target - center) / (next_center - center);
+                return Some(pair[0].mean + (pair[1].mean - pair[0].mean) * t);
+            }
+            center = next_center;
+        }
+        Some(last.mean)
+    }
+}
+
+/// `approx_percentile(fraction) WITHIN GROUP (ORDER BY v)`, which estimates
+/// `percentile_cont(fraction)` with a [`TDigest`].
+#[derive(Clone)]
+pub struct ApproxPercentile {
+    fraction: f64,
+    digest: TDigest,
+}
+
+impl ApproxPercentile {
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction,
+            digest: TDigest::default(),
+        }
+    }
+}
+
+impl<A: Array> Accumulator<A> for ApproxPercentile
+where
+    for<'a> A::RefItem<'a>: AsF64,
+{
+    fn accumulate(&mut self, v: A::RefItem<'_>) -> Result<()> {
+        self.digest.add(v.as_f64());
+        Ok(())
+    }
+
+    fn merge(&mut self, other: Self) -> Result<()> {
+        self.digest.merge(&other.digest);
+        Ok(())
+    }
+
+    fn estimated_heap_size(&self) -> usize {
+        self.digest.estimated_heap_size()
+    }
+
+    fn finish(&self) -> Result<Option<ScalarImpl>> {
+        Ok(self.digest.quantile(self.fraction).map(Into::into))
+    }
+}
@@ -0,0 +1,250 @@
+//! Implements [`Batch`], a set of columns with the same number of rows, and [`BatchBuilder`],
+//! which builds batches row by row.
+
+use anyhow::{Result, bail};
+
+use crate::array::{ArrayBuilderImpl, ArrayImpl};
+use crate::dataType::DataType;
+use crate::scalar::ScalarRefImpl;
+
+/// A batch of rows stored column by column, which is the unit of data passed between
+/// operators.
+#[derive(Clone)]
+pub struct Batch {
+    columns: Vec<ArrayImpl>,
+    num_rows: usize,
+}
+
+impl Batch {
+    /// Create a batch from columns, which must have the same length. Use [`Batch::no_columns`]
+    /// for a batch without columns.
+    pub fn new(columns: Vec<ArrayImpl>) -> Result<Self> {
+        let Some(num_rows) = columns.first().map(|column| column.len()) else {
+            bail!("cannot create a batch without columns, use `Batch::no_columns` instead");
+        };
+        if let Some(column) = columns.iter().find(|column| column.len() != num_rows) {
+            bail!(
+                "columns of a batch must have the same length, expect {num_rows}, get {}",
+                column.len()
+            );
+        }
+        Ok(Self { columns, num_rows })
+    }
+
+    /// Create a batch with `num_rows` rows but no columns, like the input of `SELECT count(*)`.
+    pub fn no_columns(num_rows: usize) -> Self {
+        Self {
+            columns: vec![],
+            num_rows,
+        }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_rows == 0
+    }
+
+    pub fn columns(&self) -> &[ArrayImpl] {
+        &self.columns
+    }
+
+    pub fn column(&self, idx: usize) -> &ArrayImpl {
+        &self.columns[idx]
+    }
+
+    /// Get references to all columns, which is the input of
+    /// [`Expression::eval_expr`](crate::expr::Expression::eval_expr).
+    pub fn column_refs(&self) -> Vec<&ArrayImpl> {
+        self.columns.iter().collect()
+    }
+
+    pub fn into_columns(self) -> Vec<ArrayImpl> {
+        self.columns
+    }
+
+    /// Estimated number of bytes used by the batch.
+    pub fn estimated_size(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|column| column.estimated_size())
+            .sum()
+    }
+
+    /// Get a batch with only the rows where `visibility` is `true`.
+    pub fn filter(&self, visibility: &[bool]) -> Self {
+        assert_eq!(
+            visibility.len(),
+            self.num_rows,
+            "visibility length mismatch"
+        );
+        Self {
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.filter(visibility))
+                .collect(),
+            num_rows: visibility.iter().filter(|v| **v).count(),
+        }
+    }
+
+    /// Get a batch with the rows at `indices`, in the order of `indices`.
+    pub fn take(&self, indices: &[usize]) -> Self {
+        Self {
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.take(indices))
+                .collect(),
+            num_rows: indices.len(),
+        }
+    }
+
+    /// Get a batch with the rows at `indices`, in the order of `indices`, where `None` is a row of
+    /// `NULL`s.
+    pub fn take_nullable(&self, indices: &[Option<usize>]) -> Self {
+        Self {
+            columns: self
+                .columns
+                .iter()
+                .map(|column| column.take_nullable(indices))
+                .collect(),
+            num_rows: indices.len(),
+        }
+    }
+
+    /// Concatenate the rows of `batches`, whose columns are of `types`.
+    pub fn concat(types: &[DataType], batches: &[Batch]) -> Result<Self> {
+        let num_rows = batches.iter().map(|batch| batch.num_rows).sum();
+        let mut builders = create_builders(types, num_rows)?;
+        for batch in batches {
+            if batch.columns.len() != types.len() {
+                bail!(
+                    "expect {} columns in a batch, get {}",
+                    types.len(),
+                    batch.columns.len()
+                );
+            }
+            for (builder, column) in builders.iter_mut().zip(&batch.columns) {
+                for row in 0..batch.num_rows {
+                    builder.push(column.get(row));
+                }
+            }
+        }
+        let columns = builders.into_iter().map(|b| b.finish()).collect::<Vec<_>>();
+        match columns.is_empty() {
+            true => Ok(Self::no_columns(num_rows)),
+            false => Self::new(columns),
+        }
+    }
+
+    /// Get a batch of columns of `types` with `num_rows` rows of `NULL`s.
+    pub fn nulls(types: &[DataType], num_rows: usize) -> Result<Self> {
+        let mut builders = create_builders(types, num_rows)?;
+        for builder in &mut builders {
+            for _ in 0..num_rows {
+                builder.push(None);
+            }
+        }
+        let columns = builders.into_iter().map(|b| b.finish()).collect::<Vec<_>>();
+        match columns.is_empty() {
+            true => Ok(Self::no_columns(num_rows)),
+            false => Self::new(columns),
+        }
+    }
+
+    /// Get a batch with the columns of `self` followed by the columns of `other`, which must have
+    /// the same number of rows.
+    pub fn zip(self, other: Batch) -> Result<Self> {
+        if self.num_rows != other.num_rows {
+            bail!(
+                "batches must have the same number of rows, get {} and {}",
+                self.num_rows,
+                other.num_rows
+            );
+        }
+        let mut columns = self.columns;
+        columns.extend(other.columns);
+        Ok(Self {
+            columns,
+            num_rows: self.num_rows,
+        })
+    }
+}
+
+/// Builds batches of at most `batch_size` rows by appending rows one by one, which is used by
+/// operators producing rows gathered from their inputs, like joins.
+pub struct BatchBuilder {
+    types: Vec<DataType>,
+    builders: Vec<ArrayBuilderImpl>,
+    num_rows: usize,
+    batch_size: usize,
+}
+
+impl BatchBuilder {
+    /// Create a builder of batches of columns of `types`.
+    pub fn new(types: Vec<DataType>, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            bail!("batch size must be positive");
+        }
+        let builders = create_builders(&types, batch_size)?;
+        Ok(Self {
+            types,
+            builders,
+            num_rows: 0,
+            batch_size,
+        })
+    }
+
+    /// Append a row of `values`, one for each column. Returns a batch when it is full.
+    pub fn push_row<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = Option<ScalarRefImpl<'a>>>,
+    ) -> Result<Option<Batch>> {
+        let mut values = values.into_iter();
+        for builder in &mut self.builders {
+            let Some(value) = values.next() else {
+                bail!("expect {} values in a row", self.types.len());
+            };
+            builder.push(value);
+        }
+        if values.next().is_some() {
+            bail!("expect {} values in a row", self.types.len());
+        }
+        self.num_rows += 1;
+        match self.num_rows == self.batch_size {
+            true => self.take().map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// Take the rows appended so far as a batch, if there is any.
+    pub fn finish(mut self) -> Result<Option<Batch>> {
+        match self.num_rows {
+            0 => Ok(None),
+            _ => self.take().map(Some),
+        }
+    }
+
+    fn take(&mut self) -> Result<Batch> {
+        let builders = std::mem::replace(
+            &mut self.builders,
+            create_builders(&self.types, self.batch_size)?,
+        );
+        let num_rows = std::mem::take(&mut self.num_rows);
+        let columns = builders.into_iter().map(|b| b.finish()).collect::<Vec<_>>();
+        match columns.is_empty() {
+            true => Ok(Batch::no_columns(num_rows)),
+            false => Batch::new(columns),
+        }
+    }
+}
+
+fn create_builders(types: &[DataType], capacity: usize) -> Result<Vec<ArrayBuilderImpl>> {
+    types
+        .iter()
+        .map(|ty| ty.create_array_builder(capacity))
+        .collect()
+}
@@ -44,6 +44,65 @@ macro_rules! impl_array_dispatch {
                     )*
                 }
             }
+
+            /// Estimated number of bytes used by the array, which is used to limit memory usage.
+            pub fn estimated_size(&self) -> usize {
+                match self {
+                    $(
+                        Self::$Abc(a) => a.estimated_size(),
+                    )*
+                }
+            }
+
+            /// Get a new array with only the rows where `visibility` is `true`.
+            pub fn filter(&self, visibility: &[bool]) -> ArrayImpl {
+                assert_eq!(self.len(), visibility.len(), "array length mismatch");
+                match self {
+                    $(
+                        Self::$Abc(a) => {
+                            let len = visibility.iter().filter(|v| **v).count();
+                            let mut builder = <$AbcArrayBuilder>::with_capacity(len);
+                            for (value, visible) in a.iter().zip(visibility) {
+                                if *visible {
+                                    builder.push(value);
+                                }
+                            }
+                            builder.finish().into()
+                        }
+                    )*
+                }
+            }
+
+            /// Get a new array with the rows at `indices`, in that order.
+            pub fn take(&self, indices: &[usize]) -> ArrayImpl {
+                match self {
+                    $(
+                        Self::$Abc(a) => {
+                            let mut builder = <$AbcArrayBuilder>::with_capacity(indices.len());
+                            for idx in indices {
+                                builder.push(a.get(*idx));
+                            }
+                            builder.finish().into()
+                        }
+                    )*
+                }
+            }
+
+            /// Get a new array with the rows at `indices`, in that order, where `None` is a
+            /// `NULL`.
+            pub fn take_nullable(&self, indices: &[Option<usize>]) -> ArrayImpl {
+                match self {
+                    $(
+                        Self::$Abc(a) => {
+                            let mut builder = <$AbcArrayBuilder>::with_capacity(indices.len());
+                            for idx in indices {
+                                builder.push(idx.and_then(|idx| a.get(idx)));
+                            }
+                            builder.finish().into()
+                        }
+                    )*
+                }
+            }
         }
     }
 }
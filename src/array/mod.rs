@@ -7,12 +7,14 @@
 
 pub use crate::{
     array::{
+        batch::{Batch, BatchBuilder},
         iterator::ArrayIterator,
         primitive_array::*,
-        string_array::{StringArray, StringArrayBuilder},
+        string_array::{StringArray, StringArrayBuilder, StringWriter},
     },
     scalar::{Scalar, ScalarRef},
 };
+pub mod batch;
 pub mod impls;
 pub mod iterator;
 pub mod primitive_array;
@@ -71,6 +73,7 @@ pub trait ArrayBuilder {
 }
 
 /// Encapsules all variants of array in this library.
+#[derive(Clone)]
 pub enum ArrayImpl {
     Int16(I16Array),
     Int32(I32Array),
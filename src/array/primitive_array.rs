@@ -46,6 +46,7 @@ impl PrimitiveType for bool {}
 ///
 /// We store the bitmap apart from data, so as to reduce memory footprint compared with
 /// `Vec<Option<T>>`
+#[derive(Clone)]
 pub struct PrimitiveArray<T: PrimitiveType> {
     /// The actual data of this array.
     data: Vec<T>,
@@ -87,6 +88,13 @@ where
     }
 }
 
+impl<T: PrimitiveType> PrimitiveArray<T> {
+    /// Estimated number of bytes used by the array.
+    pub fn estimated_size(&self) -> usize {
+        self.data.len() * std::mem::size_of::<T>() + self.bitmap.len().div_ceil(8)
+    }
+}
+
 /// [`ArrayBuilder`] for [`PrimitiveType`]
 pub struct PrimitiveArrayBuilder<T: PrimitiveType> {
     /// The actual data of this array.
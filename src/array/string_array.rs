@@ -11,6 +11,7 @@ use bitvec::vec::BitVec;
 use crate::array::{Array, ArrayBuilder, iterator::ArrayIterator};
 
 /// An [`Array`] that stores [`String`]
+#[derive(Clone)]
 pub struct StringArray {
     /// The flattened data of string.
     data: Vec<u8>,
@@ -46,6 +47,15 @@ impl Array for StringArray {
     }
 }
 
+impl StringArray {
+    /// Estimated number of bytes used by the array.
+    pub fn estimated_size(&self) -> usize {
+        self.data.len()
+            + self.offset.len() * std::mem::size_of::<usize>()
+            + self.bitmap.len().div_ceil(8)
+    }
+}
+
 /// [`ArrayBuilder`] for [`String`]
 pub struct StringArrayBuilder {
     /// The flattened data of string.
@@ -91,3 +101,78 @@ impl ArrayBuilder for StringArrayBuilder {
         }
     }
 }
+
+impl StringArrayBuilder {
+    /// Get a [`StringWriter`] which writes the next value directly into the buffer of this
+    /// builder.
+    pub fn writer(&mut self) -> StringWriter<'_> {
+        StringWriter { builder: self }
+    }
+}
+
+/// Writes a string value piece by piece directly into the buffer of a [`StringArrayBuilder`],
+/// so that the value is copied only once.
+///
+/// The value is appended to the builder after [`StringWriter::finish`]. If the writer is dropped
+/// without finishing, everything written is discarded.
+pub struct StringWriter<'a> {
+    builder: &'a mut StringArrayBuilder,
+}
+
+impl StringWriter<'_> {
+    /// Append `s` to the current value.
+    pub fn write_str(&mut self, s: &str) {
+        self.builder.data.extend_from_slice(s.as_bytes());
+    }
+
+    /// Append `c` to the current value.
+    pub fn write_char(&mut self, c: char) {
+        let mut buf = [0; 4];
+        self.write_str(c.encode_utf8(&mut buf));
+    }
+
+    /// Get the current value written so far.
+    pub fn as_str(&self) -> &str {
+        let committed = *self.builder.offsets.last().unwrap();
+        // SAFETY: only complete `str`s are written after the last committed offset.
+        unsafe { std::str::from_utf8_unchecked(&self.builder.data[committed..]) }
+    }
+
+    /// Shorten the current value to `len` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` does not lie on a `char` boundary of the current value.
+    pub fn truncate(&mut self, len: usize) {
+        assert!(self.as_str().is_char_boundary(len), "not a char boundary");
+        let committed = *self.builder.offsets.last().unwrap();
+        self.builder.data.truncate(committed + len);
+    }
+
+    /// Finish the current value and append it to the builder.
+    pub fn finish(self) {
+        self.builder.offsets.push(self.builder.data.len());
+        self.builder.bitmap.push(true);
+    }
+}
+
+impl std::fmt::Write for StringWriter<'_> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        StringWriter::write_str(self, s);
+        Ok(())
+    }
+
+    fn write_char(&mut self, c: char) -> std::fmt::Result {
+        StringWriter::write_char(self, c);
+        Ok(())
+    }
+}
+
+/// Discards the unfinished value. After [`StringWriter::finish`], the last offset is already the
+/// end of the buffer, so nothing is discarded.
+impl Drop for StringWriter<'_> {
+    fn drop(&mut self) {
+        let committed = *self.builder.offsets.last().unwrap();
+        self.builder.data.truncate(committed);
+    }
+}
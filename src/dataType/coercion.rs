@@ -0,0 +1,142 @@
+//! Implements implicit type coercion rules.
+//!
+//! Numeric types form a ladder `smallint < integer < bigint < decimal < real < double
+//! precision`, where each type can be implicitly cast into the types above it, except that
+//! `integer` and `bigint` are only cast into `double precision`, as `real` cannot hold all their
+//! values. `char(n)` can be implicitly cast into `varchar`. Other types can only be cast
+//! explicitly.
+
+use thiserror::Error;
+
+use crate::dataType::DataType;
+
+/// Errors of finding a common supertype.
+#[derive(Error, Debug)]
+pub enum CoercionError {
+    /// Two types do not have a common supertype.
+    #[error("types {0} and {1} cannot be matched")]
+    Incompatible(DataType, DataType),
+    /// The common supertype cannot be built into an array, e.g. `decimal`.
+    #[error("common type {0} does not have a physical array yet")]
+    Unsupported(DataType),
+}
+
+/// Max precision of `decimal`.
+const MAX_DECIMAL_PRECISION: u16 = 38;
+
+impl DataType {
+    /// Rank of this type in the numeric ladder, or `None` if it is not numeric.
+    fn numeric_rank(&self) -> Option<u32> {
+        match self {
+            Self::SmallInt => Some(0),
+            Self::Integer => Some(1),
+            Self::BigInt => Some(2),
+            Self::Decimal { .. } => Some(3),
+            Self::Real => Some(4),
+            Self::Double => Some(5),
+            _ => None,
+        }
+    }
+
+    /// Number of digits before the decimal point that this type may hold. Only valid for
+    /// integers and decimals.
+    fn integer_digits(&self) -> u16 {
+        match self {
+            Self::SmallInt => 5,
+            Self::Integer => 10,
+            Self::BigInt => 19,
+            Self::Decimal { scale, precision } => precision.saturating_sub(*scale),
+            _ => unreachable!("{self} is not an exact numeric type"),
+        }
+    }
+
+    fn scale(&self) -> u16 {
+        match self {
+            Self::Decimal { scale, .. } => *scale,
+            _ => 0,
+        }
+    }
+
+    /// Whether this type is an integer or a decimal.
+    fn is_exact_numeric(&self) -> bool {
+        matches!(self.numeric_rank(), Some(0..=3))
+    }
+
+    /// Whether this type is numeric.
+    pub fn is_numeric(&self) -> bool {
+        self.numeric_rank().is_some()
+    }
+
+    /// Whether this type is a string type, i.e. `varchar` or `char(n)`.
+    pub fn is_string(&self) -> bool {
+        matches!(self, Self::Varchar | Self::Char { .. })
+    }
+
+    /// Cost of implicitly casting `self` into `to`, or `None` if the cast is not allowed. A
+    /// smaller cost means the types are closer, and `0` means no cast is needed.
+    pub fn implicit_cast_cost(&self, to: &DataType) -> Option<u32> {
+        match (self, to) {
+            _ if self == to => Some(0),
+            (Self::Decimal { .. }, Self::Decimal { .. }) => Some(0),
+            (Self::Char { .. }, Self::Varchar) => Some(1),
+            (Self::Integer | Self::BigInt, Self::Real) => None,
+            _ => {
+                let (from, to) = (self.numeric_rank()?, to.numeric_rank()?);
+                (from < to).then(|| to - from)
+            }
+        }
+    }
+
+    /// Whether `self` can be implicitly cast into `to`.
+    pub fn can_implicit_cast(&self, to: &DataType) -> bool {
+        self.implicit_cast_cost(to).is_some()
+    }
+
+    /// Compute the smallest type that both `self` and `other` can be implicitly cast into.
+    ///
+    /// Fails if the supertype is a `decimal`, as values must be cast into the supertype, but
+    /// decimals do not have a physical array yet.
+    pub fn common_supertype(&self, other: &DataType) -> Result<DataType, CoercionError> {
+        let incompatible = || CoercionError::Incompatible(*self, *other);
+        let ty = match (self, other) {
+            _ if self == other => *self,
+            (Self::Char { .. } | Self::Varchar, Self::Char { .. } | Self::Varchar) => Self::Varchar,
+            (Self::Decimal { .. }, _) | (_, Self::Decimal { .. })
+                if self.is_exact_numeric() && other.is_exact_numeric() =>
+            {
+                let scale = self.scale().max(other.scale());
+                let integer_digits = self.integer_digits().max(other.integer_digits());
+                Self::Decimal {
+                    scale,
+                    precision: (integer_digits + scale).min(MAX_DECIMAL_PRECISION),
+                }
+            }
+            (Self::Integer | Self::BigInt, Self::Real)
+            | (Self::Real, Self::Integer | Self::BigInt) => Self::Double,
+            _ => {
+                let from = self.numeric_rank().ok_or_else(incompatible)?;
+                let to = other.numeric_rank().ok_or_else(incompatible)?;
+                if from < to { *other } else { *self }
+            }
+        };
+        if let Self::Decimal { .. } = ty {
+            return Err(CoercionError::Unsupported(ty));
+        }
+        Ok(ty)
+    }
+}
+
+/// Compute the smallest type that all of `types` can be implicitly cast into, e.g. for the
+/// branches of `CASE` or the arguments of `COALESCE`. Returns `None` if `types` is empty.
+pub fn common_supertype_of<'a>(
+    types: impl IntoIterator<Item = &'a DataType>,
+) -> Result<Option<DataType>, CoercionError> {
+    let mut result: Option<DataType> = None;
+    for ty in types {
+        result = Some(match result {
+            Some(result) => result.common_supertype(ty)?,
+            None => ty.common_supertype(ty)?,
+        });
+    }
+    Ok(result)
+}
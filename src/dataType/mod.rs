@@ -1,7 +1,17 @@
 //! Implements logical types for a database system.
 
+pub mod coercion;
 pub(crate) mod macros;
 
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::array::*;
+use crate::dataType::macros::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DataType {
     /// Corresponding to Int16 physical type.
     SmallInt,
@@ -22,3 +32,126 @@ pub enum DataType {
     /// Corresponding to Decimal physical type.
     Decimal { scale: u16, precision: u16 },
 }
+
+/// Implements dispatch functions for [`DataType`] with the association information in
+/// [`macros`].
+macro_rules! impl_data_type_dispatch {
+    ($( $ty:ident ),*) => {
+        impl DataType {
+            /// Create an empty [`ArrayBuilderImpl`] which holds values of this type.
+            pub fn create_array_builder(&self, capacity: usize) -> Result<ArrayBuilderImpl> {
+                match self {
+                    $(
+                        $ty! { datatype_match_pattern } => Ok(
+                            <$ty! { datatype_array } as Array>::Builder::with_capacity(capacity).into()
+                        ),
+                    )*
+                    decimal! { datatype_match_pattern } => {
+                        bail!("type {} does not have a physical array yet", self)
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_data_type_dispatch! { boolean, int16, int32, int64, float32, float64, varchar, fwchar }
+
+impl DataType {
+    /// Identifier of the physical array holding values of this type, which is the same as
+    /// [`ArrayImpl::identifier`].
+    pub fn physical_identifier(&self) -> &'static str {
+        match self {
+            Self::SmallInt => "Int16",
+            Self::Integer => "Int32",
+            Self::BigInt => "Int64",
+            Self::Real => "Float32",
+            Self::Double => "Float64",
+            Self::Boolean => "Bool",
+            Self::Varchar | Self::Char { .. } => "String",
+            Self::Decimal { .. } => "Decimal",
+        }
+    }
+}
+
+impl ArrayImpl {
+    /// Get the default logical type of the current array.
+    ///
+    /// As several logical types share the same physical array, e.g. `varchar` and `char(n)`,
+    /// this always returns the most general one.
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Self::Int16(_) => DataType::SmallInt,
+            Self::Int32(_) => DataType::Integer,
+            Self::Int64(_) => DataType::BigInt,
+            Self::Float32(_) => DataType::Real,
+            Self::Float64(_) => DataType::Double,
+            Self::Bool(_) => DataType::Boolean,
+            Self::String(_) => DataType::Varchar,
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SmallInt => write!(f, "smallint"),
+            Self::Integer => write!(f, "integer"),
+            Self::BigInt => write!(f, "bigint"),
+            Self::Varchar => write!(f, "varchar"),
+            Self::Char { width } => write!(f, "char({width})"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::Real => write!(f, "real"),
+            Self::Double => write!(f, "double precision"),
+            Self::Decimal { scale, precision } => write!(f, "decimal({precision}, {scale})"),
+        }
+    }
+}
+
+/// Parse a type name. Both SQL names (e.g. `integer`, `double precision`) and the physical
+/// names used in function signatures (e.g. `int32`, `float64`) are accepted.
+impl FromStr for DataType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        let (name, params) = match s.split_once('(') {
+            Some((name, rest)) => {
+                let params = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow!("unterminated type modifier in \"{s}\""))?
+                    .split(',')
+                    .map(|p| p.trim().parse::<u16>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| anyhow!("invalid type modifier in \"{s}\""))?;
+                (name.trim(), params)
+            }
+            None => (s.as_str(), vec![]),
+        };
+        let ty = match (name, params.as_slice()) {
+            ("smallint" | "int2" | "int16", []) => Self::SmallInt,
+            ("integer" | "int" | "int4" | "int32", []) => Self::Integer,
+            ("bigint" | "int8" | "int64", []) => Self::BigInt,
+            ("real" | "float4" | "float32", []) => Self::Real,
+            ("double precision" | "double" | "float8" | "float64", []) => Self::Double,
+            ("boolean" | "bool", []) => Self::Boolean,
+            ("varchar" | "text" | "string", _) => Self::Varchar,
+            ("char" | "character", []) => Self::Char { width: 1 },
+            ("char" | "character", [width]) => Self::Char { width: *width },
+            ("decimal" | "numeric", []) => Self::Decimal {
+                scale: 0,
+                precision: 38,
+            },
+            ("decimal" | "numeric", [precision]) => Self::Decimal {
+                scale: 0,
+                precision: *precision,
+            },
+            ("decimal" | "numeric", [precision, scale]) => Self::Decimal {
+                scale: *scale,
+                precision: *precision,
+            },
+            _ => bail!("type \"{s}\" does not exist"),
+        };
+        Ok(ty)
+    }
+}
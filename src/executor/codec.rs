@@ -0,0 +1,95 @@
+//! Encodes values into bytes, which is shared by keys of
+//! [`GroupTable`](crate::executor::group_table::GroupTable) and
+//! [spill files](crate::executor::spill). Each value is encoded as a tag byte of `0` for `NULL` and
+//! `1` otherwise, followed by:
+//!
+//! * Integers and booleans: bytes in little-endian order.
+//! * Floats: bytes of the bits in little-endian order.
+//! * Strings: the length as a little-endian `u32`, followed by the bytes.
+//!
+//! When floats are normalized, all `NaN`s and `-0.0` are encoded as `NaN` and `0.0`, so that
+//! values equal in SQL are encoded into the same bytes. Otherwise values are encoded exactly.
+
+use anyhow::{Result, bail};
+
+use crate::scalar::ScalarRefImpl;
+
+/// Append `value` to `buf`, normalizing floats if `normalize`.
+pub(super) fn encode_value(
+    value: Option<ScalarRefImpl<'_>>,
+    normalize: bool,
+    buf: &mut Vec<u8>,
+) -> Result<()> {
+    let Some(value) = value else {
+        buf.push(0);
+        return Ok(());
+    };
+    buf.push(1);
+    match value {
+        ScalarRefImpl::Int16(v) => buf.extend_from_slice(&v.to_le_bytes()),
+        ScalarRefImpl::Int32(v) => buf.extend_from_slice(&v.to_le_bytes()),
+        ScalarRefImpl::Int64(v) => buf.extend_from_slice(&v.to_le_bytes()),
+        ScalarRefImpl::Float32(v) => {
+            let v = match normalize {
+                true if v.is_nan() => f32::NAN,
+                true => v + 0.0,
+                false => v,
+            };
+            buf.extend_from_slice(&v.to_bits().to_le_bytes())
+        }
+        ScalarRefImpl::Float64(v) => {
+            let v = match normalize {
+                true if v.is_nan() => f64::NAN,
+                true => v + 0.0,
+                false => v,
+            };
+            buf.extend_from_slice(&v.to_bits().to_le_bytes())
+        }
+        ScalarRefImpl::Bool(v) => buf.push(v as u8),
+        ScalarRefImpl::String(v) => {
+            let Ok(len) = u32::try_from(v.len()) else {
+                bail!("string of {} bytes is too long to encode", v.len());
+            };
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf.extend_from_slice(v.as_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn take<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N]> {
+    let Some((bytes, rest)) = buf.split_first_chunk::<N>() else {
+        bail!("unexpected end of encoded values");
+    };
+    *buf = rest;
+    Ok(*bytes)
+}
+
+/// Decode a value of physical type `identifier` from the start of `buf`, and advance `buf` past
+/// it. Strings are borrowed from `buf`.
+pub(super) fn decode_value<'a>(
+    identifier: &str,
+    buf: &mut &'a [u8],
+) -> Result<Option<ScalarRefImpl<'a>>> {
+    if take::<1>(buf)? == [0] {
+        return Ok(None);
+    }
+    Ok(Some(match identifier {
+        "Int16" => ScalarRefImpl::Int16(i16::from_le_bytes(take(buf)?)),
+        "Int32" => ScalarRefImpl::Int32(i32::from_le_bytes(take(buf)?)),
+        "Int64" => ScalarRefImpl::Int64(i64::from_le_bytes(take(buf)?)),
+        "Float32" => ScalarRefImpl::Float32(f32::from_bits(u32::from_le_bytes(take(buf)?))),
+        "Float64" => ScalarRefImpl::Float64(f64::from_bits(u64::from_le_bytes(take(buf)?))),
+        "Bool" => ScalarRefImpl::Bool(take::<1>(buf)? != [0]),
+        "String" => {
+            let len = u32::from_le_bytes(take(buf)?) as usize;
+            if buf.len() < len {
+                bail!("unexpected end of encoded values");
+            }
+            let (bytes, rest) = buf.split_at(len);
+            *buf = rest;
+            ScalarRefImpl::String(std::str::from_utf8(bytes)?)
+        }
+        other => bail!("cannot decode values of physical type {other}"),
+    }))
+}
@@ -0,0 +1,203 @@
+//! Implements [`GroupTable`], a hash table from serialized keys to group ids.
+//!
+//! Keys of many columns are serialized into bytes with [`KeySerializer`], so that they can be
+//! hashed and compared without dispatching on the types of columns. Values are encoded one after
+//! another like in spill files, except that all `NaN`s and `-0.0` are normalized as `NaN` and
+//! `0.0`, so that values equal in SQL are serialized into the same bytes.
+//!
+//! `NULL`s are equal to each other in keys, as `GROUP BY` puts all `NULL`s into one group.
+
+use std::hash::{BuildHasher, RandomState};
+
+use anyhow::{Result, bail};
+
+use crate::array::{ArrayBuilderImpl, ArrayImpl};
+use crate::dataType::DataType;
+use crate::executor::codec::{decode_value, encode_value};
+
+/// Serializes keys of rows of the given columns into bytes.
+pub struct KeySerializer<'a> {
+    columns: &'a [&'a ArrayImpl],
+}
+
+impl<'a> KeySerializer<'a> {
+    pub fn new(columns: &'a [&'a ArrayImpl]) -> Self {
+        Self { columns }
+    }
+
+    /// Append the key of `row` to `buf`.
+    pub fn serialize(&self, row: usize, buf: &mut Vec<u8>) -> Result<()> {
+        for column in self.columns {
+            encode_value(column.get(row), true, buf)?;
+        }
+        Ok(())
+    }
+
+    /// Check if any key of `row` is `NULL`. Such keys never match any key in joins, unlike in
+    /// `GROUP BY`.
+    pub fn has_null(&self, row: usize) -> bool {
+        self.columns.iter().any(|column| column.get(row).is_none())
+    }
+}
+
+/// Deserializes keys into columns of the given types.
+pub struct KeyDeserializer {
+    builders: Vec<ArrayBuilderImpl>,
+}
+
+impl KeyDeserializer {
+    pub fn new(types: &[DataType], capacity: usize) -> Result<Self> {
+        let builders = types
+            .iter()
+            .map(|ty| ty.create_array_builder(capacity))
+            .collect::<Result<_>>()?;
+        Ok(Self { builders })
+    }
+
+    /// Deserialize a key and append its values to the columns.
+    pub fn deserialize(&mut self, mut key: &[u8]) -> Result<()> {
+        for builder in &mut self.builders {
+            let value = decode_value(builder.identifier(), &mut key)?;
+            builder.push(value);
+        }
+        if !key.is_empty() {
+            bail!("{} trailing bytes after the key", key.len());
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Vec<ArrayImpl> {
+        self.builders.into_iter().map(|b| b.finish()).collect()
+    }
+}
+
+/// Marks an empty slot of [`GroupTable`].
+const EMPTY: u32 = u32::MAX;
+
+/// A hash table assigning ids to distinct keys in the order they are inserted.
+///
+/// Keys are stored back to back in one buffer. The table itself is open-addressed with linear
+/// probing, and each slot only holds a group id. Hashes of keys are kept aside, so that growing
+/// the table does not hash the keys again.
+pub struct GroupTable {
+    /// Keys of all groups, concatenated.
+    keys: Vec<u8>,
+    /// Key of group `i` is `keys[offsets[i]..offsets[i + 1]]`.
+    offsets: Vec<usize>,
+    /// Hash of the key of each group.
+    hashes: Vec<u64>,
+    /// Group ids, or [`EMPTY`]. The length is a power of two.
+    slots: Vec<u32>,
+    hash_builder: RandomState,
+}
+
+impl Default for GroupTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GroupTable {
+    pub fn new() -> Self {
+        Self {
+            keys: vec![],
+            offsets: vec![0],
+            hashes: vec![],
+            slots: vec![EMPTY; 16],
+            hash_builder: RandomState::new(),
+        }
+    }
+
+    /// Number of groups.
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Estimated number of bytes used by the table, which is used to limit memory usage.
+    pub fn estimated_size(&self) -> usize {
+        self.keys.len()
+            + self.offsets.len() * std::mem::size_of::<usize>()
+            + self.hashes.len() * std::mem::size_of::<u64>()
+            + self.slots.len() * std::mem::size_of::<u32>()
+    }
+
+    /// Get the key of group `id`.
+    pub fn key(&self, id: usize) -> &[u8] {
+        &self.keys[self.offsets[id]..self.offsets[id + 1]]
+    }
+
+    /// Get the hash of `key`.
+    pub fn hash(&self, key: &[u8]) -> u64 {
+        self.hash_builder.hash_one(key)
+    }
+
+    /// Get the id of the group of `key`, if there is one.
+    pub fn get(&self, key: &[u8]) -> Option<usize> {
+        let hash = self.hash(key);
+        self.probe(key, hash).ok()
+    }
+
+    /// Get the id of the group of `key`, inserting a new group if there is none. Returns the id,
+    /// and whether the group is new.
+    pub fn get_or_insert(&mut self, key: &[u8]) -> (usize, bool) {
+        let hash = self.hash(key);
+        match self.probe(key, hash) {
+            Ok(id) => (id, false),
+            Err(slot) => {
+                let id = self.len();
+                assert!(id < EMPTY as usize, "too many groups");
+                self.slots[slot] = id as u32;
+                self.keys.extend_from_slice(key);
+                self.offsets.push(self.keys.len());
+                self.hashes.push(hash);
+                // Keep the load factor under 1/2.
+                if self.len() * 2 > self.slots.len() {
+                    self.grow();
+                }
+                (id, true)
+            }
+        }
+    }
+
+    /// Find the group of `key`, or the empty slot to insert it into.
+    fn probe(&self, key: &[u8], hash: u64) -> Result<usize, usize> {
+        let mask = self.slots.len() - 1;
+        let mut slot = hash as usize & mask;
+        loop {
+            match self.slots[slot] {
+                EMPTY => return Err(slot),
+                id if self.hashes[id as usize] == hash && self.key(id as usize) == key => {
+                    return Ok(id as usize);
+                }
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    /// Double the number of slots, placing groups by their saved hashes.
+    fn grow(&mut self) {
+        let mut slots = vec![EMPTY; self.slots.len() * 2];
+        let mask = slots.len() - 1;
+        for (id, hash) in self.hashes.iter().enumerate() {
+            let mut slot = *hash as usize & mask;
+            while slots[slot] != EMPTY {
+                slot = (slot + 1) & mask;
+            }
+            slots[slot] = id as u32;
+        }
+        self.slots = slots;
+    }
+
+    /// Deserialize keys of all groups into columns of `types`, in the order of group ids.
+    pub fn keys_to_columns(&self, types: &[DataType]) -> Result<Vec<ArrayImpl>> {
+        let mut deserializer = KeyDeserializer::new(types, self.len())?;
+        for id in 0..self.len() {
+            deserializer.deserialize(self.key(id))?;
+        }
+        Ok(deserializer.finish())
+    }
+}
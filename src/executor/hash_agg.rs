@@ -0,0 +1,353 @@
+//! Implements hash aggregation for `GROUP BY`.
+//!
+//! When groups exceed the memory budget, the aggregation becomes a grace hash aggregation: groups
+//! in memory are kept and still updated, while rows of new groups are spilled into partitions by
+//! their keys. After the input is finished, each partition is aggregated in turn, and spilled
+//! again if its groups do not fit in memory either, up to [`MAX_SPILL_DEPTH`] times. As states of aggregate functions cannot be
+//! written to files, input rows are spilled instead of partial states.
+
+use std::hash::RandomState;
+
+use anyhow::{Result, bail};
+
+use crate::TypeMismatch;
+use crate::aggregate::{AggState, AggregateFunction};
+use crate::array::{ArrayImpl, Batch};
+use crate::dataType::DataType;
+use crate::executor::group_table::{GroupTable, KeySerializer};
+use crate::executor::spill::{MAX_SPILL_DEPTH, SpillConfig, SpillFile, SpillPartitions};
+
+/// An aggregate function with the input columns of its arguments.
+pub struct AggCall {
+    pub func: Box<dyn AggregateFunction>,
+    /// Indexes of the argument columns in input batches.
+    pub args: Vec<usize>,
+}
+
+impl AggCall {
+    pub fn new(func: Box<dyn AggregateFunction>, args: Vec<usize>) -> Self {
+        Self { func, args }
+    }
+}
+
+/// Aggregates input batches by groups of key columns, producing one row for each group.
+///
+/// Output batches have the key columns first, followed by one column for each aggregate call.
+/// Without key columns, all rows form one group, and there is exactly one output row even if
+/// there is no input, like `SELECT count(*) FROM t`. Such aggregations never spill.
+pub struct HashAggregate {
+    /// Indexes of the key columns in input batches.
+    keys: Vec<usize>,
+    key_types: Vec<DataType>,
+    calls: Vec<AggCall>,
+    groups: GroupTable,
+    /// States of each aggregate call, indexed by group id.
+    states: Vec<Vec<AggState>>,
+    /// Estimated number of bytes of all states.
+    states_size: usize,
+    /// Types of the input columns, once any input is seen, which are needed to spill rows.
+    input_types: Option<Vec<DataType>>,
+    /// Reused buffer for serializing keys.
+    key_buf: Vec<u8>,
+    config: SpillConfig,
+    /// Number of times the rows being aggregated have been partitioned.
+    depth: usize,
+    /// Rows of groups not in memory, once the memory budget is exceeded.
+    spilled: Option<SpilledRows>,
+}
+
+/// Input rows spilled by [`HashAggregate`].
+struct SpilledRows {
+    partitions: SpillPartitions,
+    /// Types of the input columns.
+    types: Vec<DataType>,
+}
+
+impl HashAggregate {
+    /// Create a hash aggregation grouping by the columns `keys`, which are of `key_types`.
+    pub fn new(keys: Vec<usize>, key_types: Vec<DataType>, calls: Vec<AggCall>) -> Result<Self> {
+        if keys.len() != key_types.len() {
+            bail!("expect {} key types, get {}", keys.len(), key_types.len());
+        }
+        let states = calls.iter().map(|_| vec![]).collect();
+        Ok(Self {
+            keys,
+            key_types,
+            calls,
+            groups: GroupTable::new(),
+            states,
+            states_size: 0,
+            input_types: None,
+            key_buf: vec![],
+            config: SpillConfig::default(),
+            depth: 0,
+            spilled: None,
+        })
+    }
+
+    /// Set where and when to spill input rows.
+    pub fn with_spill_config(self, config: SpillConfig) -> Self {
+        Self { config, ..self }
+    }
+
+    /// Number of groups found so far.
+    pub fn num_groups(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Estimated number of bytes of groups and their states.
+    fn estimated_size(&self) -> usize {
+        let boxes = self.groups.len() * self.calls.len() * size_of::<AggState>();
+        self.groups.estimated_size() + boxes + self.states_size
+    }
+
+    /// Start spilling rows of new groups if groups in memory exceed the memory budget.
+    fn check_memory(&mut self) -> Result<()> {
+        if self.keys.is_empty() || self.spilled.is_some() || self.depth >= MAX_SPILL_DEPTH {
+            return Ok(());
+        }
+        let Some(types) = &self.input_types else {
+            return Ok(());
+        };
+        if self.estimated_size() > self.config.memory_budget {
+            let partitions =
+                SpillPartitions::new(&self.config.dir, self.keys.clone(), RandomState::new())?;
+            let types = types.clone();
+            self.spilled = Some(SpilledRows { partitions, types });
+        }
+        Ok(())
+    }
+
+    /// Types of the output columns.
+    pub fn output_types(&self) -> Vec<DataType> {
+        let calls = self.calls.iter().map(|call| call.func.return_type());
+        self.key_types.iter().copied().chain(calls).collect()
+    }
+
+    /// Assign a group id to each row of `batch`, creating new groups when needed.
+    fn assign_groups(&mut self, batch: &Batch) -> Result<Vec<usize>> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|idx| batch.column(*idx))
+            .collect::<Vec<_>>();
+        let serializer = KeySerializer::new(&keys);
+        let mut groups = Vec::with_capacity(batch.num_rows());
+        for row in 0..batch.num_rows() {
+            self.key_buf.clear();
+            serializer.serialize(row, &mut self.key_buf)?;
+            let (id, is_new) = self.groups.get_or_insert(&self.key_buf);
+            if is_new {
+                for (call, states) in self.calls.iter().zip(&mut self.states) {
+                    let state = call.func.create_state();
+                    self.states_size += call.func.estimated_state_size(&state)?;
+                    states.push(state);
+                }
+            }
+            groups.push(id);
+        }
+        Ok(groups)
+    }
+
+    /// Aggregate rows of `batch`.
+    pub fn update(&mut self, batch: &Batch) -> Result<()> {
+        // Keys are deserialized by `key_types`, so other types would produce wrong keys.
+        for (idx, ty) in self.keys.iter().zip(&self.key_types) {
+            let column = batch.column(*idx);
+            if column.identifier() != ty.physical_identifier() {
+                return Err(TypeMismatch(ty.physical_identifier(), column.identifier()).into());
+            }
+        }
+        if self.input_types.is_none() {
+            self.input_types = Some(batch.columns().iter().map(|c| c.data_type()).collect());
+        }
+        if self.spilled.is_some() {
+            return self.update_or_spill(batch);
+        }
+        let groups = self.assign_groups(batch)?;
+        self.update_states(batch, &groups)?;
+        self.check_memory()
+    }
+
+    /// Aggregate rows of `batch` whose groups are in memory, and spill the other rows.
+    fn update_or_spill(&mut self, batch: &Batch) -> Result<()> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|idx| batch.column(*idx))
+            .collect::<Vec<_>>();
+        let serializer = KeySerializer::new(&keys);
+        let mut groups = Vec::with_capacity(batch.num_rows());
+        let mut visibility = Vec::with_capacity(batch.num_rows());
+        for row in 0..batch.num_rows() {
+            self.key_buf.clear();
+            serializer.serialize(row, &mut self.key_buf)?;
+            let group = self.groups.get(&self.key_buf);
+            groups.extend(group);
+            visibility.push(group.is_some());
+        }
+        if groups.len() == batch.num_rows() {
+            return self.update_states(batch, &groups);
+        }
+        let spilled = visibility.iter().map(|v| !v).collect::<Vec<_>>();
+        let partitions = &mut self.spilled.as_mut().unwrap().partitions;
+        partitions.write(&batch.filter(&spilled))?;
+        if !groups.is_empty() {
+            self.update_states(&batch.filter(&visibility), &groups)?;
+        }
+        Ok(())
+    }
+
+    /// Update states of `groups`, which are the groups of rows of `batch`.
+    fn update_states(&mut self, batch: &Batch, groups: &[usize]) -> Result<()> {
+        // States may grow when updated, like those keeping values, so the sizes of the updated
+        // states are estimated again.
+        let mut touched = groups.to_vec();
+        touched.sort_unstable();
+        touched.dedup();
+        for (call, states) in self.calls.iter().zip(&mut self.states) {
+            let args = call
+                .args
+                .iter()
+                .map(|idx| batch.column(*idx))
+                .collect::<Vec<_>>();
+            let before = states_size(call.func.as_ref(), states, &touched)?;
+            call.func.update_grouped(states, groups, &args)?;
+            let after = states_size(call.func.as_ref(), states, &touched)?;
+            self.states_size = self.states_size + after - before;
+        }
+        Ok(())
+    }
+
+    /// Merge `other`, which aggregates other input rows by the same keys and calls, as if its
+    /// input had been aggregated by this one. This combines partial aggregations, like those of
+    /// workers of parallel execution.
+    pub fn merge(&mut self, mut other: HashAggregate) -> Result<()> {
+        if other.keys.len() != self.keys.len() || other.calls.len() != self.calls.len() {
+            bail!("cannot merge aggregations of different keys or calls");
+        }
+        // Spilled rows never belong to groups in memory, but groups of `other` may be new here, so
+        // rows spilled so far are aggregated again after the groups are merged.
+        let spilled = self.spilled.take();
+        let mut other_states = std::mem::take(&mut other.states)
+            .into_iter()
+            .map(|states| states.into_iter())
+            .collect::<Vec<_>>();
+        for id in 0..other.groups.len() {
+            let (group, is_new) = self.groups.get_or_insert(other.groups.key(id));
+            let calls = self.calls.iter().zip(&mut self.states);
+            for ((call, states), other_states) in calls.zip(&mut other_states) {
+                let state = other_states.next().unwrap();
+                if is_new {
+                    self.states_size += call.func.estimated_state_size(&state)?;
+                    states.push(state);
+                } else {
+                    let before = call.func.estimated_state_size(&states[group])?;
+                    call.func.merge(&mut states[group], state)?;
+                    let after = call.func.estimated_state_size(&states[group])?;
+                    self.states_size = self.states_size + after - before;
+                }
+            }
+        }
+        if self.input_types.is_none() {
+            self.input_types = other.input_types.take();
+        }
+        self.check_memory()?;
+        if let Some(spilled) = spilled {
+            self.update_spilled(spilled)?;
+        }
+        if let Some(spilled) = other.spilled.take() {
+            self.update_spilled(spilled)?;
+        }
+        Ok(())
+    }
+
+    /// Aggregate all rows of `spilled`.
+    fn update_spilled(&mut self, spilled: SpilledRows) -> Result<()> {
+        for file in spilled.partitions.into_files()? {
+            for batch in file.into_reader(spilled.types.clone())? {
+                self.update(&batch?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the result of all groups, with one batch for the groups in memory and one for each
+    /// spilled partition. Groups in memory are in the order they first appeared in the input.
+    pub fn finish(mut self) -> Result<Vec<Batch>> {
+        if self.keys.is_empty() && self.groups.is_empty() {
+            self.assign_groups(&Batch::no_columns(1))?;
+        }
+        let mut outputs = vec![];
+        // Spilled partitions with their depths, which are only opened when popped.
+        let mut partitions: Vec<(SpillFile, usize)> = vec![];
+        let mut types = vec![];
+        loop {
+            if let Some(spilled) = self.spilled.take() {
+                let depth = self.depth + 1;
+                let files = spilled.partitions.into_files()?;
+                partitions.extend(files.into_iter().map(|file| (file, depth)));
+                types = spilled.types;
+            }
+            if !self.groups.is_empty() {
+                outputs.push(self.finish_groups()?);
+            }
+            let Some((file, depth)) = partitions.pop() else {
+                break;
+            };
+            self.depth = depth;
+            for batch in file.into_reader(types.clone())? {
+                self.update(&batch?)?;
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Get the result of the groups in memory, and remove them.
+    fn finish_groups(&mut self) -> Result<Batch> {
+        let groups = std::mem::take(&mut self.groups);
+        let mut columns = groups.keys_to_columns(&self.key_types)?;
+        for (call, states) in self.calls.iter().zip(&mut self.states) {
+            columns.push(finish_states(call.func.as_ref(), states)?);
+            states.clear();
+        }
+        self.states_size = 0;
+        Ok(match columns.is_empty() {
+            true => Batch::no_columns(groups.len()),
+            false => Batch::new(columns)?,
+        })
+    }
+
+    /// Aggregate all `batches` and get the result.
+    pub fn aggregate(
+        mut self,
+        batches: impl IntoIterator<Item = Result<Batch>>,
+    ) -> Result<Vec<Batch>> {
+        for batch in batches {
+            self.update(&batch?)?;
+        }
+        self.finish()
+    }
+}
+
+/// Estimated number of bytes of the states of `groups`.
+fn states_size(
+    func: &dyn AggregateFunction,
+    states: &[AggState],
+    groups: &[usize],
+) -> Result<usize> {
+    groups
+        .iter()
+        .map(|group| func.estimated_state_size(&states[*group]))
+        .sum()
+}
+
+/// Get the results of `states` as a column.
+fn finish_states(func: &dyn AggregateFunction, states: &[AggState]) -> Result<ArrayImpl> {
+    let mut builder = func.return_type().create_array_builder(states.len())?;
+    for state in states {
+        let value = func.finish(state)?;
+        builder.push(value.as_ref().map(|v| v.as_scalar_ref()));
+    }
+    Ok(builder.finish())
+}
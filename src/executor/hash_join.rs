@@ -0,0 +1,458 @@
+//! Implements hash join.
+//!
+//! The right input is the build side: its rows are put into a hash table by their keys. The left
+//! input is the probe side: each of its rows looks up the rows of the build side with the same
+//! key. Keys are serialized with [`KeySerializer`] like `GROUP BY` keys, except that keys with any
+//! `NULL` never match, as `NULL = NULL` is not true.
+//!
+//! When the build side exceeds the memory budget, the join becomes a grace hash join: rows of both
+//! sides are spilled into partitions by their keys, and each pair of partitions is joined by
+//! another hash join afterwards, which may spill again with a different partitioning.
+
+use std::hash::RandomState;
+use std::ops::Range;
+
+use anyhow::{Result, bail};
+
+use crate::TypeMismatch;
+use crate::array::{ArrayImpl, Batch};
+use crate::dataType::DataType;
+use crate::executor::DEFAULT_BATCH_SIZE;
+use crate::executor::check_batch;
+use crate::executor::group_table::{GroupTable, KeySerializer};
+use crate::executor::spill::{MAX_SPILL_DEPTH, SpillConfig, SpillPartitions};
+
+/// Types of joins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinType {
+    Inner,
+    /// Unmatched left rows are returned with `NULL`s as the right columns.
+    LeftOuter,
+    /// Unmatched right rows are returned with `NULL`s as the left columns.
+    RightOuter,
+    /// Both unmatched left rows and unmatched right rows are returned.
+    FullOuter,
+    /// Left rows with any match, like `EXISTS`. Only left columns are returned.
+    LeftSemi,
+    /// Left rows without any match, like `NOT EXISTS`. Only left columns are returned.
+    LeftAnti,
+    /// Right rows with any match. Only right columns are returned.
+    RightSemi,
+    /// Right rows without any match. Only right columns are returned.
+    RightAnti,
+}
+
+impl JoinType {
+    /// Types of the output columns, given the types of both inputs.
+    pub fn output_types(&self, left_types: &[DataType], right_types: &[DataType]) -> Vec<DataType> {
+        match self {
+            Self::LeftSemi | Self::LeftAnti => left_types.to_vec(),
+            Self::RightSemi | Self::RightAnti => right_types.to_vec(),
+            _ => [left_types, right_types].concat(),
+        }
+    }
+
+    /// Whether matched right rows need to be tracked to produce the output.
+    fn tracks_right_matches(&self) -> bool {
+        matches!(
+            self,
+            Self::RightOuter | Self::FullOuter | Self::RightSemi | Self::RightAnti
+        )
+    }
+}
+
+/// Marks the end of a chain of build rows.
+const NONE: usize = usize::MAX;
+
+/// Joins rows of the left input and the right input with equal keys.
+///
+/// Batches of the right input are added by [`HashJoin::build`] first, then batches of the left
+/// input are probed by [`HashJoin::probe`], and finally [`HashJoin::finish`] returns the
+/// remaining rows, including unmatched right rows of right and full outer joins. The rows of both
+/// sides of the output are collected as indexes first, and then their columns are gathered into
+/// batches of `batch_size` rows, except for the last one. After spilling, all output is produced
+/// by [`HashJoin::finish`].
+pub struct HashJoin {
+    join_type: JoinType,
+    left_types: Vec<DataType>,
+    right_types: Vec<DataType>,
+    /// Indexes of the key columns in left batches.
+    left_keys: Vec<usize>,
+    /// Indexes of the key columns in right batches.
+    right_keys: Vec<usize>,
+
+    build: BuildRows,
+    /// Groups of distinct keys of build rows.
+    groups: GroupTable,
+    /// First and last build row of each group. Rows of a group are linked by `next`.
+    chains: Vec<(usize, usize)>,
+    /// The next build row of the same group, or [`NONE`].
+    next: Vec<usize>,
+    /// Group of each build row, or [`NONE`] if its key has a `NULL`.
+    row_groups: Vec<usize>,
+    /// Whether each group has matched any probe row.
+    matched: Vec<bool>,
+
+    output: OutputBuffer,
+    /// Reused buffer for serializing keys.
+    key_buf: Vec<u8>,
+
+    config: SpillConfig,
+    /// Number of times the input has been partitioned.
+    depth: usize,
+    /// Estimated number of bytes of build batches.
+    build_size: usize,
+    /// Rows of both sides, once the memory budget is exceeded.
+    spilled: Option<SpilledRows>,
+}
+
+/// Rows of both inputs spilled by [`HashJoin`], partitioned in the same way.
+struct SpilledRows {
+    left: SpillPartitions,
+    right: SpillPartitions,
+}
+
+impl HashJoin {
+    /// Create a hash join of left batches of `left_types` and right batches of `right_types`,
+    /// where `left_keys` and `right_keys` are the indexes of key columns to be compared in pairs.
+    pub fn new(
+        join_type: JoinType,
+        left_types: Vec<DataType>,
+        right_types: Vec<DataType>,
+        left_keys: Vec<usize>,
+        right_keys: Vec<usize>,
+    ) -> Result<Self> {
+        if left_keys.len() != right_keys.len() {
+            bail!(
+                "expect the same number of keys on both sides, get {} and {}",
+                left_keys.len(),
+                right_keys.len()
+            );
+        }
+        for (left, right) in left_keys.iter().zip(&right_keys) {
+            let (Some(left), Some(right)) = (left_types.get(*left), right_types.get(*right)) else {
+                bail!("key column index out of range");
+            };
+            if left.physical_identifier() != right.physical_identifier() {
+                return Err(
+                    TypeMismatch(left.physical_identifier(), right.physical_identifier()).into(),
+                );
+            }
+        }
+        let output = OutputBuffer::new(
+            join_type.output_types(&left_types, &right_types),
+            DEFAULT_BATCH_SIZE,
+        );
+        Ok(Self {
+            join_type,
+            left_types,
+            right_types,
+            left_keys,
+            right_keys,
+            build: BuildRows::default(),
+            groups: GroupTable::new(),
+            chains: vec![],
+            next: vec![],
+            row_groups: vec![],
+            matched: vec![],
+            output,
+            key_buf: vec![],
+            config: SpillConfig::default(),
+            depth: 0,
+            build_size: 0,
+            spilled: None,
+        })
+    }
+
+    /// Set the maximum number of rows of output batches.
+    pub fn with_batch_size(self, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            bail!("batch size must be positive");
+        }
+        Ok(Self {
+            output: OutputBuffer::new(self.output_types(), batch_size),
+            ..self
+        })
+    }
+
+    /// Set where and when to spill input rows.
+    pub fn with_spill_config(self, config: SpillConfig) -> Self {
+        Self { config, ..self }
+    }
+
+    pub fn join_type(&self) -> JoinType {
+        self.join_type
+    }
+
+    /// Types of the output columns.
+    pub fn output_types(&self) -> Vec<DataType> {
+        self.join_type
+            .output_types(&self.left_types, &self.right_types)
+    }
+
+    /// Add rows of `batch` of the right input to the hash table.
+    pub fn build(&mut self, batch: Batch) -> Result<()> {
+        check_batch(&batch, &self.right_types)?;
+        if let Some(spilled) = &mut self.spilled {
+            return spilled.right.write(&batch);
+        }
+        let keys = key_columns(&batch, &self.right_keys);
+        let serializer = KeySerializer::new(&keys);
+        for row in 0..batch.num_rows() {
+            let id = self.next.len();
+            self.next.push(NONE);
+            if serializer.has_null(row) {
+                self.row_groups.push(NONE);
+                continue;
+            }
+            self.key_buf.clear();
+            serializer.serialize(row, &mut self.key_buf)?;
+            let (group, is_new) = self.groups.get_or_insert(&self.key_buf);
+            self.row_groups.push(group);
+            if is_new {
+                self.chains.push((id, id));
+                self.matched.push(false);
+            } else {
+                let last = self.chains[group].1;
+                self.next[last] = id;
+                self.chains[group].1 = id;
+            }
+        }
+        self.build_size += batch.estimated_size();
+        self.build.batches.push(batch);
+        if self.depth < MAX_SPILL_DEPTH && self.estimated_size() > self.config.memory_budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Estimated number of bytes of the build side and the hash table.
+    fn estimated_size(&self) -> usize {
+        self.build_size
+            + self.groups.estimated_size()
+            + self.next.len() * 2 * std::mem::size_of::<usize>()
+            + self.chains.len() * 2 * std::mem::size_of::<usize>()
+    }
+
+    /// Move all build rows into partitions, and partition rows of both sides from now on.
+    fn spill(&mut self) -> Result<()> {
+        let hasher = RandomState::new();
+        let dir = &self.config.dir;
+        let mut right = SpillPartitions::new(dir, self.right_keys.clone(), hasher.clone())?;
+        let left = SpillPartitions::new(dir, self.left_keys.clone(), hasher)?;
+        for batch in std::mem::take(&mut self.build).batches {
+            right.write(&batch)?;
+        }
+        self.groups = GroupTable::new();
+        self.chains = vec![];
+        self.next = vec![];
+        self.row_groups = vec![];
+        self.matched = vec![];
+        self.build_size = 0;
+        self.spilled = Some(SpilledRows { left, right });
+        Ok(())
+    }
+
+    /// Probe the hash table with rows of `batch` of the left input, and return the output batches
+    /// that are full.
+    pub fn probe(&mut self, batch: &Batch) -> Result<Vec<Batch>> {
+        check_batch(batch, &self.left_types)?;
+        if let Some(spilled) = &mut self.spilled {
+            spilled.left.write(batch)?;
+            return Ok(vec![]);
+        }
+        let keys = key_columns(batch, &self.left_keys);
+        let serializer = KeySerializer::new(&keys);
+        // Left rows and their matched build rows of the output, where `None` is a row of `NULL`s.
+        let mut left_rows = vec![];
+        let mut right_rows = vec![];
+        for row in 0..batch.num_rows() {
+            let group = match serializer.has_null(row) {
+                true => None,
+                false => {
+                    self.key_buf.clear();
+                    serializer.serialize(row, &mut self.key_buf)?;
+                    self.groups.get(&self.key_buf)
+                }
+            };
+            let Some(group) = group else {
+                match self.join_type {
+                    JoinType::LeftOuter | JoinType::FullOuter => {
+                        left_rows.push(row);
+                        right_rows.push(None);
+                    }
+                    JoinType::LeftAnti => left_rows.push(row),
+                    _ => {}
+                }
+                continue;
+            };
+            if self.join_type.tracks_right_matches() {
+                self.matched[group] = true;
+            }
+            match self.join_type {
+                JoinType::LeftSemi => left_rows.push(row),
+                JoinType::LeftAnti | JoinType::RightSemi | JoinType::RightAnti => {}
+                _ => {
+                    let mut build_row = self.chains[group].0;
+                    while build_row != NONE {
+                        left_rows.push(row);
+                        right_rows.push(Some(build_row));
+                        build_row = self.next[build_row];
+                    }
+                }
+            }
+        }
+
+        match self.join_type {
+            JoinType::LeftSemi | JoinType::LeftAnti => self
+                .output
+                .push(left_rows.len(), |rows| Ok(batch.take(&left_rows[rows]))),
+            _ => {
+                let build = self.build.compact(&self.right_types)?;
+                self.output.push(left_rows.len(), |rows| {
+                    let left = batch.take(&left_rows[rows.clone()]);
+                    left.zip(build.take_nullable(&right_rows[rows]))
+                })
+            }
+        }
+    }
+
+    /// Return the remaining output batches, after all left batches are probed.
+    pub fn finish(mut self) -> Result<Vec<Batch>> {
+        if let Some(spilled) = self.spilled.take() {
+            return self.join_partitions(spilled);
+        }
+        let mut outputs = vec![];
+        if self.join_type.tracks_right_matches() {
+            let rows = (0..self.next.len())
+                .filter(|row| {
+                    let group = self.row_groups[*row];
+                    let matched = group != NONE && self.matched[group];
+                    matched == (self.join_type == JoinType::RightSemi)
+                })
+                .collect::<Vec<_>>();
+            let build = self.build.compact(&self.right_types)?;
+            outputs = match self.join_type {
+                JoinType::RightSemi | JoinType::RightAnti => self
+                    .output
+                    .push(rows.len(), |range| Ok(build.take(&rows[range]))),
+                _ => self.output.push(rows.len(), |range| {
+                    let left = Batch::nulls(&self.left_types, range.len())?;
+                    left.zip(build.take(&rows[range]))
+                }),
+            }?;
+        }
+        outputs.extend(self.output.finish());
+        Ok(outputs)
+    }
+
+    /// Join each pair of spilled partitions with a new hash join.
+    fn join_partitions(self, spilled: SpilledRows) -> Result<Vec<Batch>> {
+        let left = spilled.left.into_files()?;
+        let right = spilled.right.into_files()?;
+        let mut outputs = vec![];
+        for (left, right) in left.into_iter().zip(right) {
+            let left = left.into_reader(self.left_types.clone())?;
+            let right = right.into_reader(self.right_types.clone())?;
+            let mut join = HashJoin::new(
+                self.join_type,
+                self.left_types.clone(),
+                self.right_types.clone(),
+                self.left_keys.clone(),
+                self.right_keys.clone(),
+            )?
+            .with_batch_size(self.output.batch_size)?
+            .with_spill_config(self.config.clone());
+            join.depth = self.depth + 1;
+            outputs.extend(join.execute(left, right)?);
+        }
+        Ok(outputs)
+    }
+
+    /// Join all batches of `left` and `right`.
+    pub fn execute(
+        mut self,
+        left: impl IntoIterator<Item = Result<Batch>>,
+        right: impl IntoIterator<Item = Result<Batch>>,
+    ) -> Result<Vec<Batch>> {
+        for batch in right {
+            self.build(batch?)?;
+        }
+        let mut outputs = vec![];
+        for batch in left {
+            outputs.extend(self.probe(&batch?)?);
+        }
+        outputs.extend(self.finish()?);
+        Ok(outputs)
+    }
+}
+
+/// Rows of the build side. Build rows are identified by their positions in the order they are
+/// added, which are their indexes in the batch compacted from all batches.
+#[derive(Default)]
+struct BuildRows {
+    batches: Vec<Batch>,
+}
+
+impl BuildRows {
+    /// Concatenate all batches of `types` into one, so that build rows are gathered by indexes.
+    fn compact(&mut self, types: &[DataType]) -> Result<&Batch> {
+        if self.batches.len() != 1 {
+            self.batches = vec![Batch::concat(types, &self.batches)?];
+        }
+        Ok(&self.batches[0])
+    }
+}
+
+/// Gathers output rows into batches of `batch_size` rows.
+struct OutputBuffer {
+    types: Vec<DataType>,
+    batch_size: usize,
+    /// Output rows that are not enough for a batch yet.
+    pending: Option<Batch>,
+}
+
+impl OutputBuffer {
+    fn new(types: Vec<DataType>, batch_size: usize) -> Self {
+        Self {
+            types,
+            batch_size,
+            pending: None,
+        }
+    }
+
+    /// Gather `num_rows` output rows, where `gather` gets a range of them as a batch, and return
+    /// the batches that are full.
+    fn push(
+        &mut self,
+        num_rows: usize,
+        mut gather: impl FnMut(Range<usize>) -> Result<Batch>,
+    ) -> Result<Vec<Batch>> {
+        let mut outputs = vec![];
+        let mut start = 0;
+        while start < num_rows {
+            let pending = self.pending.take();
+            let pending_rows = pending.as_ref().map_or(0, |batch| batch.num_rows());
+            let end = num_rows.min(start + self.batch_size - pending_rows);
+            let mut batch = gather(start..end)?;
+            if let Some(pending) = pending {
+                batch = Batch::concat(&self.types, &[pending, batch])?;
+            }
+            match batch.num_rows() == self.batch_size {
+                true => outputs.push(batch),
+                false => self.pending = Some(batch),
+            }
+            start = end;
+        }
+        Ok(outputs)
+    }
+
+    /// Take the output rows that are not returned yet.
+    fn finish(self) -> Option<Batch> {
+        self.pending
+    }
+}
+
+fn key_columns<'a>(batch: &'a Batch, keys: &[usize]) -> Vec<&'a ArrayImpl> {
+    keys.iter().map(|idx| batch.column(*idx)).collect()
+}
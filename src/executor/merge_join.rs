@@ -0,0 +1,568 @@
+//! Implements joins over inputs sorted by their keys: [`MergeJoin`] for equality conditions and
+//! [`RangeJoin`] for inequality conditions like `a.v BETWEEN b.start AND b.end`.
+//!
+//! Both inputs are read batch by batch in one pass, and only rows that may still match are kept
+//! in memory, so these joins work for inputs too large for a hash table. Keys are compared with
+//! [`SqlOrd`] like comparison expressions, and rows with `NULL` keys never match.
+
+use std::cmp::Ordering;
+
+use anyhow::{Result, bail};
+
+use crate::TypeMismatch;
+use crate::array::{Batch, BatchBuilder};
+use crate::dataType::DataType;
+use crate::executor::DEFAULT_BATCH_SIZE;
+use crate::executor::check_batch;
+use crate::executor::hash_join::JoinType;
+use crate::expr::cmp::SqlOrd;
+use crate::scalar::{ScalarImpl, ScalarRefImpl};
+
+/// A row whose values are copied out of its batch.
+type OwnedRow = Box<[Option<ScalarImpl>]>;
+
+fn to_owned_row(batch: &Batch, row: usize) -> OwnedRow {
+    batch
+        .columns()
+        .iter()
+        .map(|column| column.get(row).map(|v| v.to_owned_scalar()))
+        .collect()
+}
+
+fn owned_values(row: &OwnedRow) -> impl Iterator<Item = Option<ScalarRefImpl<'_>>> {
+    row.iter().map(|v| v.as_ref().map(|v| v.as_scalar_ref()))
+}
+
+/// Reads rows of batches one by one.
+struct Cursor<I> {
+    batches: I,
+    batch: Option<Batch>,
+    row: usize,
+}
+
+impl<I: Iterator<Item = Result<Batch>>> Cursor<I> {
+    fn new(batches: I, types: &[DataType]) -> Result<Self> {
+        let mut cursor = Self {
+            batches,
+            batch: None,
+            row: 0,
+        };
+        cursor.load(types)?;
+        Ok(cursor)
+    }
+
+    /// Load the next non-empty batch if the current one is exhausted.
+    fn load(&mut self, types: &[DataType]) -> Result<()> {
+        while self.batch.as_ref().is_none_or(|b| self.row >= b.num_rows()) {
+            let Some(batch) = self.batches.next() else {
+                self.batch = None;
+                return Ok(());
+            };
+            let batch = batch?;
+            check_batch(&batch, types)?;
+            self.batch = Some(batch);
+            self.row = 0;
+        }
+        Ok(())
+    }
+
+    /// Get the current batch and row, or `None` if all rows are read.
+    fn current(&self) -> Option<(&Batch, usize)> {
+        self.batch.as_ref().map(|batch| (batch, self.row))
+    }
+
+    fn advance(&mut self, types: &[DataType]) -> Result<()> {
+        self.row += 1;
+        self.load(types)
+    }
+}
+
+/// Check that `left` and `right` columns of each pair are of the same type.
+fn check_key_types(
+    left_types: &[DataType],
+    right_types: &[DataType],
+    pairs: impl IntoIterator<Item = (usize, usize)>,
+) -> Result<()> {
+    for (left, right) in pairs {
+        let (Some(left), Some(right)) = (left_types.get(left), right_types.get(right)) else {
+            bail!("key column index out of range");
+        };
+        if left.physical_identifier() != right.physical_identifier() {
+            return Err(
+                TypeMismatch(left.physical_identifier(), right.physical_identifier()).into(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Get the key of `row`, or `None` if any key is `NULL`.
+fn get_key<'a>(batch: &'a Batch, row: usize, keys: &[usize]) -> Option<Vec<ScalarRefImpl<'a>>> {
+    keys.iter().map(|key| batch.column(*key).get(row)).collect()
+}
+
+/// Check if the key of `row` equals `key`, or return `None` if any key of `row` is `NULL`.
+fn key_matches(key: &[ScalarImpl], batch: &Batch, row: usize, keys: &[usize]) -> Option<bool> {
+    let row_key = get_key(batch, row, keys)?;
+    let key = key.iter().map(|v| v.as_scalar_ref()).collect::<Vec<_>>();
+    Some(compare_keys(&row_key, &key).is_eq())
+}
+
+fn compare_keys(a: &[ScalarRefImpl<'_>], b: &[ScalarRefImpl<'_>]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.sql_cmp(b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Joins rows of the left input and the right input with equal keys, where both inputs are
+/// sorted by their keys in ascending order. `NULL` keys may be anywhere, as they are skipped.
+///
+/// Rows of the right input with the same key are kept in memory while left rows with that key are
+/// joined with them.
+pub struct MergeJoin {
+    join_type: JoinType,
+    left_types: Vec<DataType>,
+    right_types: Vec<DataType>,
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    batch_size: usize,
+}
+
+impl MergeJoin {
+    /// Create a merge join of left batches of `left_types` and right batches of `right_types`,
+    /// where `left_keys` and `right_keys` are the indexes of key columns to be compared in pairs.
+    pub fn new(
+        join_type: JoinType,
+        left_types: Vec<DataType>,
+        right_types: Vec<DataType>,
+        left_keys: Vec<usize>,
+        right_keys: Vec<usize>,
+    ) -> Result<Self> {
+        if left_keys.len() != right_keys.len() {
+            bail!(
+                "expect the same number of keys on both sides, get {} and {}",
+                left_keys.len(),
+                right_keys.len()
+            );
+        }
+        check_key_types(
+            &left_types,
+            &right_types,
+            left_keys.iter().copied().zip(right_keys.iter().copied()),
+        )?;
+        Ok(Self {
+            join_type,
+            left_types,
+            right_types,
+            left_keys,
+            right_keys,
+            batch_size: DEFAULT_BATCH_SIZE,
+        })
+    }
+
+    /// Set the maximum number of rows of output batches.
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    /// Types of the output columns.
+    pub fn output_types(&self) -> Vec<DataType> {
+        self.join_type
+            .output_types(&self.left_types, &self.right_types)
+    }
+
+    /// Join all batches of `left` and `right`, which are sorted by their keys.
+    pub fn execute(
+        self,
+        left: impl IntoIterator<Item = Result<Batch>>,
+        right: impl IntoIterator<Item = Result<Batch>>,
+    ) -> Result<Vec<Batch>> {
+        let mut output = JoinOutput::new(
+            self.join_type,
+            &self.left_types,
+            &self.right_types,
+            self.batch_size,
+        )?;
+        let mut left = Cursor::new(left.into_iter(), &self.left_types)?;
+        let mut right = Cursor::new(right.into_iter(), &self.right_types)?;
+        // Right rows with the same key, which is the key of the first one.
+        let mut run: Vec<OwnedRow> = vec![];
+
+        loop {
+            let (l, r) = (left.current(), right.current());
+            let ordering = match (l, r) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((lb, lr)), Some((rb, rr))) => {
+                    match (
+                        get_key(lb, lr, &self.left_keys),
+                        get_key(rb, rr, &self.right_keys),
+                    ) {
+                        (None, _) => Ordering::Less,
+                        (_, None) => Ordering::Greater,
+                        (Some(lk), Some(rk)) => compare_keys(&lk, &rk),
+                    }
+                }
+            };
+            match ordering {
+                Ordering::Less => {
+                    let (batch, row) = l.unwrap();
+                    output.left_unmatched(batch, row)?;
+                    left.advance(&self.left_types)?;
+                }
+                Ordering::Greater => {
+                    let (batch, row) = r.unwrap();
+                    output.right_unmatched(&to_owned_row(batch, row))?;
+                    right.advance(&self.right_types)?;
+                }
+                Ordering::Equal => {
+                    // Collect right rows with the key, and join them with left rows with the key.
+                    // Rows with `NULL` keys in between are unmatched.
+                    run.clear();
+                    let (batch, row) = r.unwrap();
+                    run.push(to_owned_row(batch, row));
+                    let run_key = self
+                        .right_keys
+                        .iter()
+                        .map(|k| run[0][*k].clone().unwrap())
+                        .collect::<Vec<_>>();
+                    right.advance(&self.right_types)?;
+                    while let Some((batch, row)) = right.current() {
+                        match key_matches(&run_key, batch, row, &self.right_keys) {
+                            None => output.right_unmatched(&to_owned_row(batch, row))?,
+                            Some(true) => run.push(to_owned_row(batch, row)),
+                            Some(false) => break,
+                        }
+                        right.advance(&self.right_types)?;
+                    }
+                    while let Some((batch, row)) = left.current() {
+                        match key_matches(&run_key, batch, row, &self.left_keys) {
+                            None => output.left_unmatched(batch, row)?,
+                            Some(true) => output.left_matched(batch, row, &run)?,
+                            Some(false) => break,
+                        }
+                        left.advance(&self.left_types)?;
+                    }
+                    output.right_matched(&run)?;
+                }
+            }
+        }
+        output.finish()
+    }
+}
+
+/// A comparison operator of a [`RangeCondition`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeOp {
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl RangeOp {
+    /// Check if `ordering`, which compares the left value with the right value, satisfies the
+    /// operator.
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Lt => ordering.is_lt(),
+            Self::LtEq => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            Self::GtEq => ordering.is_ge(),
+        }
+    }
+
+    /// Whether the operator bounds the left value from below, like `left.v > right.start`.
+    fn is_lower(self) -> bool {
+        matches!(self, Self::Gt | Self::GtEq)
+    }
+}
+
+/// `left.v op right.w` as a condition of a [`RangeJoin`], where `left` and `right` are indexes
+/// of columns of each input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RangeCondition {
+    pub left: usize,
+    pub op: RangeOp,
+    pub right: usize,
+}
+
+impl RangeCondition {
+    pub fn new(left: usize, op: RangeOp, right: usize) -> Self {
+        Self { left, op, right }
+    }
+
+    /// `left.value BETWEEN right.start AND right.end`, which is `left.value >= right.start AND
+    /// left.value <= right.end`.
+    pub fn between(value: usize, start: usize, end: usize) -> Vec<Self> {
+        vec![
+            Self::new(value, RangeOp::GtEq, start),
+            Self::new(value, RangeOp::LtEq, end),
+        ]
+    }
+
+    /// Check if the condition holds for `value` of the left row and `bound` of the right row,
+    /// which never holds for `NULL` bounds.
+    fn holds(&self, value: ScalarRefImpl<'_>, bound: Option<ScalarRefImpl<'_>>) -> bool {
+        bound.is_some_and(|bound| self.op.holds(value.sql_cmp(&bound)))
+    }
+}
+
+/// Joins each row of the left input with rows of the right input with equal keys, where a value
+/// of the left row is between bounds of the right row, like `a.ts BETWEEN b.start AND b.end` or
+/// `a.ts > b.start`. The bounds are given by one or two [`RangeCondition`]s on the same left
+/// column, with at most one lower bound (`>` or `>=`) and at most one upper bound (`<` or `<=`).
+///
+/// The left input must be sorted by its keys and then by the value, and the right input must be
+/// sorted by its keys and then by the lower bound if any, all in ascending order. Right rows
+/// whose lower bound is satisfied are kept in memory until their upper bound is not, or until
+/// the key changes, so the memory used depends on how much the ranges overlap. Without a lower
+/// bound, all right rows with the key of the left row are kept. Only `Inner`, `LeftOuter`,
+/// `LeftSemi` and `LeftAnti` joins are supported.
+pub struct RangeJoin {
+    join_type: JoinType,
+    left_types: Vec<DataType>,
+    right_types: Vec<DataType>,
+    left_keys: Vec<usize>,
+    right_keys: Vec<usize>,
+    /// Index of the bounded value in left batches.
+    value: usize,
+    lower: Option<RangeCondition>,
+    upper: Option<RangeCondition>,
+    batch_size: usize,
+}
+
+impl RangeJoin {
+    /// Create a range join of left batches of `left_types` and right batches of `right_types`,
+    /// where `left_keys` and `right_keys` are the indexes of key columns to be compared for
+    /// equality in pairs, and `conditions` bound a value of left rows.
+    pub fn new(
+        join_type: JoinType,
+        left_types: Vec<DataType>,
+        right_types: Vec<DataType>,
+        left_keys: Vec<usize>,
+        right_keys: Vec<usize>,
+        conditions: Vec<RangeCondition>,
+    ) -> Result<Self> {
+        if !matches!(
+            join_type,
+            JoinType::Inner | JoinType::LeftOuter | JoinType::LeftSemi | JoinType::LeftAnti
+        ) {
+            bail!("{join_type:?} join is not supported by range join");
+        }
+        if left_keys.len() != right_keys.len() {
+            bail!(
+                "expect the same number of keys on both sides, get {} and {}",
+                left_keys.len(),
+                right_keys.len()
+            );
+        }
+        let Some(value) = conditions.first().map(|c| c.left) else {
+            bail!("range join requires a condition");
+        };
+        if conditions.iter().any(|c| c.left != value) {
+            bail!("conditions of range join must compare the same left column");
+        }
+        let (lower, upper): (Vec<_>, Vec<_>) = conditions.iter().partition(|c| c.op.is_lower());
+        if lower.len() > 1 || upper.len() > 1 {
+            bail!("range join accepts at most one lower bound and one upper bound");
+        }
+        let pairs = left_keys.iter().copied().zip(right_keys.iter().copied());
+        let bounds = conditions.iter().map(|c| (c.left, c.right));
+        check_key_types(&left_types, &right_types, pairs.chain(bounds))?;
+        Ok(Self {
+            join_type,
+            left_types,
+            right_types,
+            left_keys,
+            right_keys,
+            value,
+            lower: lower.first().copied(),
+            upper: upper.first().copied(),
+            batch_size: DEFAULT_BATCH_SIZE,
+        })
+    }
+
+    /// Set the maximum number of rows of output batches.
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    /// Types of the output columns.
+    pub fn output_types(&self) -> Vec<DataType> {
+        self.join_type
+            .output_types(&self.left_types, &self.right_types)
+    }
+
+    /// Join all batches of `left` and `right`, which are sorted by their keys and then by the
+    /// value and the lower bound.
+    pub fn execute(
+        self,
+        left: impl IntoIterator<Item = Result<Batch>>,
+        right: impl IntoIterator<Item = Result<Batch>>,
+    ) -> Result<Vec<Batch>> {
+        let mut output = JoinOutput::new(
+            self.join_type,
+            &self.left_types,
+            &self.right_types,
+            self.batch_size,
+        )?;
+        let mut left = Cursor::new(left.into_iter(), &self.left_types)?;
+        let mut right = Cursor::new(right.into_iter(), &self.right_types)?;
+        // Right rows with the key `active_key` whose lower bounds are satisfied, in the order of
+        // the lower bounds.
+        let mut active: Vec<OwnedRow> = vec![];
+        let mut active_key: Option<Vec<ScalarImpl>> = None;
+
+        while let Some((batch, row)) = left.current() {
+            let (Some(key), Some(value)) = (
+                get_key(batch, row, &self.left_keys),
+                batch.column(self.value).get(row),
+            ) else {
+                output.left_unmatched(batch, row)?;
+                left.advance(&self.left_types)?;
+                continue;
+            };
+            // Keys are ascending, so rows of other keys never match again.
+            if !active_key.as_ref().is_some_and(|k| {
+                let k = k.iter().map(|v| v.as_scalar_ref()).collect::<Vec<_>>();
+                compare_keys(&k, &key).is_eq()
+            }) {
+                active.clear();
+                active_key = Some(key.iter().map(|v| v.to_owned_scalar()).collect());
+            }
+            while let Some((rb, rr)) = right.current() {
+                let Some(right_key) = get_key(rb, rr, &self.right_keys) else {
+                    right.advance(&self.right_types)?;
+                    continue;
+                };
+                match compare_keys(&right_key, &key) {
+                    Ordering::Less => {}
+                    Ordering::Greater => break,
+                    Ordering::Equal => {
+                        // The lower bounds are ascending, so the following rows do not match
+                        // either. Rows with `NULL` bounds never match.
+                        match &self.lower {
+                            Some(lower) => match rb.column(lower.right).get(rr) {
+                                None => {}
+                                bound if !lower.holds(value, bound) => break,
+                                _ => active.push(to_owned_row(rb, rr)),
+                            },
+                            None => active.push(to_owned_row(rb, rr)),
+                        }
+                    }
+                }
+                right.advance(&self.right_types)?;
+            }
+            // Values are ascending, so rows exceeding their upper bounds never match again.
+            if let Some(upper) = &self.upper {
+                active.retain(|r| {
+                    let bound = r[upper.right].as_ref().map(|v| v.as_scalar_ref());
+                    upper.holds(value, bound)
+                });
+            }
+            match active.is_empty() {
+                true => output.left_unmatched(batch, row)?,
+                false => output.left_matched(batch, row, &active)?,
+            }
+            left.advance(&self.left_types)?;
+        }
+        output.finish()
+    }
+}
+
+/// Gathers output rows of a join into batches, depending on the join type.
+struct JoinOutput {
+    join_type: JoinType,
+    num_left_columns: usize,
+    num_right_columns: usize,
+    builder: BatchBuilder,
+    batches: Vec<Batch>,
+}
+
+impl JoinOutput {
+    fn new(
+        join_type: JoinType,
+        left_types: &[DataType],
+        right_types: &[DataType],
+        batch_size: usize,
+    ) -> Result<Self> {
+        Ok(Self {
+            join_type,
+            num_left_columns: left_types.len(),
+            num_right_columns: right_types.len(),
+            builder: BatchBuilder::new(
+                join_type.output_types(left_types, right_types),
+                batch_size,
+            )?,
+            batches: vec![],
+        })
+    }
+
+    fn push_row<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = Option<ScalarRefImpl<'a>>>,
+    ) -> Result<()> {
+        self.batches.extend(self.builder.push_row(values)?);
+        Ok(())
+    }
+
+    /// Output a left row without any match.
+    fn left_unmatched(&mut self, batch: &Batch, row: usize) -> Result<()> {
+        let left = batch.columns().iter().map(|column| column.get(row));
+        match self.join_type {
+            JoinType::LeftOuter | JoinType::FullOuter => {
+                let nulls = std::iter::repeat_n(None, self.num_right_columns);
+                self.push_row(left.chain(nulls))
+            }
+            JoinType::LeftAnti => self.push_row(left),
+            _ => Ok(()),
+        }
+    }
+
+    /// Output a right row without any match.
+    fn right_unmatched(&mut self, right: &OwnedRow) -> Result<()> {
+        match self.join_type {
+            JoinType::RightOuter | JoinType::FullOuter => {
+                let nulls = std::iter::repeat_n(None, self.num_left_columns);
+                self.push_row(nulls.chain(owned_values(right)))
+            }
+            JoinType::RightAnti => self.push_row(owned_values(right)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Output a left row matching all `rights`, which is not empty.
+    fn left_matched(&mut self, batch: &Batch, row: usize, rights: &[OwnedRow]) -> Result<()> {
+        let left = batch.columns().iter().map(|column| column.get(row));
+        match self.join_type {
+            JoinType::LeftSemi => self.push_row(left),
+            JoinType::LeftAnti | JoinType::RightSemi | JoinType::RightAnti => Ok(()),
+            _ => {
+                for right in rights {
+                    self.push_row(left.clone().chain(owned_values(right)))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Output right rows matching any left row, after all left rows matching them are output by
+    /// [`JoinOutput::left_matched`].
+    fn right_matched(&mut self, rights: &[OwnedRow]) -> Result<()> {
+        if self.join_type == JoinType::RightSemi {
+            for right in rights {
+                self.push_row(owned_values(right))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<Batch>> {
+        self.batches.extend(self.builder.finish()?);
+        Ok(self.batches)
+    }
+}
@@ -0,0 +1,51 @@
+//! Implements operators executing queries over batches of rows.
+
+use anyhow::{Result, bail};
+
+use crate::TypeMismatch;
+use crate::array::Batch;
+use crate::dataType::DataType;
+
+mod codec;
+pub mod group_table;
+pub mod hash_agg;
+pub mod hash_join;
+pub mod merge_join;
+pub mod operator;
+pub mod order;
+pub mod parallel;
+pub mod sort;
+pub mod spill;
+pub mod window;
+
+pub use self::hash_agg::{AggCall, HashAggregate};
+pub use self::hash_join::{HashJoin, JoinType};
+pub use self::merge_join::{MergeJoin, RangeCondition, RangeJoin, RangeOp};
+pub use self::operator::{
+    BoxedOperator, Filter, Limit, PhysicalOperator, Projection, Scan, UnionAll, Values, batches,
+};
+pub use self::order::ColumnOrder;
+pub use self::parallel::{ParallelExecutor, Pipeline};
+pub use self::sort::{Sort, TopN};
+pub use self::spill::SpillConfig;
+pub use self::window::{Frame, FrameBound, Window, WindowCall, WindowFunction};
+
+/// Maximum number of rows of batches produced by operators.
+pub const DEFAULT_BATCH_SIZE: usize = 1024;
+
+/// Check that `batch` has columns of `types`.
+pub(crate) fn check_batch(batch: &Batch, types: &[DataType]) -> Result<()> {
+    if batch.columns().len() != types.len() {
+        bail!(
+            "expect {} columns, get {}",
+            types.len(),
+            batch.columns().len()
+        );
+    }
+    for (column, ty) in batch.columns().iter().zip(types) {
+        if column.identifier() != ty.physical_identifier() {
+            return Err(TypeMismatch(ty.physical_identifier(), column.identifier()).into());
+        }
+    }
+    Ok(())
+}
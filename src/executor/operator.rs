@@ -0,0 +1,313 @@
+//! Implements [`PhysicalOperator`], the interface of operators in a query plan, and basic
+//! operators: scan, values, filter, projection, limit and union all.
+//!
+//! Operators form a tree, where each operator pulls batches from its children by
+//! [`PhysicalOperator::next_batch`] and produces batches for its parent, so that a query is run
+//! by pulling batches from the root until it returns `None`.
+
+use anyhow::{Result, bail};
+
+use crate::TypeMismatch;
+use crate::array::{Array, ArrayImpl, Batch, BatchBuilder, BoolArray};
+use crate::dataType::DataType;
+use crate::executor::{DEFAULT_BATCH_SIZE, check_batch};
+use crate::expr::Expression;
+use crate::scalar::ScalarImpl;
+
+/// A trait over all physical operators, which produce batches of columns of
+/// [`PhysicalOperator::output_types`].
+pub trait PhysicalOperator {
+    /// Types of the output columns.
+    fn output_types(&self) -> &[DataType];
+
+    /// Get the next batch, or `None` if all batches are produced. Batches are never empty.
+    fn next_batch(&mut self) -> Result<Option<Batch>>;
+}
+
+pub type BoxedOperator = Box<dyn PhysicalOperator>;
+
+/// Get the remaining batches of `operator` as an iterator, which is the input of operators
+/// consuming all batches at once, like [`HashAggregate::aggregate`].
+///
+/// [`HashAggregate::aggregate`]: crate::executor::HashAggregate::aggregate
+pub fn batches(operator: &mut dyn PhysicalOperator) -> impl Iterator<Item = Result<Batch>> + '_ {
+    std::iter::from_fn(move || operator.next_batch().transpose())
+}
+
+/// Scans batches in memory.
+pub struct Scan {
+    types: Vec<DataType>,
+    batches: std::vec::IntoIter<Batch>,
+}
+
+impl Scan {
+    /// Create a scan of `batches`, which have columns of `types`.
+    pub fn new(types: Vec<DataType>, batches: Vec<Batch>) -> Result<Self> {
+        for batch in &batches {
+            check_batch(batch, &types)?;
+        }
+        Ok(Self {
+            types,
+            batches: batches.into_iter(),
+        })
+    }
+}
+
+impl PhysicalOperator for Scan {
+    fn output_types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        Ok(self.batches.find(|batch| !batch.is_empty()))
+    }
+}
+
+/// `VALUES (..), (..)`, which produces rows of constants.
+pub struct Values {
+    types: Vec<DataType>,
+    batches: std::vec::IntoIter<Batch>,
+}
+
+impl Values {
+    /// Create rows of values of `types`. `None` represents `NULL`.
+    pub fn new(types: Vec<DataType>, rows: Vec<Vec<Option<ScalarImpl>>>) -> Result<Self> {
+        let mut builder = BatchBuilder::new(types.clone(), DEFAULT_BATCH_SIZE)?;
+        let mut batches = vec![];
+        for row in &rows {
+            if row.len() != types.len() {
+                bail!("VALUES lists must all be the same length");
+            }
+            for (value, ty) in row.iter().zip(&types) {
+                if let Some(value) = value
+                    && value.identifier() != ty.physical_identifier()
+                {
+                    return Err(TypeMismatch(ty.physical_identifier(), value.identifier()).into());
+                }
+            }
+            let values = row.iter().map(|v| v.as_ref().map(|v| v.as_scalar_ref()));
+            batches.extend(builder.push_row(values)?);
+        }
+        batches.extend(builder.finish()?);
+        Ok(Self {
+            types,
+            batches: batches.into_iter(),
+        })
+    }
+}
+
+impl PhysicalOperator for Values {
+    fn output_types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        Ok(self.batches.next())
+    }
+}
+
+/// `WHERE predicate`, which keeps rows where the predicate is `true`.
+pub struct Filter {
+    child: BoxedOperator,
+    predicate: Box<dyn Expression>,
+}
+
+impl Filter {
+    pub fn new(child: BoxedOperator, predicate: Box<dyn Expression>) -> Self {
+        Self { child, predicate }
+    }
+}
+
+impl PhysicalOperator for Filter {
+    fn output_types(&self) -> &[DataType] {
+        self.child.output_types()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        while let Some(batch) = self.child.next_batch()? {
+            let batch = filter_batch(self.predicate.as_ref(), batch)?;
+            if !batch.is_empty() {
+                return Ok(Some(batch));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Keep rows of `batch` where `predicate` is `true`.
+pub(crate) fn filter_batch(predicate: &dyn Expression, batch: Batch) -> Result<Batch> {
+    let result = eval_on_batch(predicate, &batch)?;
+    let result: &BoolArray = (&result).try_into()?;
+    let visibility = result.iter().map(|v| v == Some(true)).collect::<Vec<_>>();
+    Ok(match visibility.iter().all(|v| *v) {
+        true => batch,
+        false => batch.filter(&visibility),
+    })
+}
+
+/// Evaluate `expr` on `batch`, which may not have any column.
+fn eval_on_batch(expr: &dyn Expression, batch: &Batch) -> Result<ArrayImpl> {
+    let result = expr.eval_rows(&batch.column_refs(), batch.num_rows())?;
+    if result.len() != batch.num_rows() {
+        bail!(
+            "expression produces {} rows on a batch of {} rows",
+            result.len(),
+            batch.num_rows()
+        );
+    }
+    Ok(result)
+}
+
+/// `SELECT exprs`, which evaluates expressions on each batch.
+pub struct Projection {
+    child: BoxedOperator,
+    exprs: Vec<Box<dyn Expression>>,
+    types: Vec<DataType>,
+}
+
+impl Projection {
+    /// Create a projection of `exprs`, whose results are of `types`.
+    pub fn new(
+        child: BoxedOperator,
+        exprs: Vec<Box<dyn Expression>>,
+        types: Vec<DataType>,
+    ) -> Result<Self> {
+        if exprs.len() != types.len() {
+            bail!("expect {} types, get {}", exprs.len(), types.len());
+        }
+        Ok(Self {
+            child,
+            exprs,
+            types,
+        })
+    }
+}
+
+impl PhysicalOperator for Projection {
+    fn output_types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        let Some(batch) = self.child.next_batch()? else {
+            return Ok(None);
+        };
+        project_batch(&self.exprs, &self.types, &batch).map(Some)
+    }
+}
+
+/// Evaluate `exprs` on `batch`, whose results are of `types`.
+pub(crate) fn project_batch(
+    exprs: &[Box<dyn Expression>],
+    types: &[DataType],
+    batch: &Batch,
+) -> Result<Batch> {
+    let columns = exprs
+        .iter()
+        .map(|expr| eval_on_batch(expr.as_ref(), batch))
+        .collect::<Result<Vec<ArrayImpl>>>()?;
+    let batch = match columns.is_empty() {
+        true => Batch::no_columns(batch.num_rows()),
+        false => Batch::new(columns)?,
+    };
+    check_batch(&batch, types)?;
+    Ok(batch)
+}
+
+/// `LIMIT limit OFFSET offset`, which skips the first `offset` rows and produces at most `limit`
+/// rows after them. The child is not pulled any more once the limit is reached.
+pub struct Limit {
+    child: BoxedOperator,
+    /// Number of rows to skip yet.
+    offset: usize,
+    /// Number of rows to produce yet, or `None` if unlimited.
+    limit: Option<usize>,
+}
+
+impl Limit {
+    pub fn new(child: BoxedOperator, limit: Option<usize>, offset: usize) -> Self {
+        Self {
+            child,
+            offset,
+            limit,
+        }
+    }
+}
+
+impl PhysicalOperator for Limit {
+    fn output_types(&self) -> &[DataType] {
+        self.child.output_types()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        while self.limit != Some(0) {
+            let Some(batch) = self.child.next_batch()? else {
+                break;
+            };
+            let num_rows = batch.num_rows();
+            let skip = self.offset.min(num_rows);
+            self.offset -= skip;
+            let take = self.limit.unwrap_or(usize::MAX).min(num_rows - skip);
+            if take == 0 {
+                continue;
+            }
+            if let Some(limit) = &mut self.limit {
+                *limit -= take;
+            }
+            if take == num_rows {
+                return Ok(Some(batch));
+            }
+            let visibility = (0..num_rows)
+                .map(|row| row >= skip && row < skip + take)
+                .collect::<Vec<_>>();
+            return Ok(Some(batch.filter(&visibility)));
+        }
+        Ok(None)
+    }
+}
+
+/// `UNION ALL`, which produces all batches of each child in order.
+pub struct UnionAll {
+    children: Vec<BoxedOperator>,
+    /// Index of the child being pulled.
+    current: usize,
+}
+
+impl UnionAll {
+    /// Create a union of `children`, which must have the same output types.
+    pub fn new(children: Vec<BoxedOperator>) -> Result<Self> {
+        let Some((first, rest)) = children.split_first() else {
+            bail!("UNION ALL requires at least one input");
+        };
+        for child in rest {
+            if child.output_types().len() != first.output_types().len() {
+                bail!("each UNION query must have the same number of columns");
+            }
+            for (a, b) in first.output_types().iter().zip(child.output_types()) {
+                if a.physical_identifier() != b.physical_identifier() {
+                    bail!("UNION types {a} and {b} cannot be matched");
+                }
+            }
+        }
+        Ok(Self {
+            children,
+            current: 0,
+        })
+    }
+}
+
+impl PhysicalOperator for UnionAll {
+    fn output_types(&self) -> &[DataType] {
+        self.children[0].output_types()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        while let Some(child) = self.children.get_mut(self.current) {
+            if let Some(batch) = child.next_batch()? {
+                return Ok(Some(batch));
+            }
+            self.current += 1;
+        }
+        Ok(None)
+    }
+}
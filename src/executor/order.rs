@@ -0,0 +1,82 @@
+//! Implements the order of rows in `ORDER BY`.
+
+use std::cmp::Ordering;
+
+use crate::array::ArrayImpl;
+use crate::expr::cmp::SqlOrd;
+use crate::scalar::ScalarRefImpl;
+
+/// The order of one column in `ORDER BY`. Values are compared by [`SqlOrd`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColumnOrder {
+    /// Index of the column in input batches.
+    pub column: usize,
+    pub desc: bool,
+    pub nulls_first: bool,
+}
+
+impl ColumnOrder {
+    /// `ORDER BY column ASC`, where `NULL`s are put last like PostgreSQL.
+    pub fn asc(column: usize) -> Self {
+        Self {
+            column,
+            desc: false,
+            nulls_first: false,
+        }
+    }
+
+    /// `ORDER BY column DESC`, where `NULL`s are put first like PostgreSQL.
+    pub fn desc(column: usize) -> Self {
+        Self {
+            column,
+            desc: true,
+            nulls_first: true,
+        }
+    }
+
+    /// Set `NULLS FIRST` or `NULLS LAST`.
+    pub fn nulls_first(self, nulls_first: bool) -> Self {
+        Self {
+            nulls_first,
+            ..self
+        }
+    }
+
+    /// Compare two values of the column.
+    pub fn compare(&self, a: Option<ScalarRefImpl<'_>>, b: Option<ScalarRefImpl<'_>>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => match self.desc {
+                true => b.sql_cmp(&a),
+                false => a.sql_cmp(&b),
+            },
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if self.nulls_first => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) if self.nulls_first => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+        }
+    }
+}
+
+/// Compare row `a` and row `b` of `columns` by `orders`.
+pub fn compare_rows(columns: &[ArrayImpl], orders: &[ColumnOrder], a: usize, b: usize) -> Ordering {
+    compare_by(
+        orders,
+        |column| columns[column].get(a),
+        |column| columns[column].get(b),
+    )
+}
+
+/// Compare two rows by `orders`, where the values of columns of the rows are got by `a` and `b`,
+/// so that rows of different batches or rows copied out of batches can be compared.
+pub fn compare_by<'a, 'b>(
+    orders: &[ColumnOrder],
+    a: impl Fn(usize) -> Option<ScalarRefImpl<'a>>,
+    b: impl Fn(usize) -> Option<ScalarRefImpl<'b>>,
+) -> Ordering {
+    orders
+        .iter()
+        .map(|order| order.compare(a(order.column), b(order.column)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
@@ -0,0 +1,324 @@
+//! Implements morsel-driven parallel execution.
+//!
+//! Input batches of a scan are split into morsels of at most `morsel_size` rows. Each call of a
+//! [`ParallelExecutor`] starts its worker threads in a [`std::thread::scope`], which take morsels
+//! one by one, so that faster workers take more morsels, and run a [`Pipeline`] of filters and
+//! projections on each morsel. Results of morsels are then combined by one of the sinks of
+//! [`ParallelExecutor`]:
+//!
+//! * [`ParallelExecutor::collect`] returns the results in the order of morsels.
+//! * [`ParallelExecutor::aggregate`] aggregates the results of each worker into a partial
+//!   [`HashAggregate`], and merges the partial aggregations.
+//! * [`ParallelExecutor::sort`] sorts the results of each morsel into a sorted run, and merges the
+//!   runs.
+//!
+//! Results of `collect` and `sort` are the same as executing sequentially, no matter how morsels
+//! are distributed to workers, as morsels are ordered by their positions in the input and sorting
+//! is stable. Only the order of groups of `aggregate` may differ between runs.
+
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use anyhow::{Result, bail};
+
+use crate::array::Batch;
+use crate::dataType::DataType;
+use crate::executor::operator::{filter_batch, project_batch};
+use crate::executor::order::ColumnOrder;
+use crate::executor::sort::{Merger, check_orders, sort_chunks};
+use crate::executor::{DEFAULT_BATCH_SIZE, HashAggregate, check_batch};
+use crate::expr::Expression;
+
+/// Default maximum number of rows of a morsel.
+pub const DEFAULT_MORSEL_SIZE: usize = DEFAULT_BATCH_SIZE;
+
+/// Filters and projections run on each morsel, in the order they are added.
+pub struct Pipeline {
+    input_types: Vec<DataType>,
+    steps: Vec<PipelineStep>,
+}
+
+enum PipelineStep {
+    /// Keep rows where the predicate is `true`, like [`Filter`](crate::executor::Filter).
+    Filter(Box<dyn Expression>),
+    /// Evaluate expressions of the given result types, like
+    /// [`Projection`](crate::executor::Projection).
+    Project(Vec<Box<dyn Expression>>, Vec<DataType>),
+}
+
+impl Pipeline {
+    /// Create an empty pipeline of input batches of `input_types`, which returns its input.
+    pub fn new(input_types: Vec<DataType>) -> Self {
+        Self {
+            input_types,
+            steps: vec![],
+        }
+    }
+
+    /// Add a filter of `predicate`.
+    pub fn filter(mut self, predicate: Box<dyn Expression>) -> Self {
+        self.steps.push(PipelineStep::Filter(predicate));
+        self
+    }
+
+    /// Add a projection of `exprs`, whose results are of `types`.
+    pub fn project(
+        mut self,
+        exprs: Vec<Box<dyn Expression>>,
+        types: Vec<DataType>,
+    ) -> Result<Self> {
+        if exprs.len() != types.len() {
+            bail!("expect {} types, get {}", exprs.len(), types.len());
+        }
+        self.steps.push(PipelineStep::Project(exprs, types));
+        Ok(self)
+    }
+
+    pub fn input_types(&self) -> &[DataType] {
+        &self.input_types
+    }
+
+    /// Types of the output columns.
+    pub fn output_types(&self) -> &[DataType] {
+        let last = self.steps.iter().rev().find_map(|step| match step {
+            PipelineStep::Project(_, types) => Some(types),
+            PipelineStep::Filter(_) => None,
+        });
+        last.unwrap_or(&self.input_types)
+    }
+
+    /// Run all steps on `batch`, or return `None` if all rows are filtered out.
+    pub fn execute(&self, mut batch: Batch) -> Result<Option<Batch>> {
+        for step in &self.steps {
+            if batch.is_empty() {
+                return Ok(None);
+            }
+            batch = match step {
+                PipelineStep::Filter(predicate) => filter_batch(predicate.as_ref(), batch)?,
+                PipelineStep::Project(exprs, types) => project_batch(exprs, types, &batch)?,
+            };
+        }
+        Ok((!batch.is_empty()).then_some(batch))
+    }
+}
+
+/// Runs pipelines on morsels of input batches with a pool of worker threads. The workers of each
+/// call are scoped to the call, so that they may borrow its inputs.
+pub struct ParallelExecutor {
+    num_workers: usize,
+    morsel_size: usize,
+    batch_size: usize,
+}
+
+impl Default for ParallelExecutor {
+    /// Create an executor with one worker for each available CPU.
+    fn default() -> Self {
+        Self {
+            num_workers: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            morsel_size: DEFAULT_MORSEL_SIZE,
+            batch_size: DEFAULT_BATCH_SIZE,
+        }
+    }
+}
+
+impl ParallelExecutor {
+    /// Create an executor with `num_workers` worker threads.
+    pub fn new(num_workers: usize) -> Result<Self> {
+        if num_workers == 0 {
+            bail!("number of workers must be positive");
+        }
+        Ok(Self {
+            num_workers,
+            ..Self::default()
+        })
+    }
+
+    /// Set the maximum number of rows of morsels.
+    pub fn with_morsel_size(mut self, morsel_size: usize) -> Result<Self> {
+        if morsel_size == 0 {
+            bail!("morsel size must be positive");
+        }
+        self.morsel_size = morsel_size;
+        Ok(self)
+    }
+
+    /// Set the maximum number of rows of output batches of [`ParallelExecutor::sort`].
+    pub fn with_batch_size(mut self, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            bail!("batch size must be positive");
+        }
+        self.batch_size = batch_size;
+        Ok(self)
+    }
+
+    pub fn num_workers(&self) -> usize {
+        self.num_workers
+    }
+
+    /// Run `pipeline` on `batches`, and return the non-empty results in the order of the input.
+    pub fn collect(&self, batches: &[Batch], pipeline: &Pipeline) -> Result<Vec<Batch>> {
+        let results = self.run(
+            batches,
+            pipeline,
+            || Ok(vec![]),
+            |results, morsel, batch| {
+                results.push((morsel, batch));
+                Ok(())
+            },
+        )?;
+        let mut results = results.into_iter().flatten().collect::<Vec<_>>();
+        results.sort_unstable_by_key(|(morsel, _)| *morsel);
+        Ok(results.into_iter().map(|(_, batch)| batch).collect())
+    }
+
+    /// Run `pipeline` on `batches`, and aggregate the results with aggregations created by
+    /// `create`, one for each worker. Partial aggregations of workers are merged by
+    /// [`HashAggregate::merge`].
+    pub fn aggregate(
+        &self,
+        batches: &[Batch],
+        pipeline: &Pipeline,
+        create: impl Fn() -> Result<HashAggregate> + Sync,
+    ) -> Result<Vec<Batch>> {
+        let partials = self.run(batches, pipeline, &create, |agg, _, batch| {
+            agg.update(&batch)
+        })?;
+        let mut partials = partials.into_iter();
+        let Some(mut agg) = partials.next() else {
+            return create()?.finish();
+        };
+        for partial in partials {
+            agg.merge(partial)?;
+        }
+        agg.finish()
+    }
+
+    /// Run `pipeline` on `batches`, and sort the results by `orders`. Rows with equal order keys
+    /// are in the order of the input.
+    pub fn sort(
+        &self,
+        batches: &[Batch],
+        pipeline: &Pipeline,
+        orders: Vec<ColumnOrder>,
+    ) -> Result<Vec<Batch>> {
+        let types = pipeline.output_types();
+        check_orders(&orders, types)?;
+        let runs = self.run(
+            batches,
+            pipeline,
+            || Ok(vec![]),
+            |runs, morsel, batch| {
+                let run = sort_chunks(&[batch], &orders, types, self.batch_size)?;
+                runs.push((morsel, run));
+                Ok(())
+            },
+        )?;
+        // Runs are merged in the order of morsels, as the merger puts equal rows of earlier runs
+        // first.
+        let mut runs = runs.into_iter().flatten().collect::<Vec<_>>();
+        runs.sort_unstable_by_key(|(morsel, _)| *morsel);
+        let runs = runs
+            .into_iter()
+            .map(|(_, run)| Box::new(run.into_iter().map(Ok)) as Box<dyn Iterator<Item = _>>)
+            .collect();
+        let mut merger = Merger::new(runs, orders, types.to_vec(), self.batch_size)?;
+        let mut outputs = vec![];
+        while let Some(batch) = merger.next_batch()? {
+            outputs.push(batch);
+        }
+        Ok(outputs)
+    }
+
+    /// Split `batches` into morsels, given as batch indexes and ranges of rows.
+    fn morsels(&self, batches: &[Batch]) -> Vec<(usize, usize, usize)> {
+        let mut morsels = vec![];
+        for (idx, batch) in batches.iter().enumerate() {
+            for start in (0..batch.num_rows()).step_by(self.morsel_size) {
+                let end = (start + self.morsel_size).min(batch.num_rows());
+                morsels.push((idx, start, end));
+            }
+        }
+        morsels
+    }
+
+    /// Run `pipeline` on morsels of `batches` with the workers. Each worker taking any morsel
+    /// creates a state with `init`, and passes it to `sink` with the index and the result of each
+    /// morsel it takes. Returns the states of the workers, or the first error of any morsel.
+    fn run<S: Send>(
+        &self,
+        batches: &[Batch],
+        pipeline: &Pipeline,
+        init: impl Fn() -> Result<S> + Sync,
+        sink: impl Fn(&mut S, usize, Batch) -> Result<()> + Sync,
+    ) -> Result<Vec<S>> {
+        for batch in batches {
+            check_batch(batch, pipeline.input_types())?;
+        }
+        let morsels = self.morsels(batches);
+        // Workers take the next morsel from `next` until all are taken, or any morsel fails.
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let process = |state: &mut Option<S>, morsel: usize| -> Result<()> {
+            let state = match state {
+                Some(state) => state,
+                None => state.insert(init()?),
+            };
+            let (idx, start, end) = morsels[morsel];
+            let batch = &batches[idx];
+            let batch = match end - start == batch.num_rows() {
+                true => batch.clone(),
+                false => batch.take(&(start..end).collect::<Vec<_>>()),
+            };
+            if let Some(batch) = pipeline.execute(batch)? {
+                sink(state, morsel, batch)?;
+            }
+            Ok(())
+        };
+        let worker = || -> Result<Option<S>> {
+            let mut state = None;
+            while !failed.load(Ordering::Relaxed) {
+                let morsel = next.fetch_add(1, Ordering::Relaxed);
+                if morsel >= morsels.len() {
+                    break;
+                }
+                match std::panic::catch_unwind(AssertUnwindSafe(|| process(&mut state, morsel))) {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => {
+                        failed.store(true, Ordering::Relaxed);
+                        return Err(e);
+                    }
+                    Err(panic) => {
+                        failed.store(true, Ordering::Relaxed);
+                        std::panic::resume_unwind(panic);
+                    }
+                }
+            }
+            Ok(state)
+        };
+        let results = std::thread::scope(|scope| {
+            let workers = (0..self.num_workers.min(morsels.len()))
+                .map(|_| scope.spawn(worker))
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|worker| worker.join())
+                .collect::<Vec<_>>()
+        });
+
+        let mut states = Vec::with_capacity(results.len());
+        let mut error = None;
+        for result in results {
+            match result {
+                Ok(Ok(state)) => states.extend(state),
+                Ok(Err(e)) => {
+                    error.get_or_insert(e);
+                }
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+        match error {
+            Some(error) => Err(error),
+            None => Ok(states),
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Implements `ORDER BY`: [`Sort`], which sorts all rows and spills sorted runs to disk when they
+//! do not fit in memory, and [`TopN`] for `ORDER BY .. LIMIT n`, which only keeps the first `n`
+//! rows.
+//!
+//! Both are stable: rows with equal order keys are produced in the order of the input.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+
+use crate::array::{Batch, BatchBuilder};
+use crate::dataType::DataType;
+use crate::executor::DEFAULT_BATCH_SIZE;
+use crate::executor::operator::{BoxedOperator, PhysicalOperator};
+use crate::executor::order::{ColumnOrder, compare_by};
+use crate::executor::spill::{SpillConfig, SpillFile};
+use crate::scalar::{ScalarImpl, ScalarRefImpl};
+
+/// Default max number of sorted runs merged at once by [`Sort`].
+pub const DEFAULT_FAN_IN: usize = 64;
+
+/// Check that columns of `orders` are in `types`.
+pub(crate) fn check_orders(orders: &[ColumnOrder], types: &[DataType]) -> Result<()> {
+    if let Some(order) = orders.iter().find(|order| order.column >= types.len()) {
+        bail!(
+            "column index {} out of range, there are {} input columns",
+            order.column,
+            types.len()
+        );
+    }
+    Ok(())
+}
+
+/// Sort rows of `chunks` by `orders` with an argsort, and gather them into batches.
+pub(crate) fn sort_chunks(
+    chunks: &[Batch],
+    orders: &[ColumnOrder],
+    types: &[DataType],
+    batch_size: usize,
+) -> Result<Vec<Batch>> {
+    let mut indices = chunks
+        .iter()
+        .enumerate()
+        .flat_map(|(chunk, batch)| (0..batch.num_rows()).map(move |row| (chunk, row)))
+        .collect::<Vec<_>>();
+    indices.sort_by(|(ca, ra), (cb, rb)| {
+        compare_by(
+            orders,
+            |column| chunks[*ca].column(column).get(*ra),
+            |column| chunks[*cb].column(column).get(*rb),
+        )
+    });
+    let mut builder = BatchBuilder::new(types.to_vec(), batch_size)?;
+    let mut batches = vec![];
+    for (chunk, row) in indices {
+        let values = chunks[chunk].columns().iter().map(|column| column.get(row));
+        batches.extend(builder.push_row(values)?);
+    }
+    batches.extend(builder.finish()?);
+    Ok(batches)
+}
+
+/// Sorts all rows of its child by `orders`.
+///
+/// Input batches are kept in memory until their estimated size exceeds the memory budget, when
+/// they are sorted and written to a temporary file as a sorted run. After all input is read,
+/// sorted runs and rows left in memory are merged with a k-way merge. At most `fan_in` runs are
+/// merged at once, so when there are more runs, adjacent runs are merged into longer runs in
+/// passes first.
+pub struct Sort {
+    child: BoxedOperator,
+    types: Vec<DataType>,
+    orders: Vec<ColumnOrder>,
+    config: SpillConfig,
+    batch_size: usize,
+    /// Max number of runs merged at once, including rows left in memory.
+    fan_in: usize,
+    /// Sorted output, which is ready after all input is read.
+    output: Option<SortOutput>,
+}
+
+enum SortOutput {
+    /// All rows fit in memory.
+    InMemory(std::vec::IntoIter<Batch>),
+    /// Rows are merged from sorted runs.
+    Merge(Merger),
+}
+
+impl Sort {
+    pub fn new(child: BoxedOperator, orders: Vec<ColumnOrder>) -> Result<Self> {
+        let types = child.output_types().to_vec();
+        check_orders(&orders, &types)?;
+        Ok(Self {
+            child,
+            types,
+            orders,
+            config: SpillConfig::default(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            fan_in: DEFAULT_FAN_IN,
+            output: None,
+        })
+    }
+
+    /// Set where and when to spill sorted runs.
+    pub fn with_spill_config(self, config: SpillConfig) -> Self {
+        Self { config, ..self }
+    }
+
+    /// Set the maximum number of rows of output batches.
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    /// Set the maximum number of runs merged at once, which is at least 2.
+    pub fn with_fan_in(self, fan_in: usize) -> Result<Self> {
+        if fan_in < 2 {
+            bail!("fan-in of merging sorted runs must be at least 2, get {fan_in}");
+        }
+        Ok(Self { fan_in, ..self })
+    }
+
+    /// Read all input, spilling sorted runs if needed.
+    fn sort_input(&mut self) -> Result<SortOutput> {
+        let mut chunks = vec![];
+        let mut size = 0;
+        let mut runs = vec![];
+        while let Some(batch) = self.child.next_batch()? {
+            size += batch.estimated_size();
+            chunks.push(batch);
+            if size > self.config.memory_budget {
+                let mut file = SpillFile::create(&self.config.dir)?;
+                for batch in sort_chunks(&chunks, &self.orders, &self.types, self.batch_size)? {
+                    file.write(&batch)?;
+                }
+                file.finish()?;
+                runs.push(file);
+                chunks.clear();
+                size = 0;
+            }
+        }
+        let sorted = sort_chunks(&chunks, &self.orders, &self.types, self.batch_size)?;
+        if runs.is_empty() {
+            return Ok(SortOutput::InMemory(sorted.into_iter()));
+        }
+        while runs.len() + 1 > self.fan_in {
+            runs = self.merge_pass(runs)?;
+        }
+        let mut runs = self.open_runs(runs)?;
+        // Rows left in memory are the last run, so that equal rows keep the order of the input.
+        runs.push(Box::new(sorted.into_iter().map(Ok)));
+        Ok(SortOutput::Merge(self.merger(runs)?))
+    }
+
+    /// Merge every `fan_in` adjacent spilled runs into one. Runs stay in the order of the input,
+    /// so that equal rows keep their order.
+    fn merge_pass(&self, runs: Vec<SpillFile>) -> Result<Vec<SpillFile>> {
+        let mut merged = vec![];
+        let mut runs = runs.into_iter().peekable();
+        while runs.peek().is_some() {
+            let group = runs.by_ref().take(self.fan_in).collect::<Vec<_>>();
+            if group.len() == 1 {
+                merged.extend(group);
+                continue;
+            }
+            let mut merger = self.merger(self.open_runs(group)?)?;
+            let mut file = SpillFile::create(&self.config.dir)?;
+            while let Some(batch) = merger.next_batch()? {
+                file.write(&batch)?;
+            }
+            file.finish()?;
+            merged.push(file);
+        }
+        Ok(merged)
+    }
+
+    fn open_runs(&self, runs: Vec<SpillFile>) -> Result<Vec<Run>> {
+        runs.into_iter()
+            .map(|file| Ok(Box::new(file.into_reader(self.types.clone())?) as Run))
+            .collect()
+    }
+
+    fn merger(&self, runs: Vec<Run>) -> Result<Merger> {
+        Merger::new(
+            runs,
+            self.orders.clone(),
+            self.types.clone(),
+            self.batch_size,
+        )
+    }
+}
+
+impl PhysicalOperator for Sort {
+    fn output_types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        if self.output.is_none() {
+            self.output = Some(self.sort_input()?);
+        }
+        match self.output.as_mut().unwrap() {
+            SortOutput::InMemory(batches) => Ok(batches.next()),
+            SortOutput::Merge(merger) => merger.next_batch(),
+        }
+    }
+}
+
+/// A sorted run of batches.
+pub(crate) type Run = Box<dyn Iterator<Item = Result<Batch>>>;
+
+/// Merges sorted runs of batches, with a binary heap of runs ordered by their current rows.
+pub(crate) struct Merger {
+    runs: Vec<Run>,
+    /// The current batch and row of each run, or `None` if the run is exhausted.
+    current: Vec<Option<(Batch, usize)>>,
+    /// Indexes of runs that are not exhausted, as a min-heap by their current rows.
+    heap: Vec<usize>,
+    orders: Vec<ColumnOrder>,
+    types: Vec<DataType>,
+    batch_size: usize,
+}
+
+impl Merger {
+    pub(crate) fn new(
+        mut runs: Vec<Run>,
+        orders: Vec<ColumnOrder>,
+        types: Vec<DataType>,
+        batch_size: usize,
+    ) -> Result<Self> {
+        let current = runs
+            .iter_mut()
+            .map(|run| next_non_empty(run.as_mut()).map(|batch| batch.map(|b| (b, 0))))
+            .collect::<Result<Vec<_>>>()?;
+        let mut merger = Self {
+            runs,
+            heap: vec![],
+            current,
+            orders,
+            types,
+            batch_size,
+        };
+        for run in 0..merger.runs.len() {
+            if merger.current[run].is_some() {
+                merger.heap.push(run);
+                merger.sift_up(merger.heap.len() - 1);
+            }
+        }
+        Ok(merger)
+    }
+
+    pub(crate) fn next_batch(&mut self) -> Result<Option<Batch>> {
+        let mut builder = BatchBuilder::new(self.types.clone(), self.batch_size)?;
+        while let Some(run) = self.heap.first().copied() {
+            let (batch, row) = self.current[run].as_mut().unwrap();
+            let values = batch.columns().iter().map(|column| column.get(*row));
+            let output = builder.push_row(values)?;
+            *row += 1;
+            if *row >= batch.num_rows() {
+                self.current[run] = next_non_empty(self.runs[run].as_mut())?.map(|b| (b, 0));
+            }
+            if self.current[run].is_none() {
+                let last = self.heap.pop().unwrap();
+                if !self.heap.is_empty() {
+                    self.heap[0] = last;
+                }
+            }
+            self.sift_down(0);
+            if output.is_some() {
+                return Ok(output);
+            }
+        }
+        builder.finish()
+    }
+
+    /// Compare the current rows of two runs. Equal rows are ordered by their runs.
+    fn compare(&self, a: usize, b: usize) -> Ordering {
+        let (batch_a, row_a) = self.current[a].as_ref().unwrap();
+        let (batch_b, row_b) = self.current[b].as_ref().unwrap();
+        compare_by(
+            &self.orders,
+            |column| batch_a.column(column).get(*row_a),
+            |column| batch_b.column(column).get(*row_b),
+        )
+        .then(a.cmp(&b))
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.compare(self.heap[i], self.heap[parent]).is_ge() {
+                break;
+            }
+            self.heap.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut smallest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len()
+                    && self.compare(self.heap[child], self.heap[smallest]).is_lt()
+                {
+                    smallest = child;
+                }
+            }
+            if smallest == i {
+                return;
+            }
+            self.heap.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+fn next_non_empty(run: &mut dyn Iterator<Item = Result<Batch>>) -> Result<Option<Batch>> {
+    for batch in run {
+        let batch = batch?;
+        if !batch.is_empty() {
+            return Ok(Some(batch));
+        }
+    }
+    Ok(None)
+}
+
+/// A row copied out of its batch, ordered by `orders` and then by its position in the input.
+struct HeapRow {
+    values: Box<[Option<ScalarImpl>]>,
+    seq: usize,
+    orders: Arc<[ColumnOrder]>,
+}
+
+impl HeapRow {
+    fn value(&self, column: usize) -> Option<ScalarRefImpl<'_>> {
+        self.values[column].as_ref().map(|v| v.as_scalar_ref())
+    }
+}
+
+impl Ord for HeapRow {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_by(
+            &self.orders,
+            |column| self.value(column),
+            |column| other.value(column),
+        )
+        .then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for HeapRow {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapRow {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for HeapRow {}
+
+/// `ORDER BY orders LIMIT limit OFFSET offset`, which keeps the first `limit + offset` rows in a
+/// max-heap, so that each input row is compared with the last row kept.
+pub struct TopN {
+    child: BoxedOperator,
+    types: Vec<DataType>,
+    orders: Arc<[ColumnOrder]>,
+    limit: usize,
+    offset: usize,
+    batch_size: usize,
+    /// Output batches, which are ready after all input is read.
+    output: Option<std::vec::IntoIter<Batch>>,
+}
+
+impl TopN {
+    pub fn new(
+        child: BoxedOperator,
+        orders: Vec<ColumnOrder>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Self> {
+        let types = child.output_types().to_vec();
+        check_orders(&orders, &types)?;
+        Ok(Self {
+            child,
+            types,
+            orders: orders.into(),
+            limit,
+            offset,
+            batch_size: DEFAULT_BATCH_SIZE,
+            output: None,
+        })
+    }
+
+    /// Set the maximum number of rows of output batches.
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    fn top_rows(&mut self) -> Result<Vec<Batch>> {
+        let capacity = self.limit.saturating_add(self.offset);
+        let mut heap = BinaryHeap::<HeapRow>::new();
+        let mut seq = 0;
+        if self.limit > 0 {
+            while let Some(batch) = self.child.next_batch()? {
+                for row in 0..batch.num_rows() {
+                    // A later row must be strictly less than the last row kept to replace it.
+                    if heap.len() >= capacity {
+                        let last = heap.peek().unwrap();
+                        let ordering = compare_by(
+                            &self.orders,
+                            |column| batch.column(column).get(row),
+                            |column| last.value(column),
+                        );
+                        if ordering.is_ge() {
+                            continue;
+                        }
+                    }
+                    let values = batch
+                        .columns()
+                        .iter()
+                        .map(|column| column.get(row).map(|v| v.to_owned_scalar()))
+                        .collect();
+                    let new = HeapRow {
+                        values,
+                        seq,
+                        orders: self.orders.clone(),
+                    };
+                    seq += 1;
+                    match heap.len() >= capacity {
+                        true => *heap.peek_mut().unwrap() = new,
+                        false => heap.push(new),
+                    }
+                }
+            }
+        }
+        let mut builder = BatchBuilder::new(self.types.clone(), self.batch_size)?;
+        let mut batches = vec![];
+        for row in heap.into_sorted_vec().into_iter().skip(self.offset) {
+            let values = (0..self.types.len()).map(|column| row.value(column));
+            batches.extend(builder.push_row(values)?);
+        }
+        batches.extend(builder.finish()?);
+        Ok(batches)
+    }
+}
+
+impl PhysicalOperator for TopN {
+    fn output_types(&self) -> &[DataType] {
+        &self.types
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        if self.output.is_none() {
+            self.output = Some(self.top_rows()?.into_iter());
+        }
+        Ok(self.output.as_mut().unwrap().next())
+    }
+}
@@ -0,0 +1,270 @@
+//! Implements spilling batches to temporary files, for operators whose state exceeds their memory
+//! budget.
+//!
+//! Batches are written one after another in the following format, where all integers are
+//! little-endian:
+//!
+//! * The number of rows as a `u32`, and the number of bytes of the values as a `u64`.
+//! * For each column, for each row: a tag byte of `0` for `NULL` and `1` otherwise, followed by the
+//!   value: bytes of integers, bits of floats, a byte for booleans, or the length of strings as a
+//!   `u32` followed by their bytes.
+//!
+//! Values are encoded like keys of [`KeySerializer`](crate::executor::group_table::KeySerializer),
+//! except that they are written exactly, so `-0.0` and payloads of `NaN`s are kept. The types of
+//! columns are not written, as the operator reading a file knows them. Values of a batch are read
+//! into a buffer at once, and decoded from it into arrays directly.
+//!
+//! Grace hash aggregation and joins spill rows into [`SpillPartitions`], which are processed one
+//! by one afterwards, and partitioned again if they are still too large.
+
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, RandomState};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result, bail};
+
+use crate::array::Batch;
+use crate::dataType::DataType;
+use crate::executor::codec::{decode_value, encode_value};
+use crate::executor::group_table::KeySerializer;
+
+/// Default memory budget of operators that spill, in bytes.
+pub const DEFAULT_MEMORY_BUDGET: usize = 64 << 20;
+
+/// Number of partitions rows are spilled into by [`SpillPartitions`].
+pub const NUM_PARTITIONS: usize = 16;
+
+/// Maximum number of times rows are partitioned again. Deeper partitions are processed in memory,
+/// as they are likely to have too many rows of the same key to be split.
+pub const MAX_SPILL_DEPTH: usize = 4;
+
+/// Where and when operators spill.
+#[derive(Clone, Debug)]
+pub struct SpillConfig {
+    /// Directory of temporary files.
+    pub dir: PathBuf,
+    /// Estimated number of bytes of batches an operator keeps in memory before spilling.
+    pub memory_budget: usize,
+}
+
+impl Default for SpillConfig {
+    fn default() -> Self {
+        Self {
+            dir: std::env::temp_dir(),
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+        }
+    }
+}
+
+/// A temporary file of batches, which is removed when dropped.
+pub struct SpillFile {
+    path: PathBuf,
+    /// The open file, or `None` once writing is finished.
+    writer: Option<BufWriter<File>>,
+}
+
+impl SpillFile {
+    /// Create an empty file in `dir`.
+    pub fn create(dir: &Path) -> Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "type_rust-spill-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = dir.join(name);
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("failed to create spill file {}", path.display()))?;
+        Ok(Self {
+            path,
+            writer: Some(BufWriter::new(file)),
+        })
+    }
+
+    /// Append `batch` to the file.
+    pub fn write(&mut self, batch: &Batch) -> Result<()> {
+        let Some(writer) = &mut self.writer else {
+            bail!("cannot write to a finished spill file");
+        };
+        let mut buf = vec![];
+        serialize_batch(batch, &mut buf)?;
+        writer.write_all(&buf)?;
+        Ok(())
+    }
+
+    /// Finish writing and close the file, so that many files can be kept without holding a file
+    /// descriptor for each.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Finish writing, and read batches of columns of `types` from the start of the file.
+    pub fn into_reader(mut self, types: Vec<DataType>) -> Result<SpillReader> {
+        self.finish()?;
+        let reader = BufReader::new(File::open(&self.path)?);
+        Ok(SpillReader {
+            _file: self,
+            reader,
+            types,
+            buf: vec![],
+        })
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Reads batches from a [`SpillFile`], which is removed when the reader is dropped.
+pub struct SpillReader {
+    _file: SpillFile,
+    reader: BufReader<File>,
+    types: Vec<DataType>,
+    /// Reused buffer of the values of a batch.
+    buf: Vec<u8>,
+}
+
+impl SpillReader {
+    /// Read the next batch, or `None` at the end of the file.
+    pub fn next_batch(&mut self) -> Result<Option<Batch>> {
+        let mut num_rows = [0; 4];
+        match self.reader.read_exact(&mut num_rows) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let num_rows = u32::from_le_bytes(num_rows) as usize;
+        let mut len = [0; 8];
+        self.reader
+            .read_exact(&mut len)
+            .context("unexpected end of spill file")?;
+        self.buf.resize(u64::from_le_bytes(len) as usize, 0);
+        self.reader
+            .read_exact(&mut self.buf)
+            .context("unexpected end of spill file")?;
+
+        let mut values = self.buf.as_slice();
+        let mut columns = Vec::with_capacity(self.types.len());
+        for ty in &self.types {
+            let mut builder = ty.create_array_builder(num_rows)?;
+            for _ in 0..num_rows {
+                builder.push(decode_value(builder.identifier(), &mut values)?);
+            }
+            columns.push(builder.finish());
+        }
+        if !values.is_empty() {
+            bail!(
+                "{} trailing bytes after a batch in spill file",
+                values.len()
+            );
+        }
+        Ok(Some(match columns.is_empty() {
+            true => Batch::no_columns(num_rows),
+            false => Batch::new(columns)?,
+        }))
+    }
+}
+
+impl Iterator for SpillReader {
+    type Item = Result<Batch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+/// Temporary files of rows partitioned by the hashes of their keys.
+///
+/// Keys are serialized with [`KeySerializer`] like keys of hash tables, so rows with equal keys
+/// are always in the same partition. Inputs that are processed together, like both sides of a
+/// join, must be partitioned with the same `hasher`, while partitioning a partition again needs
+/// a new one.
+pub struct SpillPartitions {
+    /// Indexes of the key columns in batches.
+    keys: Vec<usize>,
+    hasher: RandomState,
+    files: Vec<SpillFile>,
+    /// Reused buffer for serializing keys.
+    key_buf: Vec<u8>,
+}
+
+impl SpillPartitions {
+    /// Create [`NUM_PARTITIONS`] empty files in `dir`, for batches partitioned by the columns
+    /// `keys`.
+    pub fn new(dir: &Path, keys: Vec<usize>, hasher: RandomState) -> Result<Self> {
+        let files = (0..NUM_PARTITIONS)
+            .map(|_| SpillFile::create(dir))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            keys,
+            hasher,
+            files,
+            key_buf: vec![],
+        })
+    }
+
+    /// Append rows of `batch` to their partitions.
+    pub fn write(&mut self, batch: &Batch) -> Result<()> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|idx| batch.column(*idx))
+            .collect::<Vec<_>>();
+        let serializer = KeySerializer::new(&keys);
+        let mut partitions = Vec::with_capacity(batch.num_rows());
+        for row in 0..batch.num_rows() {
+            self.key_buf.clear();
+            serializer.serialize(row, &mut self.key_buf)?;
+            let hash = self.hasher.hash_one(&self.key_buf);
+            partitions.push(hash as usize % self.files.len());
+        }
+        for (partition, file) in self.files.iter_mut().enumerate() {
+            let visibility = partitions
+                .iter()
+                .map(|p| *p == partition)
+                .collect::<Vec<_>>();
+            if visibility.contains(&true) {
+                file.write(&batch.filter(&visibility))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish writing, and get the file of each partition. The files are closed, so that they
+    /// are only opened when read one by one.
+    pub fn into_files(self) -> Result<Vec<SpillFile>> {
+        let mut files = self.files;
+        for file in &mut files {
+            file.finish()?;
+        }
+        Ok(files)
+    }
+}
+
+/// Serialize `batch` and append it to `buf`.
+pub fn serialize_batch(batch: &Batch, buf: &mut Vec<u8>) -> Result<()> {
+    let Ok(num_rows) = u32::try_from(batch.num_rows()) else {
+        bail!("cannot spill a batch of {} rows", batch.num_rows());
+    };
+    buf.extend_from_slice(&num_rows.to_le_bytes());
+    let len_offset = buf.len();
+    buf.extend_from_slice(&0u64.to_le_bytes());
+    for column in batch.columns() {
+        for row in 0..batch.num_rows() {
+            encode_value(column.get(row), false, buf)?;
+        }
+    }
+    let len = (buf.len() - len_offset - 8) as u64;
+    buf[len_offset..len_offset + 8].copy_from_slice(&len.to_le_bytes());
+    Ok(())
+}
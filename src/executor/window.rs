@@ -0,0 +1,570 @@
+//! Implements window functions, which compute a value for each row from other rows of its
+//! partition, like `sum(x) OVER (PARTITION BY a ORDER BY b ROWS BETWEEN 2 PRECEDING AND CURRENT
+//! ROW)`.
+//!
+//! The input is sorted by the partition keys and then the order keys, so that each partition is
+//! a range of rows, and rows with equal order keys (peers) are adjacent. The frame of each row is
+//! then a range of rows as well.
+//!
+//! Aggregates over frames starting at the start of the partition, including the default frame,
+//! are evaluated in one pass by adding rows to a single state as the frame grows. Other frames
+//! are evaluated with a segment tree of states, so that each frame is combined from
+//! `O(log n)` states instead of aggregating all rows of the frame again.
+
+use std::ops::Range;
+
+use anyhow::{Result, bail};
+
+use crate::TypeMismatch;
+use crate::aggregate::{AggState, AggregateFunction};
+use crate::array::{ArrayBuilderImpl, ArrayImpl, Batch};
+use crate::dataType::DataType;
+use crate::executor::AggCall;
+use crate::executor::check_batch;
+use crate::executor::order::{ColumnOrder, compare_rows};
+use crate::scalar::ScalarImpl;
+
+/// Whether the frame is measured in rows or in peer groups of the order keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameUnits {
+    Rows,
+    /// Only `UNBOUNDED` and `CURRENT ROW` bounds are supported, where `CURRENT ROW` includes all
+    /// peers of the current row.
+    Range,
+}
+
+/// A bound of the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(usize),
+    CurrentRow,
+    Following(usize),
+    UnboundedFollowing,
+}
+
+/// The frame of a window function, which is the set of rows used to compute the value of the
+/// current row.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+impl Default for Frame {
+    /// `RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW`, which is the whole partition if
+    /// there is no order key, as all rows are peers.
+    fn default() -> Self {
+        Self::range(FrameBound::UnboundedPreceding, FrameBound::CurrentRow)
+    }
+}
+
+impl Frame {
+    /// `ROWS BETWEEN start AND end`.
+    pub fn rows(start: FrameBound, end: FrameBound) -> Self {
+        Self {
+            units: FrameUnits::Rows,
+            start,
+            end,
+        }
+    }
+
+    /// `RANGE BETWEEN start AND end`.
+    pub fn range(start: FrameBound, end: FrameBound) -> Self {
+        Self {
+            units: FrameUnits::Range,
+            start,
+            end,
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        use FrameBound::*;
+        match (self.start, self.end) {
+            (UnboundedFollowing, _) => bail!("frame start cannot be UNBOUNDED FOLLOWING"),
+            (_, UnboundedPreceding) => bail!("frame end cannot be UNBOUNDED PRECEDING"),
+            (CurrentRow, Preceding(_)) => {
+                bail!("frame starting from current row cannot have preceding rows")
+            }
+            (Following(_), Preceding(_) | CurrentRow) => {
+                bail!("frame starting from following row cannot have preceding rows")
+            }
+            _ => {}
+        }
+        if self.units == FrameUnits::Range
+            && [self.start, self.end]
+                .iter()
+                .any(|bound| matches!(bound, Preceding(_) | Following(_)))
+        {
+            bail!("RANGE with offset PRECEDING/FOLLOWING is not supported");
+        }
+        Ok(())
+    }
+}
+
+/// A window function.
+pub enum WindowFunction {
+    /// `row_number()`, the number of the row in its partition, counting from 1.
+    RowNumber,
+    /// `rank()`, the row number of the first peer of the row, with gaps.
+    Rank,
+    /// `dense_rank()`, the number of the peer group of the row, without gaps.
+    DenseRank,
+    /// `ntile(buckets)`, which divides the partition into `buckets` groups as equally as
+    /// possible, and returns the number of the group of the row, counting from 1.
+    Ntile(i32),
+    /// `lag(arg, offset, default)`, the value of the row `offset` rows before the current row in
+    /// its partition, or `default` if there is no such row.
+    Lag {
+        arg: usize,
+        offset: usize,
+        default: Option<ScalarImpl>,
+    },
+    /// `lead(arg, offset, default)`, the value of the row `offset` rows after the current row
+    /// in its partition, or `default` if there is no such row.
+    Lead {
+        arg: usize,
+        offset: usize,
+        default: Option<ScalarImpl>,
+    },
+    /// `first_value(arg)`, the value of the first row of the frame.
+    FirstValue(usize),
+    /// `last_value(arg)`, the value of the last row of the frame.
+    LastValue(usize),
+    /// An aggregate function over the rows of the frame.
+    Aggregate(AggCall),
+}
+
+impl WindowFunction {
+    fn return_type(&self, input_types: &[DataType]) -> DataType {
+        match self {
+            Self::RowNumber | Self::Rank | Self::DenseRank => DataType::BigInt,
+            Self::Ntile(_) => DataType::Integer,
+            Self::Lag { arg, .. }
+            | Self::Lead { arg, .. }
+            | Self::FirstValue(arg)
+            | Self::LastValue(arg) => input_types[*arg],
+            Self::Aggregate(call) => call.func.return_type(),
+        }
+    }
+
+    /// Input columns used by the function.
+    fn args(&self) -> &[usize] {
+        match self {
+            Self::RowNumber | Self::Rank | Self::DenseRank | Self::Ntile(_) => &[],
+            Self::Lag { arg, .. }
+            | Self::Lead { arg, .. }
+            | Self::FirstValue(arg)
+            | Self::LastValue(arg) => std::slice::from_ref(arg),
+            Self::Aggregate(call) => &call.args,
+        }
+    }
+}
+
+/// A window function with its frame. Only `first_value`, `last_value` and aggregates depend on
+/// the frame.
+pub struct WindowCall {
+    pub func: WindowFunction,
+    pub frame: Frame,
+}
+
+impl WindowCall {
+    /// Create a call with the default frame.
+    pub fn new(func: WindowFunction) -> Self {
+        Self {
+            func,
+            frame: Frame::default(),
+        }
+    }
+
+    pub fn with_frame(self, frame: Frame) -> Self {
+        Self { frame, ..self }
+    }
+}
+
+/// Computes window functions sharing the same `PARTITION BY` and `ORDER BY`.
+///
+/// Output batches have the input columns sorted by the partition keys and the order keys,
+/// followed by one column for each window function.
+pub struct Window {
+    input_types: Vec<DataType>,
+    /// Indexes of the partition key columns in input batches.
+    partition_by: Vec<usize>,
+    order_by: Vec<ColumnOrder>,
+    calls: Vec<WindowCall>,
+}
+
+impl Window {
+    /// Create a window operator over input batches of columns of `input_types`.
+    pub fn new(
+        input_types: Vec<DataType>,
+        partition_by: Vec<usize>,
+        order_by: Vec<ColumnOrder>,
+        calls: Vec<WindowCall>,
+    ) -> Result<Self> {
+        let columns = partition_by
+            .iter()
+            .chain(order_by.iter().map(|order| &order.column))
+            .chain(calls.iter().flat_map(|call| call.func.args()));
+        if let Some(column) = columns.into_iter().find(|c| **c >= input_types.len()) {
+            bail!(
+                "column index {column} out of range, there are {} input columns",
+                input_types.len()
+            );
+        }
+        for call in &calls {
+            call.frame.check()?;
+            match &call.func {
+                WindowFunction::Ntile(buckets) if *buckets <= 0 => {
+                    bail!("argument of ntile must be greater than zero")
+                }
+                WindowFunction::Lag {
+                    arg,
+                    default: Some(default),
+                    ..
+                }
+                | WindowFunction::Lead {
+                    arg,
+                    default: Some(default),
+                    ..
+                } if default.identifier() != input_types[*arg].physical_identifier() => {
+                    return Err(TypeMismatch(
+                        input_types[*arg].physical_identifier(),
+                        default.identifier(),
+                    )
+                    .into());
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            input_types,
+            partition_by,
+            order_by,
+            calls,
+        })
+    }
+
+    /// Types of the output columns.
+    pub fn output_types(&self) -> Vec<DataType> {
+        let calls = self
+            .calls
+            .iter()
+            .map(|call| call.func.return_type(&self.input_types));
+        self.input_types.iter().copied().chain(calls).collect()
+    }
+
+    /// Compute window functions over all `batches`.
+    pub fn execute(self, batches: impl IntoIterator<Item = Result<Batch>>) -> Result<Batch> {
+        let (columns, num_rows) = self.concat(batches)?;
+
+        // Sort rows by the partition keys and then the order keys.
+        let partition_orders = self
+            .partition_by
+            .iter()
+            .map(|column| ColumnOrder::asc(*column))
+            .collect::<Vec<_>>();
+        let sort_orders = [partition_orders.as_slice(), &self.order_by].concat();
+        let mut indices = (0..num_rows).collect::<Vec<_>>();
+        indices.sort_by(|a, b| compare_rows(&columns, &sort_orders, *a, *b));
+        let mut columns = columns
+            .iter()
+            .map(|column| column.take(&indices))
+            .collect::<Vec<_>>();
+
+        let partitions = split(0..num_rows, |a, b| {
+            compare_rows(&columns, &partition_orders, a, b).is_eq()
+        });
+        let mut outputs = self
+            .calls
+            .iter()
+            .map(|call| {
+                call.func
+                    .return_type(&self.input_types)
+                    .create_array_builder(num_rows)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for partition in partitions {
+            let peers = split(partition.clone(), |a, b| {
+                compare_rows(&columns, &self.order_by, a, b).is_eq()
+            });
+            let partition = Partition {
+                rows: partition,
+                peers,
+                columns: &columns,
+            };
+            for (call, output) in self.calls.iter().zip(&mut outputs) {
+                partition.compute(call, output)?;
+            }
+        }
+
+        columns.extend(outputs.into_iter().map(|output| output.finish()));
+        Ok(match columns.is_empty() {
+            true => Batch::no_columns(num_rows),
+            false => Batch::new(columns)?,
+        })
+    }
+
+    /// Concatenate all `batches` into columns, returning them with the number of rows.
+    fn concat(
+        &self,
+        batches: impl IntoIterator<Item = Result<Batch>>,
+    ) -> Result<(Vec<ArrayImpl>, usize)> {
+        let mut builders = self
+            .input_types
+            .iter()
+            .map(|ty| ty.create_array_builder(0))
+            .collect::<Result<Vec<_>>>()?;
+        let mut num_rows = 0;
+        for batch in batches {
+            let batch = batch?;
+            check_batch(&batch, &self.input_types)?;
+            for (builder, column) in builders.iter_mut().zip(batch.columns()) {
+                for row in 0..batch.num_rows() {
+                    builder.push(column.get(row));
+                }
+            }
+            num_rows += batch.num_rows();
+        }
+        let columns = builders.into_iter().map(|b| b.finish()).collect();
+        Ok((columns, num_rows))
+    }
+}
+
+/// Split `rows` into ranges of adjacent rows where `same(first, row)` holds.
+fn split(rows: Range<usize>, same: impl Fn(usize, usize) -> bool) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = rows.start;
+    for row in rows.clone() {
+        if !same(start, row) {
+            ranges.push(start..row);
+            start = row;
+        }
+    }
+    if start < rows.end {
+        ranges.push(start..rows.end);
+    }
+    ranges
+}
+
+/// A partition of the sorted input.
+struct Partition<'a> {
+    rows: Range<usize>,
+    /// Ranges of peers, in order.
+    peers: Vec<Range<usize>>,
+    columns: &'a [ArrayImpl],
+}
+
+impl Partition<'_> {
+    /// Compute `call` for each row of the partition, and push the results to `output`.
+    fn compute(&self, call: &WindowCall, output: &mut ArrayBuilderImpl) -> Result<()> {
+        let start = self.rows.start;
+        match &call.func {
+            WindowFunction::RowNumber => {
+                for row in self.rows.clone() {
+                    output.push(Some(((row - start + 1) as i64).into()));
+                }
+            }
+            WindowFunction::Rank => {
+                for peers in &self.peers {
+                    let rank = (peers.start - start + 1) as i64;
+                    for _ in peers.clone() {
+                        output.push(Some(rank.into()));
+                    }
+                }
+            }
+            WindowFunction::DenseRank => {
+                for (rank, peers) in self.peers.iter().enumerate() {
+                    for _ in peers.clone() {
+                        output.push(Some((rank as i64 + 1).into()));
+                    }
+                }
+            }
+            WindowFunction::Ntile(buckets) => {
+                // The first `len % buckets` buckets have one more row than the others.
+                let len = self.rows.len();
+                let buckets = *buckets as usize;
+                let (size, larger) = (len / buckets, len % buckets);
+                for row in 0..len {
+                    let bucket = match row < larger * (size + 1) {
+                        true => row / (size + 1),
+                        false => larger + (row - larger * (size + 1)) / size,
+                    };
+                    output.push(Some((bucket as i32 + 1).into()));
+                }
+            }
+            WindowFunction::Lag {
+                arg,
+                offset,
+                default,
+            } => {
+                for row in self.rows.clone() {
+                    let target = row.checked_sub(*offset).filter(|r| *r >= start);
+                    self.push_shifted(*arg, target, default, output);
+                }
+            }
+            WindowFunction::Lead {
+                arg,
+                offset,
+                default,
+            } => {
+                for row in self.rows.clone() {
+                    let target = row.checked_add(*offset).filter(|r| *r < self.rows.end);
+                    self.push_shifted(*arg, target, default, output);
+                }
+            }
+            WindowFunction::FirstValue(arg) => {
+                for frame in self.frames(&call.frame) {
+                    let value = (!frame.is_empty()).then(|| self.columns[*arg].get(frame.start));
+                    output.push(value.flatten());
+                }
+            }
+            WindowFunction::LastValue(arg) => {
+                for frame in self.frames(&call.frame) {
+                    let value = (!frame.is_empty()).then(|| self.columns[*arg].get(frame.end - 1));
+                    output.push(value.flatten());
+                }
+            }
+            WindowFunction::Aggregate(agg) => self.aggregate(agg, &call.frame, output)?,
+        }
+        Ok(())
+    }
+
+    /// Push the value of `arg` at row `target`, or `default` if there is no such row.
+    fn push_shifted(
+        &self,
+        arg: usize,
+        target: Option<usize>,
+        default: &Option<ScalarImpl>,
+        output: &mut ArrayBuilderImpl,
+    ) {
+        match target {
+            Some(target) => output.push(self.columns[arg].get(target)),
+            None => output.push(default.as_ref().map(|v| v.as_scalar_ref())),
+        }
+    }
+
+    /// Get the frame of each row. Both ends of frames never move backwards.
+    fn frames(&self, frame: &Frame) -> Vec<Range<usize>> {
+        let Range { start, end } = self.rows.clone();
+        let mut frames = Vec::with_capacity(self.rows.len());
+        for peers in &self.peers {
+            for row in peers.clone() {
+                let (current_start, current_end) = match frame.units {
+                    FrameUnits::Rows => (row, row + 1),
+                    FrameUnits::Range => (peers.start, peers.end),
+                };
+                let frame_start = match frame.start {
+                    FrameBound::UnboundedPreceding => start,
+                    FrameBound::Preceding(n) => row.saturating_sub(n).max(start),
+                    FrameBound::CurrentRow => current_start,
+                    FrameBound::Following(n) => row.saturating_add(n).min(end),
+                    FrameBound::UnboundedFollowing => end,
+                };
+                let frame_end = match frame.end {
+                    FrameBound::UnboundedPreceding => start,
+                    FrameBound::Preceding(n) => (row + 1).saturating_sub(n).max(start),
+                    FrameBound::CurrentRow => current_end,
+                    FrameBound::Following(n) => row.saturating_add(n).saturating_add(1).min(end),
+                    FrameBound::UnboundedFollowing => end,
+                };
+                frames.push(frame_start..frame_end.max(frame_start));
+            }
+        }
+        frames
+    }
+
+    /// Compute an aggregate over the frame of each row.
+    fn aggregate(&self, agg: &AggCall, frame: &Frame, output: &mut ArrayBuilderImpl) -> Result<()> {
+        let func = agg.func.as_ref();
+        let frames = self.frames(frame);
+        let rows = self.rows.clone().collect::<Vec<_>>();
+        let args = agg
+            .args
+            .iter()
+            .map(|arg| self.columns[*arg].take(&rows))
+            .collect::<Vec<_>>();
+        let args = args.iter().collect::<Vec<_>>();
+        let offset = self.rows.start;
+
+        if frame.start == FrameBound::UnboundedPreceding {
+            // Frames only grow, so rows can be added to one state.
+            let mut state = func.create_state();
+            let mut added = offset;
+            for frame in frames {
+                if frame.end > added {
+                    let rows = (added - offset..frame.end - offset).collect::<Vec<_>>();
+                    let slice = args.iter().map(|arg| arg.take(&rows)).collect::<Vec<_>>();
+                    let slice = slice.iter().collect::<Vec<_>>();
+                    func.update(&mut state, &slice, rows.len())?;
+                    added = frame.end;
+                }
+                let value = func.finish(&state)?;
+                output.push(value.as_ref().map(|v| v.as_scalar_ref()));
+            }
+        } else {
+            let tree = SegmentTree::new(func, &args, rows.len())?;
+            for frame in frames {
+                let state = tree.query(frame.start - offset..frame.end - offset)?;
+                let value = func.finish(&state)?;
+                output.push(value.as_ref().map(|v| v.as_scalar_ref()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A segment tree over aggregate states of rows. Each node is the state of the rows under it, so
+/// that the state of any range of rows is merged from `O(log n)` nodes.
+struct SegmentTree<'a> {
+    func: &'a dyn AggregateFunction,
+    /// Number of leaves, which is a power of two.
+    size: usize,
+    /// Nodes in the order of a binary heap: the root is at 1, and the children of node `i` are
+    /// `2i` and `2i + 1`. Leaves start at `size`.
+    nodes: Vec<AggState>,
+}
+
+impl<'a> SegmentTree<'a> {
+    fn new(func: &'a dyn AggregateFunction, args: &[&ArrayImpl], num_rows: usize) -> Result<Self> {
+        let size = num_rows.next_power_of_two();
+        let mut nodes = (0..2 * size)
+            .map(|_| func.create_state())
+            .collect::<Vec<_>>();
+        let groups = (0..num_rows).collect::<Vec<_>>();
+        func.update_grouped(&mut nodes[size..size + num_rows], &groups, args)?;
+        for i in (1..size).rev() {
+            let mut state = func.clone_state(&nodes[2 * i])?;
+            func.merge(&mut state, func.clone_state(&nodes[2 * i + 1])?)?;
+            nodes[i] = state;
+        }
+        Ok(Self { func, size, nodes })
+    }
+
+    /// Get the state of `rows`.
+    fn query(&self, rows: Range<usize>) -> Result<AggState> {
+        // Nodes covering the range from the left and from the right.
+        let (mut left, mut right) = (vec![], vec![]);
+        let (mut l, mut r) = (rows.start + self.size, rows.end + self.size);
+        while l < r {
+            if l & 1 == 1 {
+                left.push(l);
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                right.push(r);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        let mut state = self.func.create_state();
+        // Merge in the order of rows, as some functions like `string_agg` depend on it.
+        for node in left.into_iter().chain(right.into_iter().rev()) {
+            self.func
+                .merge(&mut state, self.func.clone_state(&self.nodes[node])?)?;
+        }
+        Ok(state)
+    }
+}
@@ -0,0 +1,121 @@
+//! Implements arithmetic functions for numeric types.
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::expr::function;
+
+/// Numeric types supporting checked arithmetic, which returns `None` on overflow.
+pub trait CheckedArith: Copy + PartialEq + Default {
+    /// SQL name of the type, used in error messages.
+    const TYPE_NAME: &'static str;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_arith_int {
+    ($($ty:ty: $name:literal),*) => {
+        $(
+            impl CheckedArith for $ty {
+                const TYPE_NAME: &'static str = $name;
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$ty>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$ty>::checked_sub(self, rhs)
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$ty>::checked_mul(self, rhs)
+                }
+
+                fn checked_div(self, rhs: Self) -> Option<Self> {
+                    <$ty>::checked_div(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_checked_arith_int! { i16: "smallint", i32: "integer", i64: "bigint" }
+
+/// Floating-point arithmetic overflows if it produces an infinity out of finite inputs.
+macro_rules! impl_checked_arith_float {
+    ($($ty:ty: $name:literal),*) => {
+        $(
+            impl CheckedArith for $ty {
+                const TYPE_NAME: &'static str = $name;
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    check_float_overflow(self, rhs, self + rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    check_float_overflow(self, rhs, self - rhs)
+                }
+
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    check_float_overflow(self, rhs, self * rhs)
+                }
+
+                fn checked_div(self, rhs: Self) -> Option<Self> {
+                    check_float_overflow(self, rhs, self / rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_checked_arith_float! { f32: "real", f64: "double precision" }
+
+fn check_float_overflow<T: Into<f64> + Copy>(lhs: T, rhs: T, result: T) -> Option<T> {
+    let (l, r, o) = (lhs.into(), rhs.into(), result.into());
+    (!o.is_infinite() || l.is_infinite() || r.is_infinite()).then_some(result)
+}
+
+fn out_of_range<T: CheckedArith>() -> anyhow::Error {
+    anyhow!("{} out of range", T::TYPE_NAME)
+}
+
+#[function("add(int16, int16) -> int16")]
+#[function("add(int32, int32) -> int32")]
+#[function("add(int64, int64) -> int64")]
+#[function("add(float32, float32) -> float32")]
+#[function("add(float64, float64) -> float64")]
+pub fn add<T: CheckedArith>(l: T, r: T) -> Result<T> {
+    l.checked_add(r).ok_or_else(out_of_range::<T>)
+}
+
+#[function("subtract(int16, int16) -> int16")]
+#[function("subtract(int32, int32) -> int32")]
+#[function("subtract(int64, int64) -> int64")]
+#[function("subtract(float32, float32) -> float32")]
+#[function("subtract(float64, float64) -> float64")]
+pub fn subtract<T: CheckedArith>(l: T, r: T) -> Result<T> {
+    l.checked_sub(r).ok_or_else(out_of_range::<T>)
+}
+
+#[function("multiply(int16, int16) -> int16")]
+#[function("multiply(int32, int32) -> int32")]
+#[function("multiply(int64, int64) -> int64")]
+#[function("multiply(float32, float32) -> float32")]
+#[function("multiply(float64, float64) -> float64")]
+pub fn multiply<T: CheckedArith>(l: T, r: T) -> Result<T> {
+    l.checked_mul(r).ok_or_else(out_of_range::<T>)
+}
+
+#[function("divide(int16, int16) -> int16")]
+#[function("divide(int32, int32) -> int32")]
+#[function("divide(int64, int64) -> int64")]
+#[function("divide(float32, float32) -> float32")]
+#[function("divide(float64, float64) -> float64")]
+pub fn divide<T: CheckedArith>(l: T, r: T) -> Result<T> {
+    if r == T::default() {
+        bail!("division by zero");
+    }
+    l.checked_div(r).ok_or_else(out_of_range::<T>)
+}
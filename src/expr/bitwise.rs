@@ -0,0 +1,219 @@
+//! Implements bitwise and integer functions for integer types.
+
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::expr::arith::CheckedArith;
+use crate::expr::{FunctionRegistry, function};
+
+/// Integer types supporting bitwise and integer functions.
+pub trait SqlInteger:
+    CheckedArith
+    + Ord
+    + Into<i128>
+    + TryFrom<i128>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+{
+    /// Number of bits of this type.
+    const BITS: u32;
+
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+    /// Shift left by `n` bits, where `n < BITS`.
+    fn shl(self, n: u32) -> Self;
+    /// Arithmetic shift right by `n` bits, where `n < BITS`.
+    fn shr(self, n: u32) -> Self;
+    fn count_ones(self) -> u32;
+}
+
+macro_rules! impl_sql_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl SqlInteger for $ty {
+                const BITS: u32 = <$ty>::BITS;
+
+                fn checked_rem(self, rhs: Self) -> Option<Self> {
+                    <$ty>::checked_rem(self, rhs)
+                }
+
+                fn shl(self, n: u32) -> Self {
+                    self << n
+                }
+
+                fn shr(self, n: u32) -> Self {
+                    self >> n
+                }
+
+                fn count_ones(self) -> u32 {
+                    <$ty>::count_ones(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_integer! { i16, i32, i64 }
+
+fn out_of_range<T: SqlInteger>() -> anyhow::Error {
+    anyhow!("{} out of range", T::TYPE_NAME)
+}
+
+/// Convert `v` back into `T`, or report that it is out of range.
+fn narrow<T: SqlInteger>(v: i128) -> Result<T> {
+    T::try_from(v).map_err(|_| out_of_range::<T>())
+}
+
+/// `l & r`
+#[function("bitwise_and(int16, int16) -> int16")]
+#[function("bitwise_and(int32, int32) -> int32")]
+#[function("bitwise_and(int64, int64) -> int64")]
+pub fn bitwise_and<T: SqlInteger>(l: T, r: T) -> T {
+    l & r
+}
+
+/// `l | r`
+#[function("bitwise_or(int16, int16) -> int16")]
+#[function("bitwise_or(int32, int32) -> int32")]
+#[function("bitwise_or(int64, int64) -> int64")]
+pub fn bitwise_or<T: SqlInteger>(l: T, r: T) -> T {
+    l | r
+}
+
+/// `l # r` in PostgreSQL, or `l ^ r` in other dialects.
+#[function("bitwise_xor(int16, int16) -> int16")]
+#[function("bitwise_xor(int32, int32) -> int32")]
+#[function("bitwise_xor(int64, int64) -> int64")]
+pub fn bitwise_xor<T: SqlInteger>(l: T, r: T) -> T {
+    l ^ r
+}
+
+/// `~v`
+#[function("bitwise_not(int16) -> int16")]
+#[function("bitwise_not(int32) -> int32")]
+#[function("bitwise_not(int64) -> int64")]
+pub fn bitwise_not<T: SqlInteger>(v: T) -> T {
+    !v
+}
+
+/// Check the shift amount `n`, and get it if it is less than the number of bits of `T`.
+fn shift_amount<T: SqlInteger>(n: i32) -> Result<Option<u32>> {
+    if n < 0 {
+        bail!("negative shift amount {n}");
+    }
+    Ok(((n as u32) < T::BITS).then_some(n as u32))
+}
+
+/// `v << n`. All bits are shifted out if `n` is not less than the number of bits.
+#[function("shift_left(int16, int32) -> int16")]
+#[function("shift_left(int32, int32) -> int32")]
+#[function("shift_left(int64, int32) -> int64")]
+pub fn shift_left<T: SqlInteger>(v: T, n: i32) -> Result<T> {
+    Ok(match shift_amount::<T>(n)? {
+        Some(n) => v.shl(n),
+        None => T::default(),
+    })
+}
+
+/// `v >> n`, which keeps the sign of `v`. All bits other than the sign are shifted out if `n` is
+/// not less than the number of bits.
+#[function("shift_right(int16, int32) -> int16")]
+#[function("shift_right(int32, int32) -> int32")]
+#[function("shift_right(int64, int32) -> int64")]
+pub fn shift_right<T: SqlInteger>(v: T, n: i32) -> Result<T> {
+    Ok(match shift_amount::<T>(n)? {
+        Some(n) => v.shr(n),
+        None => v.shr(T::BITS - 1),
+    })
+}
+
+/// Number of bits set in `v`, counting the sign bit of negative values.
+#[function("bit_count(int16) -> int64")]
+#[function("bit_count(int32) -> int64")]
+#[function("bit_count(int64) -> int64")]
+pub fn bit_count<T: SqlInteger>(v: T) -> i64 {
+    v.count_ones() as i64
+}
+
+/// Whether bit `n` of `v` is set, where bit 0 is the least significant one. This tests a flag in
+/// a bitset column.
+#[function("get_bit(int16, int32) -> boolean")]
+#[function("get_bit(int32, int32) -> boolean")]
+#[function("get_bit(int64, int32) -> boolean")]
+pub fn get_bit<T: SqlInteger>(v: T, n: i32) -> Result<bool> {
+    if n < 0 || n as u32 >= T::BITS {
+        bail!("bit index {n} out of valid range (0..{})", T::BITS - 1);
+    }
+    let v: i128 = v.into();
+    Ok((v >> n) & 1 != 0)
+}
+
+/// Greatest common divisor, which is always non-negative. `gcd(0, 0)` is 0.
+#[function("gcd(int16, int16) -> int16")]
+#[function("gcd(int32, int32) -> int32")]
+#[function("gcd(int64, int64) -> int64")]
+pub fn gcd<T: SqlInteger>(l: T, r: T) -> Result<T> {
+    narrow(gcd_i128(l.into(), r.into()))
+}
+
+fn gcd_i128(l: i128, r: i128) -> i128 {
+    let (mut a, mut b) = (l.abs(), r.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Least common multiple, which is always non-negative. It is 0 if either input is 0.
+#[function("lcm(int16, int16) -> int16")]
+#[function("lcm(int32, int32) -> int32")]
+#[function("lcm(int64, int64) -> int64")]
+pub fn lcm<T: SqlInteger>(l: T, r: T) -> Result<T> {
+    let (l, r): (i128, i128) = (l.into(), r.into());
+    if l == 0 || r == 0 {
+        return Ok(T::default());
+    }
+    narrow((l / gcd_i128(l, r) * r).abs())
+}
+
+/// Integer quotient of `l / r`, truncated towards zero.
+#[function("div(int16, int16) -> int16")]
+#[function("div(int32, int32) -> int32")]
+#[function("div(int64, int64) -> int64")]
+pub fn div<T: SqlInteger>(l: T, r: T) -> Result<T> {
+    if r == T::default() {
+        bail!("division by zero");
+    }
+    l.checked_div(r).ok_or_else(out_of_range::<T>)
+}
+
+/// Remainder of `l / r`, which has the same sign as `l`.
+#[function("mod(int16, int16) -> int16")]
+#[function("mod(int32, int32) -> int32")]
+#[function("mod(int64, int64) -> int64")]
+pub fn modulo<T: SqlInteger>(l: T, r: T) -> Result<T> {
+    if r == T::default() {
+        bail!("division by zero");
+    }
+    // The only overflowing case is `MIN % -1`, which is 0.
+    Ok(l.checked_rem(r).unwrap_or_default())
+}
+
+/// Register all bitwise and integer functions into `registry`.
+pub fn register_bitwise_functions(registry: &mut FunctionRegistry) -> Result<()> {
+    register_bitwise_and(registry)?;
+    register_bitwise_or(registry)?;
+    register_bitwise_xor(registry)?;
+    register_bitwise_not(registry)?;
+    register_shift_left(registry)?;
+    register_shift_right(registry)?;
+    register_bit_count(registry)?;
+    register_get_bit(registry)?;
+    register_gcd(registry)?;
+    register_lcm(registry)?;
+    register_div(registry)?;
+    register_modulo(registry)?;
+    Ok(())
+}
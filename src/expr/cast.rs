@@ -0,0 +1,392 @@
+//! Implements cast functions for [`Array`] types.
+//!
+//! [`cast`] converts an array of any type into any other type, following the semantics of SQL
+//! `CAST`: narrowing numbers fails on overflow, floats are rounded to the nearest integer, and
+//! strings are parsed as the target type. [`try_cast`] produces `NULL` instead of failing on
+//! values that cannot be cast.
+
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::expr::Expression;
+use crate::macros::for_all_variants;
+
+/// Converts a value into type `T` with SQL `CAST` semantics.
+pub trait CastInto<T> {
+    fn cast_into(self) -> Result<T>;
+}
+
+fn out_of_range(ty: &str) -> anyhow::Error {
+    anyhow!("{ty} out of range")
+}
+
+/// Implements casts between integers, which fail if the value does not fit in the target type.
+macro_rules! impl_cast_int_to_int {
+    ($({ $from:ty, $to:ty, $name:literal }),*) => {
+        $(
+            impl CastInto<$to> for $from {
+                fn cast_into(self) -> Result<$to> {
+                    <$to>::try_from(self).map_err(|_| out_of_range($name))
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_int_to_int! {
+    { i16, i16, "smallint" }, { i32, i16, "smallint" }, { i64, i16, "smallint" },
+    { i16, i32, "integer" }, { i32, i32, "integer" }, { i64, i32, "integer" },
+    { i16, i64, "bigint" }, { i32, i64, "bigint" }, { i64, i64, "bigint" }
+}
+
+/// Implements casts from floats to integers. Floats are rounded to the nearest integer, with ties
+/// to even, as in PostgreSQL.
+macro_rules! impl_cast_float_to_int {
+    ($({ $from:ty, $to:ty, $name:literal }),*) => {
+        $(
+            impl CastInto<$to> for $from {
+                fn cast_into(self) -> Result<$to> {
+                    let rounded = self.round_ties_even();
+                    // `MIN` is a power of two, so both bounds are exact in floats.
+                    if rounded.is_nan()
+                        || rounded < <$to>::MIN as $from
+                        || rounded >= -(<$to>::MIN as $from)
+                    {
+                        return Err(out_of_range($name));
+                    }
+                    Ok(rounded as $to)
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_float_to_int! {
+    { f32, i16, "smallint" }, { f64, i16, "smallint" },
+    { f32, i32, "integer" }, { f64, i32, "integer" },
+    { f32, i64, "bigint" }, { f64, i64, "bigint" }
+}
+
+/// Implements casts into floats which never fail, though integers may lose precision.
+macro_rules! impl_cast_to_float {
+    ($({ $from:ty, $to:ty }),*) => {
+        $(
+            impl CastInto<$to> for $from {
+                fn cast_into(self) -> Result<$to> {
+                    Ok(self as $to)
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_to_float! {
+    { i16, f32 }, { i32, f32 }, { i64, f32 }, { f32, f32 },
+    { i16, f64 }, { i32, f64 }, { i64, f64 }, { f32, f64 }, { f64, f64 }
+}
+
+impl CastInto<f32> for f64 {
+    fn cast_into(self) -> Result<f32> {
+        let v = self as f32;
+        if v.is_infinite() && self.is_finite() {
+            bail!("value out of range: overflow");
+        }
+        if v == 0.0 && self != 0.0 {
+            bail!("value out of range: underflow");
+        }
+        Ok(v)
+    }
+}
+
+/// Implements casts between numbers and booleans. Zero is `false` and everything else is `true`.
+macro_rules! impl_cast_num_bool {
+    ($($ty:ty),*) => {
+        $(
+            impl CastInto<bool> for $ty {
+                fn cast_into(self) -> Result<bool> {
+                    Ok(self != <$ty>::default())
+                }
+            }
+
+            impl CastInto<$ty> for bool {
+                fn cast_into(self) -> Result<$ty> {
+                    Ok(self as u8 as $ty)
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_num_bool! { i16, i32, i64, f32, f64 }
+
+impl CastInto<bool> for bool {
+    fn cast_into(self) -> Result<bool> {
+        Ok(self)
+    }
+}
+
+/// Cast a value into a string by writing it into a [`StringWriter`], so that the string is
+/// copied into the output array once.
+pub trait CastWrite {
+    fn cast_write(self, writer: &mut StringWriter) -> Result<()>;
+}
+
+/// Implements casts of integers and booleans to strings.
+macro_rules! impl_cast_to_string {
+    ($($ty:ty),*) => {
+        $(
+            impl CastWrite for $ty {
+                fn cast_write(self, writer: &mut StringWriter) -> Result<()> {
+                    write!(writer, "{}", self)?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_to_string! { i16, i32, i64, bool }
+
+/// Implements casts of floats to strings, which spell infinities as in PostgreSQL.
+macro_rules! impl_cast_float_to_string {
+    ($($ty:ty),*) => {
+        $(
+            impl CastWrite for $ty {
+                fn cast_write(self, writer: &mut StringWriter) -> Result<()> {
+                    match self {
+                        <$ty>::INFINITY => writer.write_str("Infinity"),
+                        <$ty>::NEG_INFINITY => writer.write_str("-Infinity"),
+                        v => write!(writer, "{}", v)?,
+                    }
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_float_to_string! { f32, f64 }
+
+impl CastWrite for &str {
+    fn cast_write(self, writer: &mut StringWriter) -> Result<()> {
+        writer.write_str(self);
+        Ok(())
+    }
+}
+
+/// Implements parsing strings into integers.
+macro_rules! impl_cast_string_to_int {
+    ($({ $to:ty, $name:literal }),*) => {
+        $(
+            impl CastInto<$to> for &str {
+                fn cast_into(self) -> Result<$to> {
+                    use std::num::IntErrorKind::*;
+
+                    self.trim().parse::<$to>().map_err(|e| match e.kind() {
+                        PosOverflow | NegOverflow => {
+                            anyhow!("value \"{}\" is out of range for type {}", self, $name)
+                        }
+                        _ => anyhow!("invalid input syntax for type {}: \"{}\"", $name, self),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_string_to_int! { { i16, "smallint" }, { i32, "integer" }, { i64, "bigint" } }
+
+/// Implements parsing strings into floats. `NaN`, `Infinity` and `-Infinity` are accepted.
+macro_rules! impl_cast_string_to_float {
+    ($({ $to:ty, $name:literal }),*) => {
+        $(
+            impl CastInto<$to> for &str {
+                fn cast_into(self) -> Result<$to> {
+                    let v = self
+                        .trim()
+                        .parse::<$to>()
+                        .map_err(|_| anyhow!("invalid input syntax for type {}: \"{}\"", $name, self))?;
+                    let literal_infinity = self.trim().trim_start_matches(['+', '-']).to_ascii_lowercase();
+                    if v.is_infinite() && !matches!(literal_infinity.as_str(), "inf" | "infinity") {
+                        bail!("\"{}\" is out of range for type {}", self, $name);
+                    }
+                    Ok(v)
+                }
+            }
+        )*
+    };
+}
+
+impl_cast_string_to_float! { { f32, "real" }, { f64, "double precision" } }
+
+/// Parse a string into a boolean. As in PostgreSQL, `t`, `true`, `y`, `yes`, `on`, `1` and their
+/// `false` counterparts are accepted, as well as unique prefixes of them.
+impl CastInto<bool> for &str {
+    fn cast_into(self) -> Result<bool> {
+        let s = self.trim().to_ascii_lowercase();
+        let is_prefix_of = |word: &str, min_len: usize| s.len() >= min_len && word.starts_with(&s);
+        if is_prefix_of("true", 1) || is_prefix_of("yes", 1) || s == "on" || s == "1" {
+            Ok(true)
+        } else if is_prefix_of("false", 1)
+            || is_prefix_of("no", 1)
+            || is_prefix_of("off", 2)
+            || s == "0"
+        {
+            Ok(false)
+        } else {
+            bail!("invalid input syntax for type boolean: \"{}\"", self)
+        }
+    }
+}
+
+/// Pad the value in `writer` with spaces, or truncate it, to exactly `width` characters, as for
+/// `char(width)`.
+fn pad_char(writer: &mut StringWriter, width: u16) {
+    let width = width as usize;
+    match writer.as_str().char_indices().nth(width) {
+        Some((idx, _)) => writer.truncate(idx),
+        None => {
+            let len = writer.as_str().chars().count();
+            (len..width).for_each(|_| writer.write_char(' '));
+        }
+    }
+}
+
+/// Cast every value of `array` with `f`. If `safe` is set, values failed to cast are turned into
+/// `NULL`. Otherwise, the first error is returned.
+fn cast_kernel<I: Array, O: Array>(
+    array: &I,
+    safe: bool,
+    f: impl for<'a> Fn(I::RefItem<'a>) -> Result<O::OwnedItem>,
+) -> Result<ArrayImpl> {
+    let mut builder = O::Builder::with_capacity(array.len());
+    for value in array.iter() {
+        match value.map(&f).transpose() {
+            Ok(v) => builder.push(v.as_ref().map(|v| v.as_scalar_ref())),
+            Err(_) if safe => builder.push(None),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(builder.finish().into())
+}
+
+/// Cast every value of `array` into a string by writing it with `f`. If `safe` is set, values
+/// failed to cast are turned into `NULL`. Otherwise, the first error is returned.
+fn cast_write_kernel<I: Array>(
+    array: &I,
+    safe: bool,
+    f: impl for<'a> Fn(I::RefItem<'a>, &mut StringWriter) -> Result<()>,
+) -> Result<ArrayImpl> {
+    let mut builder = StringArrayBuilder::with_capacity(array.len());
+    for value in array.iter() {
+        let Some(value) = value else {
+            builder.push(None);
+            continue;
+        };
+        let mut writer = builder.writer();
+        match f(value, &mut writer) {
+            Ok(()) => writer.finish(),
+            Err(_) if safe => {
+                drop(writer);
+                builder.push(None);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(builder.finish().into())
+}
+
+/// Cast `array` of type `I` into type `to`.
+fn cast_from<I: Array>(array: &I, to: DataType, safe: bool) -> Result<ArrayImpl>
+where
+    for<'a> I::RefItem<'a>: CastInto<i16>
+        + CastInto<i32>
+        + CastInto<i64>
+        + CastInto<f32>
+        + CastInto<f64>
+        + CastInto<bool>
+        + CastWrite,
+{
+    use DataType::*;
+
+    match to {
+        SmallInt => cast_kernel::<I, I16Array>(array, safe, |v| v.cast_into()),
+        Integer => cast_kernel::<I, I32Array>(array, safe, |v| v.cast_into()),
+        BigInt => cast_kernel::<I, I64Array>(array, safe, |v| v.cast_into()),
+        Real => cast_kernel::<I, F32Array>(array, safe, |v| v.cast_into()),
+        Double => cast_kernel::<I, F64Array>(array, safe, |v| v.cast_into()),
+        Boolean => cast_kernel::<I, BoolArray>(array, safe, |v| v.cast_into()),
+        Varchar => cast_write_kernel(array, safe, |v, writer| v.cast_write(writer)),
+        Char { width } => cast_write_kernel(array, safe, |v, writer| {
+            v.cast_write(writer)?;
+            pad_char(writer, width);
+            Ok(())
+        }),
+        Decimal { .. } => bail!("type {to} does not have a physical array yet"),
+    }
+}
+
+/// Implements dispatch of the cast kernels on the input array type.
+macro_rules! impl_cast_dispatch {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        fn cast_impl(array: &ArrayImpl, to: DataType, safe: bool) -> Result<ArrayImpl> {
+            match array {
+                $(
+                    ArrayImpl::$Abc(array) => cast_from::<$AbcArray>(array, to, safe),
+                )*
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_cast_dispatch }
+
+/// Cast `array` into type `to`. Returns an error if any value cannot be cast.
+pub fn cast(array: &ArrayImpl, to: DataType) -> Result<ArrayImpl> {
+    cast_impl(array, to, false)
+}
+
+/// Cast `array` into type `to`, producing `NULL` for values that cannot be cast.
+///
+/// An error is still returned if the types cannot be cast at all.
+pub fn try_cast(array: &ArrayImpl, to: DataType) -> Result<ArrayImpl> {
+    cast_impl(array, to, true)
+}
+
+/// An expression casting its only input into type `to`.
+pub struct CastExpression {
+    to: DataType,
+    safe: bool,
+}
+
+impl CastExpression {
+    /// Create an expression behaving as [`cast`].
+    pub fn new(to: DataType) -> Self {
+        Self { to, safe: false }
+    }
+
+    /// Create an expression behaving as [`try_cast`].
+    pub fn new_try(to: DataType) -> Self {
+        Self { to, safe: true }
+    }
+}
+
+impl Expression for CastExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        if data.len() != 1 {
+            bail!("Expect one input for CastExpression");
+        }
+        cast_impl(data[0], self.to, self.safe)
+    }
+}
+
+/// Build an expression which casts an array of type `from` into an array of type `to`.
+pub fn build_cast_expression(from: DataType, to: DataType) -> Result<Box<dyn Expression>> {
+    if matches!(from, DataType::Decimal { .. }) || matches!(to, DataType::Decimal { .. }) {
+        bail!("cannot cast type {from} to {to}");
+    }
+    Ok(Box::new(CastExpression::new(to)))
+}
@@ -1,83 +1,180 @@
 //! Implements compare functions for [`Array`] types.
+//!
+//! All comparisons follow SQL semantics on non-null values:
+//!
+//! * Floats are totally ordered as in PostgreSQL: `NaN` equals `NaN` and is greater than every
+//!   other value, including infinity. `-0.0` equals `0.0`.
+//! * Strings are compared byte-wise, which is the same as comparing their code points.
+//! * `false` is less than `true`.
+//!
+//! Comparing with `NULL` always produces `NULL`, which is handled by the vectorized expression.
 
 use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 
 use crate::array::*;
 use crate::expr::vectorize::BinaryExpFunc;
+use crate::macros::for_all_variants;
+use crate::scalar::ScalarRefImpl;
 
-/// Return if `i1 < i2`. Note that `i1` and `i2` could be different types. This
-/// function will automatically cast them into `C` type.
-///
-/// * `I1`: left input type.
-/// * `I2`: right input type.
-/// * `C`: cast type.
-pub struct ExprCmpLe<I1: Array, I2: Array, C: Array>(pub PhantomData<(I1, I2, C)>);
-
-impl<I1: Array, I2: Array, C: Array> BinaryExpFunc<I1, I2, BoolArray> for ExprCmpLe<I2, I2, C>
-where
-    for<'a> I1::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> I2::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> C::RefItem<'a>: PartialOrd,
-{
-    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> bool {
-        i1.into().partial_cmp(&i2.into()).unwrap() == Ordering::Less
-    }
+/// Total order of values used by SQL comparisons.
+pub trait SqlOrd {
+    fn sql_cmp(&self, other: &Self) -> Ordering;
 }
 
-/// Return if `i1 > i2`. Note that `i1` and `i2` could be different types. This
-/// function will automatically cast them into `C` type.
-///
-/// * `I1`: left input type.
-/// * `I2`: right input type.
-/// * `C`: cast type.
-pub struct ExprCmpGe<I1: Array, I2: Array, C: Array>(pub PhantomData<(I1, I2, C)>);
-
-impl<I1: Array, I2: Array, C: Array> BinaryExpFunc<I1, I2, BoolArray> for ExprCmpGe<I2, I2, C>
-where
-    for<'a> I1::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> I2::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> C::RefItem<'a>: PartialOrd,
-{
-    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> bool {
-        i1.into().partial_cmp(&i2.into()).unwrap() == Ordering::Greater
-    }
+macro_rules! impl_sql_ord {
+    ($($ty:ty),*) => {
+        $(
+            impl SqlOrd for $ty {
+                fn sql_cmp(&self, other: &Self) -> Ordering {
+                    self.cmp(other)
+                }
+            }
+        )*
+    };
 }
 
-/// Return if `i1 == i2`. Note that `i1` and `i2` could be different types. This
-/// function will automatically cast them into `C` type.
-///
-/// * `I1`: left input type.
-/// * `I2`: right input type.
-/// * `C`: cast type.
-pub struct ExprCmpEq<I1: Array, I2: Array, C: Array>(pub PhantomData<(I1, I2, C)>);
-
-impl<I1: Array, I2: Array, C: Array> BinaryExpFunc<I1, I2, BoolArray> for ExprCmpEq<I2, I2, C>
-where
-    for<'a> I1::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> I2::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> C::RefItem<'a>: PartialEq,
-{
-    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> bool {
-        i1.into().eq(&i2.into())
-    }
+impl_sql_ord! { i16, i32, i64, bool }
+
+macro_rules! impl_sql_ord_float {
+    ($($ty:ty),*) => {
+        $(
+            impl SqlOrd for $ty {
+                fn sql_cmp(&self, other: &Self) -> Ordering {
+                    match self.partial_cmp(other) {
+                        Some(ordering) => ordering,
+                        // At least one side is NaN, and NaN is greater than everything else.
+                        None => self.is_nan().cmp(&other.is_nan()),
+                    }
+                }
+            }
+        )*
+    };
 }
 
-/// Return if `i1 > i2`. Note that `i1` and `i2` could be different types. This
-/// function will automatically cast them into `C` type.
-///
-/// * `I1`: left input type.
-/// * `I2`: right input type.
-/// * `C`: cast type.
-pub struct ExprCmpNe<I1: Array, I2: Array, C: Array>(pub PhantomData<(I1, I2, C)>);
-
-impl<I1: Array, I2: Array, C: Array> BinaryExpFunc<I1, I2, BoolArray> for ExprCmpNe<I2, I2, C>
-where
-    for<'a> I1::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> I2::RefItem<'a>: Into<C::RefItem<'a>>,
-    for<'a> C::RefItem<'a>: PartialEq,
-{
-    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> bool {
-        !i1.into().eq(&i2.into())
+impl_sql_ord_float! { f32, f64 }
+
+impl SqlOrd for &str {
+    fn sql_cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
     }
 }
+
+/// Hashing of values consistent with [`SqlOrd`]: values equal by [`SqlOrd::sql_cmp`] have the
+/// same hash.
+pub trait SqlHash {
+    fn sql_hash<H: Hasher>(&self, state: &mut H);
+}
+
+macro_rules! impl_sql_hash {
+    ($($ty:ty),*) => {
+        $(
+            impl SqlHash for $ty {
+                fn sql_hash<H: Hasher>(&self, state: &mut H) {
+                    self.hash(state)
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_hash! { i16, i32, i64, bool, &str }
+
+macro_rules! impl_sql_hash_float {
+    ($($ty:ty),*) => {
+        $(
+            impl SqlHash for $ty {
+                fn sql_hash<H: Hasher>(&self, state: &mut H) {
+                    // All `NaN`s are equal, and `-0.0` equals `0.0`.
+                    let v = if self.is_nan() {
+                        <$ty>::NAN
+                    } else if *self == 0.0 {
+                        0.0
+                    } else {
+                        *self
+                    };
+                    v.to_bits().hash(state)
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_hash_float! { f32, f64 }
+
+/// Implements [`SqlOrd`] and [`SqlHash`] for [`ScalarRefImpl`] by dispatching on the variant.
+macro_rules! impl_sql_ord_scalar_ref {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        impl ScalarRefImpl<'_> {
+            /// Compare with `other` by [`SqlOrd`], or return `None` if they are of different types.
+            pub fn try_sql_cmp(&self, other: &Self) -> Option<Ordering> {
+                match (self, other) {
+                    $(
+                        (Self::$Abc(l), Self::$Abc(r)) => Some(l.sql_cmp(r)),
+                    )*
+                    _ => None,
+                }
+            }
+        }
+
+        impl SqlOrd for ScalarRefImpl<'_> {
+            /// # Panics
+            ///
+            /// Panics if the values are of different types. Use [`ScalarRefImpl::try_sql_cmp`] to
+            /// compare values whose types are not known to match.
+            fn sql_cmp(&self, other: &Self) -> Ordering {
+                self.try_sql_cmp(other).unwrap_or_else(|| {
+                    panic!("cannot compare {} with {}", self.identifier(), other.identifier())
+                })
+            }
+        }
+
+        impl SqlHash for ScalarRefImpl<'_> {
+            fn sql_hash<H: Hasher>(&self, state: &mut H) {
+                match self {
+                    $(
+                        Self::$Abc(v) => v.sql_hash(state),
+                    )*
+                }
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_sql_ord_scalar_ref }
+
+/// Implements comparison functions with the given name, operator and matching orderings.
+macro_rules! impl_cmp {
+    ($({ $Name:ident, $op:literal, $($ordering:ident)|+ }),*) => {
+        $(
+            #[doc = concat!("Return if `i1 ", $op, " i2`. Note that `i1` and `i2` could be different types. This")]
+            /// function will automatically cast them into `C` type.
+            ///
+            /// * `I1`: left input type.
+            /// * `I2`: right input type.
+            /// * `C`: cast type.
+            pub struct $Name<I1: Array, I2: Array, C: Array>(pub PhantomData<(I1, I2, C)>);
+
+            impl<I1: Array, I2: Array, C: Array> BinaryExpFunc<I1, I2, BoolArray> for $Name<I1, I2, C>
+            where
+                for<'a> I1::RefItem<'a>: Into<C::RefItem<'a>>,
+                for<'a> I2::RefItem<'a>: Into<C::RefItem<'a>>,
+                for<'a> C::RefItem<'a>: SqlOrd,
+            {
+                fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> bool {
+                    matches!(i1.into().sql_cmp(&i2.into()), $(Ordering::$ordering)|+)
+                }
+            }
+        )*
+    };
+}
+
+impl_cmp! {
+    { ExprCmpLt, "<", Less },
+    { ExprCmpLe, "<=", Less | Equal },
+    { ExprCmpGt, ">", Greater },
+    { ExprCmpGe, ">=", Greater | Equal },
+    { ExprCmpEq, "=", Equal },
+    { ExprCmpNe, "<>", Less | Greater }
+}
@@ -0,0 +1,234 @@
+//! Implements conditional expressions: `CASE`, `IF`, `COALESCE` and `NULLIF`.
+//!
+//! Branches of `CASE` and arguments of `COALESCE` are evaluated lazily: each of them is evaluated
+//! only on the rows where its result may be used. For example, in
+//! `CASE WHEN x = 0 THEN 0 ELSE 1 / x END`, `1 / x` is evaluated without the rows where `x` is 0,
+//! so that it does not fail with division by zero. The results of branches are then merged into
+//! one output array.
+
+use anyhow::Result;
+
+use crate::TypeMismatch;
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::expr::Expression;
+use crate::expr::cmp::SqlOrd;
+use crate::expr::tree::{eval_visible, num_rows};
+use crate::macros::for_all_variants;
+
+/// Check that `array` holds values of `data_type`.
+fn check_type(array: &ArrayImpl, data_type: DataType) -> Result<()> {
+    if array.identifier() != data_type.physical_identifier() {
+        return Err(TypeMismatch(data_type.physical_identifier(), array.identifier()).into());
+    }
+    Ok(())
+}
+
+/// Merge results of branches into one array of `data_type`. `assigned[row]` is the index of the
+/// branch producing the row, or `None` for `NULL`. The result of each branch only contains the
+/// rows assigned to it, in order.
+fn merge(
+    data_type: DataType,
+    assigned: &[Option<usize>],
+    results: &[Option<ArrayImpl>],
+) -> Result<ArrayImpl> {
+    for result in results.iter().flatten() {
+        check_type(result, data_type)?;
+    }
+    let mut builder = data_type.create_array_builder(assigned.len())?;
+    let mut cursors = vec![0; results.len()];
+    for branch in assigned {
+        match branch {
+            Some(branch) => {
+                let result = results[*branch].as_ref().expect("branch not evaluated");
+                builder.push(result.get(cursors[*branch]));
+                cursors[*branch] += 1;
+            }
+            None => builder.push(None),
+        }
+    }
+    Ok(builder.finish())
+}
+
+/// `CASE WHEN cond THEN value ... ELSE value END`.
+///
+/// Each condition is only evaluated on the rows not matched by the previous conditions, and each
+/// value is only evaluated on the rows it is selected for. Rows not matched by any condition
+/// produce the `ELSE` value, or `NULL` if there is no `ELSE`.
+pub struct CaseExpression {
+    branches: Vec<(Box<dyn Expression>, Box<dyn Expression>)>,
+    else_value: Option<Box<dyn Expression>>,
+    return_type: DataType,
+}
+
+impl CaseExpression {
+    /// Create a `CASE` expression with `(condition, value)` pairs. All values must be of
+    /// `return_type`, and all conditions must be `boolean`.
+    pub fn new(
+        branches: Vec<(Box<dyn Expression>, Box<dyn Expression>)>,
+        else_value: Option<Box<dyn Expression>>,
+        return_type: DataType,
+    ) -> Self {
+        Self {
+            branches,
+            else_value,
+            return_type,
+        }
+    }
+
+    /// `IF(cond, then_value, else_value)`, which is the same as
+    /// `CASE WHEN cond THEN then_value ELSE else_value END`.
+    pub fn if_then_else(
+        cond: Box<dyn Expression>,
+        then_value: Box<dyn Expression>,
+        else_value: Box<dyn Expression>,
+        return_type: DataType,
+    ) -> Self {
+        Self::new(vec![(cond, then_value)], Some(else_value), return_type)
+    }
+}
+
+impl Expression for CaseExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        self.eval_rows(data, num_rows(data)?)
+    }
+
+    fn eval_rows(&self, data: &[&ArrayImpl], len: usize) -> Result<ArrayImpl> {
+        let mut assigned = vec![None; len];
+        // Rows not matched by any condition yet.
+        let mut remaining = vec![true; len];
+        for (branch, (cond, _)) in self.branches.iter().enumerate() {
+            let Some(matched) = eval_visible(cond.as_ref(), data, &remaining)? else {
+                break;
+            };
+            let matched: BoolArray = matched.try_into()?;
+            let rows = (0..len).filter(|row| remaining[*row]).collect::<Vec<_>>();
+            for (row, matched) in rows.into_iter().zip(matched.iter()) {
+                if matched == Some(true) {
+                    assigned[row] = Some(branch);
+                    remaining[row] = false;
+                }
+            }
+        }
+        if self.else_value.is_some() {
+            for (row, remaining) in remaining.iter().enumerate() {
+                if *remaining {
+                    assigned[row] = Some(self.branches.len());
+                }
+            }
+        }
+
+        let values = self
+            .branches
+            .iter()
+            .map(|(_, value)| value)
+            .chain(self.else_value.as_ref());
+        let mut results = vec![];
+        for (branch, value) in values.enumerate() {
+            let visibility = assigned
+                .iter()
+                .map(|assigned| *assigned == Some(branch))
+                .collect::<Vec<_>>();
+            results.push(eval_visible(value.as_ref(), data, &visibility)?);
+        }
+        merge(self.return_type, &assigned, &results)
+    }
+}
+
+/// `COALESCE(a, b, ...)`, which produces the first non-null argument of each row.
+///
+/// Each argument is only evaluated on the rows where all previous arguments are `NULL`.
+pub struct CoalesceExpression {
+    args: Vec<Box<dyn Expression>>,
+    return_type: DataType,
+}
+
+impl CoalesceExpression {
+    /// Create a `COALESCE` expression. All arguments must be of `return_type`.
+    pub fn new(args: Vec<Box<dyn Expression>>, return_type: DataType) -> Self {
+        Self { args, return_type }
+    }
+}
+
+impl Expression for CoalesceExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        self.eval_rows(data, num_rows(data)?)
+    }
+
+    fn eval_rows(&self, data: &[&ArrayImpl], len: usize) -> Result<ArrayImpl> {
+        let mut assigned = vec![None; len];
+        // Rows where all arguments so far are `NULL`.
+        let mut remaining = vec![true; len];
+        let mut results = vec![];
+        for (arg_idx, arg) in self.args.iter().enumerate() {
+            let Some(result) = eval_visible(arg.as_ref(), data, &remaining)? else {
+                break;
+            };
+            // Keep only the non-null rows in the result, which are the rows assigned to it.
+            let rows = (0..len).filter(|row| remaining[*row]).collect::<Vec<_>>();
+            let mut non_null = Vec::with_capacity(rows.len());
+            for (idx, row) in rows.into_iter().enumerate() {
+                let is_some = result.get(idx).is_some();
+                if is_some {
+                    assigned[row] = Some(arg_idx);
+                    remaining[row] = false;
+                }
+                non_null.push(is_some);
+            }
+            results.push(Some(result.filter(&non_null)));
+        }
+        merge(self.return_type, &assigned, &results)
+    }
+}
+
+/// Implements `NULLIF` for each array type.
+macro_rules! impl_nullif {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        /// Get `a`, or `NULL` on rows where `a` equals `b`.
+        fn nullif(a: &ArrayImpl, b: &ArrayImpl) -> Result<ArrayImpl> {
+            assert_eq!(a.len(), b.len(), "array length mismatch");
+            match (a, b) {
+                $(
+                    (ArrayImpl::$Abc(a), ArrayImpl::$Abc(b)) => {
+                        let mut builder = <$AbcArrayBuilder>::with_capacity(a.len());
+                        for (a, b) in a.iter().zip(b.iter()) {
+                            match (a, b) {
+                                (Some(a), Some(b)) if a.sql_cmp(&b).is_eq() => builder.push(None),
+                                (a, _) => builder.push(a),
+                            }
+                        }
+                        Ok(builder.finish().into())
+                    }
+                )*
+                (a, b) => Err(TypeMismatch(a.identifier(), b.identifier()).into()),
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_nullif }
+
+/// `NULLIF(a, b)`, which produces `NULL` if `a` equals `b`, or `a` otherwise. `a` and `b` must be
+/// of the same type.
+pub struct NullIfExpression {
+    a: Box<dyn Expression>,
+    b: Box<dyn Expression>,
+}
+
+impl NullIfExpression {
+    pub fn new(a: Box<dyn Expression>, b: Box<dyn Expression>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Expression for NullIfExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        self.eval_rows(data, num_rows(data)?)
+    }
+
+    fn eval_rows(&self, data: &[&ArrayImpl], num_rows: usize) -> Result<ArrayImpl> {
+        let a = self.a.eval_rows(data, num_rows)?;
+        let b = self.b.eval_rows(data, num_rows)?;
+        nullif(&a, &b)
+    }
+}
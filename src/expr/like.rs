@@ -0,0 +1,357 @@
+//! Implements SQL `LIKE` and `ILIKE` pattern matching for [`StringArray`].
+//!
+//! In a pattern, `%` matches any sequence of characters, `_` matches exactly one character, and
+//! the escape character (`\` by default) makes the next character match literally. `ILIKE`
+//! matches case-insensitively.
+//!
+//! A pattern is compiled into a [`LikePattern`] before matching. Common patterns like `abc%`,
+//! `%abc` and `%abc%` are matched with plain string searches instead of the general matcher. For
+//! `ILIKE`, both the pattern and the input are case-folded before such searches.
+
+use anyhow::{Result, bail};
+
+use crate::array::*;
+use crate::expr::vectorize::{UnaryExpFunc, UnaryExpression};
+use crate::expr::{Expression, FunctionRegistry, FunctionSignature};
+use crate::scalar::ScalarRefImpl;
+
+/// Default escape character of `LIKE` patterns.
+const DEFAULT_ESCAPE: char = '\\';
+
+/// An element of a compiled `LIKE` pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    /// Matches the character.
+    Literal(char),
+    /// `_`, which matches any character.
+    AnyChar,
+    /// `%`, which matches any sequence of characters.
+    AnyString,
+}
+
+/// How a compiled pattern is matched.
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// `abc`
+    Exact(String),
+    /// `abc%`
+    Prefix(String),
+    /// `%abc`
+    Suffix(String),
+    /// `%abc%`
+    Contains(String),
+    /// `%`
+    Any,
+    /// Any other pattern.
+    General(Vec<Token>),
+}
+
+/// A compiled `LIKE` or `ILIKE` pattern.
+#[derive(Debug, Clone)]
+pub struct LikePattern {
+    matcher: Matcher,
+    case_insensitive: bool,
+}
+
+impl LikePattern {
+    /// Compile `pattern` for `LIKE`. `escape` is the escape character, or `None` if escaping is
+    /// disabled.
+    pub fn like(pattern: &str, escape: Option<char>) -> Result<Self> {
+        Self::compile(pattern, escape, false)
+    }
+
+    /// Compile `pattern` for `ILIKE`. `escape` is the escape character, or `None` if escaping is
+    /// disabled.
+    pub fn ilike(pattern: &str, escape: Option<char>) -> Result<Self> {
+        Self::compile(pattern, escape, true)
+    }
+
+    fn compile(pattern: &str, escape: Option<char>, case_insensitive: bool) -> Result<Self> {
+        let mut tokens = vec![];
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                _ if Some(c) == escape => match chars.next() {
+                    Some(c) => Token::Literal(c),
+                    None => bail!("LIKE pattern must not end with escape character"),
+                },
+                '%' if tokens.last() == Some(&Token::AnyString) => continue,
+                '%' => Token::AnyString,
+                '_' => Token::AnyChar,
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+        }
+        if case_insensitive {
+            for token in &mut tokens {
+                if let Token::Literal(c) = token {
+                    *c = fold_case(*c);
+                }
+            }
+        }
+        Ok(Self {
+            matcher: Self::fast_path(&tokens).unwrap_or(Matcher::General(tokens)),
+            case_insensitive,
+        })
+    }
+
+    /// Find a fast path for patterns without `_` and with `%` only at the ends.
+    fn fast_path(tokens: &[Token]) -> Option<Matcher> {
+        let (starts_any, tokens) = match tokens.split_first() {
+            Some((Token::AnyString, rest)) => (true, rest),
+            _ => (false, tokens),
+        };
+        let (ends_any, tokens) = match tokens.split_last() {
+            Some((Token::AnyString, rest)) => (true, rest),
+            _ => (false, tokens),
+        };
+        let literal = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Literal(c) => Some(*c),
+                _ => None,
+            })
+            .collect::<Option<String>>()?;
+        let matcher = match (starts_any, ends_any) {
+            _ if literal.is_empty() && (starts_any || ends_any) => Matcher::Any,
+            (false, false) => Matcher::Exact(literal),
+            (false, true) => Matcher::Prefix(literal),
+            (true, false) => Matcher::Suffix(literal),
+            (true, true) => Matcher::Contains(literal),
+        };
+        Some(matcher)
+    }
+
+    /// Check if `s` matches the pattern.
+    pub fn matches(&self, s: &str) -> bool {
+        let folded: String;
+        let s = match &self.matcher {
+            Matcher::General(_) | Matcher::Any => s,
+            _ if !self.case_insensitive => s,
+            _ => {
+                folded = s.chars().map(fold_case).collect();
+                &folded
+            }
+        };
+        match &self.matcher {
+            Matcher::Exact(literal) => s == literal,
+            Matcher::Prefix(literal) => s.starts_with(literal.as_str()),
+            Matcher::Suffix(literal) => s.ends_with(literal.as_str()),
+            Matcher::Contains(literal) => s.contains(literal.as_str()),
+            Matcher::Any => true,
+            Matcher::General(tokens) => self.matches_tokens(tokens, s),
+        }
+    }
+
+    /// Match `s` against `tokens`. When a mismatch happens, retry from the last `%` with one more
+    /// character consumed by it. This takes `O(len(s) * len(tokens))` time in the worst case.
+    fn matches_tokens(&self, tokens: &[Token], s: &str) -> bool {
+        let (mut t, mut i) = (0, 0);
+        // Index of the token after the last `%`, and the position in `s` where it is tried.
+        let mut backtrack = None;
+        loop {
+            let c = s[i..].chars().next();
+            match (tokens.get(t), c) {
+                (None, None) => return true,
+                (Some(Token::AnyString), _) => {
+                    t += 1;
+                    backtrack = Some((t, i));
+                    continue;
+                }
+                (Some(Token::AnyChar), Some(c)) => {
+                    t += 1;
+                    i += c.len_utf8();
+                    continue;
+                }
+                (Some(Token::Literal(l)), Some(c)) if *l == self.fold(c) => {
+                    t += 1;
+                    i += c.len_utf8();
+                    continue;
+                }
+                _ => {}
+            }
+            let Some((bt, bi)) = backtrack else {
+                return false;
+            };
+            let Some(c) = s[bi..].chars().next() else {
+                return false;
+            };
+            t = bt;
+            i = bi + c.len_utf8();
+            backtrack = Some((t, i));
+        }
+    }
+
+    fn fold(&self, c: char) -> char {
+        if self.case_insensitive {
+            fold_case(c)
+        } else {
+            c
+        }
+    }
+}
+
+/// Map `c` to its lowercase form for case-insensitive matching.
+pub(crate) fn fold_case(c: char) -> char {
+    if c.is_ascii() {
+        c.to_ascii_lowercase()
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Matching a constant pattern.
+impl UnaryExpFunc<StringArray, BoolArray> for LikePattern {
+    fn eval(&self, s: &str) -> bool {
+        self.matches(s)
+    }
+}
+
+/// Parse the escape string of `LIKE ... ESCAPE escape`, or the one of `SIMILAR TO`. An empty
+/// string disables escaping.
+pub(crate) fn parse_escape(escape: &str) -> Result<Option<char>> {
+    let mut chars = escape.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => Ok(None),
+        (Some(c), None) => Ok(Some(c)),
+        _ => bail!("invalid escape string"),
+    }
+}
+
+/// Evaluates `LIKE` or `ILIKE` where the pattern is a column, i.e. `like(s, pattern)` or
+/// `like(s, pattern, escape)`.
+///
+/// The pattern is compiled for each row, unless it is the same as the one of the previous row.
+/// Therefore, a pattern repeated in every row of a batch is only compiled once. If the pattern is
+/// known to be constant, use [`LikeExpression::constant`] instead, which is also built for calls
+/// with constant patterns by [`FunctionRegistry::build_with_constants`].
+pub struct LikeExpression {
+    case_insensitive: bool,
+}
+
+impl LikeExpression {
+    /// Create a `LIKE` expression.
+    pub fn like() -> Self {
+        Self {
+            case_insensitive: false,
+        }
+    }
+
+    /// Create an `ILIKE` expression.
+    pub fn ilike() -> Self {
+        Self {
+            case_insensitive: true,
+        }
+    }
+
+    /// Create an expression matching its only input with a constant pattern, which is compiled
+    /// once here.
+    pub fn constant(pattern: LikePattern) -> UnaryExpression<StringArray, BoolArray, LikePattern> {
+        UnaryExpression::new(pattern)
+    }
+
+    /// Evaluate the expression with the given arrays.
+    pub fn eval_batch(
+        &self,
+        s: &ArrayImpl,
+        pattern: &ArrayImpl,
+        escape: Option<&ArrayImpl>,
+    ) -> Result<ArrayImpl> {
+        let s: &StringArray = s.try_into()?;
+        let pattern: &StringArray = pattern.try_into()?;
+        let escape: Option<&StringArray> = escape.map(|e| e.try_into()).transpose()?;
+        if pattern.len() != s.len() || escape.is_some_and(|escape| escape.len() != s.len()) {
+            bail!("array length mismatch");
+        }
+
+        let mut builder = BoolArrayBuilder::with_capacity(s.len());
+        let mut cache: Option<(&str, &str, LikePattern)> = None;
+        for row in 0..s.len() {
+            let escape_str = match escape {
+                Some(escape) => escape.get(row),
+                None => Some("\\"),
+            };
+            let (Some(s), Some(p), Some(e)) = (s.get(row), pattern.get(row), escape_str) else {
+                builder.push(None);
+                continue;
+            };
+            let compiled = match &cache {
+                Some((cp, ce, compiled)) if *cp == p && *ce == e => compiled,
+                _ => {
+                    let escape = match escape {
+                        Some(_) => parse_escape(e)?,
+                        None => Some(DEFAULT_ESCAPE),
+                    };
+                    let compiled = LikePattern::compile(p, escape, self.case_insensitive)?;
+                    &cache.insert((p, e, compiled)).2
+                }
+            };
+            builder.push(Some(compiled.matches(s)));
+        }
+        Ok(builder.finish().into())
+    }
+}
+
+impl Expression for LikeExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        match data {
+            [s, pattern] => self.eval_batch(s, pattern, None),
+            [s, pattern, escape] => self.eval_batch(s, pattern, Some(escape)),
+            _ => Err(anyhow::anyhow!(
+                "Expect two or three inputs for LikeExpression"
+            )),
+        }
+    }
+}
+
+/// Evaluates [`LikeExpression::constant`] on the first input of a `like` or `ilike` call, whose
+/// other inputs are the constant pattern and escape.
+struct ConstantLikeExpression(UnaryExpression<StringArray, BoolArray, LikePattern>);
+
+impl Expression for ConstantLikeExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        let ([s, _] | [s, _, _]) = data else {
+            bail!("Expect two or three inputs for LikeExpression");
+        };
+        self.0.eval_batch(s)
+    }
+}
+
+/// Compile the pattern of a `like` or `ilike` call once if it is constant, as well as the escape
+/// if given.
+fn specialize_like(
+    constants: &[Option<ScalarRefImpl<'_>>],
+    case_insensitive: bool,
+) -> Result<Option<Box<dyn Expression>>> {
+    let (pattern, escape) = match constants {
+        [_, Some(ScalarRefImpl::String(pattern))] => (*pattern, Some(DEFAULT_ESCAPE)),
+        [
+            _,
+            Some(ScalarRefImpl::String(pattern)),
+            Some(ScalarRefImpl::String(escape)),
+        ] => (*pattern, parse_escape(escape)?),
+        _ => return Ok(None),
+    };
+    let pattern = LikePattern::compile(pattern, escape, case_insensitive)?;
+    Ok(Some(Box::new(ConstantLikeExpression(
+        LikeExpression::constant(pattern),
+    ))))
+}
+
+/// Register `like` and `ilike` into `registry`.
+pub fn register_like_functions(registry: &mut FunctionRegistry) -> Result<()> {
+    for (signature, case_insensitive) in [
+        ("like(varchar, varchar) -> boolean", false),
+        ("like(varchar, varchar, varchar) -> boolean", false),
+        ("ilike(varchar, varchar) -> boolean", true),
+        ("ilike(varchar, varchar, varchar) -> boolean", true),
+    ] {
+        let signature: FunctionSignature = signature.parse()?;
+        registry.register_specialized(
+            signature,
+            move || Box::new(LikeExpression { case_insensitive }),
+            move |constants| specialize_like(constants, case_insensitive),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,495 @@
+//! Implements mathematical functions for numeric types.
+//!
+//! Functions preserving the input type, like `abs` and `floor`, are defined for every numeric
+//! type. Other functions, like `sqrt` and `sin`, are defined for `double precision` only, and
+//! other types are implicitly cast into it. Inputs outside of the domain of a function are
+//! reported as errors instead of producing `NaN`, as in PostgreSQL. A `NaN` input still produces
+//! `NaN`.
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::TypeMismatch;
+use crate::array::ArrayImpl;
+use crate::dataType::DataType;
+use crate::expr::arith::CheckedArith;
+use crate::expr::cmp::SqlOrd;
+use crate::expr::{Expression, FunctionRegistry, FunctionSignature, function};
+
+/// Numeric types supporting the type-preserving functions like `abs` and `floor`.
+pub trait SqlNumeric: CheckedArith + PartialOrd {
+    /// Absolute value, or `None` on overflow.
+    fn checked_abs(self) -> Option<Self>;
+    /// `-1`, `0` or `1` depending on the sign, or `NaN` for `NaN`.
+    fn sign(self) -> Self;
+    fn ceil(self) -> Self;
+    fn floor(self) -> Self;
+    fn trunc(self) -> Self;
+    fn round(self) -> Self;
+}
+
+macro_rules! impl_sql_numeric_int {
+    ($($ty:ty),*) => {
+        $(
+            impl SqlNumeric for $ty {
+                fn checked_abs(self) -> Option<Self> {
+                    <$ty>::checked_abs(self)
+                }
+
+                fn sign(self) -> Self {
+                    self.signum()
+                }
+
+                fn ceil(self) -> Self {
+                    self
+                }
+
+                fn floor(self) -> Self {
+                    self
+                }
+
+                fn trunc(self) -> Self {
+                    self
+                }
+
+                fn round(self) -> Self {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_numeric_int! { i16, i32, i64 }
+
+/// As in PostgreSQL, `round` of floats rounds half to even.
+macro_rules! impl_sql_numeric_float {
+    ($($ty:ty),*) => {
+        $(
+            impl SqlNumeric for $ty {
+                fn checked_abs(self) -> Option<Self> {
+                    Some(self.abs())
+                }
+
+                fn sign(self) -> Self {
+                    match self {
+                        v if v > 0.0 => 1.0,
+                        v if v < 0.0 => -1.0,
+                        v => v * 0.0,
+                    }
+                }
+
+                fn ceil(self) -> Self {
+                    <$ty>::ceil(self)
+                }
+
+                fn floor(self) -> Self {
+                    <$ty>::floor(self)
+                }
+
+                fn trunc(self) -> Self {
+                    <$ty>::trunc(self)
+                }
+
+                fn round(self) -> Self {
+                    self.round_ties_even()
+                }
+            }
+        )*
+    };
+}
+
+impl_sql_numeric_float! { f32, f64 }
+
+#[function("abs(int16) -> int16")]
+#[function("abs(int32) -> int32")]
+#[function("abs(int64) -> int64")]
+#[function("abs(float32) -> float32")]
+#[function("abs(float64) -> float64")]
+pub fn abs<T: SqlNumeric>(x: T) -> Result<T> {
+    x.checked_abs()
+        .ok_or_else(|| anyhow!("{} out of range", T::TYPE_NAME))
+}
+
+#[function("sign(int16) -> int16")]
+#[function("sign(int32) -> int32")]
+#[function("sign(int64) -> int64")]
+#[function("sign(float32) -> float32")]
+#[function("sign(float64) -> float64")]
+pub fn sign<T: SqlNumeric>(x: T) -> T {
+    x.sign()
+}
+
+#[function("ceil(int16) -> int16")]
+#[function("ceil(int32) -> int32")]
+#[function("ceil(int64) -> int64")]
+#[function("ceil(float32) -> float32")]
+#[function("ceil(float64) -> float64")]
+pub fn ceil<T: SqlNumeric>(x: T) -> T {
+    x.ceil()
+}
+
+#[function("floor(int16) -> int16")]
+#[function("floor(int32) -> int32")]
+#[function("floor(int64) -> int64")]
+#[function("floor(float32) -> float32")]
+#[function("floor(float64) -> float64")]
+pub fn floor<T: SqlNumeric>(x: T) -> T {
+    x.floor()
+}
+
+#[function("trunc(int16) -> int16")]
+#[function("trunc(int32) -> int32")]
+#[function("trunc(int64) -> int64")]
+#[function("trunc(float32) -> float32")]
+#[function("trunc(float64) -> float64")]
+pub fn trunc<T: SqlNumeric>(x: T) -> T {
+    x.trunc()
+}
+
+#[function("round(int16) -> int16")]
+#[function("round(int32) -> int32")]
+#[function("round(int64) -> int64")]
+#[function("round(float32) -> float32")]
+#[function("round(float64) -> float64")]
+pub fn round<T: SqlNumeric>(x: T) -> T {
+    x.round()
+}
+
+/// Rounds `x` to `digits` decimal places, which rounds to tens, hundreds and so on if `digits` is
+/// negative. Ties are rounded away from zero.
+#[function("round(int16, int32) -> int16")]
+#[function("round(int32, int32) -> int32")]
+#[function("round(int64, int32) -> int64")]
+pub fn round_digits_int<T>(x: T, digits: i32) -> Result<T>
+where
+    T: CheckedArith + Into<i128> + TryFrom<i128>,
+{
+    if digits >= 0 {
+        return Ok(x);
+    }
+    let x: i128 = x.into();
+    // Every 64-bit integer is rounded to 0 if `scale` does not even fit in `i128`.
+    let Some(scale) = 10_i128.checked_pow(digits.unsigned_abs()) else {
+        return Ok(T::default());
+    };
+    let half = scale / 2;
+    let rounded = match x >= 0 {
+        true => (x + half) / scale * scale,
+        false => (x - half) / scale * scale,
+    };
+    T::try_from(rounded).map_err(|_| anyhow!("{} out of range", T::TYPE_NAME))
+}
+
+/// Rounds `x` to `digits` decimal places, which rounds to tens, hundreds and so on if `digits` is
+/// negative. Ties are rounded away from zero.
+#[function("round(float64, int32) -> float64")]
+pub fn round_digits(x: f64, digits: i32) -> f64 {
+    if !x.is_finite() {
+        return x;
+    }
+    let scale = 10_f64.powi(digits.unsigned_abs().min(400) as i32);
+    if digits < 0 && scale.is_infinite() {
+        // Rounding to a power of ten beyond the range of floats leaves nothing.
+        return 0.0_f64.copysign(x);
+    }
+    let rounded = match digits >= 0 {
+        true => (x * scale).round() / scale,
+        false => (x / scale).round() * scale,
+    };
+    // Too many digits to round at all.
+    if rounded.is_finite() { rounded } else { x }
+}
+
+/// Check the result of a function with finite inputs, like PostgreSQL does for `pow` and `exp`.
+fn check_float(input_finite: bool, result: f64, zero_is_underflow: bool) -> Result<f64> {
+    if input_finite && result.is_infinite() {
+        bail!("value out of range: overflow");
+    }
+    if zero_is_underflow && result == 0.0 {
+        bail!("value out of range: underflow");
+    }
+    Ok(result)
+}
+
+#[function("sqrt(float64) -> float64")]
+pub fn sqrt(x: f64) -> Result<f64> {
+    if x < 0.0 {
+        bail!("cannot take square root of a negative number");
+    }
+    Ok(x.sqrt())
+}
+
+#[function("cbrt(float64) -> float64")]
+pub fn cbrt(x: f64) -> f64 {
+    x.cbrt()
+}
+
+/// `x` raised to the power of `y`.
+#[function("pow(float64, float64) -> float64")]
+pub fn pow(x: f64, y: f64) -> Result<f64> {
+    if x == 0.0 && y < 0.0 {
+        bail!("zero raised to a negative power is undefined");
+    }
+    if x < 0.0 && y.is_finite() && y.fract() != 0.0 {
+        bail!("a negative number raised to a non-integer power yields a complex result");
+    }
+    let result = x.powf(y);
+    let finite = x.is_finite() && y.is_finite();
+    check_float(finite, result, finite && x != 0.0)
+}
+
+#[function("exp(float64) -> float64")]
+pub fn exp(x: f64) -> Result<f64> {
+    check_float(x.is_finite(), x.exp(), x.is_finite())
+}
+
+/// Check the domain of logarithms.
+fn check_log(x: f64) -> Result<()> {
+    if x == 0.0 {
+        bail!("cannot take logarithm of zero");
+    }
+    if x < 0.0 {
+        bail!("cannot take logarithm of a negative number");
+    }
+    Ok(())
+}
+
+/// Natural logarithm.
+#[function("ln(float64) -> float64")]
+pub fn ln(x: f64) -> Result<f64> {
+    check_log(x)?;
+    Ok(x.ln())
+}
+
+#[function("log10(float64) -> float64")]
+pub fn log10(x: f64) -> Result<f64> {
+    check_log(x)?;
+    Ok(x.log10())
+}
+
+/// Logarithm of `x` to `base`.
+#[function("log(float64, float64) -> float64")]
+pub fn log(base: f64, x: f64) -> Result<f64> {
+    check_log(base)?;
+    check_log(x)?;
+    if base == 1.0 {
+        bail!("division by zero");
+    }
+    Ok(x.ln() / base.ln())
+}
+
+/// Check the input of trigonometric functions, which must not be infinite.
+fn check_trig(x: f64) -> Result<()> {
+    if x.is_infinite() {
+        bail!("input is out of range");
+    }
+    Ok(())
+}
+
+#[function("sin(float64) -> float64")]
+pub fn sin(x: f64) -> Result<f64> {
+    check_trig(x)?;
+    Ok(x.sin())
+}
+
+#[function("cos(float64) -> float64")]
+pub fn cos(x: f64) -> Result<f64> {
+    check_trig(x)?;
+    Ok(x.cos())
+}
+
+#[function("tan(float64) -> float64")]
+pub fn tan(x: f64) -> Result<f64> {
+    check_trig(x)?;
+    Ok(x.tan())
+}
+
+/// Cotangent, which is infinity at 0.
+#[function("cot(float64) -> float64")]
+pub fn cot(x: f64) -> Result<f64> {
+    check_trig(x)?;
+    Ok(1.0 / x.tan())
+}
+
+/// Check the input of inverse sine and cosine, which must be in `[-1, 1]`.
+fn check_unit(x: f64) -> Result<()> {
+    if x.abs() > 1.0 {
+        bail!("input is out of range");
+    }
+    Ok(())
+}
+
+#[function("asin(float64) -> float64")]
+pub fn asin(x: f64) -> Result<f64> {
+    check_unit(x)?;
+    Ok(x.asin())
+}
+
+#[function("acos(float64) -> float64")]
+pub fn acos(x: f64) -> Result<f64> {
+    check_unit(x)?;
+    Ok(x.acos())
+}
+
+#[function("atan(float64) -> float64")]
+pub fn atan(x: f64) -> f64 {
+    x.atan()
+}
+
+/// Arc tangent of `y / x`, using the signs of both to determine the quadrant.
+#[function("atan2(float64, float64) -> float64")]
+pub fn atan2(y: f64, x: f64) -> f64 {
+    y.atan2(x)
+}
+
+#[function("sinh(float64) -> float64")]
+pub fn sinh(x: f64) -> f64 {
+    x.sinh()
+}
+
+#[function("cosh(float64) -> float64")]
+pub fn cosh(x: f64) -> f64 {
+    x.cosh()
+}
+
+#[function("tanh(float64) -> float64")]
+pub fn tanh(x: f64) -> f64 {
+    x.tanh()
+}
+
+/// Converts radians to degrees.
+#[function("degrees(float64) -> float64")]
+pub fn degrees(x: f64) -> Result<f64> {
+    check_float(x.is_finite(), x.to_degrees(), false)
+}
+
+/// Converts degrees to radians.
+#[function("radians(float64) -> float64")]
+pub fn radians(x: f64) -> f64 {
+    x.to_radians()
+}
+
+/// `greatest(x...)` or `least(x...)` over any number of arguments of the same numeric type.
+/// `NULL` arguments are ignored, so the result is only `NULL` if all arguments are.
+pub struct GreatestExpression {
+    least: bool,
+    return_type: DataType,
+}
+
+impl GreatestExpression {
+    /// Create a `greatest` expression, or a `least` expression if `least` is true. All arguments
+    /// must be of `return_type`.
+    pub fn new(least: bool, return_type: DataType) -> Self {
+        Self { least, return_type }
+    }
+}
+
+impl Expression for GreatestExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        let Some(len) = data.first().map(|array| array.len()) else {
+            bail!("Expect at least one input for GreatestExpression");
+        };
+        for array in data {
+            if array.identifier() != self.return_type.physical_identifier() {
+                let expected = self.return_type.physical_identifier();
+                return Err(TypeMismatch(expected, array.identifier()).into());
+            }
+            if array.len() != len {
+                bail!("array length mismatch");
+            }
+        }
+        let mut builder = self.return_type.create_array_builder(len)?;
+        for row in 0..len {
+            // On ties, the first argument is kept.
+            let result = data
+                .iter()
+                .filter_map(|array| array.get(row))
+                .reduce(|best, v| {
+                    let ordering = v.sql_cmp(&best);
+                    let better = if self.least {
+                        ordering.is_lt()
+                    } else {
+                        ordering.is_gt()
+                    };
+                    if better { v } else { best }
+                });
+            builder.push(result);
+        }
+        Ok(builder.finish())
+    }
+}
+
+/// The bucket that `x` falls in, among `count` buckets of equal width dividing the range from
+/// `low` to `high`. Returns 0 for values before the range and `count + 1` for values after it.
+/// The range is reversed if `low` is greater than `high`.
+#[function("width_bucket(float64, float64, float64, int32) -> int32")]
+pub fn width_bucket(x: f64, low: f64, high: f64, count: i32) -> Result<i32> {
+    if count <= 0 {
+        bail!("count must be greater than zero");
+    }
+    if x.is_nan() || low.is_nan() || high.is_nan() {
+        bail!("operand, lower bound, and upper bound cannot be NaN");
+    }
+    if !low.is_finite() || !high.is_finite() {
+        bail!("lower and upper bounds must be finite");
+    }
+    let (before, after, offset) = match low.partial_cmp(&high) {
+        Some(std::cmp::Ordering::Less) => (x < low, x >= high, (x - low) / (high - low)),
+        Some(std::cmp::Ordering::Greater) => (x > low, x <= high, (low - x) / (low - high)),
+        _ => bail!("lower bound cannot equal upper bound"),
+    };
+    if before {
+        return Ok(0);
+    }
+    if after {
+        return count
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("integer out of range"));
+    }
+    // The offset is in `[0, 1)`, but may be rounded up to 1.
+    let bucket = (offset * count as f64).floor() as i32;
+    Ok(bucket.min(count - 1) + 1)
+}
+
+/// Register all math functions into `registry`.
+pub fn register_math_functions(registry: &mut FunctionRegistry) -> Result<()> {
+    register_abs(registry)?;
+    register_sign(registry)?;
+    register_ceil(registry)?;
+    register_floor(registry)?;
+    register_trunc(registry)?;
+    register_round(registry)?;
+    register_round_digits_int(registry)?;
+    register_round_digits(registry)?;
+    register_sqrt(registry)?;
+    register_cbrt(registry)?;
+    register_pow(registry)?;
+    register_exp(registry)?;
+    register_ln(registry)?;
+    register_log10(registry)?;
+    register_log(registry)?;
+    register_sin(registry)?;
+    register_cos(registry)?;
+    register_tan(registry)?;
+    register_cot(registry)?;
+    register_asin(registry)?;
+    register_acos(registry)?;
+    register_atan(registry)?;
+    register_atan2(registry)?;
+    register_sinh(registry)?;
+    register_cosh(registry)?;
+    register_tanh(registry)?;
+    register_degrees(registry)?;
+    register_radians(registry)?;
+    for ty in ["int16", "int32", "int64", "float32", "float64"] {
+        for (name, least) in [("greatest", false), ("least", true)] {
+            let signature: FunctionSignature = format!("{name}({ty}...) -> {ty}").parse()?;
+            let return_type = signature.ret;
+            registry.register(signature, move || {
+                Box::new(GreatestExpression::new(least, return_type))
+            })?;
+        }
+    }
+    register_width_bucket(registry)?;
+    Ok(())
+}
@@ -1,23 +1,38 @@
-use std::marker::PhantomData;
-
-use crate::array::ArrayBuilder;
-use crate::expr::vectorize::BinaryExpression;
-use crate::{
-    TypeMismatch,
-    array::{Array, ArrayImpl},
-};
+use crate::array::ArrayImpl;
 use anyhow::Result;
-mod cmp;
-mod string;
-mod vectorize;
+pub mod arith;
+pub mod bitwise;
+pub mod cast;
+pub mod cmp;
+pub mod conditional;
+pub mod like;
+pub mod math;
+pub mod predicate;
+pub mod regex;
+pub mod registry;
+pub mod string;
+pub mod tree;
+pub mod vectorize;
+
+pub use registry::{FunctionRegistry, FunctionSignature};
+pub use type_rust_macros::function;
 
-/// A trait over all expressions -- unary, binary, etc
-pub trait Expression {
+/// A trait over all expressions -- unary, binary, etc. Expressions are shared by worker threads
+/// of parallel execution.
+pub trait Expression: Send + Sync {
     /// Evaluate an expression with run-time number of [`ArrayImpl`]s.
     fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl>;
+
+    /// Evaluate an expression on `num_rows` rows of `data`. Unlike [`Expression::eval_expr`], it
+    /// works on an input without columns, which only has a number of rows. Expressions that do not
+    /// get the number of rows from their input, like [`Literal`](tree::Literal), override it.
+    fn eval_rows(&self, data: &[&ArrayImpl], _num_rows: usize) -> Result<ArrayImpl> {
+        self.eval_expr(data)
+    }
 }
 
 /// All supported expression functions.
+#[deprecated(note = "resolve functions by name and argument types with `FunctionRegistry`")]
 pub enum ExpressionFunc {
     CmpLe,
     CmpGe,
@@ -26,27 +41,21 @@ pub enum ExpressionFunc {
     StrContains,
 }
 
+/// Build the expression of `f`, which compares `int32`s or checks if a `varchar` contains another.
+#[deprecated(note = "use `FunctionRegistry::build` instead")]
+#[allow(deprecated)]
 pub fn build_binary_expression(f: ExpressionFunc) -> Box<dyn Expression> {
-    use crate::array::*;
-    use crate::expr::cmp::*;
-    use crate::expr::string::*;
+    use crate::dataType::DataType;
     use ExpressionFunc::*;
 
-    match f {
-        CmpLe => Box::new(BinaryExpression::<I32Array, I32Array, BoolArray, _>::new(
-            ExprCmpLe::<_, _, I32Array>(PhantomData),
-        )),
-        CmpGe => Box::new(BinaryExpression::<I32Array, I32Array, BoolArray, _>::new(
-            ExprCmpGe::<_, _, I32Array>(PhantomData),
-        )),
-        CmpEq => Box::new(BinaryExpression::<I32Array, I32Array, BoolArray, _>::new(
-            ExprCmpEq::<_, _, I32Array>(PhantomData),
-        )),
-        CmpNe => Box::new(BinaryExpression::<I32Array, I32Array, BoolArray, _>::new(
-            ExprCmpNe::<_, _, I32Array>(PhantomData),
-        )),
-        StrContains => Box::new(
-            BinaryExpression::<StringArray, StringArray, BoolArray, _>::new(ExprStrContains),
-        ),
-    }
+    let (name, arg) = match f {
+        CmpLe => ("less_than", DataType::Integer),
+        CmpGe => ("greater_than", DataType::Integer),
+        CmpEq => ("equal", DataType::Integer),
+        CmpNe => ("not_equal", DataType::Integer),
+        StrContains => ("contains", DataType::Varchar),
+    };
+    FunctionRegistry::with_builtins()
+        .build(name, &[arg, arg])
+        .expect("builtin function must exist")
 }
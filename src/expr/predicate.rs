@@ -0,0 +1,188 @@
+//! Implements `IN` and `BETWEEN` predicates.
+//!
+//! Both follow the three-valued logic of SQL:
+//!
+//! * `x IN (v1, v2, ...)` is `true` if `x` equals any of the values. Otherwise, it is `NULL` if `x`
+//!   or any of the values is `NULL`, and `false` if not.
+//! * `x BETWEEN a AND b` is `a <= x AND x <= b`, so it is `false` as soon as one of the comparisons
+//!   is `false`, even if the other one is `NULL`.
+//!
+//! The negated forms `NOT IN` and `NOT BETWEEN` negate the result, where `NULL` stays `NULL`.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+
+use anyhow::{Result, anyhow};
+
+use crate::TypeMismatch;
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::expr::cmp::{SqlHash, SqlOrd};
+use crate::expr::{Expression, FunctionRegistry, function};
+use crate::macros::for_all_variants;
+use crate::scalar::{Scalar, ScalarImpl};
+
+/// Lists with more values than this are looked up with a hash index. Shorter ones are scanned,
+/// which is faster than hashing each input value.
+const HASH_THRESHOLD: usize = 8;
+
+/// `x IN (v1, v2, ...)` with a list of constant values of array type `A`.
+pub struct InList<A: Array> {
+    /// Non-null values of the list.
+    values: Vec<A::OwnedItem>,
+    /// Indexes into `values` grouped by their hashes, built only if the list is long.
+    index: Option<HashMap<u64, Vec<usize>>>,
+    hash_builder: RandomState,
+    /// Whether the list contains `NULL`.
+    has_null: bool,
+    negated: bool,
+}
+
+impl<A: Array> InList<A>
+where
+    for<'a> A::RefItem<'a>: SqlOrd + SqlHash,
+{
+    /// Create `x IN (values)`, or `x NOT IN (values)` if `negated` is set. `None` represents
+    /// `NULL`.
+    pub fn new(values: Vec<Option<A::OwnedItem>>, negated: bool) -> Self {
+        let has_null = values.iter().any(Option::is_none);
+        let values = values.into_iter().flatten().collect::<Vec<_>>();
+        let hash_builder = RandomState::new();
+        let index = (values.len() > HASH_THRESHOLD).then(|| {
+            let mut index = HashMap::<u64, Vec<usize>>::new();
+            for (i, value) in values.iter().enumerate() {
+                let hash = hash_one(&hash_builder, value.as_scalar_ref());
+                index.entry(hash).or_default().push(i);
+            }
+            index
+        });
+        Self {
+            values,
+            index,
+            hash_builder,
+            has_null,
+            negated,
+        }
+    }
+
+    /// Check if the list contains `v`.
+    fn contains<'a>(&'a self, v: A::RefItem<'a>) -> bool {
+        let matches = |i: &usize| self.values[*i].as_scalar_ref().sql_cmp(&v).is_eq();
+        match &self.index {
+            Some(index) => index
+                .get(&hash_one(&self.hash_builder, v))
+                .is_some_and(|bucket| bucket.iter().any(matches)),
+            None => (0..self.values.len()).any(|i| matches(&i)),
+        }
+    }
+
+    /// Evaluate the predicate on `v`.
+    fn eval<'a>(&'a self, v: Option<A::RefItem<'a>>) -> Option<bool> {
+        let found = match v {
+            Some(v) if self.contains(v) => Some(true),
+            Some(_) if !self.has_null => Some(false),
+            _ => None,
+        };
+        found.map(|found| found != self.negated)
+    }
+
+    fn eval_array<'a>(&'a self, array: &'a A) -> ArrayImpl {
+        let mut builder = BoolArrayBuilder::with_capacity(array.len());
+        for v in array.iter() {
+            builder.push(self.eval(v));
+        }
+        builder.finish().into()
+    }
+}
+
+fn hash_one(hash_builder: &RandomState, v: impl SqlHash) -> u64 {
+    let mut hasher = hash_builder.build_hasher();
+    v.sql_hash(&mut hasher);
+    std::hash::Hasher::finish(&hasher)
+}
+
+impl<A: Array> Expression for InList<A>
+where
+    for<'a> &'a A: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    for<'a> A::RefItem<'a>: SqlOrd + SqlHash,
+{
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        if data.len() != 1 {
+            return Err(anyhow!("Expect one input for InList"));
+        }
+        let array: &A = data[0].try_into()?;
+        Ok(self.eval_array(array))
+    }
+}
+
+/// Implements [`in_list`] by dispatching on the physical type.
+macro_rules! impl_in_list {
+    ([], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+        /// Create `x IN (values)`, or `x NOT IN (values)` if `negated` is set, where `x` is of
+        /// `data_type`. All values must be of `data_type` as well, and `None` represents `NULL`.
+        pub fn in_list(
+            data_type: DataType,
+            values: Vec<Option<ScalarImpl>>,
+            negated: bool,
+        ) -> Result<Box<dyn Expression>> {
+            match data_type.physical_identifier() {
+                $(
+                    stringify!($Abc) => {
+                        let values = values
+                            .into_iter()
+                            .map(|v| v.map(<$Owned>::try_from).transpose())
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Ok(Box::new(InList::<$AbcArray>::new(values, negated)))
+                    }
+                )*
+                other => Err(anyhow!("IN is not supported for physical type {other}")),
+            }
+        }
+    };
+}
+
+for_all_variants! { impl_in_list }
+
+/// Evaluate `l <= r` with `NULL` inputs producing `NULL`.
+fn le<T: SqlOrd>(l: &Option<T>, r: &Option<T>) -> Option<bool> {
+    match (l, r) {
+        (Some(l), Some(r)) => Some(l.sql_cmp(r).is_le()),
+        _ => None,
+    }
+}
+
+/// `v BETWEEN lo AND hi`, which is `lo <= v AND v <= hi`.
+#[function("between(int16, int16, int16) -> boolean")]
+#[function("between(int32, int32, int32) -> boolean")]
+#[function("between(int64, int64, int64) -> boolean")]
+#[function("between(float32, float32, float32) -> boolean")]
+#[function("between(float64, float64, float64) -> boolean")]
+#[function("between(boolean, boolean, boolean) -> boolean")]
+#[function("between(varchar, varchar, varchar) -> boolean")]
+pub fn between<T: SqlOrd>(v: Option<T>, lo: Option<T>, hi: Option<T>) -> Option<bool> {
+    match (le(&lo, &v), le(&v, &hi)) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+/// `v NOT BETWEEN lo AND hi`, which is `v < lo OR hi < v`.
+#[function("not_between(int16, int16, int16) -> boolean")]
+#[function("not_between(int32, int32, int32) -> boolean")]
+#[function("not_between(int64, int64, int64) -> boolean")]
+#[function("not_between(float32, float32, float32) -> boolean")]
+#[function("not_between(float64, float64, float64) -> boolean")]
+#[function("not_between(boolean, boolean, boolean) -> boolean")]
+#[function("not_between(varchar, varchar, varchar) -> boolean")]
+pub fn not_between<T: SqlOrd>(v: Option<T>, lo: Option<T>, hi: Option<T>) -> Option<bool> {
+    between(v, lo, hi).map(|b| !b)
+}
+
+/// Register all predicate functions into `registry`. `IN` lists are built with [`in_list`]
+/// instead, as their values are not inputs of the function.
+pub fn register_predicate_functions(registry: &mut FunctionRegistry) -> Result<()> {
+    register_between(registry)?;
+    register_not_between(registry)?;
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! Compiles an [`Ast`] into a [`Program`] for the [Pike VM](super::pike).
+
+use anyhow::{Result, bail};
+
+use super::parse::{Ast, Class};
+
+/// Max number of instructions in a program, which limits the cost of matching.
+const MAX_PROGRAM_SIZE: usize = 100_000;
+
+/// A zero-width assertion.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Look {
+    Start,
+    End,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+/// An instruction of the program.
+#[derive(Debug, Clone)]
+pub(super) enum Inst {
+    /// Consume the character.
+    Char(char),
+    /// Consume any character.
+    Any,
+    /// Consume a character in the class.
+    Class(Class),
+    /// Continue only if the assertion holds.
+    Look(Look),
+    /// Continue at both targets, preferring the first one.
+    Split(usize, usize),
+    Jmp(usize),
+    /// Save the current position into the slot.
+    Save(usize),
+    Match,
+}
+
+/// A compiled regular expression.
+#[derive(Debug, Clone)]
+pub(super) struct Program {
+    pub(super) insts: Vec<Inst>,
+    /// Number of slots, which is twice the number of capture groups, including the whole match
+    /// as group 0.
+    pub(super) slots: usize,
+    pub(super) case_insensitive: bool,
+}
+
+/// Compile `ast` with `groups` capture groups.
+pub(super) fn compile(ast: &Ast, groups: usize, case_insensitive: bool) -> Result<Program> {
+    let mut compiler = Compiler {
+        insts: vec![],
+        case_insensitive,
+    };
+    compiler.push(Inst::Save(0))?;
+    compiler.compile(ast)?;
+    compiler.push(Inst::Save(1))?;
+    compiler.push(Inst::Match)?;
+    Ok(Program {
+        insts: compiler.insts,
+        slots: (groups + 1) * 2,
+        case_insensitive,
+    })
+}
+
+struct Compiler {
+    insts: Vec<Inst>,
+    case_insensitive: bool,
+}
+
+impl Compiler {
+    /// Append `inst` and get its index.
+    fn push(&mut self, inst: Inst) -> Result<usize> {
+        if self.insts.len() >= MAX_PROGRAM_SIZE {
+            bail!("regular expression is too complex");
+        }
+        self.insts.push(inst);
+        Ok(self.insts.len() - 1)
+    }
+
+    /// Index of the next instruction.
+    fn next(&self) -> usize {
+        self.insts.len()
+    }
+
+    /// Set the targets of the `Split` or `Jmp` at `idx`.
+    fn patch(&mut self, idx: usize, target: usize) {
+        match &mut self.insts[idx] {
+            Inst::Jmp(x) => *x = target,
+            Inst::Split(_, y) => *y = target,
+            inst => unreachable!("cannot patch {inst:?}"),
+        }
+    }
+
+    fn compile(&mut self, ast: &Ast) -> Result<()> {
+        match ast {
+            Ast::Empty => {}
+            Ast::Literal(c) => {
+                let c = match self.case_insensitive {
+                    true => super::fold_case(*c),
+                    false => *c,
+                };
+                self.push(Inst::Char(c))?;
+            }
+            Ast::Any => {
+                self.push(Inst::Any)?;
+            }
+            Ast::Class(class) => {
+                self.push(Inst::Class(class.clone()))?;
+            }
+            Ast::Start => {
+                self.push(Inst::Look(Look::Start))?;
+            }
+            Ast::End => {
+                self.push(Inst::Look(Look::End))?;
+            }
+            Ast::WordBoundary { negated: false } => {
+                self.push(Inst::Look(Look::WordBoundary))?;
+            }
+            Ast::WordBoundary { negated: true } => {
+                self.push(Inst::Look(Look::NotWordBoundary))?;
+            }
+            Ast::Group(ast, None) => self.compile(ast)?,
+            Ast::Group(ast, Some(index)) => {
+                self.push(Inst::Save(index * 2))?;
+                self.compile(ast)?;
+                self.push(Inst::Save(index * 2 + 1))?;
+            }
+            Ast::Concat(items) => {
+                for item in items {
+                    self.compile(item)?;
+                }
+            }
+            Ast::Alternate(branches) => {
+                // Split(L1, L2); L1: a; Jmp(end); L2: Split(L2', L3); ...; Ln: z; end:
+                let mut jumps = vec![];
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 == branches.len() {
+                        self.compile(branch)?;
+                        break;
+                    }
+                    let split = self.push(Inst::Split(self.next() + 1, 0))?;
+                    self.compile(branch)?;
+                    jumps.push(self.push(Inst::Jmp(0))?);
+                    let next = self.next();
+                    self.patch(split, next);
+                }
+                let end = self.next();
+                for jump in jumps {
+                    self.patch(jump, end);
+                }
+            }
+            Ast::Repeat {
+                ast,
+                min,
+                max,
+                greedy,
+            } => self.compile_repeat(ast, *min, *max, *greedy)?,
+        }
+        Ok(())
+    }
+
+    /// Push a `Split` preferring the next instruction if `greedy`, or the target otherwise. The
+    /// target is patched later with [`Compiler::patch_split`].
+    fn push_split(&mut self, greedy: bool) -> Result<usize> {
+        let next = self.next() + 1;
+        match greedy {
+            true => self.push(Inst::Split(next, 0)),
+            false => self.push(Inst::Split(0, next)),
+        }
+    }
+
+    fn patch_split(&mut self, idx: usize, target: usize) {
+        match &mut self.insts[idx] {
+            Inst::Split(x, _) if *x == idx + 1 => self.patch(idx, target),
+            Inst::Split(x, _) => *x = target,
+            inst => unreachable!("cannot patch {inst:?}"),
+        }
+    }
+
+    fn compile_repeat(
+        &mut self,
+        ast: &Ast,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    ) -> Result<()> {
+        match max {
+            // `a{2,}` is compiled as `aa*`, except that `a{1,}` is compiled as `L: a; Split(L)`.
+            None if min > 0 => {
+                for _ in 1..min {
+                    self.compile(ast)?;
+                }
+                let start = self.next();
+                self.compile(ast)?;
+                // The preferred branch of a greedy loop goes back to the start.
+                let next = self.next() + 1;
+                match greedy {
+                    true => self.push(Inst::Split(start, next))?,
+                    false => self.push(Inst::Split(next, start))?,
+                };
+            }
+            // `a*` is compiled as `L: Split(L1, end); L1: a; Jmp(L); end:`.
+            None => {
+                let split = self.push_split(greedy)?;
+                self.compile(ast)?;
+                self.push(Inst::Jmp(split))?;
+                let end = self.next();
+                self.patch_split(split, end);
+            }
+            // `a{1,3}` is compiled as `a(a(a)?)?`.
+            Some(max) => {
+                for _ in 0..min {
+                    self.compile(ast)?;
+                }
+                let mut splits = vec![];
+                for _ in min..max {
+                    splits.push(self.push_split(greedy)?);
+                    self.compile(ast)?;
+                }
+                let end = self.next();
+                for split in splits {
+                    self.patch_split(split, end);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,591 @@
+//! Implements regular expressions and the `regexp_*` functions for [`StringArray`].
+//!
+//! Patterns are compiled into a program for a [Pike VM](pike), which matches in time linear to
+//! the input without backtracking. The supported syntax is a common subset of POSIX and Perl
+//! regular expressions:
+//!
+//! * Literals, `.`, and escapes like `\.`, `\n` and `\t`.
+//! * Bracket expressions like `[a-z_]`, `[^0-9]` and `[[:alpha:]]`, and the classes `\d`, `\w`,
+//!   `\s` and their negations.
+//! * Anchors `^` and `$`, and word boundaries `\b` and `\B`.
+//! * Capture groups `(...)` and non-capture groups `(?:...)`, and alternations `a|b`.
+//! * Quantifiers `*`, `+`, `?`, `{m}`, `{m,}` and `{m,n}`, and their lazy versions like `*?`.
+//!
+//! Back references are not supported, since they cannot be matched without backtracking.
+
+mod compile;
+mod parse;
+mod pike;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::array::*;
+use crate::expr::like::{fold_case, parse_escape};
+use crate::expr::{Expression, FunctionRegistry, FunctionSignature};
+use crate::scalar::ScalarRefImpl;
+
+use self::compile::Program;
+
+/// A compiled regular expression.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Program,
+    groups: usize,
+    flags: RegexFlags,
+}
+
+/// Options of a regular expression given by a flags string like `'gi'`, as in PostgreSQL.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegexFlags {
+    /// `i`: match case-insensitively. `c` resets it.
+    pub case_insensitive: bool,
+    /// `g`: replace all matches instead of the first one.
+    pub global: bool,
+}
+
+impl RegexFlags {
+    /// Parse a flags string.
+    pub fn parse(flags: &str) -> Result<Self> {
+        let mut result = Self::default();
+        for flag in flags.chars() {
+            match flag {
+                'i' => result.case_insensitive = true,
+                'c' => result.case_insensitive = false,
+                'g' => result.global = true,
+                _ => bail!("invalid regular expression option: \"{flag}\""),
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl Regex {
+    /// Compile `pattern` with the default options.
+    pub fn new(pattern: &str) -> Result<Self> {
+        Self::with_flags(pattern, RegexFlags::default())
+    }
+
+    /// Compile `pattern` with `flags`.
+    pub fn with_flags(pattern: &str, flags: RegexFlags) -> Result<Self> {
+        let (ast, groups) = parse::parse(pattern)?;
+        let program = compile::compile(&ast, groups, flags.case_insensitive)?;
+        Ok(Self {
+            program,
+            groups,
+            flags,
+        })
+    }
+
+    /// Options the regular expression is compiled with.
+    pub fn flags(&self) -> RegexFlags {
+        self.flags
+    }
+
+    /// Number of capture groups, excluding the whole match.
+    pub fn groups(&self) -> usize {
+        self.groups
+    }
+
+    /// Check if `s` contains a match.
+    pub fn is_match(&self, s: &str) -> bool {
+        pike::search(&self.program, s, 0, true).is_some()
+    }
+
+    /// Find the first match in `s` starting at or after byte offset `start`.
+    pub fn captures_at<'a>(&self, s: &'a str, start: usize) -> Option<Captures<'a>> {
+        let slots = pike::search(&self.program, s, start, false)?;
+        Some(Captures { input: s, slots })
+    }
+
+    /// Find the first match in `s`.
+    pub fn captures<'a>(&self, s: &'a str) -> Option<Captures<'a>> {
+        self.captures_at(s, 0)
+    }
+
+    /// Iterate over all non-overlapping matches in `s`.
+    pub fn captures_iter<'r, 'a>(&'r self, s: &'a str) -> CapturesIter<'r, 'a> {
+        CapturesIter {
+            regex: self,
+            input: s,
+            pos: Some(0),
+            last_end: None,
+        }
+    }
+
+    /// Write `s` with the first match, or all matches if `global`, replaced by `replacement`
+    /// into `writer`.
+    ///
+    /// In `replacement`, `\1` to `\9` refer to capture groups, `\&` refers to the whole match,
+    /// and `\\` is a backslash.
+    pub fn replace_into(
+        &self,
+        s: &str,
+        replacement: &str,
+        global: bool,
+        writer: &mut StringWriter,
+    ) {
+        let mut last = 0;
+        for captures in self.captures_iter(s) {
+            let (start, end) = captures.range(0).unwrap();
+            writer.write_str(&s[last..start]);
+            captures.expand(replacement, writer);
+            last = end;
+            if !global {
+                break;
+            }
+        }
+        writer.write_str(&s[last..]);
+    }
+
+    /// Iterate over the substrings of `s` separated by matches.
+    pub fn split<'r, 'a>(&'r self, s: &'a str) -> impl Iterator<Item = &'a str> + 'r
+    where
+        'a: 'r,
+    {
+        let mut last = 0;
+        let mut matches = self.captures_iter(s);
+        std::iter::from_fn(move || {
+            if last > s.len() {
+                return None;
+            }
+            // An empty match at the start or the end does not produce an empty field.
+            let next = matches.by_ref().find_map(|captures| {
+                let (start, end) = captures.range(0).unwrap();
+                (end > 0 && start < s.len()).then_some((start, end))
+            });
+            let (field, next_last) = match next {
+                Some((start, end)) => (&s[last..start], end),
+                None => (&s[last..], s.len() + 1),
+            };
+            last = next_last;
+            Some(field)
+        })
+    }
+}
+
+/// Positions of a match and its capture groups.
+pub struct Captures<'a> {
+    input: &'a str,
+    slots: pike::Slots,
+}
+
+impl<'a> Captures<'a> {
+    /// Byte range of capture group `i` in the input, where group 0 is the whole match. Returns
+    /// `None` if the group did not participate in the match.
+    pub fn range(&self, i: usize) -> Option<(usize, usize)> {
+        match (self.slots.get(2 * i)?, self.slots.get(2 * i + 1)?) {
+            (Some(start), Some(end)) => Some((*start, *end)),
+            _ => None,
+        }
+    }
+
+    /// Text of capture group `i`, where group 0 is the whole match.
+    pub fn get(&self, i: usize) -> Option<&'a str> {
+        self.range(i).map(|(start, end)| &self.input[start..end])
+    }
+
+    /// Write `replacement` with references to groups substituted.
+    fn expand(&self, replacement: &str, writer: &mut StringWriter) {
+        let mut chars = replacement.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                writer.write_char(c);
+                continue;
+            }
+            match chars.next() {
+                Some(d @ '1'..='9') => {
+                    let group = d as usize - '0' as usize;
+                    writer.write_str(self.get(group).unwrap_or_default());
+                }
+                Some('&') => writer.write_str(self.get(0).unwrap_or_default()),
+                Some(c) => writer.write_char(c),
+                None => writer.write_char('\\'),
+            }
+        }
+    }
+}
+
+/// An iterator over non-overlapping matches, created by [`Regex::captures_iter`].
+pub struct CapturesIter<'r, 'a> {
+    regex: &'r Regex,
+    input: &'a str,
+    /// Position to search from, or `None` if the iterator is exhausted.
+    pos: Option<usize>,
+    /// End of the previous match.
+    last_end: Option<usize>,
+}
+
+impl<'a> Iterator for CapturesIter<'_, 'a> {
+    type Item = Captures<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let captures = self.regex.captures_at(self.input, self.pos?);
+            let Some(captures) = captures else {
+                self.pos = None;
+                return None;
+            };
+            let (start, end) = captures.range(0).unwrap();
+            // After an empty match, skip a character to avoid matching at the same position again.
+            self.pos = if start < end {
+                Some(end)
+            } else {
+                self.input[end..].chars().next().map(|c| end + c.len_utf8())
+            };
+            // An empty match right after the previous match is not reported.
+            let adjacent = start == end && self.last_end == Some(start);
+            self.last_end = Some(end);
+            if !adjacent {
+                return Some(captures);
+            }
+        }
+    }
+}
+
+/// Translate a `SIMILAR TO` pattern into a regular expression matching the whole string.
+///
+/// `SIMILAR TO` patterns are regular expressions where `%` and `_` have the meaning as in `LIKE`,
+/// and `.`, `^` and `$` are literals. `escape` is the escape character, or `None` if escaping is
+/// disabled.
+pub fn similar_to_regex(pattern: &str, escape: Option<char>) -> Result<String> {
+    let mut regex = String::from("^(?:");
+    let mut in_brackets = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            _ if Some(c) == escape => {
+                let c = chars.next().ok_or_else(|| {
+                    anyhow!("SIMILAR TO pattern must not end with escape character")
+                })?;
+                if !c.is_ascii_alphanumeric() {
+                    regex.push('\\');
+                }
+                regex.push(c);
+            }
+            _ if in_brackets => {
+                in_brackets = c != ']';
+                if c == '\\' {
+                    regex.push('\\');
+                }
+                regex.push(c);
+            }
+            '[' => {
+                in_brackets = true;
+                regex.push(c);
+            }
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '.' | '^' | '$' | '\\' => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            c => regex.push(c),
+        }
+    }
+    regex.push_str(")$");
+    Ok(regex)
+}
+
+/// Compiled regular expression of the previous row, which is reused if the pattern and options of
+/// the current row are the same.
+#[derive(Default)]
+struct RegexCache<'a> {
+    last: Option<(&'a str, &'a str, Regex)>,
+}
+
+impl<'a> RegexCache<'a> {
+    fn get(
+        &mut self,
+        pattern: &'a str,
+        options: &'a str,
+        compile: impl FnOnce() -> Result<Regex>,
+    ) -> Result<&Regex> {
+        let hit = matches!(&self.last, Some((p, o, _)) if *p == pattern && *o == options);
+        if !hit {
+            self.last = Some((pattern, options, compile()?));
+        }
+        Ok(&self.last.as_ref().unwrap().2)
+    }
+}
+
+/// Regular expression functions.
+#[derive(Debug, Clone, Copy)]
+pub enum RegexFunction {
+    /// `regexp_like(s, pattern [, flags]) -> boolean`
+    Like,
+    /// `similar_to(s, pattern [, escape]) -> boolean`
+    SimilarTo,
+    /// `regexp_replace(s, pattern, replacement [, flags]) -> varchar`
+    Replace,
+    /// `regexp_extract(s, pattern [, group]) -> varchar`
+    Extract,
+    /// `regexp_split_part(s, pattern, n) -> varchar`
+    SplitPart,
+}
+
+impl RegexFunction {
+    /// Index of the argument holding the options the pattern is compiled with, i.e. the flags
+    /// or the escape, if any.
+    fn options_arg(self) -> Option<usize> {
+        match self {
+            Self::Like | Self::SimilarTo => Some(2),
+            Self::Replace => Some(3),
+            Self::Extract | Self::SplitPart => None,
+        }
+    }
+
+    /// Default value of the options if they are not given.
+    fn default_options(self) -> &'static str {
+        match self {
+            Self::SimilarTo => "\\",
+            _ => "",
+        }
+    }
+
+    /// Compile `pattern` with `options` for this function.
+    fn compile(self, pattern: &str, options: &str) -> Result<Regex> {
+        match self {
+            Self::Like => {
+                let flags = RegexFlags::parse(options)?;
+                if flags.global {
+                    bail!("regexp_like() does not support the \"global\" option");
+                }
+                Regex::with_flags(pattern, flags)
+            }
+            Self::SimilarTo => Regex::new(&similar_to_regex(pattern, parse_escape(options)?)?),
+            Self::Replace => Regex::with_flags(pattern, RegexFlags::parse(options)?),
+            Self::Extract | Self::SplitPart => Regex::new(pattern),
+        }
+    }
+}
+
+/// Evaluates a [`RegexFunction`] whose first two inputs are the string and the pattern.
+///
+/// The pattern is compiled for each row, unless it is the same as the one of the previous row.
+/// Therefore, a pattern repeated in every row of a batch is only compiled once. Calls with a
+/// constant pattern built by [`FunctionRegistry::build_with_constants`] compile it only once
+/// when the call is built.
+pub struct RegexExpression {
+    func: RegexFunction,
+    /// The pattern compiled with its options if both are constant.
+    constant: Option<Regex>,
+}
+
+impl RegexExpression {
+    pub fn new(func: RegexFunction) -> Self {
+        Self {
+            func,
+            constant: None,
+        }
+    }
+
+    /// Create a [`RegexExpression`] whose pattern and options are constant, and are compiled
+    /// into `regex`.
+    pub fn constant(func: RegexFunction, regex: Regex) -> Self {
+        Self {
+            func,
+            constant: Some(regex),
+        }
+    }
+
+    /// Get the compiled `pattern` with `options`.
+    fn regex<'s, 'a>(
+        &'s self,
+        cache: &'s mut RegexCache<'a>,
+        pattern: &'a str,
+        options: &'a str,
+    ) -> Result<&'s Regex> {
+        match &self.constant {
+            Some(regex) => Ok(regex),
+            None => cache.get(pattern, options, || self.func.compile(pattern, options)),
+        }
+    }
+}
+
+/// Compile the pattern of a call to `func` once if it is constant, as well as its options if
+/// given.
+fn specialize_regex(
+    constants: &[Option<ScalarRefImpl<'_>>],
+    func: RegexFunction,
+) -> Result<Option<Box<dyn Expression>>> {
+    let Some(Some(ScalarRefImpl::String(pattern))) = constants.get(1) else {
+        return Ok(None);
+    };
+    let options = match func.options_arg().and_then(|idx| constants.get(idx)) {
+        Some(Some(ScalarRefImpl::String(options))) => *options,
+        Some(_) => return Ok(None),
+        None => func.default_options(),
+    };
+    let regex = func.compile(pattern, options)?;
+    Ok(Some(Box::new(RegexExpression::constant(func, regex))))
+}
+
+/// Get the string at `row` of an optional argument, or `default` if it is not given.
+fn optional_arg<'a>(arg: Option<&'a StringArray>, row: usize, default: &'a str) -> Option<&'a str> {
+    match arg {
+        Some(arg) => arg.get(row),
+        None => Some(default),
+    }
+}
+
+impl Expression for RegexExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        let [s, pattern, rest @ ..] = data else {
+            bail!("Expect at least two inputs for RegexExpression");
+        };
+        if data.iter().any(|array| array.len() != s.len()) {
+            bail!("array length mismatch");
+        }
+        let s: &StringArray = (*s).try_into()?;
+        let pattern: &StringArray = (*pattern).try_into()?;
+        let mut cache = RegexCache::default();
+
+        match (self.func, rest) {
+            (RegexFunction::Like, [] | [_]) => {
+                let flags: Option<&StringArray> =
+                    rest.first().map(|a| (*a).try_into()).transpose()?;
+                let mut builder = BoolArrayBuilder::with_capacity(s.len());
+                for row in 0..s.len() {
+                    let (Some(v), Some(p), Some(f)) = (
+                        s.get(row),
+                        pattern.get(row),
+                        optional_arg(flags, row, self.func.default_options()),
+                    ) else {
+                        builder.push(None);
+                        continue;
+                    };
+                    let regex = self.regex(&mut cache, p, f)?;
+                    builder.push(Some(regex.is_match(v)));
+                }
+                Ok(builder.finish().into())
+            }
+            (RegexFunction::SimilarTo, [] | [_]) => {
+                let escape: Option<&StringArray> =
+                    rest.first().map(|a| (*a).try_into()).transpose()?;
+                let mut builder = BoolArrayBuilder::with_capacity(s.len());
+                for row in 0..s.len() {
+                    let (Some(v), Some(p), Some(e)) = (
+                        s.get(row),
+                        pattern.get(row),
+                        optional_arg(escape, row, self.func.default_options()),
+                    ) else {
+                        builder.push(None);
+                        continue;
+                    };
+                    let regex = self.regex(&mut cache, p, e)?;
+                    builder.push(Some(regex.is_match(v)));
+                }
+                Ok(builder.finish().into())
+            }
+            (RegexFunction::Replace, [replacement, flags @ ..]) if flags.len() <= 1 => {
+                let replacement: &StringArray = (*replacement).try_into()?;
+                let flags: Option<&StringArray> =
+                    flags.first().map(|a| (*a).try_into()).transpose()?;
+                let mut builder = StringArrayBuilder::with_capacity(s.len());
+                for row in 0..s.len() {
+                    let (Some(v), Some(p), Some(r), Some(f)) = (
+                        s.get(row),
+                        pattern.get(row),
+                        replacement.get(row),
+                        optional_arg(flags, row, self.func.default_options()),
+                    ) else {
+                        builder.push(None);
+                        continue;
+                    };
+                    // Flags are parsed together with the pattern, so constant flags are only
+                    // parsed once.
+                    let regex = self.regex(&mut cache, p, f)?;
+                    let mut writer = builder.writer();
+                    regex.replace_into(v, r, regex.flags().global, &mut writer);
+                    writer.finish();
+                }
+                Ok(builder.finish().into())
+            }
+            (RegexFunction::Extract, [] | [_]) => {
+                let group: Option<&I32Array> = rest.first().map(|a| (*a).try_into()).transpose()?;
+                let mut builder = StringArrayBuilder::with_capacity(s.len());
+                for row in 0..s.len() {
+                    let group = match group {
+                        Some(group) => group.get(row),
+                        None => Some(0),
+                    };
+                    let (Some(v), Some(p), Some(group)) = (s.get(row), pattern.get(row), group)
+                    else {
+                        builder.push(None);
+                        continue;
+                    };
+                    let regex = self.regex(&mut cache, p, "")?;
+                    if group < 0 || group as usize > regex.groups() {
+                        bail!("regexp group index {group} out of range");
+                    }
+                    let extracted = regex
+                        .captures(v)
+                        .and_then(|captures| captures.get(group as usize));
+                    builder.push(extracted);
+                }
+                Ok(builder.finish().into())
+            }
+            (RegexFunction::SplitPart, [n]) => {
+                let n: &I32Array = (*n).try_into()?;
+                let mut builder = StringArrayBuilder::with_capacity(s.len());
+                for row in 0..s.len() {
+                    let (Some(v), Some(p), Some(n)) = (s.get(row), pattern.get(row), n.get(row))
+                    else {
+                        builder.push(None);
+                        continue;
+                    };
+                    if n <= 0 {
+                        bail!("field position must be greater than zero");
+                    }
+                    let regex = self.regex(&mut cache, p, "")?;
+                    builder.push(Some(regex.split(v).nth(n as usize - 1).unwrap_or_default()));
+                }
+                Ok(builder.finish().into())
+            }
+            (func, _) => bail!("unexpected number of inputs for {func:?}: {}", data.len()),
+        }
+    }
+}
+
+/// Register all regular expression functions into `registry`.
+///
+/// `regexp_split_part(s, pattern, n)` returns the `n`-th field of `s` split by the pattern. A
+/// `regexp_split` returning all of them is left out, as there is no list array.
+pub fn register_regex_functions(registry: &mut FunctionRegistry) -> Result<()> {
+    use RegexFunction::*;
+
+    for (signature, func) in [
+        ("regexp_like(varchar, varchar) -> boolean", Like),
+        ("regexp_like(varchar, varchar, varchar) -> boolean", Like),
+        ("similar_to(varchar, varchar) -> boolean", SimilarTo),
+        (
+            "similar_to(varchar, varchar, varchar) -> boolean",
+            SimilarTo,
+        ),
+        (
+            "regexp_replace(varchar, varchar, varchar) -> varchar",
+            Replace,
+        ),
+        (
+            "regexp_replace(varchar, varchar, varchar, varchar) -> varchar",
+            Replace,
+        ),
+        ("regexp_extract(varchar, varchar) -> varchar", Extract),
+        (
+            "regexp_extract(varchar, varchar, int32) -> varchar",
+            Extract,
+        ),
+        (
+            "regexp_split_part(varchar, varchar, int32) -> varchar",
+            SplitPart,
+        ),
+    ] {
+        let signature: FunctionSignature = signature.parse()?;
+        registry.register_specialized(
+            signature,
+            move || Box::new(RegexExpression::new(func)),
+            move |constants| specialize_regex(constants, func),
+        )?;
+    }
+    Ok(())
+}
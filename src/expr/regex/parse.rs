@@ -0,0 +1,372 @@
+//! Parses a regular expression into an [`Ast`].
+
+use anyhow::{Result, bail};
+
+/// Max count in a bounded repetition like `a{1,1000}`.
+const MAX_REPEAT: u32 = 1000;
+
+/// Max number of groups and quantifiers nested in each other. It bounds the recursion of parsing,
+/// compiling and dropping the syntax tree, which must fit in the 2 MiB stack of spawned threads
+/// even in debug builds.
+const MAX_NESTING: usize = 500;
+
+/// A set of characters like `[a-z_]` or `\d`.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Class {
+    /// Inclusive ranges of characters in this set.
+    ranges: Vec<(char, char)>,
+    negated: bool,
+}
+
+impl Class {
+    fn from_ranges(ranges: &[(char, char)], negated: bool) -> Self {
+        Self {
+            ranges: ranges.to_vec(),
+            negated,
+        }
+    }
+
+    /// Check if `c` is in this set.
+    pub(super) fn matches(&self, c: char) -> bool {
+        self.ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)) != self.negated
+    }
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[('\t', '\r'), (' ', ' ')];
+
+/// Syntax tree of a regular expression.
+#[derive(Debug, Clone)]
+pub(super) enum Ast {
+    /// Matches the empty string.
+    Empty,
+    Literal(char),
+    /// `.`, which matches any character.
+    Any,
+    Class(Class),
+    /// `^`
+    Start,
+    /// `$`
+    End,
+    /// `\b`, or `\B` if negated.
+    WordBoundary {
+        negated: bool,
+    },
+    /// `(...)` with the index of the capture group, or `(?:...)` without one.
+    Group(Box<Ast>, Option<usize>),
+    Concat(Vec<Ast>),
+    Alternate(Vec<Ast>),
+    Repeat {
+        ast: Box<Ast>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+/// Parse `pattern`. Returns the syntax tree and the number of capture groups.
+pub(super) fn parse(pattern: &str) -> Result<(Ast, usize)> {
+    let mut parser = Parser {
+        chars: pattern.chars().collect(),
+        pos: 0,
+        groups: 0,
+        depth: 0,
+        nesting: 0,
+    };
+    let ast = parser.parse_alternate()?;
+    if parser.pos < parser.chars.len() {
+        // The only way to stop early is an unmatched `)`.
+        bail!("parentheses () not balanced");
+    }
+    Ok((ast, parser.groups))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+    /// Number of groups enclosing the current position.
+    depth: usize,
+    /// Nesting of groups and quantifiers in the last parsed expression.
+    nesting: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += c.is_some() as usize;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        self.pos += eaten as usize;
+        eaten
+    }
+
+    /// `a|b|...`
+    fn parse_alternate(&mut self) -> Result<Ast> {
+        let mut branches = vec![self.parse_concat()?];
+        let mut nesting = self.nesting;
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+            nesting = nesting.max(self.nesting);
+        }
+        self.nesting = nesting;
+        Ok(match branches.len() {
+            1 => branches.pop().unwrap(),
+            _ => Ast::Alternate(branches),
+        })
+    }
+
+    /// `abc...`
+    fn parse_concat(&mut self) -> Result<Ast> {
+        let mut items = vec![];
+        let mut nesting = 0;
+        while !matches!(self.peek(), None | Some('|' | ')')) {
+            items.push(self.parse_repeat()?);
+            nesting = nesting.max(self.nesting);
+        }
+        self.nesting = nesting;
+        Ok(match items.len() {
+            0 => Ast::Empty,
+            1 => items.pop().unwrap(),
+            _ => Ast::Concat(items),
+        })
+    }
+
+    /// An atom followed by any number of quantifiers.
+    fn parse_repeat(&mut self) -> Result<Ast> {
+        let mut ast = self.parse_atom()?;
+        while let Some((min, max)) = self.parse_quantifier()? {
+            if matches!(ast, Ast::Start | Ast::End | Ast::WordBoundary { .. }) {
+                bail!("quantifier operand invalid");
+            }
+            let greedy = !self.eat('?');
+            self.nest()?;
+            ast = Ast::Repeat {
+                ast: Box::new(ast),
+                min,
+                max,
+                greedy,
+            };
+        }
+        Ok(ast)
+    }
+
+    /// `*`, `+`, `?`, `{m}`, `{m,}` or `{m,n}`.
+    fn parse_quantifier(&mut self) -> Result<Option<(u32, Option<u32>)>> {
+        let quantifier = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.parse_number()?;
+                let max = match self.eat(',') {
+                    true if self.peek() == Some('}') => None,
+                    true => Some(self.parse_number()?),
+                    false => Some(min),
+                };
+                if !self.eat('}') {
+                    bail!("invalid repetition count(s)");
+                }
+                if max.is_some_and(|max| max < min) {
+                    bail!("invalid repetition count(s)");
+                }
+                return Ok(Some((min, max)));
+            }
+            _ => return Ok(None),
+        };
+        self.pos += 1;
+        Ok(Some(quantifier))
+    }
+
+    fn parse_number(&mut self) -> Result<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse::<u32>() {
+            Ok(n) if n <= MAX_REPEAT => Ok(n),
+            _ => bail!("invalid repetition count(s)"),
+        }
+    }
+
+    /// Nest the last parsed expression in a group or a quantifier.
+    fn nest(&mut self) -> Result<()> {
+        self.nesting += 1;
+        if self.nesting > MAX_NESTING {
+            bail!("invalid regular expression: nesting too deep");
+        }
+        Ok(())
+    }
+
+    fn parse_atom(&mut self) -> Result<Ast> {
+        let Some(c) = self.next() else {
+            unreachable!("atom at the end of pattern");
+        };
+        self.nesting = 0;
+        let ast = match c {
+            '(' => {
+                // Groups are parsed recursively, so their depth is limited before parsing them.
+                self.depth += 1;
+                if self.depth > MAX_NESTING {
+                    bail!("invalid regular expression: nesting too deep");
+                }
+                let index = if self.eat('?') {
+                    if !self.eat(':') {
+                        bail!("invalid regular expression: unsupported group syntax");
+                    }
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                let ast = self.parse_alternate()?;
+                if !self.eat(')') {
+                    bail!("parentheses () not balanced");
+                }
+                self.depth -= 1;
+                self.nest()?;
+                Ast::Group(Box::new(ast), index)
+            }
+            '[' => Ast::Class(self.parse_class()?),
+            '.' => Ast::Any,
+            '^' => Ast::Start,
+            '$' => Ast::End,
+            '*' | '+' | '?' | '{' => bail!("quantifier operand invalid"),
+            '\\' => self.parse_escape()?,
+            c => Ast::Literal(c),
+        };
+        Ok(ast)
+    }
+
+    /// An escape sequence outside of brackets, after the `\`.
+    fn parse_escape(&mut self) -> Result<Ast> {
+        let Some(c) = self.next() else {
+            bail!("invalid escape \\ sequence");
+        };
+        let ast = match c {
+            'b' => Ast::WordBoundary { negated: false },
+            'B' => Ast::WordBoundary { negated: true },
+            '1'..='9' => bail!("invalid regular expression: back references are not supported"),
+            c => match self.escape_class(c) {
+                Some(class) => Ast::Class(class),
+                None => Ast::Literal(escape_char(c)?),
+            },
+        };
+        Ok(ast)
+    }
+
+    /// Classes of `\d`, `\w`, `\s` and their negations.
+    fn escape_class(&self, c: char) -> Option<Class> {
+        let (ranges, negated) = match c {
+            'd' => (DIGIT, false),
+            'D' => (DIGIT, true),
+            'w' => (WORD, false),
+            'W' => (WORD, true),
+            's' => (SPACE, false),
+            'S' => (SPACE, true),
+            _ => return None,
+        };
+        Some(Class::from_ranges(ranges, negated))
+    }
+
+    /// A bracket expression like `[^a-z\d[:punct:]]`, after the `[`.
+    fn parse_class(&mut self) -> Result<Class> {
+        let mut class = Class {
+            ranges: vec![],
+            negated: self.eat('^'),
+        };
+        let mut first = true;
+        loop {
+            let Some(c) = self.next() else {
+                bail!("brackets [] not balanced");
+            };
+            let lo = match c {
+                ']' if !first => return Ok(class),
+                '[' if self.eat(':') => {
+                    class.ranges.extend_from_slice(self.parse_posix_class()?);
+                    first = false;
+                    continue;
+                }
+                '\\' => {
+                    let Some(c) = self.next() else {
+                        bail!("brackets [] not balanced");
+                    };
+                    if let Some(escaped) = self.escape_class(c) {
+                        if escaped.negated {
+                            bail!("invalid regular expression: negated class in brackets");
+                        }
+                        class.ranges.extend(escaped.ranges);
+                        first = false;
+                        continue;
+                    }
+                    escape_char(c)?
+                }
+                c => c,
+            };
+            first = false;
+            // A `-` at the end is a literal.
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let hi = match self.next() {
+                    Some('\\') => escape_char(self.next().unwrap_or('\\'))?,
+                    Some(c) => c,
+                    None => bail!("brackets [] not balanced"),
+                };
+                if hi < lo {
+                    bail!("invalid character range");
+                }
+                class.ranges.push((lo, hi));
+            } else {
+                class.ranges.push((lo, lo));
+            }
+        }
+    }
+
+    /// A POSIX class like `[:alpha:]`, after the `[:`.
+    fn parse_posix_class(&mut self) -> Result<&'static [(char, char)]> {
+        let start = self.pos;
+        while !matches!(self.peek(), None | Some(':')) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        if !(self.eat(':') && self.eat(']')) {
+            bail!("brackets [] not balanced");
+        }
+        let ranges: &[(char, char)] = match name.as_str() {
+            "alpha" => &[('A', 'Z'), ('a', 'z')],
+            "digit" => DIGIT,
+            "alnum" => &[('0', '9'), ('A', 'Z'), ('a', 'z')],
+            "upper" => &[('A', 'Z')],
+            "lower" => &[('a', 'z')],
+            "space" => SPACE,
+            "xdigit" => &[('0', '9'), ('A', 'F'), ('a', 'f')],
+            "punct" => &[('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+            _ => bail!("invalid character class"),
+        };
+        Ok(ranges)
+    }
+}
+
+/// The character of an escape sequence like `\n` or `\.`.
+fn escape_char(c: char) -> Result<char> {
+    let c = match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'f' => '\x0c',
+        'v' => '\x0b',
+        c if c.is_ascii_alphanumeric() => bail!("invalid escape \\ sequence"),
+        c => c,
+    };
+    Ok(c)
+}
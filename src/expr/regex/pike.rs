@@ -0,0 +1,197 @@
+//! Runs a [`Program`] with a Pike VM.
+//!
+//! The VM simulates all threads of the program in lockstep over the input, so matching takes
+//! `O(len(input) * len(program))` time without any backtracking. Threads are kept in priority
+//! order, so that the match found is the same as the one a backtracking engine would find.
+
+use super::compile::{Inst, Look, Program};
+use super::fold_case;
+
+/// Positions saved by a thread, where `slots[2 * i]` and `slots[2 * i + 1]` are the start and end
+/// of capture group `i`.
+pub(super) type Slots = Vec<Option<usize>>;
+
+/// A list of threads, ordered by priority, with at most one thread on each instruction.
+struct Threads {
+    /// Instructions of threads in priority order.
+    dense: Vec<usize>,
+    /// Index of each instruction in `dense`, which is valid only if it points back.
+    sparse: Vec<usize>,
+    /// Slots of the thread on each instruction.
+    slots: Vec<Option<usize>>,
+    slots_per_thread: usize,
+}
+
+impl Threads {
+    fn new(insts: usize, slots_per_thread: usize) -> Self {
+        Self {
+            dense: Vec::with_capacity(insts),
+            sparse: vec![0; insts],
+            slots: vec![None; insts * slots_per_thread],
+            slots_per_thread,
+        }
+    }
+
+    fn contains(&self, pc: usize) -> bool {
+        self.dense.get(self.sparse[pc]) == Some(&pc)
+    }
+
+    fn insert(&mut self, pc: usize) {
+        self.sparse[pc] = self.dense.len();
+        self.dense.push(pc);
+    }
+
+    fn slots(&self, pc: usize) -> &[Option<usize>] {
+        &self.slots[pc * self.slots_per_thread..(pc + 1) * self.slots_per_thread]
+    }
+
+    fn slots_mut(&mut self, pc: usize) -> &mut [Option<usize>] {
+        &mut self.slots[pc * self.slots_per_thread..(pc + 1) * self.slots_per_thread]
+    }
+}
+
+/// A pending step of [`Vm::add_thread`].
+enum Frame {
+    /// Follow the instruction.
+    Explore(usize),
+    /// Restore the slot after exploring a branch.
+    Restore(usize, Option<usize>),
+}
+
+struct Vm<'a> {
+    program: &'a Program,
+    input: &'a str,
+    stack: Vec<Frame>,
+}
+
+/// Search for the first match in `input` starting at or after byte offset `start`. If `earliest`
+/// is set, returns as soon as any match is found, whose end may be shorter than the preferred
+/// one.
+pub(super) fn search(
+    program: &Program,
+    input: &str,
+    start: usize,
+    earliest: bool,
+) -> Option<Slots> {
+    let n = program.insts.len();
+    let mut clist = Threads::new(n, program.slots);
+    let mut nlist = Threads::new(n, program.slots);
+    let mut vm = Vm {
+        program,
+        input,
+        stack: vec![],
+    };
+    let mut slots = vec![None; program.slots];
+    let mut matched = None;
+    let mut pos = start;
+    loop {
+        // Start a new thread at the current position, with the lowest priority.
+        if matched.is_none() {
+            slots.fill(None);
+            vm.add_thread(&mut clist, 0, pos, &mut slots);
+        }
+        if clist.dense.is_empty() {
+            break;
+        }
+        let c = input[pos..].chars().next();
+        let next_pos = pos + c.map_or(0, char::len_utf8);
+        for i in 0..clist.dense.len() {
+            let pc = clist.dense[i];
+            let consumed = match (&program.insts[pc], c) {
+                (Inst::Match, _) => {
+                    matched = Some(clist.slots(pc).to_vec());
+                    if earliest {
+                        return matched;
+                    }
+                    // Threads with lower priority are cut off.
+                    break;
+                }
+                (Inst::Char(l), Some(c)) => *l == vm.fold(c),
+                (Inst::Any, Some(_)) => true,
+                (Inst::Class(class), Some(c)) => {
+                    class.matches(c)
+                        || (program.case_insensitive
+                            && (class.matches(fold_case(c))
+                                || c.to_uppercase().any(|u| class.matches(u))))
+                }
+                _ => false,
+            };
+            if consumed {
+                slots.copy_from_slice(clist.slots(pc));
+                vm.add_thread(&mut nlist, pc + 1, next_pos, &mut slots);
+            }
+        }
+        if c.is_none() {
+            break;
+        }
+        pos = next_pos;
+        std::mem::swap(&mut clist, &mut nlist);
+        nlist.dense.clear();
+    }
+    matched
+}
+
+impl Vm<'_> {
+    fn fold(&self, c: char) -> char {
+        match self.program.case_insensitive {
+            true => fold_case(c),
+            false => c,
+        }
+    }
+
+    /// Add a thread at `pc` to `list`, following all instructions that do not consume input.
+    fn add_thread(
+        &mut self,
+        list: &mut Threads,
+        pc: usize,
+        pos: usize,
+        slots: &mut [Option<usize>],
+    ) {
+        self.stack.push(Frame::Explore(pc));
+        while let Some(frame) = self.stack.pop() {
+            let mut pc = match frame {
+                Frame::Explore(pc) => pc,
+                Frame::Restore(slot, value) => {
+                    slots[slot] = value;
+                    continue;
+                }
+            };
+            while !list.contains(pc) {
+                list.insert(pc);
+                match &self.program.insts[pc] {
+                    Inst::Jmp(x) => pc = *x,
+                    Inst::Split(x, y) => {
+                        self.stack.push(Frame::Explore(*y));
+                        pc = *x;
+                    }
+                    Inst::Save(slot) => {
+                        self.stack.push(Frame::Restore(*slot, slots[*slot]));
+                        slots[*slot] = Some(pos);
+                        pc += 1;
+                    }
+                    Inst::Look(look) if self.look(*look, pos) => pc += 1,
+                    Inst::Look(_) => break,
+                    Inst::Char(_) | Inst::Any | Inst::Class(_) | Inst::Match => {
+                        list.slots_mut(pc).copy_from_slice(slots);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Check if the assertion holds at `pos`.
+    fn look(&self, look: Look, pos: usize) -> bool {
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let at_boundary = || {
+            is_word(self.input[..pos].chars().next_back())
+                != is_word(self.input[pos..].chars().next())
+        };
+        match look {
+            Look::Start => pos == 0,
+            Look::End => pos == self.input.len(),
+            Look::WordBoundary => at_boundary(),
+            Look::NotWordBoundary => !at_boundary(),
+        }
+    }
+}
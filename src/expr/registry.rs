@@ -0,0 +1,501 @@
+//! Implements a registry of scalar functions.
+//!
+//! Each function is registered with a [`FunctionSignature`], and a function may be overloaded
+//! with several signatures of the same name, e.g. `less_than(int32, int32) -> boolean` and
+//! `less_than(float64, float64) -> boolean`. A variadic function like `concat(varchar...) ->
+//! varchar` repeats its last argument type one or more times. [`FunctionRegistry::build`]
+//! resolves a call with the given argument types to one of the signatures, and implicitly casts
+//! the arguments into the types of the signature if they are not exactly the same, following the
+//! rules in [`crate::dataType::coercion`].
+//!
+//! A function may also be registered with a [`Specializer`], which builds a faster expression
+//! when some arguments are known to be constant, e.g. `LIKE` with a constant pattern. Such calls
+//! are built by [`FunctionRegistry::build_with_constants`].
+
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::TypeMismatch;
+use crate::array::*;
+use crate::dataType::DataType;
+use crate::expr::Expression;
+use crate::expr::cast::build_cast_expression;
+use crate::expr::vectorize::{
+    BinaryExpFunc, BinaryExpression, BinaryStrExpFunc, BinaryStrExpression,
+};
+use crate::macros::for_all_variants;
+use crate::scalar::{ScalarImpl, ScalarRefImpl};
+
+/// Signature of a scalar function, e.g. `add(int32, int32) -> int32`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionSignature {
+    /// Name of the function.
+    pub name: String,
+    /// Types of the arguments.
+    pub args: Vec<DataType>,
+    /// Whether the last argument may be repeated any number of times, but at least once.
+    pub variadic: bool,
+    /// Type of the return value.
+    pub ret: DataType,
+}
+
+impl fmt::Display for FunctionSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let variadic = if self.variadic { "..." } else { "" };
+        write!(
+            f,
+            "{}({}{variadic}) -> {}",
+            self.name,
+            DisplayArgs(&self.args),
+            self.ret
+        )
+    }
+}
+
+/// Parse a signature like `add(int32, int32) -> int32`, or `concat(varchar...) -> varchar` for a
+/// variadic function.
+impl FromStr for FunctionSignature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid function signature \"{s}\"");
+        let (call, ret) = s.split_once("->").ok_or_else(invalid)?;
+        let (name, args) = call.trim().split_once('(').ok_or_else(invalid)?;
+        let args = args.strip_suffix(')').ok_or_else(invalid)?;
+        let (args, variadic) = match args.trim_end().strip_suffix("...") {
+            Some(args) if !args.trim().is_empty() => (args, true),
+            Some(_) => return Err(invalid()),
+            None => (args, false),
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        // Split arguments on top-level commas, so that `decimal(10, 2)` is kept as one type.
+        let mut types = vec![];
+        let (mut depth, mut start) = (0, 0);
+        for (idx, c) in args.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    types.push(&args[start..idx]);
+                    start = idx + 1;
+                }
+                _ => {}
+            }
+        }
+        if !args.trim().is_empty() {
+            types.push(&args[start..]);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            args: types
+                .into_iter()
+                .map(DataType::from_str)
+                .collect::<Result<_>>()?,
+            variadic,
+            ret: ret.parse()?,
+        })
+    }
+}
+
+/// Formats a list of types as `integer, varchar`.
+pub(crate) struct DisplayArgs<'a>(pub(crate) &'a [DataType]);
+
+impl FunctionSignature {
+    /// Get the types that arguments of `num_args` are passed as, or `None` if the function
+    /// cannot take `num_args` arguments.
+    pub fn arg_types(&self, num_args: usize) -> Option<impl Iterator<Item = &DataType> + Clone> {
+        let accepted = match self.variadic {
+            true => num_args >= self.args.len(),
+            false => num_args == self.args.len(),
+        };
+        let repeated = self.args.last().filter(|_| self.variadic);
+        accepted.then(|| {
+            self.args.iter().take(num_args).chain(
+                std::iter::repeat_n(repeated, num_args.saturating_sub(self.args.len())).flatten(),
+            )
+        })
+    }
+}
+
+impl fmt::Display for DisplayArgs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, ty) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{ty}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Creates a new [`Expression`] for a registered function.
+pub type ExpressionBuilder = Arc<dyn Fn() -> Box<dyn Expression> + Send + Sync>;
+
+/// Creates an [`Expression`] specialized for constant arguments, given the value of each
+/// argument which is constant, or `None` if it is not. Returns `None` if the call cannot be
+/// specialized.
+///
+/// The specialized expression is still evaluated with all arguments, including constant ones.
+pub type Specializer =
+    Arc<dyn Fn(&[Option<ScalarRefImpl<'_>>]) -> Result<Option<Box<dyn Expression>>> + Send + Sync>;
+
+/// A function registered with its signature.
+struct FunctionEntry {
+    signature: FunctionSignature,
+    builder: ExpressionBuilder,
+    specializer: Option<Specializer>,
+}
+
+/// A registry mapping function names to their signatures.
+#[derive(Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, Vec<FunctionEntry>>,
+}
+
+impl FunctionRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with all builtin functions of this library.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry
+            .register_builtins()
+            .expect("builtin functions should have distinct signatures");
+        registry
+    }
+
+    /// Register a function with the given signature. `builder` will be called every time a call
+    /// to this function is built.
+    pub fn register(
+        &mut self,
+        signature: FunctionSignature,
+        builder: impl Fn() -> Box<dyn Expression> + Send + Sync + 'static,
+    ) -> Result<()> {
+        self.register_entry(signature, Arc::new(builder), None)
+    }
+
+    /// Register a function like [`FunctionRegistry::register`], together with `specializer`,
+    /// which is called by [`FunctionRegistry::build_with_constants`] if any argument is constant.
+    pub fn register_specialized(
+        &mut self,
+        signature: FunctionSignature,
+        builder: impl Fn() -> Box<dyn Expression> + Send + Sync + 'static,
+        specializer: impl Fn(&[Option<ScalarRefImpl<'_>>]) -> Result<Option<Box<dyn Expression>>>
+        + Send
+        + Sync
+        + 'static,
+    ) -> Result<()> {
+        self.register_entry(signature, Arc::new(builder), Some(Arc::new(specializer)))
+    }
+
+    fn register_entry(
+        &mut self,
+        signature: FunctionSignature,
+        builder: ExpressionBuilder,
+        specializer: Option<Specializer>,
+    ) -> Result<()> {
+        let entries = self.functions.entry(signature.name.clone()).or_default();
+        if entries.iter().any(|e| {
+            e.signature.args == signature.args && e.signature.variadic == signature.variadic
+        }) {
+            bail!("function {signature} already exists");
+        }
+        entries.push(FunctionEntry {
+            signature,
+            builder,
+            specializer,
+        });
+        Ok(())
+    }
+
+    /// Register a [`BinaryExpFunc`] with a signature like `add(int32, int32) -> int32`.
+    ///
+    /// The types in the signature must be stored in `I1`, `I2` and `O` respectively.
+    pub fn register_binary<I1: Array, I2: Array, O: Array, F>(
+        &mut self,
+        signature: &str,
+        func: F,
+    ) -> Result<()>
+    where
+        for<'a> &'a I1: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+        for<'a> &'a I2: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+        F: BinaryExpFunc<I1, I2, O> + Send + Sync + 'static,
+    {
+        let signature: FunctionSignature = signature.parse()?;
+        let ([a1, a2], false) = (signature.args.as_slice(), signature.variadic) else {
+            bail!("expect two arguments for binary function {signature}");
+        };
+        for (ty, identifier) in [
+            (a1, array_identifier::<I1>()),
+            (a2, array_identifier::<I2>()),
+            (&signature.ret, array_identifier::<O>()),
+        ] {
+            if ty.physical_identifier() != identifier {
+                return Err(TypeMismatch(ty.physical_identifier(), identifier).into());
+            }
+        }
+
+        let func = Arc::new(func);
+        self.register(signature, move || {
+            Box::new(BinaryExpression::<I1, I2, O, _>::new(func.clone()))
+        })
+    }
+
+    /// Register a [`BinaryStrExpFunc`] with a signature like
+    /// `concat_op(varchar, varchar) -> varchar`.
+    ///
+    /// The argument types in the signature must be stored in `I1` and `I2` respectively, and the
+    /// return type must be a string type.
+    pub fn register_binary_str<I1: Array, I2: Array, F>(
+        &mut self,
+        signature: &str,
+        func: F,
+    ) -> Result<()>
+    where
+        for<'a> &'a I1: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+        for<'a> &'a I2: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+        F: BinaryStrExpFunc<I1, I2> + Send + Sync + 'static,
+    {
+        let signature: FunctionSignature = signature.parse()?;
+        let ([a1, a2], false) = (signature.args.as_slice(), signature.variadic) else {
+            bail!("expect two arguments for binary function {signature}");
+        };
+        for (ty, identifier) in [
+            (a1, array_identifier::<I1>()),
+            (a2, array_identifier::<I2>()),
+            (&signature.ret, array_identifier::<StringArray>()),
+        ] {
+            if ty.physical_identifier() != identifier {
+                return Err(TypeMismatch(ty.physical_identifier(), identifier).into());
+            }
+        }
+
+        let func = Arc::new(func);
+        self.register(signature, move || {
+            Box::new(BinaryStrExpression::<I1, I2, _>::new(func.clone()))
+        })
+    }
+
+    /// Resolve a call to function `name` with arguments of type `args`.
+    ///
+    /// A signature matching all types exactly is always preferred. Otherwise, we choose the
+    /// signature which all arguments can be implicitly cast to with the least cost. On a tie, a
+    /// non-variadic signature is preferred over a variadic one.
+    pub fn resolve(&self, name: &str, args: &[DataType]) -> Result<&FunctionSignature> {
+        self.resolve_entry(name, args).map(|entry| &entry.signature)
+    }
+
+    /// Build an expression calling function `name` with arguments of type `args`. Arguments are
+    /// cast into the types of the resolved signature before being passed to the function.
+    pub fn build(&self, name: &str, args: &[DataType]) -> Result<Box<dyn Expression>> {
+        self.build_with_constants(name, args, &[])
+    }
+
+    /// Build an expression like [`FunctionRegistry::build`], where `constants` are the values of
+    /// arguments known to be constant, or `None` for the others. If the function is registered
+    /// with a [`Specializer`], the expression may be specialized for the constant arguments.
+    ///
+    /// The expression is still evaluated with all arguments. `constants` may be empty if no
+    /// argument is constant.
+    pub fn build_with_constants(
+        &self,
+        name: &str,
+        args: &[DataType],
+        constants: &[Option<ScalarImpl>],
+    ) -> Result<Box<dyn Expression>> {
+        if !constants.is_empty() && constants.len() != args.len() {
+            bail!(
+                "expect {} constants for function {name}, get {}",
+                args.len(),
+                constants.len()
+            );
+        }
+        let entry = self.resolve_entry(name, args)?;
+        let types = entry.signature.arg_types(args.len()).unwrap();
+
+        // Constants which need to be cast are not passed to the specializer.
+        let constants = types
+            .clone()
+            .zip(args)
+            .enumerate()
+            .map(|(idx, (to, from))| match constants.get(idx) {
+                Some(Some(value)) if from == to => Some(value.as_scalar_ref()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let specialized = match &entry.specializer {
+            Some(specializer) if constants.iter().any(Option::is_some) => specializer(&constants)?,
+            _ => None,
+        };
+        let func = specialized.unwrap_or_else(|| (entry.builder)());
+
+        let casts = args
+            .iter()
+            .zip(types)
+            .map(|(from, to)| {
+                // Types of the same physical array may still need a cast, e.g. padding into
+                // `char(n)`.
+                if from == to {
+                    Ok(None)
+                } else {
+                    build_cast_expression(*from, *to).map(Some)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if casts.iter().all(Option::is_none) {
+            return Ok(func);
+        }
+        Ok(Box::new(CastedFunctionCall { casts, func }))
+    }
+
+    fn resolve_entry(&self, name: &str, args: &[DataType]) -> Result<&FunctionEntry> {
+        let not_exist = || anyhow!("function {name}({}) does not exist", DisplayArgs(args));
+        let entries = self.functions.get(name).ok_or_else(not_exist)?;
+
+        // Signatures are ordered by the cost of casts, and then non-variadic ones are preferred.
+        let mut best: Option<((u32, bool), &FunctionEntry)> = None;
+        let mut ambiguous = false;
+        for entry in entries {
+            let Some(types) = entry.signature.arg_types(args.len()) else {
+                continue;
+            };
+            let Some(cost) = args
+                .iter()
+                .zip(types)
+                .map(|(from, to)| from.implicit_cast_cost(to))
+                .sum::<Option<u32>>()
+            else {
+                continue;
+            };
+            let cost = (cost, entry.signature.variadic);
+            match best {
+                Some((best_cost, _)) if best_cost < cost => {}
+                Some((best_cost, _)) if best_cost == cost => ambiguous = true,
+                _ => {
+                    best = Some((cost, entry));
+                    ambiguous = false;
+                }
+            }
+        }
+
+        match best {
+            Some(_) if ambiguous => {
+                bail!("function {name}({}) is not unique", DisplayArgs(args))
+            }
+            Some((_, entry)) => Ok(entry),
+            None => Err(not_exist()),
+        }
+    }
+
+    fn register_builtins(&mut self) -> Result<()> {
+        use crate::expr::arith::*;
+        use crate::expr::bitwise::*;
+        use crate::expr::cmp::*;
+        use crate::expr::like::*;
+        use crate::expr::math::*;
+        use crate::expr::predicate::*;
+        use crate::expr::regex::*;
+        use crate::expr::string::*;
+
+        /// Registers comparison functions for every array type.
+        macro_rules! register_cmp {
+            ([$registry:ident], $( { $Abc:ident, $abc:ident, $AbcArray:ty, $AbcArrayBuilder:ty, $Owned:ty, $Ref:ty } ),*) => {
+                $(
+                    $registry.register_binary::<$AbcArray, $AbcArray, BoolArray, _>(
+                        concat!("less_than(", stringify!($abc), ", ", stringify!($abc), ") -> boolean"),
+                        ExprCmpLt::<_, _, $AbcArray>(PhantomData),
+                    )?;
+                    $registry.register_binary::<$AbcArray, $AbcArray, BoolArray, _>(
+                        concat!("less_than_or_equal(", stringify!($abc), ", ", stringify!($abc), ") -> boolean"),
+                        ExprCmpLe::<_, _, $AbcArray>(PhantomData),
+                    )?;
+                    $registry.register_binary::<$AbcArray, $AbcArray, BoolArray, _>(
+                        concat!("greater_than(", stringify!($abc), ", ", stringify!($abc), ") -> boolean"),
+                        ExprCmpGt::<_, _, $AbcArray>(PhantomData),
+                    )?;
+                    $registry.register_binary::<$AbcArray, $AbcArray, BoolArray, _>(
+                        concat!("greater_than_or_equal(", stringify!($abc), ", ", stringify!($abc), ") -> boolean"),
+                        ExprCmpGe::<_, _, $AbcArray>(PhantomData),
+                    )?;
+                    $registry.register_binary::<$AbcArray, $AbcArray, BoolArray, _>(
+                        concat!("equal(", stringify!($abc), ", ", stringify!($abc), ") -> boolean"),
+                        ExprCmpEq::<_, _, $AbcArray>(PhantomData),
+                    )?;
+                    $registry.register_binary::<$AbcArray, $AbcArray, BoolArray, _>(
+                        concat!("not_equal(", stringify!($abc), ", ", stringify!($abc), ") -> boolean"),
+                        ExprCmpNe::<_, _, $AbcArray>(PhantomData),
+                    )?;
+                )*
+            };
+        }
+
+        for_all_variants! { register_cmp, self }
+
+        self.register_binary::<StringArray, StringArray, BoolArray, _>(
+            "contains(varchar, varchar) -> boolean",
+            ExprStrContains,
+        )?;
+        self.register_binary_str::<StringArray, StringArray, _>(
+            "concat_op(varchar, varchar) -> varchar",
+            ExprStrConcat,
+        )?;
+        register_string_functions(self)?;
+        register_predicate_functions(self)?;
+        register_like_functions(self)?;
+        register_regex_functions(self)?;
+
+        register_add(self)?;
+        register_subtract(self)?;
+        register_multiply(self)?;
+        register_divide(self)?;
+        register_math_functions(self)?;
+        register_bitwise_functions(self)?;
+        Ok(())
+    }
+}
+
+/// Get the identifier of array `A`, which is the same as [`ArrayImpl::identifier`].
+fn array_identifier<A: Array>() -> &'static str {
+    let array: ArrayImpl = A::Builder::with_capacity(0).finish().into();
+    array.identifier()
+}
+
+/// A function call whose arguments are implicitly cast into the types of the signature.
+struct CastedFunctionCall {
+    /// Cast expression for each argument, or `None` if it can be passed as-is.
+    casts: Vec<Option<Box<dyn Expression>>>,
+    func: Box<dyn Expression>,
+}
+
+impl Expression for CastedFunctionCall {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        if data.len() != self.casts.len() {
+            bail!("Expect {} inputs for function call", self.casts.len());
+        }
+        let casted = data
+            .iter()
+            .zip(&self.casts)
+            .map(|(array, cast)| cast.as_ref().map(|c| c.eval_expr(&[*array])).transpose())
+            .collect::<Result<Vec<_>>>()?;
+        let inputs = data
+            .iter()
+            .zip(&casted)
+            .map(|(array, casted)| casted.as_ref().unwrap_or(*array))
+            .collect::<Vec<_>>();
+        self.func.eval_expr(&inputs)
+    }
+}
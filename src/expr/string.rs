@@ -1,8 +1,19 @@
 //! Implements string function for [`Array`] types.
+//!
+//! Functions returning strings write their results into a [`StringWriter`], so that each result
+//! is copied into the output array once, without allocating a `String` for each row. Positions
+//! and lengths are counted in characters, as in PostgreSQL.
+
+use anyhow::{Result, bail};
 
 use crate::{
-    array::{BoolArray, StringArray},
-    expr::vectorize::BinaryExpFunc,
+    array::{
+        Array, ArrayBuilder, ArrayImpl, BoolArray, StringArray, StringArrayBuilder, StringWriter,
+    },
+    expr::{
+        Expression, FunctionRegistry, function,
+        vectorize::{BinaryExpFunc, BinaryStrExpFunc},
+    },
 };
 
 /// Checks if `i1.contains(i2)` for two string inputs.
@@ -13,3 +24,315 @@ impl BinaryExpFunc<StringArray, StringArray, BoolArray> for ExprStrContains {
         i1.contains(i2)
     }
 }
+
+/// Concatenates two strings as the `||` operator. Unlike `concat`, the result is `NULL` if any
+/// input is `NULL`.
+pub struct ExprStrConcat;
+
+impl BinaryStrExpFunc<StringArray, StringArray> for ExprStrConcat {
+    fn eval(&self, i1: &str, i2: &str, writer: &mut StringWriter) {
+        writer.write_str(i1);
+        writer.write_str(i2);
+    }
+}
+
+/// Max length in bytes of a string produced by functions like `repeat` and `lpad`.
+const MAX_STRING_LENGTH: usize = 1 << 30;
+
+/// Number of characters in `s`.
+#[function("length(varchar) -> int32")]
+pub fn length(s: &str) -> i32 {
+    s.chars().count() as i32
+}
+
+/// Number of characters in `s`.
+#[function("char_length(varchar) -> int32")]
+pub fn char_length(s: &str) -> i32 {
+    s.chars().count() as i32
+}
+
+/// Number of bytes in `s`.
+#[function("octet_length(varchar) -> int32")]
+pub fn octet_length(s: &str) -> i32 {
+    s.len() as i32
+}
+
+#[function("upper(varchar) -> varchar")]
+pub fn upper(s: &str, writer: &mut StringWriter) {
+    for c in s.chars() {
+        if c.is_ascii() {
+            writer.write_char(c.to_ascii_uppercase());
+        } else {
+            c.to_uppercase().for_each(|c| writer.write_char(c));
+        }
+    }
+}
+
+#[function("lower(varchar) -> varchar")]
+pub fn lower(s: &str, writer: &mut StringWriter) {
+    for c in s.chars() {
+        if c.is_ascii() {
+            writer.write_char(c.to_ascii_lowercase());
+        } else {
+            c.to_lowercase().for_each(|c| writer.write_char(c));
+        }
+    }
+}
+
+/// Removes spaces from both ends of `s`.
+#[function("trim(varchar) -> varchar")]
+pub fn trim(s: &str, writer: &mut StringWriter) {
+    writer.write_str(s.trim_matches(' '));
+}
+
+/// Removes any character in `chars` from both ends of `s`.
+#[function("trim(varchar, varchar) -> varchar")]
+pub fn trim_chars(s: &str, chars: &str, writer: &mut StringWriter) {
+    writer.write_str(s.trim_matches(|c| chars.contains(c)));
+}
+
+/// Removes spaces from the start of `s`.
+#[function("ltrim(varchar) -> varchar")]
+pub fn ltrim(s: &str, writer: &mut StringWriter) {
+    writer.write_str(s.trim_start_matches(' '));
+}
+
+/// Removes any character in `chars` from the start of `s`.
+#[function("ltrim(varchar, varchar) -> varchar")]
+pub fn ltrim_chars(s: &str, chars: &str, writer: &mut StringWriter) {
+    writer.write_str(s.trim_start_matches(|c| chars.contains(c)));
+}
+
+/// Removes spaces from the end of `s`.
+#[function("rtrim(varchar) -> varchar")]
+pub fn rtrim(s: &str, writer: &mut StringWriter) {
+    writer.write_str(s.trim_end_matches(' '));
+}
+
+/// Removes any character in `chars` from the end of `s`.
+#[function("rtrim(varchar, varchar) -> varchar")]
+pub fn rtrim_chars(s: &str, chars: &str, writer: &mut StringWriter) {
+    writer.write_str(s.trim_end_matches(|c| chars.contains(c)));
+}
+
+/// Extracts `count` characters of `s` starting from the 1-based position `start`. Positions
+/// before the first character are counted but produce nothing, as in PostgreSQL.
+#[function("substr(varchar, int32, int32) -> varchar")]
+pub fn substr(s: &str, start: i32, count: i32, writer: &mut StringWriter) -> Result<()> {
+    if count < 0 {
+        bail!("negative substring length not allowed");
+    }
+    let begin = (start as i64).max(1);
+    let end = start as i64 + count as i64;
+    let skip = (begin - 1) as usize;
+    let take = (end - begin).max(0) as usize;
+    writer.write_str(char_slice(s, skip, take));
+    Ok(())
+}
+
+/// Extracts the characters of `s` starting from the 1-based position `start`.
+#[function("substr(varchar, int32) -> varchar")]
+pub fn substr_from(s: &str, start: i32, writer: &mut StringWriter) {
+    let skip = (start as i64 - 1).max(0) as usize;
+    writer.write_str(char_slice(s, skip, usize::MAX));
+}
+
+/// Get the slice of `s` with at most `take` characters after skipping `skip` characters.
+fn char_slice(s: &str, skip: usize, take: usize) -> &str {
+    let mut indices = s.char_indices().map(|(idx, _)| idx).chain([s.len()]);
+    let Some(begin) = indices.nth(skip) else {
+        return "";
+    };
+    let end = match take {
+        0 => begin,
+        _ => indices.nth(take - 1).unwrap_or(s.len()),
+    };
+    &s[begin..end]
+}
+
+/// Replaces all occurrences of `from` in `s` with `to`.
+#[function("replace(varchar, varchar, varchar) -> varchar")]
+pub fn replace(s: &str, from: &str, to: &str, writer: &mut StringWriter) {
+    if from.is_empty() {
+        writer.write_str(s);
+        return;
+    }
+    let mut last = 0;
+    for (idx, _) in s.match_indices(from) {
+        writer.write_str(&s[last..idx]);
+        writer.write_str(to);
+        last = idx + from.len();
+    }
+    writer.write_str(&s[last..]);
+}
+
+/// Concatenates any number of arguments as `concat(varchar...)`. `NULL` arguments are ignored.
+pub struct ConcatExpression;
+
+impl Expression for ConcatExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        let arrays = data
+            .iter()
+            .map(|array| <&StringArray>::try_from(*array))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some(len) = arrays.first().map(|array| array.len()) else {
+            bail!("Expect at least one input for concat");
+        };
+        if arrays.iter().any(|array| array.len() != len) {
+            bail!("array length mismatch");
+        }
+        let mut builder = StringArrayBuilder::with_capacity(len);
+        for row in 0..len {
+            let mut writer = builder.writer();
+            for s in arrays.iter().filter_map(|array| array.get(row)) {
+                writer.write_str(s);
+            }
+            writer.finish();
+        }
+        Ok(builder.finish().into())
+    }
+}
+
+/// Write `len` characters of `fill` repeatedly.
+fn write_fill(fill: &str, len: usize, writer: &mut StringWriter) {
+    fill.chars()
+        .cycle()
+        .take(len)
+        .for_each(|c| writer.write_char(c));
+}
+
+/// Check the length of the padded string, and get the number of characters to fill. If `s` is
+/// already longer than `len`, it is truncated.
+fn pad_len<'a>(s: &'a str, len: i32, fill: &str) -> Result<(&'a str, usize)> {
+    let len = len.max(0) as usize;
+    if len.saturating_mul(fill.len().max(4)) > MAX_STRING_LENGTH {
+        bail!("requested length too large");
+    }
+    let s = char_slice(s, 0, len);
+    let fill_len = if fill.is_empty() {
+        0
+    } else {
+        len - s.chars().count()
+    };
+    Ok((s, fill_len))
+}
+
+/// Fills `s` up to `len` characters by prepending spaces.
+#[function("lpad(varchar, int32) -> varchar")]
+pub fn lpad(s: &str, len: i32, writer: &mut StringWriter) -> Result<()> {
+    lpad_with(s, len, " ", writer)
+}
+
+/// Fills `s` up to `len` characters by prepending `fill` repeatedly.
+#[function("lpad(varchar, int32, varchar) -> varchar")]
+pub fn lpad_with(s: &str, len: i32, fill: &str, writer: &mut StringWriter) -> Result<()> {
+    let (s, fill_len) = pad_len(s, len, fill)?;
+    write_fill(fill, fill_len, writer);
+    writer.write_str(s);
+    Ok(())
+}
+
+/// Fills `s` up to `len` characters by appending spaces.
+#[function("rpad(varchar, int32) -> varchar")]
+pub fn rpad(s: &str, len: i32, writer: &mut StringWriter) -> Result<()> {
+    rpad_with(s, len, " ", writer)
+}
+
+/// Fills `s` up to `len` characters by appending `fill` repeatedly.
+#[function("rpad(varchar, int32, varchar) -> varchar")]
+pub fn rpad_with(s: &str, len: i32, fill: &str, writer: &mut StringWriter) -> Result<()> {
+    let (s, fill_len) = pad_len(s, len, fill)?;
+    writer.write_str(s);
+    write_fill(fill, fill_len, writer);
+    Ok(())
+}
+
+#[function("starts_with(varchar, varchar) -> boolean")]
+pub fn starts_with(s: &str, prefix: &str) -> bool {
+    s.starts_with(prefix)
+}
+
+#[function("ends_with(varchar, varchar) -> boolean")]
+pub fn ends_with(s: &str, suffix: &str) -> bool {
+    s.ends_with(suffix)
+}
+
+/// The 1-based position of the first occurrence of `substring` in `s`, or 0 if not found, as
+/// in `position(substring IN s)`.
+#[function("position(varchar, varchar) -> int32")]
+pub fn position(substring: &str, s: &str) -> i32 {
+    match s.find(substring) {
+        Some(idx) => s[..idx].chars().count() as i32 + 1,
+        None => 0,
+    }
+}
+
+/// Splits `s` on `delimiter` and returns the `n`-th field, counting from 1. A negative `n`
+/// counts from the end.
+#[function("split_part(varchar, varchar, int32) -> varchar")]
+pub fn split_part(s: &str, delimiter: &str, n: i32, writer: &mut StringWriter) -> Result<()> {
+    if n == 0 {
+        bail!("field position must not be zero");
+    }
+    if delimiter.is_empty() {
+        if n == 1 || n == -1 {
+            writer.write_str(s);
+        }
+        return Ok(());
+    }
+    let field = if n > 0 {
+        s.split(delimiter).nth(n as usize - 1)
+    } else {
+        s.rsplit(delimiter).nth(n.unsigned_abs() as usize - 1)
+    };
+    writer.write_str(field.unwrap_or_default());
+    Ok(())
+}
+
+#[function("reverse(varchar) -> varchar")]
+pub fn reverse(s: &str, writer: &mut StringWriter) {
+    s.chars().rev().for_each(|c| writer.write_char(c));
+}
+
+/// Repeats `s` for `n` times.
+#[function("repeat(varchar, int32) -> varchar")]
+pub fn repeat(s: &str, n: i32, writer: &mut StringWriter) -> Result<()> {
+    let n = n.max(0) as usize;
+    if s.len().saturating_mul(n) > MAX_STRING_LENGTH {
+        bail!("requested length too large");
+    }
+    (0..n).for_each(|_| writer.write_str(s));
+    Ok(())
+}
+
+/// Register all string functions into `registry`.
+pub fn register_string_functions(registry: &mut FunctionRegistry) -> Result<()> {
+    register_length(registry)?;
+    register_char_length(registry)?;
+    register_octet_length(registry)?;
+    register_upper(registry)?;
+    register_lower(registry)?;
+    register_trim(registry)?;
+    register_trim_chars(registry)?;
+    register_ltrim(registry)?;
+    register_ltrim_chars(registry)?;
+    register_rtrim(registry)?;
+    register_rtrim_chars(registry)?;
+    register_substr(registry)?;
+    register_substr_from(registry)?;
+    register_replace(registry)?;
+    registry.register("concat(varchar...) -> varchar".parse()?, || {
+        Box::new(ConcatExpression)
+    })?;
+    register_lpad(registry)?;
+    register_lpad_with(registry)?;
+    register_rpad(registry)?;
+    register_rpad_with(registry)?;
+    register_starts_with(registry)?;
+    register_ends_with(registry)?;
+    register_position(registry)?;
+    register_split_part(registry)?;
+    register_reverse(registry)?;
+    register_repeat(registry)?;
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Implements nodes of expression trees.
+//!
+//! The functions built by [`FunctionRegistry`](crate::expr::FunctionRegistry) take evaluated
+//! arrays as inputs. To evaluate an expression like `a + b * 2`, the inputs of a function are
+//! expressions themselves: [`InputRef`] refers to a column of the input, [`Literal`] is a constant,
+//! and [`CallExpression`] evaluates its arguments before calling the function.
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::TypeMismatch;
+use crate::array::ArrayImpl;
+use crate::dataType::DataType;
+use crate::expr::Expression;
+use crate::scalar::ScalarImpl;
+
+/// Get the number of rows of the input, which is the length of the first column.
+pub fn num_rows(data: &[&ArrayImpl]) -> Result<usize> {
+    data.first()
+        .map(|array| array.len())
+        .ok_or_else(|| anyhow!("cannot get the number of rows from an input without columns"))
+}
+
+/// Get the columns of the input with only the rows where `visibility` is `true`.
+pub fn filter_inputs(data: &[&ArrayImpl], visibility: &[bool]) -> Vec<ArrayImpl> {
+    data.iter().map(|array| array.filter(visibility)).collect()
+}
+
+/// Evaluate `expr` on the rows of `data` where `visibility` is `true`. Returns `None` if no row
+/// is visible, in which case `expr` is not evaluated at all.
+pub fn eval_visible(
+    expr: &dyn Expression,
+    data: &[&ArrayImpl],
+    visibility: &[bool],
+) -> Result<Option<ArrayImpl>> {
+    let num_visible = visibility.iter().filter(|v| **v).count();
+    if num_visible == visibility.len() {
+        return expr.eval_rows(data, num_visible).map(Some);
+    }
+    if num_visible == 0 {
+        return Ok(None);
+    }
+    let filtered = filter_inputs(data, visibility);
+    let filtered = filtered.iter().collect::<Vec<_>>();
+    expr.eval_rows(&filtered, num_visible).map(Some)
+}
+
+/// Refers to a column of the input.
+pub struct InputRef {
+    index: usize,
+}
+
+impl InputRef {
+    pub fn new(index: usize) -> Self {
+        Self { index }
+    }
+}
+
+impl Expression for InputRef {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        match data.get(self.index) {
+            Some(array) => Ok((*array).clone()),
+            None => bail!(
+                "input column {} out of range, the input has {} columns",
+                self.index,
+                data.len()
+            ),
+        }
+    }
+}
+
+/// A constant, which is repeated for every row of the input.
+pub struct Literal {
+    value: Option<ScalarImpl>,
+    data_type: DataType,
+}
+
+impl Literal {
+    /// Create a literal of `data_type`. `None` represents `NULL`.
+    pub fn new(value: Option<ScalarImpl>, data_type: DataType) -> Result<Self> {
+        if let Some(value) = &value
+            && value.identifier() != data_type.physical_identifier()
+        {
+            return Err(TypeMismatch(data_type.physical_identifier(), value.identifier()).into());
+        }
+        Ok(Self { value, data_type })
+    }
+
+    /// Create an array with the literal repeated for `len` times.
+    pub fn to_array(&self, len: usize) -> Result<ArrayImpl> {
+        let mut builder = self.data_type.create_array_builder(len)?;
+        let value = self.value.as_ref().map(|v| v.as_scalar_ref());
+        for _ in 0..len {
+            builder.push(value);
+        }
+        Ok(builder.finish())
+    }
+}
+
+impl Expression for Literal {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        self.to_array(num_rows(data)?)
+    }
+
+    fn eval_rows(&self, _: &[&ArrayImpl], num_rows: usize) -> Result<ArrayImpl> {
+        self.to_array(num_rows)
+    }
+}
+
+/// Calls a function with the results of the argument expressions.
+pub struct CallExpression {
+    func: Box<dyn Expression>,
+    args: Vec<Box<dyn Expression>>,
+}
+
+impl CallExpression {
+    pub fn new(func: Box<dyn Expression>, args: Vec<Box<dyn Expression>>) -> Self {
+        Self { func, args }
+    }
+}
+
+impl Expression for CallExpression {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        self.eval_rows(data, num_rows(data)?)
+    }
+
+    fn eval_rows(&self, data: &[&ArrayImpl], num_rows: usize) -> Result<ArrayImpl> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.eval_rows(data, num_rows))
+            .collect::<Result<Vec<_>>>()?;
+        let args = args.iter().collect::<Vec<_>>();
+        self.func.eval_expr(&args)
+    }
+}
@@ -1,15 +1,83 @@
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 use crate::{TypeMismatch, array::*, expr::Expression};
 
 use anyhow::Result;
 
+/// A trait over all unary scalar functions, which takes `I` as input parameter, and outputs array
+/// of type `O`.
+pub trait UnaryExpFunc<I: Array, O: Array> {
+    fn eval<'a>(&self, i: I::RefItem<'a>) -> O::OwnedItem;
+}
+
+/// Represents a unary expression which takes `I` as input parameter, and outputs array of type
+/// `O`.
+///
+/// Like [`BinaryExpression`], [`UnaryExpression`] vectorizes a scalar function and erases the
+/// concrete array type, so that users simply call `UnaryExpression::eval(ArrayImpl)`.
+pub struct UnaryExpression<I: Array, O: Array, F> {
+    expr: F,
+    _phantom: PhantomData<(I, O)>,
+}
+
+/// Implements [`UnaryExpression`] for any given scalar function `F`.
+impl<'a, I: Array, O: Array, F> UnaryExpression<I, O, F>
+where
+    &'a I: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    F: UnaryExpFunc<I, O>,
+{
+    /// Create a unary expression from existing function
+    pub fn new(expr: F) -> Self {
+        Self {
+            expr,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Evaluate the expression with the given array.
+    pub fn eval_batch(&self, i: &'a ArrayImpl) -> Result<ArrayImpl> {
+        let ia: &'a I = i.try_into()?;
+        let mut builder: O::Builder = O::Builder::with_capacity(i.len());
+        for i in ia.iter() {
+            match i {
+                Some(i) => builder.push(Some(self.expr.eval(i).as_scalar_ref())),
+                None => builder.push(None),
+            }
+        }
+        Ok(builder.finish().into())
+    }
+}
+
+impl<I: Array, O: Array, F> Expression for UnaryExpression<I, O, F>
+where
+    for<'a> &'a I: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    F: UnaryExpFunc<I, O> + Send + Sync,
+{
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        if data.len() != 1 {
+            return Err(anyhow::anyhow!("Expect one input for UnaryExpression"));
+        }
+        self.eval_batch(data[0])
+    }
+}
+
 /// A trait over all binary scalar functions, which takes `I1` and `I2` as input
 /// parameter, and outputs array of type `O`.
 pub trait BinaryExpFunc<I1: Array, I2: Array, O: Array> {
     fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> O::OwnedItem;
 }
 
+/// A shared scalar function is also a scalar function, so that one function can be used by
+/// several expressions.
+impl<I1: Array, I2: Array, O: Array, F: BinaryExpFunc<I1, I2, O>> BinaryExpFunc<I1, I2, O>
+    for Arc<F>
+{
+    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> O::OwnedItem {
+        self.as_ref().eval(i1, i2)
+    }
+}
+
 /// Represents a binary expression which takes `I1` and `I2` as input parameter, and outputs array
 /// of type `)`.
 ///
@@ -65,7 +133,7 @@ impl<I1: Array, I2: Array, O: Array, F> Expression for BinaryExpression<I1, I2,
 where
     for<'a> &'a I1: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
     for<'a> &'a I2: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
-    F: BinaryExpFunc<I1, I2, O>,
+    F: BinaryExpFunc<I1, I2, O> + Send + Sync,
 {
     fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
         if data.len() != 2 {
@@ -74,3 +142,75 @@ where
         self.eval_batch(data[0], data[1])
     }
 }
+
+/// A trait over binary scalar functions producing strings, which takes `I1` and `I2` as input
+/// parameter.
+///
+/// Instead of returning an owned `String` like [`BinaryExpFunc`], the function writes its output
+/// into a [`StringWriter`], which appends the bytes directly into the buffer of the output
+/// [`StringArrayBuilder`]. Therefore, each output value is copied only once.
+pub trait BinaryStrExpFunc<I1: Array, I2: Array> {
+    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>, writer: &mut StringWriter);
+}
+
+impl<I1: Array, I2: Array, F: BinaryStrExpFunc<I1, I2>> BinaryStrExpFunc<I1, I2> for Arc<F> {
+    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>, writer: &mut StringWriter) {
+        self.as_ref().eval(i1, i2, writer)
+    }
+}
+
+/// Represents a binary expression which takes `I1` and `I2` as input parameter, and outputs
+/// [`StringArray`] with a [`BinaryStrExpFunc`].
+pub struct BinaryStrExpression<I1: Array, I2: Array, F> {
+    expr: F,
+    _phantom: PhantomData<(I1, I2)>,
+}
+
+/// Implements [`BinaryStrExpression`] for any given scalar function `F`.
+impl<'a, I1: Array, I2: Array, F> BinaryStrExpression<I1, I2, F>
+where
+    &'a I1: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    &'a I2: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    F: BinaryStrExpFunc<I1, I2>,
+{
+    /// Create a binary expression from existing function
+    pub fn new(expr: F) -> Self {
+        Self {
+            expr,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Evaluate the expression with the given array.
+    pub fn eval_batch(&self, i1: &'a ArrayImpl, i2: &'a ArrayImpl) -> Result<ArrayImpl> {
+        let i1a: &'a I1 = i1.try_into()?;
+        let i2a: &'a I2 = i2.try_into()?;
+        assert_eq!(i1.len(), i2.len(), "array length mismatch");
+        let mut builder = StringArrayBuilder::with_capacity(i1.len());
+        for (i1, i2) in i1a.iter().zip(i2a.iter()) {
+            match (i1, i2) {
+                (Some(i1), Some(i2)) => {
+                    let mut writer = builder.writer();
+                    self.expr.eval(i1, i2, &mut writer);
+                    writer.finish();
+                }
+                _ => builder.push(None),
+            }
+        }
+        Ok(builder.finish().into())
+    }
+}
+
+impl<I1: Array, I2: Array, F> Expression for BinaryStrExpression<I1, I2, F>
+where
+    for<'a> &'a I1: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    for<'a> &'a I2: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+    F: BinaryStrExpFunc<I1, I2> + Send + Sync,
+{
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        if data.len() != 2 {
+            return Err(anyhow::anyhow!("Expect two inputs for BinaryStrExpression"));
+        }
+        self.eval_batch(data[0], data[1])
+    }
+}
@@ -4,14 +4,25 @@
 //! We leverage the Rust type system to minimize runtime cost and make our development process
 //! easier.
 
+extern crate self as type_rust;
+
+pub mod aggregate;
 pub mod array;
+#[allow(non_snake_case)]
 pub mod dataType;
+pub mod executor;
 pub mod expr;
 pub mod macros;
 pub mod scalar;
 
 use thiserror::Error;
 
+/// Re-exports used by code generated by macros in `type_rust_macros`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use anyhow;
+}
+
 #[derive(Error, Debug)]
 #[error("Type mismatch on conversion: expected {0}, get {1}")]
 pub struct TypeMismatch(&'static str, &'static str);
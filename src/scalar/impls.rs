@@ -17,6 +17,15 @@ macro_rules! impl_scalar_dispatch {
                     )*
                 }
             }
+
+            /// Get a reference of the current value.
+            pub fn as_scalar_ref(&self) -> ScalarRefImpl<'_> {
+                match self {
+                    $(
+                        Self::$Abc(v) => ScalarRefImpl::$Abc(v.as_scalar_ref()),
+                    )*
+                }
+            }
         }
     }
 }
@@ -35,6 +44,15 @@ macro_rules! impl_scalar_ref_dispatch {
                     )*
                 }
             }
+
+            /// Convert the reference into owned value.
+            pub fn to_owned_scalar(&self) -> ScalarImpl {
+                match self {
+                    $(
+                        Self::$Abc(v) => ScalarImpl::$Abc(v.to_owned_scalar()),
+                    )*
+                }
+            }
         }
     }
 }
@@ -40,6 +40,7 @@ pub trait ScalarRef<'a>:
 }
 
 /// Encapsules all variants of [`Scalar`]
+#[derive(Debug, Clone)]
 pub enum ScalarImpl {
     Int16(i16),
     Int32(i32),
@@ -51,6 +52,7 @@ pub enum ScalarImpl {
 }
 
 /// Encapsulates all variants of [`ScalarRef`]
+#[derive(Debug, Clone, Copy)]
 pub enum ScalarRefImpl<'a> {
     Int16(i16),
     Int32(i32),
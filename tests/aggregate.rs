@@ -0,0 +1,553 @@
+//! Tests aggregate functions built by [`build_aggregate`].
+
+mod common;
+
+use anyhow::Result;
+use type_rust::aggregate::{
+    AggregateFunction, DistinctAggregate, FilterAggregate, build_aggregate,
+    build_ordered_set_aggregate,
+};
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::scalar::ScalarImpl;
+
+use common::{array, error, ints, strings};
+
+/// Format the result of an aggregate function, as [`ScalarImpl`] cannot be compared.
+fn format(result: Option<ScalarImpl>) -> String {
+    format!("{result:?}")
+}
+
+fn some(value: ScalarImpl) -> String {
+    format(Some(value))
+}
+
+/// Aggregate `args` with function `name`, and check that the result is the same when the rows
+/// are split into two states which are merged. Returns the formatted result.
+fn aggregate(name: &str, args: &[ArrayImpl]) -> Result<String> {
+    let types = args.iter().map(|arg| arg.data_type()).collect::<Vec<_>>();
+    run(build_aggregate(name, &types)?, args)
+}
+
+/// Aggregate `args` with `func` like [`aggregate`].
+fn run(func: Box<dyn AggregateFunction>, args: &[ArrayImpl]) -> Result<String> {
+    let num_rows = args.first().map_or(0, |arg| arg.len());
+    let mut state = func.create_state();
+    func.update(&mut state, &args.iter().collect::<Vec<_>>(), num_rows)?;
+    let result = format(func.finish(&state)?);
+
+    for split in [0, num_rows / 2, num_rows] {
+        let visibility = (0..num_rows).map(|row| row < split).collect::<Vec<_>>();
+        let inverse = visibility.iter().map(|v| !v).collect::<Vec<_>>();
+        let mut left = func.create_state();
+        let mut right = func.create_state();
+        for (state, visibility) in [(&mut left, &visibility), (&mut right, &inverse)] {
+            let args = args
+                .iter()
+                .map(|arg| arg.filter(visibility))
+                .collect::<Vec<_>>();
+            let len = visibility.iter().filter(|v| **v).count();
+            func.update(state, &args.iter().collect::<Vec<_>>(), len)?;
+        }
+        func.merge(&mut left, right)?;
+        assert_eq!(format(func.finish(&left)?), result, "split at {split}");
+    }
+    Ok(result)
+}
+
+#[test]
+fn count() {
+    let input = strings(&[Some("a"), None, Some("b"), None]);
+    assert_eq!(
+        aggregate("count", std::slice::from_ref(&input)).unwrap(),
+        some(ScalarImpl::Int64(2))
+    );
+    let func = build_aggregate("count", &[]).unwrap();
+    assert_eq!(func.return_type(), DataType::BigInt);
+    let mut state = func.create_state();
+    func.update(&mut state, &[], 4).unwrap();
+    func.update(&mut state, &[], 3).unwrap();
+    assert_eq!(
+        format(func.finish(&state).unwrap()),
+        some(ScalarImpl::Int64(7))
+    );
+
+    // The count of no rows is 0, not `NULL`.
+    assert_eq!(
+        aggregate("count", &[ints(&[])]).unwrap(),
+        some(ScalarImpl::Int64(0))
+    );
+}
+
+#[test]
+fn sum_and_avg() {
+    let input = ints(&[Some(1), None, Some(2), Some(4)]);
+    assert_eq!(
+        aggregate("sum", std::slice::from_ref(&input)).unwrap(),
+        some(ScalarImpl::Int64(7))
+    );
+    assert_eq!(
+        aggregate("avg", std::slice::from_ref(&input)).unwrap(),
+        some(ScalarImpl::Float64(7.0 / 3.0))
+    );
+    assert_eq!(aggregate("sum", &[ints(&[None])]).unwrap(), "None");
+    assert_eq!(aggregate("avg", &[ints(&[])]).unwrap(), "None");
+
+    // Integer sums are widened, so they do not overflow in the middle.
+    let input = ints(&[Some(i32::MAX), Some(i32::MAX), Some(-5)]);
+    assert_eq!(
+        aggregate("sum", &[input]).unwrap(),
+        some(ScalarImpl::Int64(2 * i32::MAX as i64 - 5))
+    );
+    let input = array::<I64Array>(&[Some(i64::MAX), Some(10), Some(-20)]);
+    assert_eq!(
+        aggregate("sum", std::slice::from_ref(&input)).unwrap(),
+        some(ScalarImpl::Int64(i64::MAX - 10))
+    );
+    let input = array::<I64Array>(&[Some(i64::MAX), Some(1)]);
+    assert_eq!(error(aggregate("sum", &[input])), "bigint out of range");
+
+    let input = array::<F64Array>(&[Some(0.5), Some(1.5), None]);
+    assert_eq!(
+        aggregate("sum", std::slice::from_ref(&input)).unwrap(),
+        some(ScalarImpl::Float64(2.0))
+    );
+    assert_eq!(
+        aggregate("avg", &[input]).unwrap(),
+        some(ScalarImpl::Float64(1.0))
+    );
+    assert_eq!(
+        build_aggregate("sum", &[DataType::Real])
+            .unwrap()
+            .return_type(),
+        DataType::Real
+    );
+}
+
+#[test]
+fn min_and_max() {
+    let input = strings(&[Some("b"), None, Some("c"), Some("a")]);
+    assert_eq!(
+        aggregate("min", std::slice::from_ref(&input)).unwrap(),
+        some(ScalarImpl::String("a".to_string()))
+    );
+    assert_eq!(
+        aggregate("max", &[input]).unwrap(),
+        some(ScalarImpl::String("c".to_string()))
+    );
+    // `NaN` is greater than any other value.
+    let input = array::<F64Array>(&[Some(1.0), Some(f64::NAN), Some(f64::INFINITY)]);
+    let max = aggregate("max", std::slice::from_ref(&input)).unwrap();
+    assert_eq!(max, some(ScalarImpl::Float64(f64::NAN)));
+    assert_eq!(
+        aggregate("min", &[input]).unwrap(),
+        some(ScalarImpl::Float64(1.0))
+    );
+    assert_eq!(aggregate("min", &[ints(&[None, None])]).unwrap(), "None");
+}
+
+#[test]
+fn bool_and_or() {
+    let bools = |values: &[Option<bool>]| array::<BoolArray>(values);
+    let input = bools(&[Some(true), None, Some(false)]);
+    assert_eq!(
+        aggregate("bool_and", std::slice::from_ref(&input)).unwrap(),
+        some(ScalarImpl::Bool(false))
+    );
+    assert_eq!(
+        aggregate("bool_or", &[input]).unwrap(),
+        some(ScalarImpl::Bool(true))
+    );
+    let input = bools(&[Some(true), None, Some(true)]);
+    assert_eq!(
+        aggregate("bool_and", &[input]).unwrap(),
+        some(ScalarImpl::Bool(true))
+    );
+    assert_eq!(aggregate("bool_or", &[bools(&[None])]).unwrap(), "None");
+}
+
+#[test]
+fn string_agg() {
+    let values = strings(&[Some("a"), None, Some("b"), Some("c")]);
+    let delimiters = strings(&[Some(", "), Some("; "), Some(", "), None]);
+    // The delimiter of each value is put before it, except for the first one, and `NULL`
+    // delimiters are empty.
+    assert_eq!(
+        aggregate("string_agg", &[values, delimiters]).unwrap(),
+        some(ScalarImpl::String("a, bc".to_string()))
+    );
+    let values = strings(&[None, Some("x")]);
+    let delimiters = strings(&[Some(","), Some("-")]);
+    assert_eq!(
+        aggregate("string_agg", &[values, delimiters]).unwrap(),
+        some(ScalarImpl::String("x".to_string()))
+    );
+    let values = strings(&[None]);
+    assert_eq!(
+        aggregate("string_agg", &[values.clone(), values]).unwrap(),
+        "None"
+    );
+}
+
+#[test]
+fn update_grouped() {
+    let func = build_aggregate("sum", &[DataType::Integer]).unwrap();
+    let mut states = (0..3).map(|_| func.create_state()).collect::<Vec<_>>();
+    let input = ints(&[Some(1), Some(2), None, Some(4), Some(8)]);
+    func.update_grouped(&mut states, &[0, 1, 2, 0, 1], &[&input])
+        .unwrap();
+    let results = states
+        .iter()
+        .map(|state| format(func.finish(state).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        [
+            some(ScalarImpl::Int64(5)),
+            some(ScalarImpl::Int64(10)),
+            "None".to_string()
+        ]
+    );
+
+    // A cloned state is independent of the original one.
+    let cloned = func.clone_state(&states[0]).unwrap();
+    func.update(&mut states[0], &[&input], input.len()).unwrap();
+    assert_eq!(
+        format(func.finish(&cloned).unwrap()),
+        some(ScalarImpl::Int64(5))
+    );
+    assert_eq!(
+        format(func.finish(&states[0]).unwrap()),
+        some(ScalarImpl::Int64(20))
+    );
+}
+
+#[test]
+fn build_errors() {
+    assert_eq!(
+        error(build_aggregate("sum", &[DataType::Varchar])),
+        "function sum(varchar) does not exist"
+    );
+    assert!(build_aggregate("bool_and", &[DataType::Integer]).is_err());
+    assert!(build_aggregate("no_such_aggregate", &[DataType::Integer]).is_err());
+
+    // States of different functions cannot be mixed.
+    let sum = build_aggregate("sum", &[DataType::Integer]).unwrap();
+    let count = build_aggregate("count", &[]).unwrap();
+    let mut state = sum.create_state();
+    assert!(sum.merge(&mut state, count.create_state()).is_err());
+    assert!(
+        sum.update(&mut state, &[&strings(&[Some("a")])], 1)
+            .is_err()
+    );
+}
+
+/// Get the result of `name` of `args` as `f64`.
+fn statistic(name: &str, args: &[ArrayImpl]) -> Option<f64> {
+    let result = aggregate(name, args).unwrap();
+    (result != "None").then(|| {
+        let value = result.trim_start_matches("Some(Float64(");
+        value.trim_end_matches("))").parse().unwrap()
+    })
+}
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.unwrap();
+    assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+}
+
+#[test]
+fn variance_and_stddev() {
+    let input = ints(&[
+        Some(2),
+        Some(4),
+        None,
+        Some(4),
+        Some(4),
+        Some(5),
+        Some(5),
+        Some(7),
+        Some(9),
+    ]);
+    let input = std::slice::from_ref(&input);
+    assert_close(statistic("var_pop", input), 4.0);
+    assert_close(statistic("stddev_pop", input), 2.0);
+    assert_close(statistic("var_samp", input), 32.0 / 7.0);
+    assert_close(statistic("variance", input), 32.0 / 7.0);
+    assert_close(statistic("stddev", input), (32.0f64 / 7.0).sqrt());
+
+    // The sample statistics of one value are `NULL`.
+    let input = array::<F64Array>(&[Some(1.5), None]);
+    let input = std::slice::from_ref(&input);
+    assert_close(statistic("var_pop", input), 0.0);
+    assert_eq!(statistic("var_samp", input), None);
+    assert_eq!(statistic("stddev_samp", input), None);
+    assert_eq!(statistic("var_pop", &[ints(&[])]), None);
+
+    // Large values with small deviations do not lose precision.
+    let input = array::<I64Array>(&[Some(1_000_000_001), Some(1_000_000_003)]);
+    assert_close(statistic("var_samp", &[input]), 2.0);
+    assert!(build_aggregate("var_pop", &[DataType::Varchar]).is_err());
+}
+
+#[test]
+fn covariance_and_regression() {
+    let y = array::<F64Array>(&[Some(2.0), Some(4.0), Some(6.0), None, Some(9.0)]);
+    let x = ints(&[Some(1), Some(2), Some(3), Some(4), None]);
+    let args = [y, x];
+    // Rows where any argument is `NULL` are skipped.
+    assert_close(statistic("covar_pop", &args), 4.0 / 3.0);
+    assert_close(statistic("covar_samp", &args), 2.0);
+    assert_close(statistic("corr", &args), 1.0);
+    assert_close(statistic("regr_slope", &args), 2.0);
+
+    let y = array::<I16Array>(&[Some(3), Some(1), Some(2)]);
+    let x = array::<I64Array>(&[Some(1), Some(2), Some(3)]);
+    let args = [y, x];
+    assert_close(statistic("corr", &args), -0.5);
+    assert_close(statistic("regr_slope", &args), -0.5);
+
+    // The correlation and slope are `NULL` if `x` does not vary.
+    let args = [ints(&[Some(1), Some(2)]), ints(&[Some(5), Some(5)])];
+    assert_close(statistic("covar_pop", &args), 0.0);
+    assert_eq!(statistic("corr", &args), None);
+    assert_eq!(statistic("regr_slope", &args), None);
+    assert_eq!(
+        statistic("covar_samp", &[ints(&[Some(1)]), ints(&[Some(1)])]),
+        None
+    );
+
+    assert_eq!(
+        error(build_aggregate(
+            "corr",
+            &[DataType::Double, DataType::Varchar]
+        )),
+        "function corr(double precision, varchar) does not exist"
+    );
+}
+
+/// Aggregate `input` with ordered-set aggregate `name` with `fraction`.
+fn ordered_set(name: &str, fraction: f64, input: &ArrayImpl) -> Result<String> {
+    let func = build_ordered_set_aggregate(name, fraction, input.data_type())?;
+    run(func, std::slice::from_ref(input))
+}
+
+#[test]
+fn percentiles() {
+    let input = ints(&[Some(40), Some(10), None, Some(30), Some(20)]);
+    let cont = |fraction| ordered_set("percentile_cont", fraction, &input).unwrap();
+    assert_eq!(cont(0.0), some(ScalarImpl::Float64(10.0)));
+    assert_eq!(cont(0.5), some(ScalarImpl::Float64(25.0)));
+    assert_eq!(cont(0.25), some(ScalarImpl::Float64(17.5)));
+    assert_eq!(cont(1.0), some(ScalarImpl::Float64(40.0)));
+    assert_eq!(
+        aggregate("median", std::slice::from_ref(&input)).unwrap(),
+        cont(0.5)
+    );
+
+    // `percentile_disc` returns one of the values, in the type of the values.
+    let disc = |fraction| ordered_set("percentile_disc", fraction, &input).unwrap();
+    assert_eq!(disc(0.0), some(ScalarImpl::Int32(10)));
+    assert_eq!(disc(0.5), some(ScalarImpl::Int32(20)));
+    assert_eq!(disc(0.51), some(ScalarImpl::Int32(30)));
+    assert_eq!(disc(1.0), some(ScalarImpl::Int32(40)));
+    let input = strings(&[Some("b"), Some("a"), Some("c")]);
+    assert_eq!(
+        ordered_set("percentile_disc", 0.5, &input).unwrap(),
+        some(ScalarImpl::String("b".to_string()))
+    );
+
+    assert_eq!(
+        ordered_set("percentile_cont", 0.5, &ints(&[None])).unwrap(),
+        "None"
+    );
+    assert_eq!(
+        error(ordered_set("percentile_cont", 1.5, &ints(&[]))),
+        "percentile value 1.5 is not between 0 and 1"
+    );
+    assert!(ordered_set("percentile_cont", 0.5, &input).is_err());
+    assert!(ordered_set("no_such_aggregate", 0.5, &ints(&[])).is_err());
+}
+
+#[test]
+fn approx_count_distinct() {
+    let values = (0..20000).map(|v| Some(v % 5000)).collect::<Vec<_>>();
+    let estimate = aggregate("approx_count_distinct", &[ints(&values)]).unwrap();
+    let estimate: i64 = estimate
+        .trim_start_matches("Some(Int64(")
+        .trim_end_matches("))")
+        .parse()
+        .unwrap();
+    assert!((estimate - 5000).abs() < 5000 / 20, "{estimate}");
+
+    // Small counts are almost exact.
+    let input = strings(&[Some("a"), Some("b"), None, Some("a"), Some("c")]);
+    assert_eq!(
+        aggregate("approx_count_distinct", &[input]).unwrap(),
+        some(ScalarImpl::Int64(3))
+    );
+    assert_eq!(
+        aggregate("approx_count_distinct", &[ints(&[None])]).unwrap(),
+        some(ScalarImpl::Int64(0))
+    );
+}
+
+#[test]
+fn approx_percentile() {
+    let values = (0..10001).rev().map(Some).collect::<Vec<_>>();
+    let input = ints(&values);
+    // Merged digests may be compressed differently, so the estimates are not compared by `run`.
+    for fraction in [0.0, 0.01, 0.25, 0.5, 0.9, 0.999, 1.0] {
+        let func = build_ordered_set_aggregate("approx_percentile", fraction, DataType::Integer);
+        let func = func.unwrap();
+        let mut state = func.create_state();
+        func.update(&mut state, &[&input], input.len()).unwrap();
+        let result = format!("{:?}", func.finish(&state).unwrap());
+        let estimate: f64 = result
+            .trim_start_matches("Some(Float64(")
+            .trim_end_matches("))")
+            .parse()
+            .unwrap();
+        let expected = fraction * 10000.0;
+        assert!((estimate - expected).abs() < 50.0, "{fraction}: {estimate}");
+    }
+
+    // Digests built on parts of the input are merged, and `NaN`s are ignored.
+    let input = array::<F64Array>(&[Some(1.0), Some(f64::NAN), Some(3.0), None, Some(2.0)]);
+    assert_eq!(
+        ordered_set("approx_percentile", 0.5, &input).unwrap(),
+        some(ScalarImpl::Float64(2.0))
+    );
+    assert_eq!(
+        ordered_set("approx_percentile", 0.5, &ints(&[])).unwrap(),
+        "None"
+    );
+}
+
+/// Build `name(DISTINCT args)` with the types of `args`.
+fn distinct(name: &str, args: &[ArrayImpl]) -> Box<dyn AggregateFunction> {
+    let types = args.iter().map(|arg| arg.data_type()).collect::<Vec<_>>();
+    let inner = build_aggregate(name, &types).unwrap();
+    Box::new(DistinctAggregate::new(inner, types).unwrap())
+}
+
+#[test]
+fn distinct_aggregates() {
+    let input = ints(&[Some(1), Some(2), None, Some(2), Some(1), Some(3), None]);
+    let args = std::slice::from_ref(&input);
+    assert_eq!(
+        run(distinct("count", args), args).unwrap(),
+        some(ScalarImpl::Int64(3))
+    );
+    assert_eq!(
+        run(distinct("sum", args), args).unwrap(),
+        some(ScalarImpl::Int64(6))
+    );
+
+    // `0.0` and `-0.0` are the same value, as are all `NaN`s.
+    let input = array::<F64Array>(&[Some(0.0), Some(-0.0), Some(f64::NAN), Some(-f64::NAN)]);
+    let args = std::slice::from_ref(&input);
+    assert_eq!(
+        run(distinct("count", args), args).unwrap(),
+        some(ScalarImpl::Int64(2))
+    );
+
+    // Rows of many arguments are distinct if any of the values differ.
+    let values = strings(&[Some("a"), Some("a"), Some("b"), Some("a")]);
+    let delimiters = strings(&[Some(","), Some(","), Some(","), Some(";")]);
+    let args = [values, delimiters];
+    let func = distinct("string_agg", &args);
+    let mut state = func.create_state();
+    func.update(&mut state, &args.iter().collect::<Vec<_>>(), 4)
+        .unwrap();
+    assert_eq!(
+        format(func.finish(&state).unwrap()),
+        some(ScalarImpl::String("a,b;a".to_string()))
+    );
+
+    // Arguments must be of the types given when the function is built.
+    let func = distinct("count", &[ints(&[])]);
+    let mut state = func.create_state();
+    assert!(
+        func.update(&mut state, &[&strings(&[Some("a")])], 1)
+            .is_err()
+    );
+    let count = build_aggregate("count", &[]).unwrap();
+    assert!(DistinctAggregate::new(count, vec![]).is_err());
+}
+
+#[test]
+fn distinct_grouped() {
+    let func = distinct("count", &[ints(&[])]);
+    let mut states = (0..2).map(|_| func.create_state()).collect::<Vec<_>>();
+    let input = ints(&[Some(1), Some(1), Some(1), Some(2), Some(2), Some(2)]);
+    func.update_grouped(&mut states, &[0, 0, 1, 0, 1, 1], &[&input])
+        .unwrap();
+    // A value seen by one group is still new in another.
+    let results = states
+        .iter()
+        .map(|state| format(func.finish(state).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        [some(ScalarImpl::Int64(2)), some(ScalarImpl::Int64(2))]
+    );
+
+    // Values seen by both states are counted once after merging.
+    let right = states.pop().unwrap();
+    let mut left = states.pop().unwrap();
+    let more = ints(&[Some(3)]);
+    let mut other = func.clone_state(&right).unwrap();
+    func.update(&mut other, &[&more], 1).unwrap();
+    func.merge(&mut left, other).unwrap();
+    assert_eq!(
+        format(func.finish(&left).unwrap()),
+        some(ScalarImpl::Int64(3))
+    );
+    assert_eq!(
+        format(func.finish(&right).unwrap()),
+        some(ScalarImpl::Int64(2))
+    );
+}
+
+#[test]
+fn filter_aggregates() {
+    let input = ints(&[Some(1), Some(2), Some(3), Some(4), None]);
+    let cond = array::<BoolArray>(&[Some(true), Some(false), None, Some(true), Some(true)]);
+    // Rows where the condition is `NULL` are filtered out.
+    let sum = FilterAggregate::new(build_aggregate("sum", &[DataType::Integer]).unwrap());
+    let args = [input.clone(), cond.clone()];
+    assert_eq!(
+        run(Box::new(sum), &args).unwrap(),
+        some(ScalarImpl::Int64(5))
+    );
+    let count = FilterAggregate::new(build_aggregate("count", &[]).unwrap());
+    assert_eq!(
+        run(Box::new(count), std::slice::from_ref(&cond)).unwrap(),
+        some(ScalarImpl::Int64(3))
+    );
+
+    // Rows filtered out are not seen by `DISTINCT`.
+    let input = ints(&[Some(1), Some(1), Some(2)]);
+    let cond = array::<BoolArray>(&[Some(false), Some(true), Some(false)]);
+    let func = FilterAggregate::new(distinct("count", &[ints(&[])]));
+    assert_eq!(
+        run(Box::new(func), &[input.clone(), cond.clone()]).unwrap(),
+        some(ScalarImpl::Int64(1))
+    );
+
+    let func = FilterAggregate::new(build_aggregate("sum", &[DataType::Integer]).unwrap());
+    let mut states = (0..2).map(|_| func.create_state()).collect::<Vec<_>>();
+    func.update_grouped(&mut states, &[0, 1, 1], &[&input, &cond])
+        .unwrap();
+    let results = states
+        .iter()
+        .map(|state| format(func.finish(state).unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(results, ["None".to_string(), some(ScalarImpl::Int64(1))]);
+
+    // The condition must be a boolean.
+    let mut state = func.create_state();
+    assert!(func.update(&mut state, &[&input, &input], 3).is_err());
+    assert!(func.update(&mut state, &[], 0).is_err());
+}
@@ -0,0 +1,152 @@
+//! Tests bitwise and integer functions registered in [`FunctionRegistry`].
+
+mod common;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::expr::FunctionRegistry;
+
+use common::{array, error, ints, values};
+
+/// Call function `name` on `args`, with the types of the arrays.
+fn call(name: &str, args: &[ArrayImpl]) -> Result<ArrayImpl> {
+    let types = args.iter().map(|arg| arg.data_type()).collect::<Vec<_>>();
+    let func = FunctionRegistry::with_builtins().build(name, &types)?;
+    func.eval_expr(&args.iter().collect::<Vec<_>>())
+}
+
+fn smallints(values: &[Option<i16>]) -> ArrayImpl {
+    array::<I16Array>(values)
+}
+
+fn bigints(values: &[Option<i64>]) -> ArrayImpl {
+    array::<I64Array>(values)
+}
+
+/// Call binary function `name` of `integer` on `l` and `r`.
+fn call_i32(name: &str, l: i32, r: i32) -> Result<i32> {
+    let result = call(name, &[ints(&[Some(l)]), ints(&[Some(r)])])?;
+    Ok(values::<I32Array>(&result)[0].unwrap())
+}
+
+#[test]
+fn bitwise_operators() {
+    let l = ints(&[Some(0b1100), Some(-1), None]);
+    let r = ints(&[Some(0b1010), Some(0), Some(1)]);
+    let eval = |name: &str| values::<I32Array>(&call(name, &[l.clone(), r.clone()]).unwrap());
+    assert_eq!(eval("bitwise_and"), [Some(0b1000), Some(0), None]);
+    assert_eq!(eval("bitwise_or"), [Some(0b1110), Some(-1), None]);
+    assert_eq!(eval("bitwise_xor"), [Some(0b0110), Some(-1), None]);
+    let result = call("bitwise_not", &[l]).unwrap();
+    assert_eq!(values::<I32Array>(&result), [Some(!0b1100), Some(0), None]);
+
+    // The type of the arguments is preserved.
+    let result = call(
+        "bitwise_and",
+        &[smallints(&[Some(0x0ff0)]), smallints(&[Some(0x00ff)])],
+    )
+    .unwrap();
+    assert_eq!(values::<I16Array>(&result), [Some(0x00f0)]);
+    // Arguments of different types are cast into the wider one.
+    let result = call(
+        "bitwise_or",
+        &[smallints(&[Some(1)]), bigints(&[Some(1 << 40)])],
+    )
+    .unwrap();
+    assert_eq!(values::<I64Array>(&result), [Some((1 << 40) | 1)]);
+}
+
+#[test]
+fn shifts() {
+    assert_eq!(call_i32("shift_left", 1, 4).unwrap(), 16);
+    assert_eq!(call_i32("shift_right", -16, 2).unwrap(), -4);
+    assert_eq!(call_i32("shift_left", 1, 32).unwrap(), 0);
+    assert_eq!(call_i32("shift_right", 5, 40).unwrap(), 0);
+    assert_eq!(call_i32("shift_right", -5, 40).unwrap(), -1);
+    assert_eq!(
+        error(call_i32("shift_left", 1, -1)),
+        "negative shift amount -1"
+    );
+
+    let result = call("shift_left", &[smallints(&[Some(1)]), ints(&[Some(15)])]).unwrap();
+    assert_eq!(values::<I16Array>(&result), [Some(i16::MIN)]);
+}
+
+#[test]
+fn bit_count_and_get_bit() {
+    let result = call("bit_count", &[smallints(&[Some(0b1011), Some(-1), None])]).unwrap();
+    assert_eq!(values::<I64Array>(&result), [Some(3), Some(16), None]);
+    let result = call("bit_count", &[bigints(&[Some(-1)])]).unwrap();
+    assert_eq!(values::<I64Array>(&result), [Some(64)]);
+
+    // Test flags in a bitset column.
+    let flags = bigints(&[Some(0b101), Some(1 << 63), Some(0), None]);
+    let get_bit = |n: i32| -> Result<Vec<Option<bool>>> {
+        let result = call("get_bit", &[flags.clone(), ints(&[Some(n); 4])])?;
+        Ok(values::<BoolArray>(&result))
+    };
+    assert_eq!(
+        get_bit(0).unwrap(),
+        [Some(true), Some(false), Some(false), None]
+    );
+    assert_eq!(
+        get_bit(1).unwrap(),
+        [Some(false), Some(false), Some(false), None]
+    );
+    assert_eq!(
+        get_bit(63).unwrap(),
+        [Some(false), Some(true), Some(false), None]
+    );
+    assert_eq!(
+        error(get_bit(64)),
+        "bit index 64 out of valid range (0..63)"
+    );
+    assert_eq!(
+        error(get_bit(-1)),
+        "bit index -1 out of valid range (0..63)"
+    );
+}
+
+#[test]
+fn gcd_and_lcm() {
+    assert_eq!(call_i32("gcd", 12, 18).unwrap(), 6);
+    assert_eq!(call_i32("gcd", -12, 18).unwrap(), 6);
+    assert_eq!(call_i32("gcd", 0, -5).unwrap(), 5);
+    assert_eq!(call_i32("gcd", 0, 0).unwrap(), 0);
+    assert_eq!(call_i32("lcm", 4, 6).unwrap(), 12);
+    assert_eq!(call_i32("lcm", -4, 6).unwrap(), 12);
+    assert_eq!(call_i32("lcm", 0, 6).unwrap(), 0);
+
+    // The results may not fit into the type.
+    assert_eq!(error(call_i32("gcd", i32::MIN, 0)), "integer out of range");
+    assert_eq!(
+        error(call_i32("lcm", i32::MAX, i32::MAX - 1)),
+        "integer out of range"
+    );
+    let result = call(
+        "gcd",
+        &[bigints(&[Some(i64::MIN)]), bigints(&[Some(i64::MIN)])],
+    );
+    assert_eq!(error(result), "bigint out of range");
+}
+
+#[test]
+fn div_and_mod() {
+    // The quotient is truncated towards zero, and the remainder has the sign of the dividend.
+    assert_eq!(call_i32("div", 7, 2).unwrap(), 3);
+    assert_eq!(call_i32("div", -7, 2).unwrap(), -3);
+    assert_eq!(call_i32("div", 7, -2).unwrap(), -3);
+    assert_eq!(call_i32("mod", 7, 3).unwrap(), 1);
+    assert_eq!(call_i32("mod", -7, 3).unwrap(), -1);
+    assert_eq!(call_i32("mod", 7, -3).unwrap(), 1);
+    assert_eq!(call_i32("mod", i32::MIN, -1).unwrap(), 0);
+
+    assert_eq!(error(call_i32("div", 1, 0)), "division by zero");
+    assert_eq!(error(call_i32("mod", 1, 0)), "division by zero");
+    assert_eq!(error(call_i32("div", i32::MIN, -1)), "integer out of range");
+    let result = call(
+        "div",
+        &[smallints(&[Some(i16::MIN)]), smallints(&[Some(-1)])],
+    );
+    assert_eq!(error(result), "smallint out of range");
+}
@@ -0,0 +1,215 @@
+//! Tests `CAST` and `TRY_CAST` between all types.
+
+mod common;
+
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::cast::{cast, try_cast};
+
+use common::{array, error, strings, values};
+
+#[test]
+fn integer_overflow() {
+    let input = array::<I32Array>(&[Some(1), Some(40000), None, Some(-32768)]);
+    let err = error(cast(&input, DataType::SmallInt));
+    assert_eq!(err, "smallint out of range");
+    // `TRY_CAST` turns values out of range into `NULL`.
+    let result = try_cast(&input, DataType::SmallInt).unwrap();
+    assert_eq!(
+        values::<I16Array>(&result),
+        vec![Some(1), None, None, Some(-32768)]
+    );
+
+    let input = array::<I64Array>(&[Some(i64::MAX)]);
+    assert_eq!(
+        error(cast(&input, DataType::Integer)),
+        "integer out of range"
+    );
+    let result = cast(&input, DataType::Double).unwrap();
+    assert_eq!(values::<F64Array>(&result), vec![Some(i64::MAX as f64)]);
+}
+
+#[test]
+fn float_to_integer_rounding() {
+    let input = array::<F64Array>(&[
+        Some(0.5),
+        Some(1.5),
+        Some(2.5),
+        Some(-0.5),
+        Some(-1.5),
+        Some(2.4999),
+        Some(-2.6),
+    ]);
+    // Ties are rounded to even.
+    let result = cast(&input, DataType::Integer).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        vec![
+            Some(0),
+            Some(2),
+            Some(2),
+            Some(0),
+            Some(-2),
+            Some(2),
+            Some(-3)
+        ]
+    );
+
+    let input = array::<F64Array>(&[
+        Some(32767.4),
+        Some(32767.5),
+        Some(-32768.5),
+        Some(f64::NAN),
+        Some(f64::INFINITY),
+    ]);
+    assert_eq!(
+        error(cast(&input, DataType::SmallInt)),
+        "smallint out of range"
+    );
+    let result = try_cast(&input, DataType::SmallInt).unwrap();
+    assert_eq!(
+        values::<I16Array>(&result),
+        vec![Some(32767), None, Some(-32768), None, None]
+    );
+
+    // The upper bound of `bigint` is not exactly representable in floats.
+    let input = array::<F32Array>(&[Some(9.223372e18), Some(-9.223372e18)]);
+    let result = try_cast(&input, DataType::BigInt).unwrap();
+    assert_eq!(values::<I64Array>(&result), vec![None, Some(i64::MIN)]);
+}
+
+#[test]
+fn double_to_real() {
+    let input = array::<F64Array>(&[Some(1.5), Some(1e300), Some(1e-300), Some(f64::INFINITY)]);
+    let result = try_cast(&input, DataType::Real).unwrap();
+    assert_eq!(
+        values::<F32Array>(&result),
+        vec![Some(1.5), None, None, Some(f32::INFINITY)]
+    );
+    assert_eq!(
+        error(cast(&input, DataType::Real)),
+        "value out of range: overflow"
+    );
+}
+
+#[test]
+fn parse_strings() {
+    let input = strings(&[
+        Some(" 42 "),
+        Some("-7"),
+        Some("4x"),
+        Some("99999999999"),
+        None,
+    ]);
+    assert_eq!(
+        error(cast(&input, DataType::Integer)),
+        "invalid input syntax for type integer: \"4x\""
+    );
+    let result = try_cast(&input, DataType::Integer).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        vec![Some(42), Some(-7), None, None, None]
+    );
+    let input = strings(&[Some("99999999999")]);
+    assert_eq!(
+        error(cast(&input, DataType::Integer)),
+        "value \"99999999999\" is out of range for type integer"
+    );
+
+    let input = strings(&[Some("1.5"), Some("-Infinity"), Some("1e400"), Some("abc")]);
+    let result = try_cast(&input, DataType::Double).unwrap();
+    assert_eq!(
+        values::<F64Array>(&result),
+        vec![Some(1.5), Some(f64::NEG_INFINITY), None, None]
+    );
+    assert_eq!(
+        error(cast(&strings(&[Some("1e400")]), DataType::Double)),
+        "\"1e400\" is out of range for type double precision"
+    );
+    let result = cast(&strings(&[Some("NaN")]), DataType::Real).unwrap();
+    assert!(values::<F32Array>(&result)[0].unwrap().is_nan());
+
+    let input = strings(&[
+        Some("t"),
+        Some("YES"),
+        Some("on"),
+        Some(" f "),
+        Some("of"),
+        Some("0"),
+        Some("o"),
+        Some("maybe"),
+    ]);
+    let result = try_cast(&input, DataType::Boolean).unwrap();
+    assert_eq!(
+        values::<BoolArray>(&result),
+        vec![
+            Some(true),
+            Some(true),
+            Some(true),
+            Some(false),
+            Some(false),
+            Some(false),
+            None,
+            None
+        ]
+    );
+}
+
+#[test]
+fn format_strings() {
+    let input = array::<F64Array>(&[
+        Some(1.5),
+        Some(f64::INFINITY),
+        Some(f64::NEG_INFINITY),
+        None,
+    ]);
+    let result = cast(&input, DataType::Varchar).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![
+            Some("1.5".to_string()),
+            Some("Infinity".to_string()),
+            Some("-Infinity".to_string()),
+            None
+        ]
+    );
+    let input = array::<BoolArray>(&[Some(true), Some(false)]);
+    let result = cast(&input, DataType::Varchar).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![Some("true".to_string()), Some("false".to_string())]
+    );
+}
+
+#[test]
+fn char_padding() {
+    let input = strings(&[Some("ab"), Some("abcd"), Some("é"), Some(""), None]);
+    let result = cast(&input, DataType::Char { width: 3 }).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![
+            Some("ab ".to_string()),
+            Some("abc".to_string()),
+            Some("é  ".to_string()),
+            Some("   ".to_string()),
+            None
+        ]
+    );
+    let input = array::<I32Array>(&[Some(7), Some(12345)]);
+    let result = cast(&input, DataType::Char { width: 4 }).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![Some("7   ".to_string()), Some("1234".to_string())]
+    );
+}
+
+#[test]
+fn decimal_is_rejected() {
+    let input = array::<I32Array>(&[Some(1)]);
+    let decimal = DataType::Decimal {
+        scale: 2,
+        precision: 10,
+    };
+    assert!(cast(&input, decimal).is_err());
+    assert!(try_cast(&input, decimal).is_err());
+}
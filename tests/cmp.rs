@@ -0,0 +1,225 @@
+//! Tests comparisons between arrays, including arrays of different numeric types.
+
+use std::marker::PhantomData;
+
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::FunctionRegistry;
+use type_rust::expr::cmp::*;
+use type_rust::expr::vectorize::{BinaryExpFunc, BinaryExpression};
+
+const LEFT: [Option<i16>; 5] = [Some(-1), Some(0), Some(1), None, Some(2)];
+const RIGHT: [Option<i16>; 5] = [Some(0), Some(0), Some(0), Some(1), None];
+
+/// Build an array of type `A` from `i16` values.
+fn array_of<A: Array>(values: &[Option<i16>]) -> ArrayImpl
+where
+    A::OwnedItem: From<i16>,
+{
+    let mut builder = A::Builder::with_capacity(values.len());
+    for value in values {
+        let value = value.map(A::OwnedItem::from);
+        builder.push(value.as_ref().map(|v| v.as_scalar_ref()));
+    }
+    builder.finish().into()
+}
+
+/// Evaluate `func` on [`LEFT`] and [`RIGHT`], and check the results against `expected` computed
+/// on `i16` values.
+fn check<I1: Array, I2: Array, F>(func: F, expected: impl Fn(i16, i16) -> bool)
+where
+    for<'a> &'a I1: TryFrom<&'a ArrayImpl, Error = type_rust::TypeMismatch>,
+    for<'a> &'a I2: TryFrom<&'a ArrayImpl, Error = type_rust::TypeMismatch>,
+    I1::OwnedItem: From<i16>,
+    I2::OwnedItem: From<i16>,
+    F: BinaryExpFunc<I1, I2, BoolArray>,
+{
+    let (left, right) = (array_of::<I1>(&LEFT), array_of::<I2>(&RIGHT));
+    let result = BinaryExpression::<I1, I2, BoolArray, _>::new(func)
+        .eval_batch(&left, &right)
+        .unwrap();
+    let result: BoolArray = result.try_into().unwrap();
+    let expected = LEFT
+        .iter()
+        .zip(RIGHT.iter())
+        .map(|(l, r)| Some(expected((*l)?, (*r)?)))
+        .collect::<Vec<_>>();
+    assert_eq!(result.iter().collect::<Vec<_>>(), expected);
+}
+
+/// Generates a test comparing `I1` and `I2` after casting both into `C`.
+macro_rules! test_cmp {
+    ($($name:ident: $I1:ty, $I2:ty => $C:ty;)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check::<$I1, $I2, _>(ExprCmpLt::<$I1, $I2, $C>(PhantomData), |l, r| l < r);
+                check::<$I1, $I2, _>(ExprCmpLe::<$I1, $I2, $C>(PhantomData), |l, r| l <= r);
+                check::<$I1, $I2, _>(ExprCmpGt::<$I1, $I2, $C>(PhantomData), |l, r| l > r);
+                check::<$I1, $I2, _>(ExprCmpGe::<$I1, $I2, $C>(PhantomData), |l, r| l >= r);
+                check::<$I1, $I2, _>(ExprCmpEq::<$I1, $I2, $C>(PhantomData), |l, r| l == r);
+                check::<$I1, $I2, _>(ExprCmpNe::<$I1, $I2, $C>(PhantomData), |l, r| l != r);
+            }
+        )*
+    };
+}
+
+// Every pair of numeric types in `for_all_primitive_variants`, except `bigint` with floats, which
+// are tested in `cmp_bigint_with_floats`.
+test_cmp! {
+    cmp_i16_i16: I16Array, I16Array => I16Array;
+    cmp_i16_i32: I16Array, I32Array => I32Array;
+    cmp_i16_i64: I16Array, I64Array => I64Array;
+    cmp_i16_f32: I16Array, F32Array => F32Array;
+    cmp_i16_f64: I16Array, F64Array => F64Array;
+    cmp_i32_i16: I32Array, I16Array => I32Array;
+    cmp_i32_i32: I32Array, I32Array => I32Array;
+    cmp_i32_i64: I32Array, I64Array => I64Array;
+    cmp_i32_f32: I32Array, F32Array => F64Array;
+    cmp_i32_f64: I32Array, F64Array => F64Array;
+    cmp_i64_i16: I64Array, I16Array => I64Array;
+    cmp_i64_i32: I64Array, I32Array => I64Array;
+    cmp_i64_i64: I64Array, I64Array => I64Array;
+    cmp_f32_i16: F32Array, I16Array => F32Array;
+    cmp_f32_i32: F32Array, I32Array => F64Array;
+    cmp_f32_f32: F32Array, F32Array => F32Array;
+    cmp_f32_f64: F32Array, F64Array => F64Array;
+    cmp_f64_i16: F64Array, I16Array => F64Array;
+    cmp_f64_i32: F64Array, I32Array => F64Array;
+    cmp_f64_f32: F64Array, F32Array => F64Array;
+    cmp_f64_f64: F64Array, F64Array => F64Array;
+}
+
+/// Build an array of `ty` from `i16` values.
+fn array_of_type(ty: DataType, values: &[Option<i16>]) -> ArrayImpl {
+    match ty {
+        DataType::BigInt => array_of::<I64Array>(values),
+        DataType::Real => array_of::<F32Array>(values),
+        DataType::Double => array_of::<F64Array>(values),
+        _ => unreachable!(),
+    }
+}
+
+/// `bigint` has no lossless conversion into floats, so instead of comparing them through `Into`,
+/// the registry casts both sides into `double precision` and compares them there.
+#[test]
+fn cmp_bigint_with_floats() {
+    use DataType::*;
+
+    let registry = FunctionRegistry::with_builtins();
+    type Cmp = fn(i16, i16) -> bool;
+    let cmps: [(&str, Cmp); 6] = [
+        ("less_than", |l, r| l < r),
+        ("less_than_or_equal", |l, r| l <= r),
+        ("greater_than", |l, r| l > r),
+        ("greater_than_or_equal", |l, r| l >= r),
+        ("equal", |l, r| l == r),
+        ("not_equal", |l, r| l != r),
+    ];
+    for (left, right) in [
+        (BigInt, Real),
+        (BigInt, Double),
+        (Real, BigInt),
+        (Double, BigInt),
+    ] {
+        for (name, expected) in cmps {
+            let func = registry.build(name, &[left, right]).unwrap();
+            let result = func
+                .eval_expr(&[&array_of_type(left, &LEFT), &array_of_type(right, &RIGHT)])
+                .unwrap();
+            let result: BoolArray = result.try_into().unwrap();
+            let expected = LEFT
+                .iter()
+                .zip(RIGHT.iter())
+                .map(|(l, r)| Some(expected((*l)?, (*r)?)))
+                .collect::<Vec<_>>();
+            assert_eq!(result.iter().collect::<Vec<_>>(), expected, "{name}");
+        }
+    }
+}
+
+#[test]
+fn cmp_float_nan() {
+    use type_rust::expr::Expression;
+
+    let values = [f64::NAN, f64::INFINITY, 0.0, -0.0, f64::NAN];
+    let (left, right): (ArrayImpl, ArrayImpl) = {
+        let (mut l, mut r) = (
+            F64ArrayBuilder::with_capacity(4),
+            F64ArrayBuilder::with_capacity(4),
+        );
+        for pair in values.windows(2) {
+            l.push(Some(pair[0]));
+            r.push(Some(pair[1]));
+        }
+        (l.finish().into(), r.finish().into())
+    };
+    let eval = |func: Box<dyn Expression>| -> Vec<Option<bool>> {
+        let result: BoolArray = func
+            .eval_expr(&[&left, &right])
+            .unwrap()
+            .try_into()
+            .unwrap();
+        result.iter().collect()
+    };
+    type Cmp<F> = BinaryExpression<F64Array, F64Array, BoolArray, F>;
+
+    // NaN > Infinity, Infinity > 0, 0 = -0, -0 < NaN.
+    assert_eq!(
+        eval(Box::new(Cmp::new(ExprCmpGt::<_, _, F64Array>(PhantomData)))),
+        vec![Some(true), Some(true), Some(false), Some(false)]
+    );
+    assert_eq!(
+        eval(Box::new(Cmp::new(ExprCmpEq::<_, _, F64Array>(PhantomData)))),
+        vec![Some(false), Some(false), Some(true), Some(false)]
+    );
+    assert_eq!(
+        eval(Box::new(Cmp::new(ExprCmpLe::<_, _, F64Array>(PhantomData)))),
+        vec![Some(false), Some(false), Some(true), Some(true)]
+    );
+
+    // NaN = NaN.
+    let nan: ArrayImpl = {
+        let mut builder = F64ArrayBuilder::with_capacity(1);
+        builder.push(Some(f64::NAN));
+        builder.finish().into()
+    };
+    let result = Cmp::new(ExprCmpEq::<_, _, F64Array>(PhantomData))
+        .eval_batch(&nan, &nan)
+        .unwrap();
+    let result: BoolArray = result.try_into().unwrap();
+    assert_eq!(result.get(0), Some(true));
+}
+
+#[test]
+fn cmp_string_bytewise() {
+    let build = |values: &[&str]| -> ArrayImpl {
+        let mut builder = StringArrayBuilder::with_capacity(values.len());
+        for value in values {
+            builder.push(Some(value));
+        }
+        builder.finish().into()
+    };
+    let left = build(&["B", "a", "abc", "é", ""]);
+    let right = build(&["a", "abc", "abc", "z", "a"]);
+    type Cmp<F> = BinaryExpression<StringArray, StringArray, BoolArray, F>;
+    let result = Cmp::new(ExprCmpLt::<_, _, StringArray>(PhantomData))
+        .eval_batch(&left, &right)
+        .unwrap();
+    let result: BoolArray = result.try_into().unwrap();
+    assert_eq!(
+        result.iter().collect::<Vec<_>>(),
+        vec![Some(true), Some(true), Some(false), Some(false), Some(true)]
+    );
+}
+
+#[test]
+fn cmp_scalar_ref_of_different_types() {
+    use std::cmp::Ordering;
+
+    use type_rust::scalar::ScalarRefImpl;
+
+    let (one, two) = (ScalarRefImpl::Int32(1), ScalarRefImpl::Int32(2));
+    assert_eq!(one.try_sql_cmp(&two), Some(Ordering::Less));
+    assert_eq!(one.try_sql_cmp(&ScalarRefImpl::Int64(1)), None);
+}
@@ -0,0 +1,93 @@
+//! Tests implicit casts and common supertypes of types.
+
+use type_rust::dataType::DataType::{self, *};
+use type_rust::dataType::coercion::{CoercionError, common_supertype_of};
+
+const DECIMAL: DataType = Decimal {
+    scale: 2,
+    precision: 10,
+};
+
+#[test]
+fn implicit_casts() {
+    assert_eq!(Integer.implicit_cast_cost(&Integer), Some(0));
+    assert_eq!(SmallInt.implicit_cast_cost(&Integer), Some(1));
+    assert_eq!(SmallInt.implicit_cast_cost(&Double), Some(5));
+    assert_eq!(SmallInt.implicit_cast_cost(&Real), Some(4));
+    assert_eq!(BigInt.implicit_cast_cost(&Double), Some(3));
+    assert_eq!(Char { width: 3 }.implicit_cast_cost(&Varchar), Some(1));
+    // Narrowing casts and casts between unrelated types are explicit.
+    assert_eq!(Integer.implicit_cast_cost(&SmallInt), None);
+    assert_eq!(Double.implicit_cast_cost(&Real), None);
+    // `real` cannot hold all values of `integer` and `bigint`.
+    assert_eq!(Integer.implicit_cast_cost(&Real), None);
+    assert_eq!(BigInt.implicit_cast_cost(&Real), None);
+    assert_eq!(Varchar.implicit_cast_cost(&Char { width: 3 }), None);
+    assert_eq!(Boolean.implicit_cast_cost(&Integer), None);
+    assert_eq!(Integer.implicit_cast_cost(&Varchar), None);
+}
+
+#[test]
+fn common_supertypes() {
+    let cases = [
+        (SmallInt, Integer, Integer),
+        (BigInt, SmallInt, BigInt),
+        (SmallInt, Real, Real),
+        (Integer, Real, Double),
+        (BigInt, Real, Double),
+        (BigInt, Double, Double),
+        (Real, Double, Double),
+        (DECIMAL, Real, Real),
+        (Char { width: 2 }, Char { width: 2 }, Char { width: 2 }),
+        (Char { width: 2 }, Char { width: 5 }, Varchar),
+        (Char { width: 2 }, Varchar, Varchar),
+        (Boolean, Boolean, Boolean),
+    ];
+    for (a, b, expected) in cases {
+        assert_eq!(a.common_supertype(&b).unwrap(), expected, "{a} and {b}");
+        assert_eq!(b.common_supertype(&a).unwrap(), expected, "{b} and {a}");
+    }
+
+    for (a, b) in [
+        (Integer, Varchar),
+        (Boolean, SmallInt),
+        (Char { width: 1 }, Double),
+    ] {
+        let err = a.common_supertype(&b).unwrap_err();
+        assert!(
+            matches!(err, CoercionError::Incompatible(..)),
+            "{a} and {b}"
+        );
+    }
+}
+
+#[test]
+fn decimal_supertype_is_unsupported() {
+    // Decimals do not have a physical array yet, so values cannot be cast into them.
+    let err = Integer.common_supertype(&DECIMAL).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "common type decimal(12, 2) does not have a physical array yet"
+    );
+    for (a, b) in [(BigInt, DECIMAL), (DECIMAL, DECIMAL)] {
+        let err = a.common_supertype(&b).unwrap_err();
+        assert!(matches!(err, CoercionError::Unsupported(..)), "{a} and {b}");
+    }
+}
+
+#[test]
+fn common_supertype_of_many() {
+    assert_eq!(common_supertype_of(&[]).unwrap(), None);
+    assert_eq!(common_supertype_of(&[Real]).unwrap(), Some(Real));
+    assert_eq!(
+        common_supertype_of(&[SmallInt, BigInt, Integer]).unwrap(),
+        Some(BigInt)
+    );
+    assert_eq!(
+        common_supertype_of(&[Char { width: 1 }, Char { width: 1 }, Varchar]).unwrap(),
+        Some(Varchar)
+    );
+    assert!(common_supertype_of(&[Integer, Real, Boolean]).is_err());
+    assert!(common_supertype_of(&[DECIMAL]).is_err());
+    assert!(common_supertype_of(&[SmallInt, DECIMAL, Integer]).is_err());
+}
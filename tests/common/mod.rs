@@ -0,0 +1,86 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use type_rust::TypeMismatch;
+use type_rust::array::*;
+
+/// Build an array of type `A` from `values`.
+pub fn array<A: Array>(values: &[Option<A::OwnedItem>]) -> ArrayImpl {
+    let mut builder = A::Builder::with_capacity(values.len());
+    for value in values {
+        builder.push(value.as_ref().map(|v| v.as_scalar_ref()));
+    }
+    builder.finish().into()
+}
+
+/// Build an `integer` array from `values`.
+pub fn ints(values: &[Option<i32>]) -> ArrayImpl {
+    array::<I32Array>(values)
+}
+
+/// Build a string array from `values`.
+pub fn strings(values: &[Option<&str>]) -> ArrayImpl {
+    let mut builder = StringArrayBuilder::with_capacity(values.len());
+    for value in values {
+        builder.push(*value);
+    }
+    builder.finish().into()
+}
+
+/// Get the values of `array`, which must be of type `A`.
+pub fn values<A: Array>(array: &ArrayImpl) -> Vec<Option<A::OwnedItem>>
+where
+    for<'a> &'a A: TryFrom<&'a ArrayImpl, Error = TypeMismatch>,
+{
+    let array: &A = array.try_into().unwrap();
+    array
+        .iter()
+        .map(|v| v.map(|v| v.to_owned_scalar()))
+        .collect()
+}
+
+/// Format the rows of `batches`, so that results of operators can be compared.
+pub fn rows(batches: &[Batch]) -> Vec<String> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            (0..batch.num_rows()).map(move |row| {
+                let values = batch
+                    .columns()
+                    .iter()
+                    .map(|column| column.get(row))
+                    .collect::<Vec<_>>();
+                format!("{values:?}")
+            })
+        })
+        .collect()
+}
+
+/// Format the rows of `batches` in sorted order, for results whose order is unspecified.
+pub fn sorted_rows(batches: &[Batch]) -> Vec<String> {
+    let mut rows = rows(batches);
+    rows.sort();
+    rows
+}
+
+/// Get the message of the error of `result`, which must fail.
+pub fn error<T>(result: anyhow::Result<T>) -> String {
+    match result {
+        Ok(_) => panic!("expect an error"),
+        Err(err) => err.to_string(),
+    }
+}
+
+/// Create a new empty directory for spill files, named after the test using it.
+pub fn spill_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("type_rust-test-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Number of files in `dir`, which are spill files not yet removed.
+pub fn num_files(dir: &std::path::Path) -> usize {
+    std::fs::read_dir(dir).unwrap().count()
+}
@@ -0,0 +1,186 @@
+//! Tests the conditional expressions `CASE`, `IF`, `COALESCE` and `NULLIF`.
+
+mod common;
+
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::conditional::{CaseExpression, CoalesceExpression, NullIfExpression};
+use type_rust::expr::tree::{CallExpression, InputRef, Literal};
+use type_rust::expr::{Expression, FunctionRegistry};
+use type_rust::scalar::ScalarImpl;
+
+use common::{array, ints, strings, values};
+
+fn input(index: usize) -> Box<dyn Expression> {
+    Box::new(InputRef::new(index))
+}
+
+fn literal(value: Option<ScalarImpl>, data_type: DataType) -> Box<dyn Expression> {
+    Box::new(Literal::new(value, data_type).unwrap())
+}
+
+fn int(value: i32) -> Box<dyn Expression> {
+    literal(Some(ScalarImpl::Int32(value)), DataType::Integer)
+}
+
+/// Call function `name` with `args` of `integer`.
+fn call(name: &str, args: Vec<Box<dyn Expression>>) -> Box<dyn Expression> {
+    let types = vec![DataType::Integer; args.len()];
+    let func = FunctionRegistry::with_builtins()
+        .build(name, &types)
+        .unwrap();
+    Box::new(CallExpression::new(func, args))
+}
+
+#[test]
+fn case_when() {
+    // CASE WHEN x < 0 THEN -1 WHEN x = 0 THEN 0 ELSE 1 END
+    let case = CaseExpression::new(
+        vec![
+            (call("less_than", vec![input(0), int(0)]), int(-1)),
+            (call("equal", vec![input(0), int(0)]), int(0)),
+        ],
+        Some(int(1)),
+        DataType::Integer,
+    );
+    let x = ints(&[Some(-5), Some(0), Some(3), None]);
+    let result = case.eval_expr(&[&x]).unwrap();
+    // `NULL` conditions are not matched.
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(-1), Some(0), Some(1), Some(1)]
+    );
+
+    // Without `ELSE`, unmatched rows are `NULL`.
+    let case = CaseExpression::new(
+        vec![(call("greater_than", vec![input(0), int(0)]), input(0))],
+        None,
+        DataType::Integer,
+    );
+    let result = case.eval_expr(&[&x]).unwrap();
+    assert_eq!(values::<I32Array>(&result), [None, None, Some(3), None]);
+
+    // The first matching branch is taken.
+    let case = CaseExpression::new(
+        vec![
+            (call("greater_than", vec![input(0), int(-10)]), int(1)),
+            (call("greater_than", vec![input(0), int(0)]), int(2)),
+        ],
+        None,
+        DataType::Integer,
+    );
+    let result = case.eval_expr(&[&x]).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(1), Some(1), Some(1), None]
+    );
+}
+
+#[test]
+fn case_evaluates_branches_lazily() {
+    // CASE WHEN x = 0 THEN 0 ELSE 10 / x END
+    let case = CaseExpression::new(
+        vec![(call("equal", vec![input(0), int(0)]), int(0))],
+        Some(call("divide", vec![int(10), input(0)])),
+        DataType::Integer,
+    );
+    let x = ints(&[Some(2), Some(0), Some(-5), Some(0)]);
+    let result = case.eval_expr(&[&x]).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(5), Some(0), Some(-2), Some(0)]
+    );
+
+    // A branch selected by no row is not evaluated at all.
+    let x = ints(&[Some(0), Some(0)]);
+    let result = case.eval_expr(&[&x]).unwrap();
+    assert_eq!(values::<I32Array>(&result), [Some(0), Some(0)]);
+
+    // The division fails if the branch is taken.
+    let unguarded = call("divide", vec![int(10), input(0)]);
+    assert!(unguarded.eval_expr(&[&x]).is_err());
+}
+
+#[test]
+fn if_then_else() {
+    let cond = array::<BoolArray>(&[Some(true), Some(false), None]);
+    let a = strings(&[Some("a1"), Some("a2"), Some("a3")]);
+    let b = strings(&[Some("b1"), Some("b2"), Some("b3")]);
+    let expr = CaseExpression::if_then_else(input(0), input(1), input(2), DataType::Varchar);
+    let result = expr.eval_expr(&[&cond, &a, &b]).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        [
+            Some("a1".to_string()),
+            Some("b2".to_string()),
+            Some("b3".to_string())
+        ]
+    );
+
+    // The condition must be a boolean.
+    let expr = CaseExpression::if_then_else(input(1), input(1), input(2), DataType::Varchar);
+    assert!(expr.eval_expr(&[&cond, &a, &b]).is_err());
+    // Values must be of the return type.
+    let expr = CaseExpression::if_then_else(input(0), input(1), input(2), DataType::Integer);
+    assert!(expr.eval_expr(&[&cond, &a, &b]).is_err());
+}
+
+#[test]
+fn coalesce() {
+    let a = ints(&[Some(1), None, None, None]);
+    let b = ints(&[Some(2), Some(2), None, None]);
+    let c = ints(&[Some(3), Some(3), Some(3), None]);
+    let expr = CoalesceExpression::new(vec![input(0), input(1), input(2)], DataType::Integer);
+    let result = expr.eval_expr(&[&a, &b, &c]).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(1), Some(2), Some(3), None]
+    );
+
+    // Arguments are only evaluated on rows where all previous ones are `NULL`.
+    let x = ints(&[Some(0), None, Some(5)]);
+    let expr = CoalesceExpression::new(
+        vec![input(0), call("divide", vec![int(1), int(0)])],
+        DataType::Integer,
+    );
+    assert!(expr.eval_expr(&[&x]).is_err());
+    let x = ints(&[Some(0), Some(1), Some(5)]);
+    let result = expr.eval_expr(&[&x]).unwrap();
+    assert_eq!(values::<I32Array>(&result), [Some(0), Some(1), Some(5)]);
+
+    let expr = CoalesceExpression::new(
+        vec![
+            input(0),
+            literal(
+                Some(ScalarImpl::String("default".to_string())),
+                DataType::Varchar,
+            ),
+        ],
+        DataType::Varchar,
+    );
+    let s = strings(&[Some("a"), None]);
+    let result = expr.eval_expr(&[&s]).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        [Some("a".to_string()), Some("default".to_string())]
+    );
+}
+
+#[test]
+fn nullif() {
+    let a = ints(&[Some(1), Some(2), None, Some(4)]);
+    let b = ints(&[Some(1), Some(3), Some(3), None]);
+    let expr = NullIfExpression::new(input(0), input(1));
+    let result = expr.eval_expr(&[&a, &b]).unwrap();
+    assert_eq!(values::<I32Array>(&result), [None, Some(2), None, Some(4)]);
+
+    // `NaN` equals `NaN`.
+    let a = array::<F64Array>(&[Some(f64::NAN), Some(0.0)]);
+    let b = array::<F64Array>(&[Some(f64::NAN), Some(-0.0)]);
+    let result = expr.eval_expr(&[&a, &b]).unwrap();
+    assert_eq!(values::<F64Array>(&result), [None, None]);
+
+    // Both arguments must be of the same type.
+    let s = strings(&[Some("a"), Some("b")]);
+    assert!(expr.eval_expr(&[&a, &s]).is_err());
+}
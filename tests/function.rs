@@ -0,0 +1,197 @@
+//! Tests vectorized functions generated by the `#[function]` macro.
+
+mod common;
+
+use anyhow::{Result, bail};
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::{FunctionRegistry, function};
+
+use common::{array, error, strings, values};
+
+#[function("twice(int16) -> int16")]
+#[function("twice(int32) -> int32")]
+#[function("twice(float64) -> float64")]
+fn twice<T: std::ops::Add<Output = T> + Copy>(v: T) -> T {
+    v + v
+}
+
+#[function("half(int32) -> int32")]
+fn half(v: i32) -> Option<i32> {
+    (v % 2 == 0).then_some(v / 2)
+}
+
+#[function("checked_neg(int32) -> int32")]
+fn checked_neg(v: i32) -> Result<i32> {
+    match v.checked_neg() {
+        Some(v) => Ok(v),
+        None => bail!("integer out of range"),
+    }
+}
+
+#[function("non_zero(int32) -> int32")]
+fn non_zero(v: i32) -> Result<Option<i32>> {
+    if v < 0 {
+        bail!("negative value");
+    }
+    Ok((v != 0).then_some(v))
+}
+
+#[function("or_default(int32, int32) -> int32")]
+fn or_default(v: Option<i32>, default: i32) -> i32 {
+    v.unwrap_or(default)
+}
+
+#[function("shout(varchar) -> varchar")]
+fn shout(s: &str, writer: &mut StringWriter) {
+    writer.write_str(&s.to_uppercase());
+    writer.write_char('!');
+}
+
+#[function("initial(varchar) -> varchar")]
+fn initial(s: &str, writer: &mut StringWriter) -> Option<()> {
+    writer.write_str("initial ");
+    writer.write_char(s.chars().next()?);
+    Some(())
+}
+
+#[function("repeat_n(varchar, int32) -> varchar")]
+fn repeat_n(s: &str, n: i32, writer: &mut StringWriter) -> Result<()> {
+    if n < 0 {
+        bail!("negative count");
+    }
+    (0..n).for_each(|_| writer.write_str(s));
+    Ok(())
+}
+
+fn registry() -> FunctionRegistry {
+    let mut registry = FunctionRegistry::new();
+    register_twice(&mut registry).unwrap();
+    register_half(&mut registry).unwrap();
+    register_checked_neg(&mut registry).unwrap();
+    register_non_zero(&mut registry).unwrap();
+    register_or_default(&mut registry).unwrap();
+    register_shout(&mut registry).unwrap();
+    register_initial(&mut registry).unwrap();
+    register_repeat_n(&mut registry).unwrap();
+    registry
+}
+
+fn eval(name: &str, args: &[(DataType, ArrayImpl)]) -> Result<ArrayImpl> {
+    let types = args.iter().map(|(ty, _)| *ty).collect::<Vec<_>>();
+    let inputs = args.iter().map(|(_, array)| array).collect::<Vec<_>>();
+    registry().build(name, &types)?.eval_expr(&inputs)
+}
+
+fn ints(values: &[Option<i32>]) -> (DataType, ArrayImpl) {
+    (DataType::Integer, array::<I32Array>(values))
+}
+
+#[test]
+fn stacked_attributes() {
+    let registry = registry();
+    for ty in [DataType::SmallInt, DataType::Integer, DataType::Double] {
+        assert_eq!(registry.resolve("twice", &[ty]).unwrap().ret, ty);
+    }
+    // Other types are cast into the closest registered signature.
+    assert_eq!(
+        registry.resolve("twice", &[DataType::Real]).unwrap().ret,
+        DataType::Double
+    );
+
+    let result = eval(
+        "twice",
+        &[(DataType::SmallInt, array::<I16Array>(&[Some(3), None]))],
+    )
+    .unwrap();
+    assert_eq!(values::<I16Array>(&result), vec![Some(6), None]);
+    let result = eval(
+        "twice",
+        &[(DataType::Double, array::<F64Array>(&[Some(1.5)]))],
+    )
+    .unwrap();
+    assert_eq!(values::<F64Array>(&result), vec![Some(3.0)]);
+}
+
+#[test]
+fn option_return() {
+    let result = eval("half", &[ints(&[Some(4), Some(3), None])]).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(2), None, None]);
+}
+
+#[test]
+fn result_return() {
+    let result = eval("checked_neg", &[ints(&[Some(1), None])]).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(-1), None]);
+    // An error of any row fails the whole batch.
+    let err = error(eval("checked_neg", &[ints(&[Some(1), Some(i32::MIN)])]));
+    assert_eq!(err, "integer out of range");
+
+    let result = eval("non_zero", &[ints(&[Some(1), Some(0), None])]).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(1), None, None]);
+    assert!(eval("non_zero", &[ints(&[Some(-1)])]).is_err());
+}
+
+#[test]
+fn nullable_arguments() {
+    let result = eval(
+        "or_default",
+        &[
+            ints(&[Some(1), None, None]),
+            ints(&[Some(0), Some(2), None]),
+        ],
+    )
+    .unwrap();
+    // The non-nullable argument still produces `NULL`.
+    assert_eq!(values::<I32Array>(&result), vec![Some(1), Some(2), None]);
+}
+
+#[test]
+fn writer_parameter() {
+    let input = strings(&[Some("hey"), None, Some("")]);
+    let result = eval("shout", &[(DataType::Varchar, input.clone())]).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![Some("HEY!".to_string()), None, Some("!".to_string())]
+    );
+
+    // Data written before returning `None` is discarded.
+    let result = eval("initial", &[(DataType::Varchar, input)]).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![Some("initial h".to_string()), None, None]
+    );
+
+    let result = eval(
+        "repeat_n",
+        &[
+            (DataType::Varchar, strings(&[Some("ab"), Some("c")])),
+            ints(&[Some(2), Some(0)]),
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![Some("abab".to_string()), Some(String::new())]
+    );
+    assert!(
+        eval(
+            "repeat_n",
+            &[
+                (DataType::Varchar, strings(&[Some("ab")])),
+                ints(&[Some(-1)]),
+            ],
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn wrong_inputs() {
+    let func = registry().build("half", &[DataType::Integer]).unwrap();
+    let (_, a) = ints(&[Some(1)]);
+    let err = error(func.eval_expr(&[&a, &a]));
+    assert_eq!(err, "Expect 1 inputs for function half");
+    let s = strings(&[Some("1")]);
+    assert!(func.eval_expr(&[&s]).is_err());
+}
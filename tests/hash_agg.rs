@@ -0,0 +1,390 @@
+//! Tests hash aggregation for `GROUP BY`.
+
+mod common;
+
+use anyhow::Result;
+use type_rust::aggregate::build_aggregate;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::executor::group_table::{GroupTable, KeyDeserializer, KeySerializer};
+use type_rust::executor::{AggCall, HashAggregate, SpillConfig};
+
+use common::{array, error, ints, num_files, rows, sorted_rows, spill_dir, strings};
+
+/// `count(*)` and `sum` of column `arg`.
+fn count_and_sum(arg: usize) -> Vec<AggCall> {
+    vec![
+        AggCall::new(build_aggregate("count", &[]).unwrap(), vec![]),
+        AggCall::new(
+            build_aggregate("sum", &[DataType::Integer]).unwrap(),
+            vec![arg],
+        ),
+    ]
+}
+
+fn batch(columns: Vec<ArrayImpl>) -> Result<Batch> {
+    Batch::new(columns)
+}
+
+#[test]
+fn group_by_keys_with_nulls() {
+    let agg = HashAggregate::new(vec![0], vec![DataType::Varchar], count_and_sum(1)).unwrap();
+    assert_eq!(
+        agg.output_types(),
+        [DataType::Varchar, DataType::BigInt, DataType::BigInt]
+    );
+    let batches = [
+        batch(vec![
+            strings(&[Some("a"), None, Some("b"), Some("a")]),
+            ints(&[Some(1), Some(2), Some(3), None]),
+        ]),
+        batch(vec![
+            strings(&[None, Some("c"), Some("a")]),
+            ints(&[Some(10), Some(20), Some(30)]),
+        ]),
+    ];
+    let output = agg.aggregate(batches).unwrap();
+    // Groups are in the order they first appear, and `NULL` keys form one group.
+    assert_eq!(
+        rows(&output),
+        [
+            "[Some(String(\"a\")), Some(Int64(3)), Some(Int64(31))]",
+            "[None, Some(Int64(2)), Some(Int64(12))]",
+            "[Some(String(\"b\")), Some(Int64(1)), Some(Int64(3))]",
+            "[Some(String(\"c\")), Some(Int64(1)), Some(Int64(20))]",
+        ]
+    );
+}
+
+#[test]
+fn group_by_many_columns() {
+    let agg = HashAggregate::new(
+        vec![1, 0],
+        vec![DataType::Varchar, DataType::Integer],
+        count_and_sum(0),
+    )
+    .unwrap();
+    // Keys of different columns do not run into each other, e.g. ("ab", "c") and ("a", "bc").
+    let input = batch(vec![
+        ints(&[Some(1), Some(1), Some(2), None, None]),
+        strings(&[Some("x"), Some("x"), Some("x"), Some("x"), Some("")]),
+    ]);
+    let output = agg.aggregate([input]).unwrap();
+    assert_eq!(
+        rows(&output),
+        [
+            "[Some(String(\"x\")), Some(Int32(1)), Some(Int64(2)), Some(Int64(2))]",
+            "[Some(String(\"x\")), Some(Int32(2)), Some(Int64(1)), Some(Int64(2))]",
+            "[Some(String(\"x\")), None, Some(Int64(1)), None]",
+            "[Some(String(\"\")), None, Some(Int64(1)), None]",
+        ]
+    );
+}
+
+#[test]
+fn group_by_many_groups() {
+    // Enough groups to grow the table several times.
+    let agg = HashAggregate::new(vec![0], vec![DataType::Integer], count_and_sum(0)).unwrap();
+    let batches = (0..4).map(|_| batch(vec![ints(&(0..1000).map(Some).collect::<Vec<_>>())]));
+    let output = agg.aggregate(batches).unwrap();
+    let rows = rows(&output);
+    assert_eq!(rows.len(), 1000);
+    for (key, row) in rows.iter().enumerate() {
+        let expected = format!(
+            "[Some(Int32({key})), Some(Int64(4)), Some(Int64({}))]",
+            key * 4
+        );
+        assert_eq!(row, &expected);
+    }
+}
+
+#[test]
+fn aggregate_without_keys() {
+    // One row is produced even without input.
+    let agg = HashAggregate::new(vec![], vec![], count_and_sum(0)).unwrap();
+    let output = agg.aggregate([]).unwrap();
+    assert_eq!(rows(&output), ["[Some(Int64(0)), None]"]);
+
+    let agg = HashAggregate::new(vec![], vec![], count_and_sum(0)).unwrap();
+    let input = batch(vec![ints(&[Some(1), Some(2), None])]);
+    let output = agg.aggregate([input]).unwrap();
+    assert_eq!(rows(&output), ["[Some(Int64(3)), Some(Int64(3))]"]);
+
+    // Without keys nor calls, there is one row of no columns.
+    let agg = HashAggregate::new(vec![], vec![], vec![]).unwrap();
+    let output = agg.aggregate([]).unwrap();
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].num_rows(), 1);
+
+    // With keys, there is no row without input.
+    let agg = HashAggregate::new(vec![0], vec![DataType::Integer], count_and_sum(0)).unwrap();
+    assert!(agg.aggregate([]).unwrap().is_empty());
+}
+
+#[test]
+fn merge_partial_aggregations() {
+    let new = || HashAggregate::new(vec![0], vec![DataType::Varchar], count_and_sum(1)).unwrap();
+    let mut left = new();
+    left.update(
+        &batch(vec![
+            strings(&[Some("a"), Some("b")]),
+            ints(&[Some(1), Some(2)]),
+        ])
+        .unwrap(),
+    )
+    .unwrap();
+    let mut right = new();
+    right
+        .update(
+            &batch(vec![
+                strings(&[Some("b"), Some("c"), None]),
+                ints(&[Some(3), Some(4), Some(5)]),
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+    left.merge(right).unwrap();
+    assert_eq!(left.num_groups(), 4);
+    assert_eq!(
+        sorted_rows(&left.finish().unwrap()),
+        [
+            "[None, Some(Int64(1)), Some(Int64(5))]",
+            "[Some(String(\"a\")), Some(Int64(1)), Some(Int64(1))]",
+            "[Some(String(\"b\")), Some(Int64(2)), Some(Int64(5))]",
+            "[Some(String(\"c\")), Some(Int64(1)), Some(Int64(4))]",
+        ]
+    );
+
+    let mut agg = new();
+    let other = HashAggregate::new(vec![], vec![], count_and_sum(0)).unwrap();
+    assert!(agg.merge(other).is_err());
+}
+
+/// Batches of `(k, v)`, where `k` is one of `num_keys` pseudo-random keys or `NULL`.
+fn many_groups(num_batches: usize, num_keys: usize, seed: u64) -> Vec<Batch> {
+    let mut seed = seed;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 33) as usize
+    };
+    (0..num_batches)
+        .map(|_| {
+            let keys = (0..50)
+                .map(|_| Some(next() % (num_keys + 1)).filter(|k| *k != 0))
+                .map(|k| k.map(|k| format!("key{k}")))
+                .collect::<Vec<_>>();
+            let keys = keys.iter().map(|k| k.as_deref()).collect::<Vec<_>>();
+            let values = (0..50)
+                .map(|_| Some(next() as i32 % 100))
+                .collect::<Vec<_>>();
+            batch(vec![strings(&keys), ints(&values)]).unwrap()
+        })
+        .collect()
+}
+
+fn new_spilling(config: &SpillConfig) -> HashAggregate {
+    HashAggregate::new(vec![0], vec![DataType::Varchar], count_and_sum(1))
+        .unwrap()
+        .with_spill_config(config.clone())
+}
+
+#[test]
+fn group_by_with_spilling() {
+    let dir = spill_dir("hash-agg");
+    let input = many_groups(40, 500, 1);
+    let expected = {
+        let agg = HashAggregate::new(vec![0], vec![DataType::Varchar], count_and_sum(1)).unwrap();
+        sorted_rows(&agg.aggregate(input.iter().cloned().map(Ok)).unwrap())
+    };
+    assert!(expected.len() > 400);
+    // Spill once some groups are found, or spill rows of almost all groups and again when
+    // aggregating each partition.
+    for memory_budget in [4096, 0] {
+        let config = SpillConfig {
+            dir: dir.clone(),
+            memory_budget,
+        };
+        let mut agg = new_spilling(&config);
+        for batch in &input {
+            agg.update(batch).unwrap();
+        }
+        assert!(num_files(&dir) > 0);
+        assert!(agg.num_groups() < 500);
+        let output = agg.finish().unwrap();
+        assert!(output.len() > 1);
+        // Each group is produced exactly once.
+        assert_eq!(sorted_rows(&output), expected, "{memory_budget}");
+        assert_eq!(num_files(&dir), 0);
+    }
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn merge_spilled_aggregations() {
+    let dir = spill_dir("hash-agg-merge");
+    let left = many_groups(10, 100, 2);
+    let right = many_groups(10, 100, 3);
+    let expected = {
+        let agg = HashAggregate::new(vec![0], vec![DataType::Varchar], count_and_sum(1)).unwrap();
+        let input = left.iter().chain(&right).cloned().map(Ok);
+        sorted_rows(&agg.aggregate(input).unwrap())
+    };
+    let spilling = SpillConfig {
+        dir: dir.clone(),
+        memory_budget: 0,
+    };
+    let in_memory = SpillConfig {
+        dir: dir.clone(),
+        memory_budget: usize::MAX,
+    };
+    // Groups merged into an aggregation that has spilled may have spilled rows.
+    for (left_config, right_config) in [
+        (&spilling, &in_memory),
+        (&in_memory, &spilling),
+        (&spilling, &spilling),
+    ] {
+        let mut agg = new_spilling(left_config);
+        let mut other = new_spilling(right_config);
+        for batch in &left {
+            agg.update(batch).unwrap();
+        }
+        for batch in &right {
+            other.update(batch).unwrap();
+        }
+        agg.merge(other).unwrap();
+        assert_eq!(sorted_rows(&agg.finish().unwrap()), expected);
+        assert_eq!(num_files(&dir), 0);
+    }
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+/// `string_agg(v, ',')` grouped by `k` of two groups, whose states grow with long values.
+fn long_strings(config: &SpillConfig) -> (HashAggregate, Batch) {
+    let calls = vec![AggCall::new(
+        build_aggregate("string_agg", &[DataType::Varchar, DataType::Varchar]).unwrap(),
+        vec![1, 2],
+    )];
+    let agg = HashAggregate::new(vec![0], vec![DataType::Integer], calls)
+        .unwrap()
+        .with_spill_config(config.clone());
+    let value = "x".repeat(1000);
+    let input = batch(vec![
+        ints(&[Some(1), Some(2), Some(1), Some(2)]),
+        strings(&[Some(value.as_str()); 4]),
+        strings(&[Some(","); 4]),
+    ])
+    .unwrap();
+    (agg, input)
+}
+
+#[test]
+fn spill_by_sizes_of_states() {
+    let dir = spill_dir("hash-agg-states");
+    let config = SpillConfig {
+        dir: dir.clone(),
+        memory_budget: 4096,
+    };
+    // Two groups are far below the budget, but not their states.
+    let (mut agg, input) = long_strings(&config);
+    agg.update(&input).unwrap();
+    let new_group = batch(vec![
+        ints(&[Some(3)]),
+        strings(&[Some("y")]),
+        strings(&[Some(",")]),
+    ])
+    .unwrap();
+    agg.update(&new_group).unwrap();
+    assert_eq!(agg.num_groups(), 2);
+    assert!(num_files(&dir) > 0);
+    let output = agg.finish().unwrap();
+    assert_eq!(sorted_rows(&output).len(), 3);
+    assert_eq!(num_files(&dir), 0);
+
+    // Aggregations within the budget may exceed it once merged.
+    let config = SpillConfig {
+        dir: dir.clone(),
+        memory_budget: 6000,
+    };
+    let (mut agg, input) = long_strings(&config);
+    agg.update(&input).unwrap();
+    let (mut other, input) = long_strings(&config);
+    other.update(&input).unwrap();
+    assert_eq!(num_files(&dir), 0);
+    agg.merge(other).unwrap();
+    agg.update(&new_group).unwrap();
+    assert_eq!(agg.num_groups(), 2);
+    assert!(num_files(&dir) > 0);
+    assert_eq!(sorted_rows(&agg.finish().unwrap()).len(), 3);
+    assert_eq!(num_files(&dir), 0);
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn key_type_errors() {
+    assert!(HashAggregate::new(vec![0], vec![], vec![]).is_err());
+
+    let mut agg = HashAggregate::new(vec![0], vec![DataType::BigInt], count_and_sum(0)).unwrap();
+    let input = batch(vec![ints(&[Some(1)])]).unwrap();
+    assert_eq!(
+        error(agg.update(&input)),
+        "Type mismatch on conversion: expected Int64, get Int32"
+    );
+}
+
+#[test]
+fn serialized_keys() {
+    let ints = ints(&[Some(1), None, Some(-1)]);
+    let floats = array::<F64Array>(&[Some(0.0), Some(-0.0), Some(f64::NAN)]);
+    let strings = strings(&[Some("é"), Some(""), None]);
+    let columns = [&ints, &floats, &strings];
+    let serializer = KeySerializer::new(&columns);
+
+    let mut table = GroupTable::new();
+    let mut buf = vec![];
+    for row in 0..3 {
+        buf.clear();
+        serializer.serialize(row, &mut buf).unwrap();
+        assert_eq!(table.get_or_insert(&buf), (row, true));
+        assert_eq!(table.get_or_insert(&buf), (row, false));
+        assert_eq!(table.get(&buf), Some(row));
+    }
+    assert!(!serializer.has_null(0));
+    assert!(serializer.has_null(1));
+
+    // `0.0` and `-0.0` are the same key, as are all `NaN`s.
+    let zeros = array::<F64Array>(&[Some(-0.0), Some(-f64::NAN)]);
+    let mut keys = vec![];
+    for row in 0..2 {
+        let mut zero = vec![];
+        KeySerializer::new(&[&zeros])
+            .serialize(row, &mut zero)
+            .unwrap();
+        keys.push(zero);
+    }
+    let mut expected = vec![];
+    KeySerializer::new(&[&floats])
+        .serialize(0, &mut expected)
+        .unwrap();
+    assert_eq!(keys[0], expected);
+    expected.clear();
+    KeySerializer::new(&[&floats])
+        .serialize(2, &mut expected)
+        .unwrap();
+    assert_eq!(keys[1], expected);
+
+    let types = [DataType::Integer, DataType::Double, DataType::Varchar];
+    let columns = table.keys_to_columns(&types).unwrap();
+    let output = Batch::new(columns).unwrap();
+    assert_eq!(
+        rows(&[output]),
+        [
+            "[Some(Int32(1)), Some(Float64(0.0)), Some(String(\"é\"))]",
+            "[None, Some(Float64(0.0)), Some(String(\"\"))]",
+            "[Some(Int32(-1)), Some(Float64(NaN)), None]",
+        ]
+    );
+
+    let mut deserializer = KeyDeserializer::new(&[DataType::Integer], 1).unwrap();
+    assert!(deserializer.deserialize(&[1, 0]).is_err());
+    assert!(deserializer.deserialize(&[0, 0]).is_err());
+}
@@ -0,0 +1,301 @@
+//! Tests hash joins of all join types.
+
+mod common;
+
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::executor::{HashJoin, JoinType, SpillConfig};
+
+use common::{array, error, ints, num_files, rows, sorted_rows, spill_dir, strings};
+
+const JOIN_TYPES: [JoinType; 8] = [
+    JoinType::Inner,
+    JoinType::LeftOuter,
+    JoinType::RightOuter,
+    JoinType::FullOuter,
+    JoinType::LeftSemi,
+    JoinType::LeftAnti,
+    JoinType::RightSemi,
+    JoinType::RightAnti,
+];
+
+const TYPES: [DataType; 2] = [DataType::Integer, DataType::Varchar];
+
+/// Rows of `(k, s)` of the left input.
+fn left() -> Batch {
+    Batch::new(vec![
+        ints(&[Some(1), Some(2), Some(2), None, Some(4)]),
+        strings(&[Some("l1"), Some("l2"), Some("l2b"), Some("ln"), Some("l4")]),
+    ])
+    .unwrap()
+}
+
+/// Rows of `(k, t)` of the right input.
+fn right() -> Batch {
+    Batch::new(vec![
+        ints(&[Some(2), Some(2), Some(3), None, Some(1)]),
+        strings(&[Some("r2"), Some("r2b"), Some("r3"), Some("rn"), Some("r1")]),
+    ])
+    .unwrap()
+}
+
+/// Join [`left`] and [`right`] on `k`, and return the sorted output rows.
+fn join(join_type: JoinType) -> Vec<String> {
+    let join = HashJoin::new(join_type, TYPES.to_vec(), TYPES.to_vec(), vec![0], vec![0]).unwrap();
+    sorted_rows(&join.execute([Ok(left())], [Ok(right())]).unwrap())
+}
+
+/// Format sorted rows of one side, where keys of `None` are `NULL`s.
+fn singles(rows: &[(Option<i32>, &str)]) -> Vec<String> {
+    let mut rows = rows
+        .iter()
+        .map(|(k, s)| {
+            let k = k.map_or("None".to_string(), |k| format!("Some(Int32({k}))"));
+            format!("[{k}, Some(String({s:?}))]")
+        })
+        .collect::<Vec<_>>();
+    rows.sort();
+    rows
+}
+
+/// Format a row of a left row and a right row, either of which may be all `NULL`s.
+fn pair(l: Option<(Option<i32>, &str)>, r: Option<(Option<i32>, &str)>) -> String {
+    let side = |row: Option<(Option<i32>, &str)>| match row {
+        Some((k, s)) => {
+            let k = k.map_or("None".to_string(), |k| format!("Some(Int32({k}))"));
+            format!("{k}, Some(String({s:?}))")
+        }
+        None => "None, None".to_string(),
+    };
+    format!("[{}, {}]", side(l), side(r))
+}
+
+/// Rows of the inner join of [`left`] and [`right`].
+fn matched() -> Vec<String> {
+    vec![
+        pair(Some((Some(1), "l1")), Some((Some(1), "r1"))),
+        pair(Some((Some(2), "l2")), Some((Some(2), "r2"))),
+        pair(Some((Some(2), "l2")), Some((Some(2), "r2b"))),
+        pair(Some((Some(2), "l2b")), Some((Some(2), "r2"))),
+        pair(Some((Some(2), "l2b")), Some((Some(2), "r2b"))),
+    ]
+}
+
+fn sorted(mut rows: Vec<String>) -> Vec<String> {
+    rows.sort();
+    rows
+}
+
+#[test]
+fn inner_and_outer_joins() {
+    // `NULL` keys never match, not even other `NULL` keys.
+    assert_eq!(join(JoinType::Inner), sorted(matched()));
+
+    let mut expected = matched();
+    expected.push(pair(Some((None, "ln")), None));
+    expected.push(pair(Some((Some(4), "l4")), None));
+    assert_eq!(join(JoinType::LeftOuter), sorted(expected.clone()));
+
+    let mut right_outer = matched();
+    right_outer.push(pair(None, Some((Some(3), "r3"))));
+    right_outer.push(pair(None, Some((None, "rn"))));
+    assert_eq!(join(JoinType::RightOuter), sorted(right_outer));
+
+    expected.push(pair(None, Some((Some(3), "r3"))));
+    expected.push(pair(None, Some((None, "rn"))));
+    assert_eq!(join(JoinType::FullOuter), sorted(expected));
+}
+
+#[test]
+fn semi_and_anti_joins() {
+    // Each row is returned at most once, however many rows it matches.
+    assert_eq!(
+        join(JoinType::LeftSemi),
+        singles(&[(Some(1), "l1"), (Some(2), "l2"), (Some(2), "l2b")])
+    );
+    assert_eq!(
+        join(JoinType::LeftAnti),
+        singles(&[(None, "ln"), (Some(4), "l4")])
+    );
+    assert_eq!(
+        join(JoinType::RightSemi),
+        singles(&[(Some(2), "r2"), (Some(2), "r2b"), (Some(1), "r1")])
+    );
+    assert_eq!(
+        join(JoinType::RightAnti),
+        singles(&[(Some(3), "r3"), (None, "rn")])
+    );
+}
+
+#[test]
+fn empty_inputs() {
+    let new = |join_type| {
+        HashJoin::new(join_type, TYPES.to_vec(), TYPES.to_vec(), vec![0], vec![0]).unwrap()
+    };
+    let output = new(JoinType::LeftOuter).execute([Ok(left())], []).unwrap();
+    assert_eq!(rows(&output).len(), 5);
+    let output = new(JoinType::LeftAnti).execute([Ok(left())], []).unwrap();
+    assert_eq!(rows(&output).len(), 5);
+    let output = new(JoinType::Inner).execute([Ok(left())], []).unwrap();
+    assert!(rows(&output).is_empty());
+    let output = new(JoinType::RightAnti).execute([], [Ok(right())]).unwrap();
+    assert_eq!(rows(&output).len(), 5);
+    let output = new(JoinType::FullOuter).execute([], []).unwrap();
+    assert!(rows(&output).is_empty());
+}
+
+#[test]
+fn multi_column_keys() {
+    // Keys of `(varchar, double precision)`, where `0.0` equals `-0.0`.
+    let types = vec![DataType::Varchar, DataType::Double];
+    let left = Batch::new(vec![
+        strings(&[Some("a"), Some("a"), Some("ab"), Some("a")]),
+        array::<F64Array>(&[Some(1.0), Some(0.0), Some(1.0), None]),
+    ]);
+    let right = Batch::new(vec![
+        strings(&[Some("a"), Some("a"), Some("b"), Some("a")]),
+        array::<F64Array>(&[Some(1.0), Some(-0.0), Some(1.0), None]),
+    ]);
+    let join = HashJoin::new(
+        JoinType::Inner,
+        types.clone(),
+        types,
+        vec![0, 1],
+        vec![0, 1],
+    )
+    .unwrap();
+    let output = join.execute([left], [right]).unwrap();
+    assert_eq!(
+        sorted_rows(&output),
+        [
+            "[Some(String(\"a\")), Some(Float64(0.0)), Some(String(\"a\")), Some(Float64(-0.0))]",
+            "[Some(String(\"a\")), Some(Float64(1.0)), Some(String(\"a\")), Some(Float64(1.0))]",
+        ]
+    );
+}
+
+#[test]
+fn output_batch_size() {
+    // Every left row matches every right row of the same key.
+    let keys = |n| ints(&vec![Some(7); n]);
+    let left = Batch::new(vec![keys(30)]).unwrap();
+    let right = Batch::new(vec![keys(20)]).unwrap();
+    let types = vec![DataType::Integer];
+    let mut join = HashJoin::new(JoinType::Inner, types.clone(), types, vec![0], vec![0])
+        .unwrap()
+        .with_batch_size(64)
+        .unwrap();
+    join.build(right).unwrap();
+    let probed = join.probe(&left).unwrap();
+    assert_eq!(probed.len(), 600 / 64);
+    assert!(probed.iter().all(|batch| batch.num_rows() == 64));
+    // Rows left from the previous batch are gathered with the rows of the next one.
+    let probed = join.probe(&left).unwrap();
+    assert_eq!(probed.len(), 1200 / 64 - 600 / 64);
+    assert!(probed.iter().all(|batch| batch.num_rows() == 64));
+    let rest = join.finish().unwrap();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].num_rows(), 1200 % 64);
+}
+
+/// Batches of `(k, id)`, where `k` is one of `num_keys` pseudo-random keys or `NULL`.
+fn random_keys(num_batches: usize, num_keys: usize, seed: u64) -> Vec<Batch> {
+    let mut seed = seed;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 33) as usize
+    };
+    (0..num_batches)
+        .map(|i| {
+            let keys = (0..20)
+                .map(|_| Some(next() % (num_keys + 1)).filter(|k| *k != 0))
+                .map(|k| k.map(|k| k as i32))
+                .collect::<Vec<_>>();
+            let ids = (0..20).map(|j| Some(i as i32 * 20 + j)).collect::<Vec<_>>();
+            Batch::new(vec![ints(&keys), ints(&ids)]).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn join_with_spilling() {
+    let dir = spill_dir("hash-join");
+    let types = vec![DataType::Integer, DataType::Integer];
+    let new = |join_type, memory_budget| {
+        let config = SpillConfig {
+            dir: dir.clone(),
+            memory_budget,
+        };
+        HashJoin::new(join_type, types.clone(), types.clone(), vec![0], vec![0])
+            .unwrap()
+            .with_batch_size(100)
+            .unwrap()
+            .with_spill_config(config)
+    };
+    let left = random_keys(30, 200, 1);
+    let right = random_keys(30, 200, 2);
+    // All rows have the same key, so partitioning again never splits them.
+    let same_key = Batch::new(vec![ints(&[Some(7); 20]), ints(&[Some(0); 20])]).unwrap();
+    // Partition again a few times, or until the maximum depth.
+    let inputs = [
+        (left, right, 1024),
+        (vec![same_key.clone(); 3], vec![same_key; 3], 0),
+    ];
+    for (left, right, memory_budget) in inputs {
+        for join_type in JOIN_TYPES {
+            let expected = new(join_type, usize::MAX)
+                .execute(left.iter().cloned().map(Ok), right.iter().cloned().map(Ok))
+                .unwrap();
+            let mut join = new(join_type, memory_budget);
+            for batch in &right {
+                join.build(batch.clone()).unwrap();
+            }
+            assert!(num_files(&dir) > 0);
+            for batch in &left {
+                // Output is produced only after all input is spilled.
+                assert!(join.probe(batch).unwrap().is_empty());
+            }
+            let output = join.finish().unwrap();
+            assert!(output.iter().all(|batch| batch.num_rows() <= 100));
+            assert_eq!(
+                sorted_rows(&output),
+                sorted_rows(&expected),
+                "{join_type:?}"
+            );
+            assert_eq!(num_files(&dir), 0);
+        }
+    }
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn join_errors() {
+    let new = |left_keys, right_keys| {
+        HashJoin::new(
+            JoinType::Inner,
+            TYPES.to_vec(),
+            TYPES.to_vec(),
+            left_keys,
+            right_keys,
+        )
+    };
+    assert_eq!(
+        error(new(vec![0], vec![])),
+        "expect the same number of keys on both sides, get 1 and 0"
+    );
+    assert_eq!(
+        error(new(vec![2], vec![0])),
+        "key column index out of range"
+    );
+    assert!(new(vec![0], vec![1]).is_err());
+
+    // Input batches must have columns of the input types.
+    let mut join = new(vec![0], vec![0]).unwrap();
+    let batch = Batch::new(vec![ints(&[Some(1)]), ints(&[Some(1)])]).unwrap();
+    assert!(join.build(batch.clone()).is_err());
+    assert!(join.probe(&batch).is_err());
+    assert!(
+        join.build(Batch::new(vec![ints(&[Some(1)])]).unwrap())
+            .is_err()
+    );
+}
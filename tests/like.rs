@@ -0,0 +1,228 @@
+//! Tests `LIKE`, `ILIKE` and `SIMILAR TO` pattern matching.
+
+mod common;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::FunctionRegistry;
+use type_rust::expr::like::LikePattern;
+use type_rust::scalar::ScalarImpl;
+
+use common::{error, strings, values};
+
+/// Match each of `inputs` against `pattern` with function `name`, both with the pattern as a
+/// column and as a constant, and check that the results are the same.
+fn matches(name: &str, inputs: &[&str], pattern: &str, escape: Option<&str>) -> Vec<bool> {
+    let registry = FunctionRegistry::with_builtins();
+    let n = inputs.len();
+    let mut args = vec![
+        strings(&inputs.iter().map(|s| Some(*s)).collect::<Vec<_>>()),
+        strings(&vec![Some(pattern); n]),
+    ];
+    let mut constants = vec![None, Some(ScalarImpl::String(pattern.to_string()))];
+    if let Some(escape) = escape {
+        args.push(strings(&vec![Some(escape); n]));
+        constants.push(Some(ScalarImpl::String(escape.to_string())));
+    }
+    let types = vec![DataType::Varchar; args.len()];
+    let inputs = args.iter().collect::<Vec<_>>();
+
+    let generic = registry.build(name, &types).unwrap();
+    let generic = values::<BoolArray>(&generic.eval_expr(&inputs).unwrap());
+    let constant = registry
+        .build_with_constants(name, &types, &constants)
+        .unwrap();
+    let constant = values::<BoolArray>(&constant.eval_expr(&inputs).unwrap());
+    assert_eq!(generic, constant, "{name} {pattern}");
+    generic.into_iter().map(Option::unwrap).collect()
+}
+
+fn like(inputs: &[&str], pattern: &str) -> Vec<bool> {
+    matches("like", inputs, pattern, None)
+}
+
+#[test]
+fn like_wildcards() {
+    let inputs = ["", "a", "abc", "xabcx", "ab", "cba"];
+    assert_eq!(
+        like(&inputs, "abc"),
+        [false, false, true, false, false, false]
+    );
+    assert_eq!(
+        like(&inputs, "ab%"),
+        [false, false, true, false, true, false]
+    );
+    assert_eq!(
+        like(&inputs, "%bc"),
+        [false, false, true, false, false, false]
+    );
+    assert_eq!(like(&inputs, "%b%"), [false, false, true, true, true, true]);
+    assert_eq!(like(&inputs, "%"), [true; 6]);
+    assert_eq!(
+        like(&inputs, "_"),
+        [false, true, false, false, false, false]
+    );
+    assert_eq!(
+        like(&inputs, "_b_"),
+        [false, false, true, false, false, true]
+    );
+    assert_eq!(
+        like(&inputs, "%a%c%"),
+        [false, false, true, true, false, false]
+    );
+    assert_eq!(
+        like(&inputs, "a%%"),
+        [false, true, true, false, true, false]
+    );
+    // `_` matches a character, not a byte.
+    assert_eq!(like(&["é", "ée"], "_"), [true, false]);
+    assert_eq!(like(&["é", "ée"], "é_"), [false, true]);
+}
+
+#[test]
+fn like_escape() {
+    let inputs = ["50%", "500", "a_b", "axb", "a\\b"];
+    assert_eq!(like(&inputs, "50\\%"), [true, false, false, false, false]);
+    assert_eq!(like(&inputs, "a\\_b"), [false, false, true, false, false]);
+    assert_eq!(like(&inputs, "a\\\\b"), [false, false, false, false, true]);
+    assert_eq!(
+        matches("like", &inputs, "a#_b", Some("#")),
+        [false, false, true, false, false]
+    );
+    // An empty escape disables escaping.
+    assert_eq!(
+        matches("like", &inputs, "a\\b", Some("")),
+        [false, false, false, false, true]
+    );
+    assert_eq!(
+        matches("like", &inputs, "50%", Some("")),
+        [true, true, false, false, false]
+    );
+}
+
+#[test]
+fn like_errors() {
+    assert!(LikePattern::like("abc\\", Some('\\')).is_err());
+    assert!(LikePattern::like("abc\\", None).is_ok());
+
+    let registry = FunctionRegistry::with_builtins();
+    let types = [DataType::Varchar; 3];
+    let like = |escape: &str| -> Result<Vec<Option<bool>>> {
+        let args = [
+            strings(&[Some("a")]),
+            strings(&[Some("a")]),
+            strings(&[Some(escape)]),
+        ];
+        let result = registry
+            .build("like", &types)?
+            .eval_expr(&args.iter().collect::<Vec<_>>())?;
+        Ok(values::<BoolArray>(&result))
+    };
+    assert_eq!(error(like("ab")), "invalid escape string");
+    assert_eq!(like("#").unwrap(), vec![Some(true)]);
+
+    // Constant patterns are compiled when the call is built.
+    let constants = [
+        None,
+        Some(ScalarImpl::String("a".to_string())),
+        Some(ScalarImpl::String("ab".to_string())),
+    ];
+    assert!(
+        registry
+            .build_with_constants("like", &types, &constants)
+            .is_err()
+    );
+
+    // All columns must have the same length, including the escape.
+    let func = registry.build("like", &types).unwrap();
+    let (one, two) = (strings(&[Some("a")]), strings(&[Some("a"), Some("b")]));
+    assert_eq!(
+        error(func.eval_expr(&[&one, &two, &one])),
+        "array length mismatch"
+    );
+    assert_eq!(
+        error(func.eval_expr(&[&one, &one, &two])),
+        "array length mismatch"
+    );
+    let constants = [None, Some(ScalarImpl::String("a".to_string()))];
+    let func = registry
+        .build_with_constants("like", &types[..2], &constants)
+        .unwrap();
+    assert_eq!(
+        error(func.eval_expr(&[&one])),
+        "Expect two or three inputs for LikeExpression"
+    );
+}
+
+#[test]
+fn like_nulls() {
+    let registry = FunctionRegistry::with_builtins();
+    let func = registry
+        .build("like", &[DataType::Varchar, DataType::Varchar])
+        .unwrap();
+    let s = strings(&[Some("a"), None, Some("a")]);
+    let pattern = strings(&[Some("a"), Some("a"), None]);
+    let result = func.eval_expr(&[&s, &pattern]).unwrap();
+    assert_eq!(values::<BoolArray>(&result), vec![Some(true), None, None]);
+}
+
+#[test]
+fn ilike_case_folding() {
+    let inputs = ["ABC", "abc", "Abc", "xyz", "ÉCOLE", "école"];
+    assert_eq!(
+        matches("ilike", &inputs, "abc", None),
+        [true, true, true, false, false, false]
+    );
+    assert_eq!(
+        matches("ilike", &inputs, "a%", None),
+        [true, true, true, false, false, false]
+    );
+    assert_eq!(
+        matches("ilike", &inputs, "%BC", None),
+        [true, true, true, false, false, false]
+    );
+    assert_eq!(
+        matches("ilike", &inputs, "%Cole%", None),
+        [false, false, false, false, true, true]
+    );
+    assert_eq!(
+        matches("ilike", &inputs, "%B_", None),
+        [true, true, true, false, false, false]
+    );
+    assert_eq!(
+        matches("ilike", &inputs, "éc%", None),
+        [false, false, false, false, true, true]
+    );
+    assert_eq!(
+        matches("like", &inputs, "éc%", None),
+        [false, false, false, false, false, true]
+    );
+}
+
+#[test]
+fn similar_to() {
+    let inputs = ["abc", "abd", "ab", "a.c", "xabc"];
+    let similar = |pattern: &str| -> Vec<bool> {
+        let registry = FunctionRegistry::with_builtins();
+        let func = registry
+            .build("similar_to", &[DataType::Varchar, DataType::Varchar])
+            .unwrap();
+        let s = strings(&inputs.iter().map(|s| Some(*s)).collect::<Vec<_>>());
+        let pattern = strings(&vec![Some(pattern); inputs.len()]);
+        let result = func.eval_expr(&[&s, &pattern]).unwrap();
+        values::<BoolArray>(&result)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()
+    };
+    // The whole string must match.
+    assert_eq!(similar("ab"), [false, false, true, false, false]);
+    assert_eq!(similar("ab%"), [true, true, true, false, false]);
+    assert_eq!(similar("ab(c|d)"), [true, true, false, false, false]);
+    assert_eq!(similar("a_c"), [true, false, false, true, false]);
+    // `.` is a literal.
+    assert_eq!(similar("a.c"), [false, false, false, true, false]);
+    assert_eq!(similar("%[bc]+"), [true, false, true, true, true]);
+    assert_eq!(similar("ab\\%"), [false; 5]);
+}
@@ -0,0 +1,294 @@
+//! Tests math functions registered in [`FunctionRegistry`].
+
+mod common;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::expr::FunctionRegistry;
+
+use common::{array, error, ints, values};
+
+/// Call function `name` on `args`, with the types of the arrays.
+fn call(name: &str, args: &[ArrayImpl]) -> Result<ArrayImpl> {
+    let types = args.iter().map(|arg| arg.data_type()).collect::<Vec<_>>();
+    let func = FunctionRegistry::with_builtins().build(name, &types)?;
+    func.eval_expr(&args.iter().collect::<Vec<_>>())
+}
+
+fn floats(values: &[Option<f64>]) -> ArrayImpl {
+    array::<F64Array>(values)
+}
+
+/// Call function `name` of `float64` on `x`.
+fn call_f64(name: &str, x: f64) -> Result<f64> {
+    let result = call(name, &[floats(&[Some(x)])])?;
+    Ok(values::<F64Array>(&result)[0].unwrap())
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-12,
+        "{actual} is not close to {expected}"
+    );
+}
+
+#[test]
+fn arithmetic() {
+    let (l, r) = (
+        ints(&[Some(7), Some(-7), None]),
+        ints(&[Some(2), Some(2), Some(1)]),
+    );
+    let eval = |name: &str| values::<I32Array>(&call(name, &[l.clone(), r.clone()]).unwrap());
+    assert_eq!(eval("add"), [Some(9), Some(-5), None]);
+    assert_eq!(eval("subtract"), [Some(5), Some(-9), None]);
+    assert_eq!(eval("multiply"), [Some(14), Some(-14), None]);
+    // Integer division truncates towards zero.
+    assert_eq!(eval("divide"), [Some(3), Some(-3), None]);
+
+    let (max, one) = (ints(&[Some(i32::MAX)]), ints(&[Some(1)]));
+    assert_eq!(
+        error(call("add", &[max, one.clone()])),
+        "integer out of range"
+    );
+    assert_eq!(
+        error(call("divide", &[one, ints(&[Some(0)])])),
+        "division by zero"
+    );
+    let result = call("divide", &[floats(&[Some(1.0)]), floats(&[Some(4.0)])]).unwrap();
+    assert_eq!(values::<F64Array>(&result), [Some(0.25)]);
+    assert_eq!(
+        error(call(
+            "multiply",
+            &[floats(&[Some(f64::MAX)]), floats(&[Some(2.0)])]
+        )),
+        "double precision out of range"
+    );
+    // Infinite inputs are not an overflow.
+    let result = call(
+        "add",
+        &[floats(&[Some(f64::INFINITY)]), floats(&[Some(1.0)])],
+    )
+    .unwrap();
+    assert_eq!(values::<F64Array>(&result), [Some(f64::INFINITY)]);
+}
+
+#[test]
+fn type_preserving_functions() {
+    let input = ints(&[Some(-3), Some(0), Some(7), None]);
+    let result = call("abs", std::slice::from_ref(&input)).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(3), Some(0), Some(7), None]
+    );
+    let result = call("sign", &[input]).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(-1), Some(0), Some(1), None]
+    );
+    assert_eq!(
+        error(call("abs", &[ints(&[Some(i32::MIN)])])),
+        "integer out of range"
+    );
+
+    let input = floats(&[Some(-1.5), Some(2.5), Some(0.5), Some(f64::NAN)]);
+    let eval = |name: &str| values::<F64Array>(&call(name, std::slice::from_ref(&input)).unwrap());
+    assert_eq!(eval("ceil")[..3], [Some(-1.0), Some(3.0), Some(1.0)]);
+    assert_eq!(eval("floor")[..3], [Some(-2.0), Some(2.0), Some(0.0)]);
+    assert_eq!(eval("trunc")[..3], [Some(-1.0), Some(2.0), Some(0.0)]);
+    // Floats are rounded half to even.
+    assert_eq!(eval("round")[..3], [Some(-2.0), Some(2.0), Some(0.0)]);
+    assert!(eval("sign")[3].unwrap().is_nan());
+    assert_eq!(eval("abs")[0], Some(1.5));
+}
+
+#[test]
+fn round_digits() {
+    let round = |x: f64, digits: i32| {
+        let result = call("round", &[floats(&[Some(x)]), ints(&[Some(digits)])]).unwrap();
+        values::<F64Array>(&result)[0].unwrap()
+    };
+    assert_close(round(1.23456, 2), 1.23);
+    assert_close(round(2.5, 0), 3.0);
+    assert_close(round(-2.5, 0), -3.0);
+    assert_close(round(1234.5, -2), 1200.0);
+    assert_close(round(1.0e300, 400), 1.0e300);
+    // Rounding to more than the largest power of ten of floats leaves a zero of the same sign.
+    assert_eq!(round(5.0, -309).to_bits(), 0.0_f64.to_bits());
+    assert_eq!(round(-1.0e308, -400).to_bits(), (-0.0_f64).to_bits());
+
+    let round_int = |x: i32, digits: i32| -> Result<Option<i32>> {
+        let result = call("round", &[ints(&[Some(x)]), ints(&[Some(digits)])])?;
+        Ok(values::<I32Array>(&result)[0])
+    };
+    assert_eq!(round_int(1234, 2).unwrap(), Some(1234));
+    assert_eq!(round_int(1250, -2).unwrap(), Some(1300));
+    assert_eq!(round_int(-1250, -2).unwrap(), Some(-1300));
+    assert_eq!(round_int(1234, -100).unwrap(), Some(0));
+    assert!(round_int(i32::MAX, -1).is_err());
+}
+
+#[test]
+fn domain_errors() {
+    assert_close(call_f64("sqrt", 2.25).unwrap(), 1.5);
+    assert_eq!(
+        error(call_f64("sqrt", -1.0)),
+        "cannot take square root of a negative number"
+    );
+    assert_close(call_f64("cbrt", -27.0).unwrap(), -3.0);
+    assert_close(call_f64("ln", std::f64::consts::E).unwrap(), 1.0);
+    assert_close(call_f64("log10", 1000.0).unwrap(), 3.0);
+    assert_eq!(error(call_f64("ln", 0.0)), "cannot take logarithm of zero");
+    assert_eq!(
+        error(call_f64("log10", -1.0)),
+        "cannot take logarithm of a negative number"
+    );
+    assert_eq!(
+        error(call_f64("exp", 1000.0)),
+        "value out of range: overflow"
+    );
+    assert_eq!(
+        error(call_f64("exp", -1000.0)),
+        "value out of range: underflow"
+    );
+    assert_eq!(call_f64("exp", f64::NEG_INFINITY).unwrap(), 0.0);
+    // `NaN` inputs produce `NaN`.
+    assert!(call_f64("sqrt", f64::NAN).unwrap().is_nan());
+
+    let log = |base: f64, x: f64| -> Result<f64> {
+        let result = call("log", &[floats(&[Some(base)]), floats(&[Some(x)])])?;
+        Ok(values::<F64Array>(&result)[0].unwrap())
+    };
+    assert_close(log(2.0, 8.0).unwrap(), 3.0);
+    assert_eq!(error(log(1.0, 8.0)), "division by zero");
+    assert_eq!(error(log(2.0, 0.0)), "cannot take logarithm of zero");
+
+    let pow = |x: f64, y: f64| -> Result<f64> {
+        let result = call("pow", &[floats(&[Some(x)]), floats(&[Some(y)])])?;
+        Ok(values::<F64Array>(&result)[0].unwrap())
+    };
+    assert_close(pow(2.0, 10.0).unwrap(), 1024.0);
+    assert_close(pow(-2.0, 3.0).unwrap(), -8.0);
+    assert_eq!(
+        error(pow(0.0, -1.0)),
+        "zero raised to a negative power is undefined"
+    );
+    assert_eq!(
+        error(pow(-8.0, 1.0 / 3.0)),
+        "a negative number raised to a non-integer power yields a complex result"
+    );
+    assert_eq!(error(pow(10.0, 400.0)), "value out of range: overflow");
+}
+
+#[test]
+fn trigonometric_functions() {
+    use std::f64::consts::PI;
+
+    assert_close(call_f64("sin", PI / 2.0).unwrap(), 1.0);
+    assert_close(call_f64("cos", PI).unwrap(), -1.0);
+    assert_close(call_f64("tan", PI / 4.0).unwrap(), 1.0);
+    assert_close(call_f64("cot", PI / 4.0).unwrap(), 1.0);
+    assert_close(call_f64("asin", 1.0).unwrap(), PI / 2.0);
+    assert_close(call_f64("acos", 1.0).unwrap(), 0.0);
+    assert_close(call_f64("atan", 1.0).unwrap(), PI / 4.0);
+    assert_close(call_f64("tanh", 0.0).unwrap(), 0.0);
+    assert_close(call_f64("degrees", PI).unwrap(), 180.0);
+    assert_close(call_f64("radians", 180.0).unwrap(), PI);
+    assert_eq!(
+        error(call_f64("sin", f64::INFINITY)),
+        "input is out of range"
+    );
+    assert_eq!(error(call_f64("asin", 1.5)), "input is out of range");
+
+    let result = call("atan2", &[floats(&[Some(-1.0)]), floats(&[Some(-1.0)])]).unwrap();
+    assert_close(values::<F64Array>(&result)[0].unwrap(), -3.0 * PI / 4.0);
+}
+
+#[test]
+fn integers_are_cast_into_float64() {
+    let result = call("sqrt", &[ints(&[Some(16)])]).unwrap();
+    assert_eq!(values::<F64Array>(&result), [Some(4.0)]);
+    let result = call("pow", &[ints(&[Some(2)]), ints(&[Some(3)])]).unwrap();
+    assert_eq!(values::<F64Array>(&result), [Some(8.0)]);
+}
+
+#[test]
+fn greatest_and_least() {
+    let a = ints(&[Some(1), Some(5), None, None]);
+    let b = ints(&[Some(3), None, Some(2), None]);
+    let c = ints(&[Some(2), Some(4), Some(-1), None]);
+    let result = call("greatest", &[a.clone(), b.clone(), c.clone()]).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(3), Some(5), Some(2), None]
+    );
+    let result = call("least", &[a.clone(), b, c]).unwrap();
+    assert_eq!(
+        values::<I32Array>(&result),
+        [Some(1), Some(4), Some(-1), None]
+    );
+    let result = call("greatest", std::slice::from_ref(&a)).unwrap();
+    assert_eq!(values::<I32Array>(&result), values::<I32Array>(&a));
+
+    // Any number of arguments is accepted, and they are cast into a common type.
+    let args = (0..6).map(|v| ints(&[Some(v)])).collect::<Vec<_>>();
+    let result = call("greatest", &args).unwrap();
+    assert_eq!(values::<I32Array>(&result), [Some(5)]);
+    let result = call("least", &[ints(&[Some(2)]), floats(&[Some(1.5)])]).unwrap();
+    assert_eq!(values::<F64Array>(&result), [Some(1.5)]);
+
+    // `NaN` is greater than any other value.
+    let result = call(
+        "greatest",
+        &[floats(&[Some(f64::NAN)]), floats(&[Some(f64::INFINITY)])],
+    )
+    .unwrap();
+    assert!(values::<F64Array>(&result)[0].unwrap().is_nan());
+    let result = call(
+        "least",
+        &[floats(&[Some(f64::NAN)]), floats(&[Some(f64::INFINITY)])],
+    )
+    .unwrap();
+    assert_eq!(values::<F64Array>(&result), [Some(f64::INFINITY)]);
+
+    assert!(call("greatest", &[]).is_err());
+    assert_eq!(
+        error(call(
+            "greatest",
+            &[ints(&[Some(1)]), ints(&[Some(1), Some(2)])]
+        )),
+        "array length mismatch"
+    );
+}
+
+#[test]
+fn width_bucket() {
+    let bucket = |x: f64, low: f64, high: f64, count: i32| -> Result<Option<i32>> {
+        let args = [
+            floats(&[Some(x)]),
+            floats(&[Some(low)]),
+            floats(&[Some(high)]),
+            ints(&[Some(count)]),
+        ];
+        Ok(values::<I32Array>(&call("width_bucket", &args)?)[0])
+    };
+    assert_eq!(bucket(5.35, 0.024, 10.06, 5).unwrap(), Some(3));
+    assert_eq!(bucket(-1.0, 0.0, 10.0, 5).unwrap(), Some(0));
+    assert_eq!(bucket(10.0, 0.0, 10.0, 5).unwrap(), Some(6));
+    assert_eq!(bucket(0.0, 0.0, 10.0, 5).unwrap(), Some(1));
+    // The range is reversed if the lower bound is greater.
+    assert_eq!(bucket(9.0, 10.0, 0.0, 5).unwrap(), Some(1));
+    assert_eq!(bucket(11.0, 10.0, 0.0, 5).unwrap(), Some(0));
+
+    assert_eq!(
+        error(bucket(1.0, 0.0, 10.0, 0)),
+        "count must be greater than zero"
+    );
+    assert_eq!(
+        error(bucket(1.0, 1.0, 1.0, 5)),
+        "lower bound cannot equal upper bound"
+    );
+    assert_eq!(
+        error(bucket(1.0, 0.0, f64::INFINITY, 5)),
+        "lower and upper bounds must be finite"
+    );
+}
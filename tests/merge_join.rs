@@ -0,0 +1,353 @@
+//! Tests merge joins and range joins over sorted inputs.
+
+mod common;
+
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::executor::{HashJoin, JoinType, MergeJoin, RangeCondition, RangeJoin, RangeOp};
+
+use common::{error, ints, rows, sorted_rows, strings};
+
+const JOIN_TYPES: [JoinType; 8] = [
+    JoinType::Inner,
+    JoinType::LeftOuter,
+    JoinType::RightOuter,
+    JoinType::FullOuter,
+    JoinType::LeftSemi,
+    JoinType::LeftAnti,
+    JoinType::RightSemi,
+    JoinType::RightAnti,
+];
+
+/// Batches of `(k, id)` with `keys`, split into batches of `batch_size` rows.
+fn batches(keys: &[Option<i32>], batch_size: usize) -> Vec<Batch> {
+    keys.chunks(batch_size)
+        .enumerate()
+        .map(|(i, keys)| {
+            let ids = (0..keys.len())
+                .map(|j| Some((i * batch_size + j) as i32))
+                .collect::<Vec<_>>();
+            Batch::new(vec![ints(keys), ints(&ids)]).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn merge_join_matches_hash_join() {
+    // Sorted keys with duplicates on both sides, and `NULL`s in between.
+    let left = [
+        Some(1),
+        Some(2),
+        None,
+        Some(2),
+        Some(2),
+        Some(4),
+        Some(5),
+        None,
+        Some(7),
+        Some(7),
+    ];
+    let right = [
+        None,
+        Some(2),
+        Some(2),
+        Some(3),
+        Some(5),
+        None,
+        Some(5),
+        Some(7),
+        Some(8),
+    ];
+    let types = vec![DataType::Integer, DataType::Integer];
+    for join_type in JOIN_TYPES {
+        let hash = HashJoin::new(join_type, types.clone(), types.clone(), vec![0], vec![0])
+            .unwrap()
+            .execute(
+                batches(&left, 10).into_iter().map(Ok),
+                batches(&right, 10).into_iter().map(Ok),
+            )
+            .unwrap();
+        let expected = sorted_rows(&hash);
+        for batch_size in [1, 3, 10] {
+            let merge = MergeJoin::new(join_type, types.clone(), types.clone(), vec![0], vec![0])
+                .unwrap()
+                .with_batch_size(4);
+            let output = merge
+                .execute(
+                    batches(&left, batch_size).into_iter().map(Ok),
+                    batches(&right, batch_size).into_iter().map(Ok),
+                )
+                .unwrap();
+            assert!(output.iter().all(|batch| batch.num_rows() <= 4));
+            assert_eq!(sorted_rows(&output), expected, "{join_type:?}");
+        }
+    }
+}
+
+#[test]
+fn merge_join_multi_column_keys() {
+    let types = vec![DataType::Varchar, DataType::Integer];
+    let left = Batch::new(vec![
+        strings(&[Some("a"), Some("a"), Some("b"), Some("c")]),
+        ints(&[Some(1), Some(2), Some(1), Some(1)]),
+    ]);
+    let right = Batch::new(vec![
+        strings(&[Some("a"), Some("b"), Some("b"), Some("c")]),
+        ints(&[Some(2), Some(1), Some(1), None]),
+    ]);
+    let join = MergeJoin::new(
+        JoinType::Inner,
+        types.clone(),
+        types,
+        vec![0, 1],
+        vec![0, 1],
+    )
+    .unwrap();
+    let output = join.execute([left], [right]).unwrap();
+    assert_eq!(
+        rows(&output),
+        [
+            "[Some(String(\"a\")), Some(Int32(2)), Some(String(\"a\")), Some(Int32(2))]",
+            "[Some(String(\"b\")), Some(Int32(1)), Some(String(\"b\")), Some(Int32(1))]",
+            "[Some(String(\"b\")), Some(Int32(1)), Some(String(\"b\")), Some(Int32(1))]",
+        ]
+    );
+
+    let new = |left_keys, right_keys| {
+        MergeJoin::new(
+            JoinType::Inner,
+            vec![DataType::Integer],
+            vec![DataType::Varchar],
+            left_keys,
+            right_keys,
+        )
+    };
+    assert!(new(vec![0], vec![0]).is_err());
+    assert!(new(vec![0], vec![]).is_err());
+    assert!(new(vec![1], vec![0]).is_err());
+}
+
+/// Join values with ranges of `(start, end)` by `value BETWEEN start AND end`.
+fn range_join(
+    join_type: JoinType,
+    values: &[Option<i32>],
+    ranges: &[(Option<i32>, Option<i32>)],
+) -> Vec<String> {
+    let types = vec![DataType::Integer, DataType::Integer];
+    let conditions = RangeCondition::between(0, 0, 1);
+    let join = RangeJoin::new(
+        join_type,
+        vec![DataType::Integer],
+        types,
+        vec![],
+        vec![],
+        conditions,
+    )
+    .unwrap()
+    .with_batch_size(2);
+    let left = values
+        .chunks(2)
+        .map(|values| Batch::new(vec![ints(values)]))
+        .collect::<Vec<_>>();
+    let (starts, ends): (Vec<_>, Vec<_>) = ranges.iter().copied().unzip();
+    let right = Batch::new(vec![ints(&starts), ints(&ends)]);
+    rows(&join.execute(left, [right]).unwrap())
+}
+
+#[test]
+fn range_joins() {
+    let values = [Some(1), Some(3), Some(5), Some(8), None, Some(20)];
+    // Ranges are sorted by start, and may overlap. Empty ranges and `NULL` bounds never match.
+    let ranges = [
+        (Some(0), Some(3)),
+        (Some(2), Some(6)),
+        (Some(4), Some(4)),
+        (Some(5), Some(1)),
+        (Some(7), None),
+        (Some(8), Some(8)),
+        (None, Some(100)),
+    ];
+    let range = |start: i32, end: i32| format!("Some(Int32({start})), Some(Int32({end}))");
+    let row = |value: i32, start, end| format!("[Some(Int32({value})), {}]", range(start, end));
+    assert_eq!(
+        range_join(JoinType::Inner, &values, &ranges),
+        [
+            row(1, 0, 3),
+            row(3, 0, 3),
+            row(3, 2, 6),
+            row(5, 2, 6),
+            row(8, 8, 8),
+        ]
+    );
+    let mut expected = range_join(JoinType::Inner, &values, &ranges);
+    expected.insert(5, "[None, None, None]".to_string());
+    expected.push("[Some(Int32(20)), None, None]".to_string());
+    assert_eq!(range_join(JoinType::LeftOuter, &values, &ranges), expected);
+    assert_eq!(
+        range_join(JoinType::LeftSemi, &values, &ranges),
+        [
+            "[Some(Int32(1))]",
+            "[Some(Int32(3))]",
+            "[Some(Int32(5))]",
+            "[Some(Int32(8))]"
+        ]
+    );
+    assert_eq!(
+        range_join(JoinType::LeftAnti, &values, &ranges),
+        ["[None]", "[Some(Int32(20))]"]
+    );
+
+    // Duplicate values match the same ranges.
+    let values = [Some(2), Some(2), Some(2)];
+    assert_eq!(
+        range_join(JoinType::Inner, &values, &[(Some(2), Some(2))]).len(),
+        3
+    );
+}
+
+/// Join `(k, v)` rows with `(k, w)` rows by `conditions` on `v` and `w`, and by equal `k` if
+/// `keys`, returning `(v, w)` of output rows.
+fn inequality_join(
+    left: &[(Option<i32>, Option<i32>)],
+    right: &[(Option<i32>, Option<i32>)],
+    keys: bool,
+    ops: &[RangeOp],
+) -> Vec<(i32, i32)> {
+    let types = vec![DataType::Integer, DataType::Integer];
+    let keys = if keys { vec![0] } else { vec![] };
+    let conditions = ops
+        .iter()
+        .map(|op| RangeCondition::new(1, *op, 1))
+        .collect();
+    let join = RangeJoin::new(
+        JoinType::Inner,
+        types.clone(),
+        types,
+        keys.clone(),
+        keys,
+        conditions,
+    )
+    .unwrap()
+    .with_batch_size(2);
+    let batch = |rows: &[(Option<i32>, Option<i32>)]| {
+        let (k, v): (Vec<_>, Vec<_>) = rows.iter().copied().unzip();
+        Batch::new(vec![ints(&k), ints(&v)])
+    };
+    let left = left.chunks(2).map(batch).collect::<Vec<_>>();
+    let right = right.chunks(2).map(batch).collect::<Vec<_>>();
+    let mut pairs = vec![];
+    for batch in join.execute(left, right).unwrap() {
+        let (v, w): (&I32Array, &I32Array) = (
+            batch.column(1).try_into().unwrap(),
+            batch.column(3).try_into().unwrap(),
+        );
+        pairs.extend(
+            v.iter()
+                .zip(w.iter())
+                .map(|(v, w)| (v.unwrap(), w.unwrap())),
+        );
+    }
+    pairs
+}
+
+#[test]
+fn inequality_joins() {
+    let values = [1, 3, 5].map(|v| (Some(0), Some(v)));
+    let bounds = [Some(1), Some(3), None, Some(4)].map(|w| (Some(0), w));
+    let join = |ops| inequality_join(&values, &bounds, false, ops);
+    // Lower bounds only, where the right input is sorted by them. `NULL` bounds never match.
+    assert_eq!(join(&[RangeOp::Gt]), [(3, 1), (5, 1), (5, 3), (5, 4)]);
+    assert_eq!(
+        join(&[RangeOp::GtEq]),
+        [(1, 1), (3, 1), (3, 3), (5, 1), (5, 3), (5, 4)]
+    );
+    // Upper bounds only.
+    assert_eq!(join(&[RangeOp::Lt]), [(1, 3), (1, 4), (3, 4)]);
+    assert_eq!(
+        join(&[RangeOp::LtEq]),
+        [(1, 1), (1, 3), (1, 4), (3, 3), (3, 4)]
+    );
+    // Both bounds.
+    assert_eq!(join(&[RangeOp::GtEq, RangeOp::LtEq]), [(1, 1), (3, 3)]);
+    assert_eq!(join(&[RangeOp::Gt, RangeOp::Lt]), []);
+}
+
+#[test]
+fn inequality_joins_with_keys() {
+    // Sorted by `k` and then by `v` or `w`. Rows with `NULL` keys never match.
+    let left = [(1, 1), (1, 5), (2, 2), (3, 4)].map(|(k, v)| (Some(k), Some(v)));
+    let mut right = [(1, 0), (1, 4), (2, 3), (3, 9)].map(|(k, w)| (Some(k), Some(w)));
+    right[2].0 = None;
+    let mut left = left.to_vec();
+    left.insert(3, (None, Some(3)));
+    // Rows of `k = 1` do not match `v = 2` of `k = 2`.
+    assert_eq!(
+        inequality_join(&left, &right, true, &[RangeOp::Gt]),
+        [(1, 0), (5, 0), (5, 4)]
+    );
+    assert_eq!(
+        inequality_join(&left, &right, true, &[RangeOp::Lt]),
+        [(1, 4), (4, 9)]
+    );
+}
+
+#[test]
+fn range_join_errors() {
+    let types = vec![DataType::Integer, DataType::Integer];
+    let new = |join_type, left_types: Vec<DataType>| {
+        let conditions = RangeCondition::between(0, 0, 1);
+        RangeJoin::new(
+            join_type,
+            left_types,
+            types.clone(),
+            vec![],
+            vec![],
+            conditions,
+        )
+    };
+    assert_eq!(
+        error(new(JoinType::RightOuter, vec![DataType::Integer])),
+        "RightOuter join is not supported by range join"
+    );
+    assert!(new(JoinType::FullOuter, vec![DataType::Integer]).is_err());
+    assert!(new(JoinType::Inner, vec![DataType::BigInt]).is_err());
+
+    // Conditions must bound one left column at most once from each side.
+    let with_conditions = |conditions| {
+        RangeJoin::new(
+            JoinType::Inner,
+            types.clone(),
+            types.clone(),
+            vec![],
+            vec![],
+            conditions,
+        )
+    };
+    assert_eq!(
+        error(with_conditions(vec![])),
+        "range join requires a condition"
+    );
+    let (gt, lt) = (RangeOp::Gt, RangeOp::Lt);
+    assert!(with_conditions(vec![RangeCondition::new(0, gt, 0)]).is_ok());
+    assert!(
+        with_conditions(vec![
+            RangeCondition::new(0, gt, 0),
+            RangeCondition::new(1, lt, 1)
+        ])
+        .is_err()
+    );
+    assert!(
+        with_conditions(vec![
+            RangeCondition::new(0, gt, 0),
+            RangeCondition::new(0, RangeOp::GtEq, 1)
+        ])
+        .is_err()
+    );
+    assert!(with_conditions(vec![RangeCondition::new(0, lt, 2)]).is_err());
+
+    // Input batches must have columns of the input types.
+    let join = new(JoinType::Inner, vec![DataType::Integer]).unwrap();
+    let left = Batch::new(vec![strings(&[Some("1")])]);
+    let right = Batch::new(vec![ints(&[Some(0)]), ints(&[Some(2)])]);
+    assert!(join.execute([left], [right]).is_err());
+}
@@ -0,0 +1,247 @@
+//! Tests basic physical operators and pipelines of them.
+
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::executor::{
+    BoxedOperator, Filter, Limit, PhysicalOperator, Projection, Scan, UnionAll, Values, batches,
+};
+use type_rust::expr::conditional::CaseExpression;
+use type_rust::expr::tree::{CallExpression, InputRef, Literal};
+use type_rust::expr::{Expression, FunctionRegistry};
+use type_rust::scalar::ScalarImpl;
+
+use common::{error, ints, rows, strings};
+
+const TYPES: [DataType; 2] = [DataType::Integer, DataType::Varchar];
+
+/// A scan of rows `(i, "s{i}")` for `i` in `0..n`, in batches of `batch_size` rows.
+fn scan(n: i32, batch_size: usize) -> BoxedOperator {
+    let batches = (0..n)
+        .collect::<Vec<_>>()
+        .chunks(batch_size)
+        .map(|chunk| {
+            let s = chunk.iter().map(|i| format!("s{i}")).collect::<Vec<_>>();
+            Batch::new(vec![
+                ints(&chunk.iter().map(|i| Some(*i)).collect::<Vec<_>>()),
+                strings(&s.iter().map(|s| Some(s.as_str())).collect::<Vec<_>>()),
+            ])
+            .unwrap()
+        })
+        .collect();
+    Box::new(Scan::new(TYPES.to_vec(), batches).unwrap())
+}
+
+/// Pull all rows of `operator`, checking that batches are not empty.
+fn collect(mut operator: impl PhysicalOperator) -> Vec<String> {
+    collect_boxed(&mut operator)
+}
+
+fn collect_boxed(operator: &mut dyn PhysicalOperator) -> Vec<String> {
+    let batches = batches(operator).collect::<Result<Vec<_>>>().unwrap();
+    assert!(batches.iter().all(|batch| !batch.is_empty()));
+    rows(&batches)
+}
+
+fn row(i: i32) -> String {
+    format!("[Some(Int32({i})), Some(String(\"s{i}\"))]")
+}
+
+fn int(value: i32) -> Box<dyn Expression> {
+    Box::new(Literal::new(Some(ScalarImpl::Int32(value)), DataType::Integer).unwrap())
+}
+
+/// Call function `name` of `integer` arguments.
+fn call(name: &str, args: Vec<Box<dyn Expression>>) -> Box<dyn Expression> {
+    let types = vec![DataType::Integer; args.len()];
+    let func = FunctionRegistry::with_builtins()
+        .build(name, &types)
+        .unwrap();
+    Box::new(CallExpression::new(func, args))
+}
+
+#[test]
+fn scan_and_values() {
+    assert_eq!(
+        collect_boxed(&mut *scan(5, 2)),
+        (0..5).map(row).collect::<Vec<_>>()
+    );
+
+    // Empty batches are skipped.
+    let empty = Batch::new(vec![ints(&[]), strings(&[])]).unwrap();
+    let scan = Scan::new(TYPES.to_vec(), vec![empty.clone(), empty]).unwrap();
+    assert!(collect(scan).is_empty());
+    let batch = Batch::new(vec![strings(&[Some("a")]), ints(&[Some(1)])]).unwrap();
+    assert!(Scan::new(TYPES.to_vec(), vec![batch]).is_err());
+
+    let values = Values::new(
+        TYPES.to_vec(),
+        vec![
+            vec![
+                Some(ScalarImpl::Int32(0)),
+                Some(ScalarImpl::String("s0".to_string())),
+            ],
+            vec![None, None],
+        ],
+    )
+    .unwrap();
+    assert_eq!(collect(values), [row(0), "[None, None]".to_string()]);
+    assert_eq!(
+        error(Values::new(TYPES.to_vec(), vec![vec![None]])),
+        "VALUES lists must all be the same length"
+    );
+    assert!(Values::new(TYPES.to_vec(), vec![vec![Some(ScalarImpl::Int32(0)); 2]]).is_err());
+}
+
+#[test]
+fn filter_and_projection() {
+    // WHERE i % 3 = 1
+    let predicate = call(
+        "equal",
+        vec![
+            call("mod", vec![Box::new(InputRef::new(0)), int(3)]),
+            int(1),
+        ],
+    );
+    let filter = Filter::new(scan(10, 4), predicate);
+    assert_eq!(collect(filter), [row(1), row(4), row(7)]);
+
+    // Batches without any row left are skipped.
+    let filter = Filter::new(
+        scan(10, 2),
+        call("greater_than", vec![Box::new(InputRef::new(0)), int(7)]),
+    );
+    assert_eq!(collect(filter), [row(8), row(9)]);
+
+    // SELECT i * 2, s
+    let exprs = vec![
+        call("multiply", vec![Box::new(InputRef::new(0)), int(2)]),
+        Box::new(InputRef::new(1)) as Box<dyn Expression>,
+    ];
+    let projection = Projection::new(scan(3, 2), exprs, TYPES.to_vec()).unwrap();
+    assert_eq!(projection.output_types(), TYPES);
+    assert_eq!(
+        collect(projection),
+        [
+            "[Some(Int32(0)), Some(String(\"s0\"))]",
+            "[Some(Int32(2)), Some(String(\"s1\"))]",
+            "[Some(Int32(4)), Some(String(\"s2\"))]",
+        ]
+    );
+
+    // The results must be of the given types.
+    let mut projection = Projection::new(scan(3, 2), vec![int(1)], vec![DataType::BigInt]).unwrap();
+    assert!(projection.next_batch().is_err());
+    assert!(Projection::new(scan(3, 2), vec![int(1)], vec![]).is_err());
+    // The predicate must be a boolean.
+    let mut filter = Filter::new(scan(3, 2), int(1));
+    assert!(filter.next_batch().is_err());
+}
+
+#[test]
+fn constants_without_input_columns() {
+    // SELECT 1 + 2 FROM (VALUES (), ()) WHERE 1 < 2
+    let values = Values::new(vec![], vec![vec![], vec![]]).unwrap();
+    let filter = Filter::new(Box::new(values), call("less_than", vec![int(1), int(2)]));
+    let exprs = vec![call("add", vec![int(1), int(2)])];
+    let projection = Projection::new(Box::new(filter), exprs, vec![DataType::Integer]).unwrap();
+    assert_eq!(
+        collect(projection),
+        ["[Some(Int32(3))]", "[Some(Int32(3))]"]
+    );
+
+    // SELECT FROM t
+    let projection = Projection::new(scan(3, 2), vec![], vec![]).unwrap();
+    assert_eq!(collect(projection), ["[]", "[]", "[]"]);
+
+    // Conditional expressions evaluate their branches on the matching rows only.
+    let values = Values::new(vec![], vec![vec![]; 3]).unwrap();
+    let case = CaseExpression::new(
+        vec![(call("less_than", vec![int(2), int(1)]), int(1))],
+        Some(int(2)),
+        DataType::Integer,
+    );
+    let mut projection = Projection::new(
+        Box::new(values),
+        vec![Box::new(case)],
+        vec![DataType::Integer],
+    )
+    .unwrap();
+    assert_eq!(
+        rows(&[projection.next_batch().unwrap().unwrap()]),
+        vec!["[Some(Int32(2))]"; 3]
+    );
+
+    // There is no column to refer to.
+    let values = Values::new(vec![], vec![vec![]]).unwrap();
+    let exprs = vec![Box::new(InputRef::new(0)) as Box<dyn Expression>];
+    let mut projection = Projection::new(Box::new(values), exprs, vec![DataType::Integer]).unwrap();
+    assert_eq!(
+        error(projection.next_batch()),
+        "input column 0 out of range, the input has 0 columns"
+    );
+}
+
+/// Counts the batches pulled from its child.
+struct Counting {
+    child: BoxedOperator,
+    pulled: Rc<Cell<usize>>,
+}
+
+impl PhysicalOperator for Counting {
+    fn output_types(&self) -> &[DataType] {
+        self.child.output_types()
+    }
+
+    fn next_batch(&mut self) -> Result<Option<Batch>> {
+        self.pulled.set(self.pulled.get() + 1);
+        self.child.next_batch()
+    }
+}
+
+#[test]
+fn limit_and_offset() {
+    let limit = |limit, offset| collect(Limit::new(scan(10, 3), limit, offset));
+    assert_eq!(limit(Some(4), 0), (0..4).map(row).collect::<Vec<_>>());
+    // Offsets and limits may cross batches.
+    assert_eq!(limit(Some(4), 2), (2..6).map(row).collect::<Vec<_>>());
+    assert_eq!(limit(None, 7), (7..10).map(row).collect::<Vec<_>>());
+    assert_eq!(limit(Some(100), 9), [row(9)]);
+    assert!(limit(Some(0), 0).is_empty());
+    assert!(limit(None, 10).is_empty());
+
+    // The child is not pulled once the limit is reached.
+    let pulled = Rc::new(Cell::new(0));
+    let child = Counting {
+        child: scan(30, 3),
+        pulled: pulled.clone(),
+    };
+    assert_eq!(collect(Limit::new(Box::new(child), Some(5), 1)).len(), 5);
+    assert_eq!(pulled.get(), 2);
+}
+
+#[test]
+fn union_all() {
+    let union = UnionAll::new(vec![scan(2, 1), scan(0, 1), scan(3, 2)]).unwrap();
+    assert_eq!(collect(union), [row(0), row(1), row(0), row(1), row(2)]);
+
+    assert_eq!(
+        error(UnionAll::new(vec![])),
+        "UNION ALL requires at least one input"
+    );
+    let values = Values::new(vec![DataType::Integer], vec![]).unwrap();
+    assert_eq!(
+        error(UnionAll::new(vec![scan(1, 1), Box::new(values)])),
+        "each UNION query must have the same number of columns"
+    );
+    let values = Values::new(vec![DataType::Varchar, DataType::Integer], vec![]).unwrap();
+    assert_eq!(
+        error(UnionAll::new(vec![scan(1, 1), Box::new(values)])),
+        "UNION types integer and varchar cannot be matched"
+    );
+}
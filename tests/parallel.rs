@@ -0,0 +1,356 @@
+//! Tests parallel execution against sequential execution of the same pipelines.
+
+mod common;
+
+use std::collections::HashSet;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+
+use anyhow::{Result, bail};
+use type_rust::aggregate::build_aggregate;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::executor::{
+    AggCall, ColumnOrder, Filter, HashAggregate, ParallelExecutor, Pipeline, Projection, Scan,
+    Sort, SpillConfig, batches,
+};
+use type_rust::expr::tree::{CallExpression, InputRef, Literal};
+use type_rust::expr::{Expression, FunctionRegistry};
+use type_rust::scalar::ScalarImpl;
+
+use common::{error, ints, num_files, rows, sorted_rows, spill_dir};
+
+const TYPES: [DataType; 2] = [DataType::Integer, DataType::Integer];
+
+/// Batches of `(k, v)` of different sizes, where `k` is pseudo-random with duplicates and `NULL`s,
+/// and `v` is the position of the row.
+fn input() -> Vec<Batch> {
+    let mut seed = 7u64;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 33) as usize
+    };
+    let mut id = 0;
+    [100, 1, 0, 250, 37, 512]
+        .into_iter()
+        .map(|len| {
+            let keys = (0..len)
+                .map(|_| Some(next() % 30).filter(|k| *k != 0).map(|k| k as i32))
+                .collect::<Vec<_>>();
+            let ids = (id..id + len).map(Some).collect::<Vec<_>>();
+            id += len;
+            Batch::new(vec![ints(&keys), ints(&ids)]).unwrap()
+        })
+        .collect()
+}
+
+fn int(value: i32) -> Box<dyn Expression> {
+    Box::new(Literal::new(Some(ScalarImpl::Int32(value)), DataType::Integer).unwrap())
+}
+
+fn column(idx: usize) -> Box<dyn Expression> {
+    Box::new(InputRef::new(idx))
+}
+
+/// Call function `name` of `integer` arguments.
+fn call(name: &str, args: Vec<Box<dyn Expression>>) -> Box<dyn Expression> {
+    let types = vec![DataType::Integer; args.len()];
+    let func = FunctionRegistry::with_builtins()
+        .build(name, &types)
+        .unwrap();
+    Box::new(CallExpression::new(func, args))
+}
+
+/// `WHERE v % 3 <> 0`
+fn predicate() -> Box<dyn Expression> {
+    let remainder = call("mod", vec![column(1), int(3)]);
+    call("not_equal", vec![remainder, int(0)])
+}
+
+/// `SELECT k, v * 2`
+fn exprs() -> Vec<Box<dyn Expression>> {
+    vec![column(0), call("multiply", vec![column(1), int(2)])]
+}
+
+fn pipeline() -> Pipeline {
+    Pipeline::new(TYPES.to_vec())
+        .filter(predicate())
+        .project(exprs(), TYPES.to_vec())
+        .unwrap()
+}
+
+/// Run [`pipeline`] sequentially with operators.
+fn sequential(input: &[Batch]) -> Vec<Batch> {
+    let scan = Scan::new(TYPES.to_vec(), input.to_vec()).unwrap();
+    let filter = Filter::new(Box::new(scan), predicate());
+    let mut projection = Projection::new(Box::new(filter), exprs(), TYPES.to_vec()).unwrap();
+    batches(&mut projection).collect::<Result<_>>().unwrap()
+}
+
+/// Executors of different numbers of workers and morsel sizes.
+fn executors() -> Vec<ParallelExecutor> {
+    let mut executors = vec![];
+    for num_workers in [1, 2, 4, 8] {
+        for morsel_size in [1, 16, 1000] {
+            let executor = ParallelExecutor::new(num_workers)
+                .unwrap()
+                .with_morsel_size(morsel_size)
+                .unwrap()
+                .with_batch_size(64)
+                .unwrap();
+            executors.push(executor);
+        }
+    }
+    executors
+}
+
+#[test]
+fn collect_in_input_order() {
+    let input = input();
+    let expected = rows(&sequential(&input));
+    let pipeline = pipeline();
+    assert_eq!(pipeline.output_types(), TYPES);
+    for executor in executors() {
+        let output = executor.collect(&input, &pipeline).unwrap();
+        assert!(output.iter().all(|batch| !batch.is_empty()));
+        assert_eq!(rows(&output), expected);
+    }
+
+    // An empty pipeline returns its input.
+    let executor = ParallelExecutor::new(3).unwrap();
+    let output = executor
+        .collect(&input, &Pipeline::new(TYPES.to_vec()))
+        .unwrap();
+    assert_eq!(rows(&output), rows(&input));
+    assert!(executor.collect(&[], &pipeline).unwrap().is_empty());
+}
+
+/// `SELECT k, count(*), sum(v * 2) .. GROUP BY k`
+fn group_by_key(config: SpillConfig) -> Result<HashAggregate> {
+    let calls = vec![
+        AggCall::new(build_aggregate("count", &[])?, vec![]),
+        AggCall::new(build_aggregate("sum", &[DataType::Integer])?, vec![1]),
+    ];
+    let agg = HashAggregate::new(vec![0], vec![DataType::Integer], calls)?;
+    Ok(agg.with_spill_config(config))
+}
+
+#[test]
+fn aggregate_partial_results() {
+    let input = input();
+    let expected = {
+        let agg = group_by_key(SpillConfig::default()).unwrap();
+        sorted_rows(
+            &agg.aggregate(sequential(&input).into_iter().map(Ok))
+                .unwrap(),
+        )
+    };
+    let dir = spill_dir("parallel-agg");
+    let pipeline = pipeline();
+    for executor in executors() {
+        let output = executor
+            .aggregate(&input, &pipeline, || group_by_key(SpillConfig::default()))
+            .unwrap();
+        assert_eq!(sorted_rows(&output), expected);
+
+        // Partial aggregations that spill are merged as well.
+        let config = SpillConfig {
+            dir: dir.clone(),
+            memory_budget: 0,
+        };
+        let output = executor
+            .aggregate(&input, &pipeline, || group_by_key(config.clone()))
+            .unwrap();
+        assert_eq!(sorted_rows(&output), expected);
+        assert_eq!(num_files(&dir), 0);
+    }
+    std::fs::remove_dir(&dir).unwrap();
+
+    // Without input, aggregations without keys still produce one row.
+    let count = || {
+        let count = AggCall::new(build_aggregate("count", &[])?, vec![]);
+        HashAggregate::new(vec![], vec![], vec![count])
+    };
+    let executor = ParallelExecutor::new(4).unwrap();
+    let output = executor.aggregate(&[], &pipeline, count).unwrap();
+    assert_eq!(rows(&output), ["[Some(Int64(0))]"]);
+}
+
+#[test]
+fn sort_stably() {
+    let input = input();
+    let orders = vec![ColumnOrder::desc(0).nulls_first(false)];
+    let expected = {
+        let scan = Scan::new(TYPES.to_vec(), sequential(&input)).unwrap();
+        let mut sort = Sort::new(Box::new(scan), orders.clone()).unwrap();
+        rows(&batches(&mut sort).collect::<Result<Vec<_>>>().unwrap())
+    };
+    let pipeline = pipeline();
+    for executor in executors() {
+        let output = executor.sort(&input, &pipeline, orders.clone()).unwrap();
+        assert!(output.iter().all(|batch| batch.num_rows() <= 64));
+        // Rows of equal keys are in the order of the input.
+        assert_eq!(rows(&output), expected);
+    }
+    let executor = ParallelExecutor::new(2).unwrap();
+    assert!(executor.sort(&[], &pipeline, orders).unwrap().is_empty());
+    assert!(
+        executor
+            .sort(&input, &pipeline, vec![ColumnOrder::asc(2)])
+            .is_err()
+    );
+}
+
+#[test]
+fn parallel_errors() {
+    assert_eq!(
+        error(ParallelExecutor::new(0)),
+        "number of workers must be positive"
+    );
+    let executor = ParallelExecutor::new(4).unwrap();
+    assert_eq!(executor.num_workers(), 4);
+    assert!(
+        ParallelExecutor::new(1)
+            .unwrap()
+            .with_morsel_size(0)
+            .is_err()
+    );
+    assert!(
+        ParallelExecutor::new(1)
+            .unwrap()
+            .with_batch_size(0)
+            .is_err()
+    );
+    assert!(
+        Pipeline::new(TYPES.to_vec())
+            .project(exprs(), vec![])
+            .is_err()
+    );
+
+    // Input batches must have columns of the input types.
+    let input = input();
+    let pipeline = Pipeline::new(vec![DataType::BigInt, DataType::Integer]);
+    assert!(executor.collect(&input, &pipeline).is_err());
+
+    // Errors of any worker are returned.
+    let pipeline = Pipeline::new(TYPES.to_vec())
+        .project(vec![column(0)], vec![DataType::BigInt])
+        .unwrap();
+    let executor = executor.with_morsel_size(10).unwrap();
+    assert!(executor.collect(&input, &pipeline).is_err());
+    let orders = vec![ColumnOrder::asc(0)];
+    assert!(executor.sort(&input, &pipeline, orders).is_err());
+}
+
+/// Returns its input column, recording the threads evaluating it, and panics on `NULL`s if
+/// `panic_on_null`.
+struct RecordThreads {
+    threads: Arc<Mutex<HashSet<ThreadId>>>,
+    panic_on_null: bool,
+}
+
+impl Expression for RecordThreads {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        self.threads
+            .lock()
+            .unwrap()
+            .insert(std::thread::current().id());
+        assert!(!self.panic_on_null || (0..data[0].len()).all(|i| data[0].get(i).is_some()));
+        Ok(data[0].clone())
+    }
+}
+
+#[test]
+fn workers_of_calls() {
+    let input = input();
+    let threads = Arc::new(Mutex::default());
+    let record = RecordThreads {
+        threads: threads.clone(),
+        panic_on_null: false,
+    };
+    let pipeline = Pipeline::new(TYPES.to_vec())
+        .project(vec![Box::new(record)], vec![DataType::Integer])
+        .unwrap();
+    let executor = ParallelExecutor::new(3)
+        .unwrap()
+        .with_morsel_size(8)
+        .unwrap();
+    for _ in 0..10 {
+        executor.collect(&input, &pipeline).unwrap();
+        // Each call runs on its own workers, none of which is the calling thread.
+        let threads = std::mem::take(&mut *threads.lock().unwrap());
+        assert!(!threads.is_empty() && threads.len() <= 3);
+        assert!(!threads.contains(&std::thread::current().id()));
+    }
+
+    // Panics of morsels are resumed by the call, and the executor is still usable.
+    let panicking = Pipeline::new(TYPES.to_vec())
+        .project(
+            vec![Box::new(RecordThreads {
+                threads: Arc::default(),
+                panic_on_null: true,
+            })],
+            vec![DataType::Integer],
+        )
+        .unwrap();
+    let result =
+        std::panic::catch_unwind(AssertUnwindSafe(|| executor.collect(&input, &panicking)));
+    assert!(result.is_err());
+    let output = executor.collect(&input, &pipeline).unwrap();
+    assert_eq!(output.iter().map(Batch::num_rows).sum::<usize>(), 900);
+}
+
+/// Returns its input column after running a parallel call on it, like a subquery would.
+struct NestedCall {
+    executor: ParallelExecutor,
+}
+
+impl Expression for NestedCall {
+    fn eval_expr(&self, data: &[&ArrayImpl]) -> Result<ArrayImpl> {
+        let input = vec![Batch::new(vec![data[0].clone()])?];
+        let pipeline = Pipeline::new(vec![DataType::Integer]);
+        let output = self.executor.collect(&input, &pipeline)?;
+        let num_rows = output.iter().map(Batch::num_rows).sum::<usize>();
+        if num_rows != data[0].len() {
+            bail!("nested call returns {num_rows} rows");
+        }
+        Ok(data[0].clone())
+    }
+}
+
+#[test]
+fn nested_calls() {
+    // Calls from workers of another call run on their own workers instead of waiting for them.
+    let nested = NestedCall {
+        executor: ParallelExecutor::new(2)
+            .unwrap()
+            .with_morsel_size(4)
+            .unwrap(),
+    };
+    let pipeline = Pipeline::new(TYPES.to_vec())
+        .project(vec![Box::new(nested)], vec![DataType::Integer])
+        .unwrap();
+    let executor = ParallelExecutor::new(2)
+        .unwrap()
+        .with_morsel_size(16)
+        .unwrap();
+    let output = executor.collect(&input(), &pipeline).unwrap();
+    assert_eq!(output.iter().map(Batch::num_rows).sum::<usize>(), 900);
+}
+
+#[test]
+fn filter_without_input_columns() {
+    // SELECT 1 FROM (VALUES (), (), ..) WHERE 1 < 2, in morsels of batches without columns.
+    let input = vec![Batch::no_columns(10), Batch::no_columns(5)];
+    let pipeline = Pipeline::new(vec![])
+        .filter(call("less_than", vec![int(1), int(2)]))
+        .project(vec![int(1)], vec![DataType::Integer])
+        .unwrap();
+    let executor = ParallelExecutor::new(2)
+        .unwrap()
+        .with_morsel_size(4)
+        .unwrap();
+    let output = executor.collect(&input, &pipeline).unwrap();
+    assert_eq!(rows(&output), vec!["[Some(Int32(1))]"; 15]);
+}
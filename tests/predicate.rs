@@ -0,0 +1,152 @@
+//! Tests the `IN` and `BETWEEN` predicates.
+
+mod common;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::FunctionRegistry;
+use type_rust::expr::predicate::in_list;
+use type_rust::scalar::ScalarImpl;
+
+use common::{array, ints, strings, values};
+
+/// Evaluate `x IN (list)` of `integer`, or `x NOT IN (list)` if `negated`.
+fn in_ints(x: &[Option<i32>], list: &[Option<i32>], negated: bool) -> Vec<Option<bool>> {
+    let list = list.iter().map(|v| v.map(ScalarImpl::Int32)).collect();
+    let expr = in_list(DataType::Integer, list, negated).unwrap();
+    values::<BoolArray>(&expr.eval_expr(&[&ints(x)]).unwrap())
+}
+
+#[test]
+fn in_list_null_semantics() {
+    let x = [Some(1), Some(4), None];
+    assert_eq!(
+        in_ints(&x, &[Some(1), Some(2), Some(3)], false),
+        [Some(true), Some(false), None]
+    );
+    assert_eq!(
+        in_ints(&x, &[Some(1), Some(2), Some(3)], true),
+        [Some(false), Some(true), None]
+    );
+    // A `NULL` in the list turns `false` into `NULL`, but keeps `true`.
+    assert_eq!(
+        in_ints(&x, &[Some(1), None], false),
+        [Some(true), None, None]
+    );
+    assert_eq!(
+        in_ints(&x, &[Some(1), None], true),
+        [Some(false), None, None]
+    );
+    assert_eq!(in_ints(&x, &[], false), [Some(false), Some(false), None]);
+}
+
+#[test]
+fn in_list_hashed() {
+    // Long lists are looked up with a hash index, which must produce the same results as a scan.
+    let list = (0..100).map(|v| Some(v * 3)).collect::<Vec<_>>();
+    let x = (-5..310).map(Some).collect::<Vec<_>>();
+    let expected = x
+        .iter()
+        .map(|v| v.map(|v| (0..300).contains(&v) && v % 3 == 0))
+        .collect::<Vec<_>>();
+    assert_eq!(in_ints(&x, &list, false), expected);
+
+    let mut list = list;
+    list.push(None);
+    let result = in_ints(&[Some(3), Some(4)], &list, false);
+    assert_eq!(result, [Some(true), None]);
+}
+
+#[test]
+fn in_list_types() {
+    let list = [
+        "apple", "banana", "cherry", "date", "elder", "fig", "grape", "kiwi", "lime",
+    ]
+    .map(|s| Some(ScalarImpl::String(s.to_string())));
+    let expr = in_list(DataType::Varchar, list.to_vec(), false).unwrap();
+    let x = strings(&[Some("fig"), Some("mango"), None]);
+    let result = expr.eval_expr(&[&x]).unwrap();
+    assert_eq!(
+        values::<BoolArray>(&result),
+        [Some(true), Some(false), None]
+    );
+
+    // `NaN` equals `NaN`, and `0.0` equals `-0.0`, in both the scan and the hash index.
+    for len in [1, 20] {
+        let mut list = vec![
+            Some(ScalarImpl::Float64(f64::NAN)),
+            Some(ScalarImpl::Float64(0.0)),
+        ];
+        list.extend((1..len).map(|v| Some(ScalarImpl::Float64(v as f64 + 0.5))));
+        let expr = in_list(DataType::Double, list, false).unwrap();
+        let x = array::<F64Array>(&[Some(f64::NAN), Some(-0.0), Some(1.0)]);
+        let result = expr.eval_expr(&[&x]).unwrap();
+        assert_eq!(
+            values::<BoolArray>(&result),
+            [Some(true), Some(true), Some(false)]
+        );
+    }
+
+    // Values must be of the type of `x`.
+    let list = vec![Some(ScalarImpl::String("1".to_string()))];
+    assert!(in_list(DataType::Integer, list, false).is_err());
+    let expr = in_list(DataType::Integer, vec![Some(ScalarImpl::Int32(1))], false).unwrap();
+    assert!(expr.eval_expr(&[&strings(&[Some("1")])]).is_err());
+}
+
+/// Evaluate `between` or `not_between` with the types of the arrays.
+fn between(name: &str, v: ArrayImpl, lo: ArrayImpl, hi: ArrayImpl) -> Result<Vec<Option<bool>>> {
+    let types = [v.data_type(), lo.data_type(), hi.data_type()];
+    let func = FunctionRegistry::with_builtins().build(name, &types)?;
+    Ok(values::<BoolArray>(&func.eval_expr(&[&v, &lo, &hi])?))
+}
+
+#[test]
+fn between_null_semantics() {
+    let v = ints(&[Some(5), Some(0), Some(5), Some(0), None, Some(10)]);
+    let lo = ints(&[Some(1), Some(1), None, None, Some(1), Some(1)]);
+    let hi = ints(&[Some(10), Some(10), Some(10), Some(-1), Some(10), Some(10)]);
+    // A `NULL` bound does not matter if the comparison with the other bound is `false`.
+    assert_eq!(
+        between("between", v.clone(), lo.clone(), hi.clone()).unwrap(),
+        [Some(true), Some(false), None, Some(false), None, Some(true)]
+    );
+    assert_eq!(
+        between("not_between", v, lo, hi).unwrap(),
+        [Some(false), Some(true), None, Some(true), None, Some(false)]
+    );
+}
+
+#[test]
+fn between_types() {
+    let s = strings(&[Some("b"), Some("d")]);
+    let result = between(
+        "between",
+        s,
+        strings(&[Some("a"), Some("a")]),
+        strings(&[Some("c"), Some("c")]),
+    );
+    assert_eq!(result.unwrap(), [Some(true), Some(false)]);
+
+    // Bounds are cast into a common type with the value.
+    let v = array::<F64Array>(&[Some(1.5), Some(f64::NAN)]);
+    let result = between(
+        "between",
+        v,
+        ints(&[Some(1), Some(1)]),
+        array::<I64Array>(&[Some(2), Some(2)]),
+    );
+    // `NaN` is greater than any other value.
+    assert_eq!(result.unwrap(), [Some(true), Some(false)]);
+
+    assert!(
+        between(
+            "between",
+            strings(&[Some("1")]),
+            ints(&[Some(0)]),
+            ints(&[Some(2)])
+        )
+        .is_err()
+    );
+}
@@ -0,0 +1,300 @@
+//! Tests the regular expression engine and the `regexp_*` functions.
+
+mod common;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::FunctionRegistry;
+use type_rust::expr::regex::{Regex, RegexFlags};
+use type_rust::scalar::ScalarImpl;
+
+use common::{array, error, strings, values};
+
+/// Call function `name` on `input` with the other arguments `args` repeated in every row, both as
+/// columns and as constants, and check that the results are the same.
+fn call(name: &str, input: &[&str], args: &[ScalarImpl]) -> Result<ArrayImpl> {
+    let registry = FunctionRegistry::with_builtins();
+    let n = input.len();
+    let mut columns = vec![strings(&input.iter().map(|s| Some(*s)).collect::<Vec<_>>())];
+    let mut constants = vec![None];
+    for arg in args {
+        columns.push(match arg {
+            ScalarImpl::String(s) => strings(&vec![Some(s.as_str()); n]),
+            ScalarImpl::Int32(v) => array::<I32Array>(&vec![Some(*v); n]),
+            _ => unreachable!(),
+        });
+        constants.push(Some(arg.clone()));
+    }
+    let types = columns
+        .iter()
+        .map(|column| column.data_type())
+        .collect::<Vec<_>>();
+    let inputs = columns.iter().collect::<Vec<_>>();
+
+    let generic = registry.build(name, &types)?.eval_expr(&inputs);
+    let constant = registry
+        .build_with_constants(name, &types, &constants)
+        .and_then(|func| func.eval_expr(&inputs));
+    let format = |result: &Result<ArrayImpl>| match result {
+        Ok(array) => format!(
+            "{:?}",
+            (0..array.len())
+                .map(|row| array.get(row))
+                .collect::<Vec<_>>()
+        ),
+        Err(err) => err.to_string(),
+    };
+    assert_eq!(format(&generic), format(&constant), "{name} {args:?}");
+    generic
+}
+
+fn string(s: &str) -> ScalarImpl {
+    ScalarImpl::String(s.to_string())
+}
+
+fn regexp_like(input: &[&str], pattern: &str, flags: Option<&str>) -> Vec<bool> {
+    let mut args = vec![string(pattern)];
+    args.extend(flags.map(string));
+    values::<BoolArray>(&call("regexp_like", input, &args).unwrap())
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+fn call_str(name: &str, input: &[&str], args: &[ScalarImpl]) -> Vec<Option<String>> {
+    values::<StringArray>(&call(name, input, args).unwrap())
+}
+
+fn owned(values: &[Option<&str>]) -> Vec<Option<String>> {
+    values.iter().map(|v| v.map(str::to_string)).collect()
+}
+
+#[test]
+fn regex_syntax() {
+    let is_match = |pattern: &str, s: &str| Regex::new(pattern).unwrap().is_match(s);
+    assert!(is_match("b.d", "abcde"));
+    assert!(!is_match("b.d", "abde"));
+    assert!(is_match("^ab", "abc"));
+    assert!(!is_match("^bc", "abc"));
+    assert!(is_match("bc$", "abc"));
+    assert!(is_match("a\\.c", "a.c"));
+    assert!(!is_match("a\\.c", "abc"));
+    assert!(is_match("^[a-c_]+$", "ab_c"));
+    assert!(!is_match("^[^0-9]+$", "ab1"));
+    assert!(is_match("^[[:alpha:]]+$", "abC"));
+    assert!(is_match("^\\d{3}-\\d{4}$", "555-1234"));
+    assert!(!is_match("^\\d{3}-\\d{4}$", "555-123"));
+    assert!(is_match("^\\w+\\s\\w+$", "hello world"));
+    assert!(is_match("\\bcat\\b", "a cat sat"));
+    assert!(!is_match("\\bcat\\b", "concatenate"));
+    assert!(is_match("^(ab|cd)+$", "abcdab"));
+    assert!(!is_match("^(ab|cd)+$", "abca"));
+    assert!(is_match("^a{2,3}$", "aaa"));
+    assert!(!is_match("^a{2,3}$", "aaaa"));
+    assert!(is_match("^a{2,}$", "aaaa"));
+    assert!(is_match("^é.$", "éa"));
+
+    // Back references are not supported, like malformed patterns.
+    for pattern in ["(a)\\1", "(ab", "a{3,2}"] {
+        assert!(Regex::new(pattern).is_err(), "{pattern}");
+    }
+}
+
+#[test]
+fn regex_nesting() {
+    let nested =
+        |depth: usize, inner: &str| format!("{}{inner}{}", "(?:".repeat(depth), ")".repeat(depth));
+    assert!(Regex::new(&nested(500, "a")).unwrap().is_match("a"));
+    assert!(Regex::new(&format!("a{}", "{1}".repeat(500))).is_ok());
+    let pattern = format!("{}a{}", "(?:".repeat(250), ")*".repeat(250));
+    assert!(Regex::new(&pattern).unwrap().is_match("aa"));
+    // Groups and quantifiers nested too deeply are errors rather than overflowing the stack.
+    for pattern in [
+        nested(501, "a"),
+        nested(100_000, "a"),
+        "(".repeat(100_000),
+        nested(499, "a**"),
+        format!("{}a{}", "(?:".repeat(251), ")*".repeat(251)),
+        format!("{}{}", nested(250, "a"), "*".repeat(251)),
+        format!("a{}", "{1}".repeat(100_000)),
+    ] {
+        let err = Regex::new(&pattern).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid regular expression: nesting too deep"
+        );
+    }
+}
+
+#[test]
+fn regex_captures() {
+    let regex = Regex::new("(\\w+)@(\\w+)(\\.com)?").unwrap();
+    assert_eq!(regex.groups(), 3);
+    let captures = regex.captures("mail alice@example now").unwrap();
+    assert_eq!(captures.get(0), Some("alice@example"));
+    assert_eq!(captures.get(1), Some("alice"));
+    assert_eq!(captures.get(2), Some("example"));
+    assert_eq!(captures.get(3), None);
+    assert_eq!(captures.range(1), Some((5, 10)));
+
+    // Lazy quantifiers match as few characters as possible.
+    let lazy = Regex::new("<.+?>").unwrap();
+    assert_eq!(lazy.captures("<a><b>").unwrap().get(0), Some("<a>"));
+    let greedy = Regex::new("<.+>").unwrap();
+    assert_eq!(greedy.captures("<a><b>").unwrap().get(0), Some("<a><b>"));
+
+    let matches = Regex::new("\\d+")
+        .unwrap()
+        .captures_iter("a1b22c333")
+        .map(|c| c.get(0).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(matches, ["1", "22", "333"]);
+}
+
+#[test]
+fn regex_flags() {
+    let flags = RegexFlags::parse("gi").unwrap();
+    assert!(flags.global && flags.case_insensitive);
+    assert!(!RegexFlags::parse("ic").unwrap().case_insensitive);
+    assert!(RegexFlags::parse("x").is_err());
+
+    let inputs = ["ABC", "abc", "xyz"];
+    assert_eq!(regexp_like(&inputs, "^abc$", None), [false, true, false]);
+    assert_eq!(
+        regexp_like(&inputs, "^abc$", Some("i")),
+        [true, true, false]
+    );
+    assert_eq!(regexp_like(&inputs, "b", Some("")), [false, true, false]);
+    assert_eq!(
+        error(call("regexp_like", &inputs, &[string("a"), string("g")])),
+        "regexp_like() does not support the \"global\" option"
+    );
+}
+
+#[test]
+fn regexp_replace() {
+    let inputs = ["foo bar foo", "FOO", ""];
+    assert_eq!(
+        call_str("regexp_replace", &inputs, &[string("foo"), string("x")]),
+        owned(&[Some("x bar foo"), Some("FOO"), Some("")])
+    );
+    assert_eq!(
+        call_str(
+            "regexp_replace",
+            &inputs,
+            &[string("foo"), string("x"), string("gi")]
+        ),
+        owned(&[Some("x bar x"), Some("x"), Some("")])
+    );
+    // Replacements refer to groups with `\1` and to the whole match with `\&`.
+    assert_eq!(
+        call_str(
+            "regexp_replace",
+            &["john smith"],
+            &[string("(\\w+) (\\w+)"), string("\\2, \\1 [\\&]")]
+        ),
+        owned(&[Some("smith, john [john smith]")])
+    );
+    // Empty matches are replaced between characters, but not right after another match.
+    assert_eq!(
+        call_str(
+            "regexp_replace",
+            &["abc"],
+            &[string("x*"), string("-"), string("g")]
+        ),
+        owned(&[Some("-a-b-c-")])
+    );
+}
+
+#[test]
+fn regexp_extract() {
+    let inputs = ["key=value", "novalue", "a=b=c"];
+    assert_eq!(
+        call_str("regexp_extract", &inputs, &[string("\\w+=\\w+")]),
+        owned(&[Some("key=value"), None, Some("a=b")])
+    );
+    assert_eq!(
+        call_str(
+            "regexp_extract",
+            &inputs,
+            &[string("(\\w+)=(\\w+)"), ScalarImpl::Int32(2)]
+        ),
+        owned(&[Some("value"), None, Some("b")])
+    );
+    assert_eq!(
+        error(call(
+            "regexp_extract",
+            &inputs,
+            &[string("(\\w+)"), ScalarImpl::Int32(2)]
+        )),
+        "regexp group index 2 out of range"
+    );
+}
+
+#[test]
+fn regexp_split_part() {
+    let split = |s: &str, pattern: &str, n: i32| {
+        call_str(
+            "regexp_split_part",
+            &[s],
+            &[string(pattern), ScalarImpl::Int32(n)],
+        )
+        .pop()
+        .unwrap()
+        .unwrap()
+    };
+    assert_eq!(split("a, b,c", ",\\s*", 1), "a");
+    assert_eq!(split("a, b,c", ",\\s*", 2), "b");
+    assert_eq!(split("a, b,c", ",\\s*", 3), "c");
+    assert_eq!(split("a, b,c", ",\\s*", 4), "");
+    assert_eq!(split("a,,b", ",", 2), "");
+    // Empty matches split between characters.
+    assert_eq!(split("abc", "", 2), "b");
+    assert_eq!(
+        error(call(
+            "regexp_split_part",
+            &["a"],
+            &[string(","), ScalarImpl::Int32(0)]
+        )),
+        "field position must be greater than zero"
+    );
+}
+
+#[test]
+fn regexp_nulls_and_errors() {
+    let registry = FunctionRegistry::with_builtins();
+    let func = registry
+        .build("regexp_like", &[DataType::Varchar, DataType::Varchar])
+        .unwrap();
+    let s = strings(&[Some("a"), None, Some("a")]);
+    let pattern = strings(&[Some("a"), Some("a"), None]);
+    let result = func.eval_expr(&[&s, &pattern]).unwrap();
+    assert_eq!(values::<BoolArray>(&result), vec![Some(true), None, None]);
+
+    // Patterns of each row are compiled separately.
+    let pattern = strings(&[Some("a"), Some("b"), Some("(")]);
+    assert!(func.eval_expr(&[&s, &pattern]).is_err());
+    let pattern = strings(&[Some("a")]);
+    assert_eq!(
+        error(func.eval_expr(&[&s, &pattern])),
+        "array length mismatch"
+    );
+
+    // Constant patterns are compiled when the call is built.
+    let types = [DataType::Varchar; 2];
+    let constants = [None, Some(string("("))];
+    assert!(
+        registry
+            .build_with_constants("regexp_like", &types, &constants)
+            .is_err()
+    );
+    // A pattern with options which are not constant is compiled for each row.
+    let types = [DataType::Varchar; 3];
+    let constants = [None, Some(string("(")), None];
+    assert!(
+        registry
+            .build_with_constants("regexp_like", &types, &constants)
+            .is_ok()
+    );
+}
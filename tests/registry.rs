@@ -0,0 +1,277 @@
+//! Tests resolution and registration of functions in [`FunctionRegistry`].
+
+mod common;
+
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::expr::vectorize::BinaryExpFunc;
+use type_rust::expr::{FunctionRegistry, FunctionSignature};
+
+use common::{array, values};
+
+fn resolve(registry: &FunctionRegistry, name: &str, args: &[DataType]) -> String {
+    registry.resolve(name, args).unwrap().to_string()
+}
+
+#[test]
+fn resolve_exact_match() {
+    let registry = FunctionRegistry::with_builtins();
+    assert_eq!(
+        resolve(
+            &registry,
+            "less_than",
+            &[DataType::Integer, DataType::Integer]
+        ),
+        "less_than(integer, integer) -> boolean"
+    );
+    assert_eq!(
+        resolve(
+            &registry,
+            "less_than",
+            &[DataType::Double, DataType::Double]
+        ),
+        "less_than(double precision, double precision) -> boolean"
+    );
+}
+
+#[test]
+fn resolve_with_implicit_casts() {
+    let registry = FunctionRegistry::with_builtins();
+    // The cheapest overload all arguments can be cast to is chosen.
+    assert_eq!(
+        resolve(
+            &registry,
+            "less_than",
+            &[DataType::SmallInt, DataType::Integer]
+        ),
+        "less_than(integer, integer) -> boolean"
+    );
+    assert_eq!(
+        resolve(&registry, "less_than", &[DataType::BigInt, DataType::Real]),
+        "less_than(double precision, double precision) -> boolean"
+    );
+    assert_eq!(
+        resolve(
+            &registry,
+            "less_than",
+            &[DataType::Integer, DataType::Double]
+        ),
+        "less_than(double precision, double precision) -> boolean"
+    );
+}
+
+#[test]
+fn resolve_errors() {
+    let registry = FunctionRegistry::with_builtins();
+    let err = registry
+        .resolve("no_such_function", &[DataType::Integer])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "function no_such_function(integer) does not exist"
+    );
+    // No overload takes three arguments, and booleans are not implicitly cast to numbers.
+    assert!(
+        registry
+            .resolve("less_than", &[DataType::Integer; 3])
+            .is_err()
+    );
+    assert!(
+        registry
+            .resolve("less_than", &[DataType::Boolean, DataType::Integer])
+            .is_err()
+    );
+    // Narrowing casts are never implicit.
+    let mut registry = FunctionRegistry::new();
+    registry
+        .register_binary::<I16Array, I16Array, I16Array, _>(
+            "first(smallint, smallint) -> smallint",
+            First,
+        )
+        .unwrap();
+    assert!(
+        registry
+            .resolve("first", &[DataType::Integer, DataType::SmallInt])
+            .is_err()
+    );
+}
+
+/// Returns the first argument.
+struct First;
+
+impl<A: Array> BinaryExpFunc<A, A, A> for First {
+    fn eval<'a>(&self, i1: A::RefItem<'a>, _: A::RefItem<'a>) -> A::OwnedItem {
+        i1.to_owned_scalar()
+    }
+}
+
+#[test]
+fn resolve_ambiguous() {
+    let mut registry = FunctionRegistry::new();
+    registry
+        .register_binary::<I64Array, I32Array, I64Array, _>("f(bigint, integer) -> bigint", Widen)
+        .unwrap();
+    registry
+        .register_binary::<I32Array, I64Array, I64Array, _>("f(integer, bigint) -> bigint", Widen)
+        .unwrap();
+    // Both overloads cost the same for two smallints.
+    let err = registry
+        .resolve("f", &[DataType::SmallInt, DataType::SmallInt])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "function f(smallint, smallint) is not unique"
+    );
+    // An exact match is not ambiguous.
+    assert_eq!(
+        resolve(&registry, "f", &[DataType::Integer, DataType::BigInt]),
+        "f(integer, bigint) -> bigint"
+    );
+}
+
+/// Adds two integers of different widths into a `bigint`.
+struct Widen;
+
+impl<I1: Array, I2: Array> BinaryExpFunc<I1, I2, I64Array> for Widen
+where
+    for<'a> I1::RefItem<'a>: Into<i64>,
+    for<'a> I2::RefItem<'a>: Into<i64>,
+{
+    fn eval<'a>(&self, i1: I1::RefItem<'a>, i2: I2::RefItem<'a>) -> i64 {
+        i1.into() + i2.into()
+    }
+}
+
+#[test]
+fn register_at_runtime() {
+    let mut registry = FunctionRegistry::new();
+    registry
+        .register_binary::<I32Array, I32Array, I32Array, _>(
+            "first(integer, integer) -> integer",
+            First,
+        )
+        .unwrap();
+
+    // Registering the same arguments twice fails, even with another return type.
+    let err = registry
+        .register_binary::<I32Array, I32Array, I64Array, _>(
+            "first(integer, integer) -> bigint",
+            Widen,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "function first(integer, integer) -> bigint already exists"
+    );
+    // Types in the signature must match the arrays of the function.
+    assert!(
+        registry
+            .register_binary::<I32Array, I32Array, I32Array, _>(
+                "first(bigint, bigint) -> bigint",
+                First,
+            )
+            .is_err()
+    );
+
+    let left = array::<I32Array>(&[Some(1), None, Some(3)]);
+    let right = array::<I32Array>(&[Some(4), Some(5), None]);
+    let func = registry
+        .build("first", &[DataType::Integer, DataType::Integer])
+        .unwrap();
+    let result = func.eval_expr(&[&left, &right]).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(1), None, None]);
+}
+
+#[test]
+#[allow(deprecated)]
+fn build_binary_expression_over_registry() {
+    use type_rust::expr::{ExpressionFunc, build_binary_expression};
+
+    let left = array::<I32Array>(&[Some(1), Some(2), None]);
+    let right = array::<I32Array>(&[Some(2), Some(2), Some(3)]);
+    let result = build_binary_expression(ExpressionFunc::CmpEq)
+        .eval_expr(&[&left, &right])
+        .unwrap();
+    assert_eq!(
+        values::<BoolArray>(&result),
+        vec![Some(false), Some(true), None]
+    );
+}
+
+#[test]
+fn build_casts_arguments() {
+    let registry = FunctionRegistry::with_builtins();
+    let func = registry
+        .build("less_than", &[DataType::SmallInt, DataType::Integer])
+        .unwrap();
+    let left = array::<I16Array>(&[Some(i16::MAX), Some(-1), None]);
+    let right = array::<I32Array>(&[Some(32768), Some(-2), Some(0)]);
+    let result = func.eval_expr(&[&left, &right]).unwrap();
+    assert_eq!(
+        values::<BoolArray>(&result),
+        vec![Some(true), Some(false), None]
+    );
+}
+
+#[test]
+fn build_casts_between_types_of_same_array() {
+    let mut registry = FunctionRegistry::new();
+    registry
+        .register_binary::<StringArray, StringArray, StringArray, _>(
+            "first(varchar, varchar) -> varchar",
+            First,
+        )
+        .unwrap();
+    // `char(n)` and `varchar` are both stored in string arrays, but `char(3)` arguments are
+    // still cast into `varchar`.
+    let func = registry
+        .build("first", &[DataType::Char { width: 3 }, DataType::Varchar])
+        .unwrap();
+    let padded = common::strings(&[Some("ab "), None]);
+    let result = func.eval_expr(&[&padded, &padded]).unwrap();
+    assert_eq!(
+        values::<StringArray>(&result),
+        vec![Some("ab ".to_string()), None]
+    );
+}
+
+#[test]
+fn variadic_signatures() {
+    let signature: FunctionSignature = "concat(varchar...) -> varchar".parse().unwrap();
+    assert!(signature.variadic);
+    assert_eq!(signature.args, vec![DataType::Varchar]);
+    assert_eq!(signature.to_string(), "concat(varchar...) -> varchar");
+    assert!("f(...) -> integer".parse::<FunctionSignature>().is_err());
+
+    let mut registry = FunctionRegistry::new();
+    registry
+        .register(
+            "f(bigint...) -> bigint".parse().unwrap(),
+            || unimplemented!(),
+        )
+        .unwrap();
+    // The last argument may be repeated any number of times, but at least once.
+    for num_args in 1..4 {
+        let args = vec![DataType::Integer; num_args];
+        assert_eq!(resolve(&registry, "f", &args), "f(bigint...) -> bigint");
+    }
+    assert_eq!(
+        registry.resolve("f", &[]).unwrap_err().to_string(),
+        "function f() does not exist"
+    );
+    registry
+        .register(
+            "f(bigint, bigint) -> bigint".parse().unwrap(),
+            || unimplemented!(),
+        )
+        .unwrap();
+    // A non-variadic signature is preferred on a tie.
+    assert_eq!(
+        resolve(&registry, "f", &[DataType::Integer, DataType::Integer]),
+        "f(bigint, bigint) -> bigint"
+    );
+    assert_eq!(
+        resolve(&registry, "f", &[DataType::Integer; 3]),
+        "f(bigint...) -> bigint"
+    );
+}
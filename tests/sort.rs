@@ -0,0 +1,268 @@
+//! Tests sorting with and without spilling, top-n, and spill files.
+
+mod common;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::executor::spill::SpillFile;
+use type_rust::executor::{
+    BoxedOperator, ColumnOrder, Limit, PhysicalOperator, Scan, Sort, SpillConfig, TopN, batches,
+};
+
+use common::{array, error, ints, num_files, rows, spill_dir, strings};
+
+const TYPES: [DataType; 3] = [DataType::Integer, DataType::Varchar, DataType::Integer];
+
+/// `n` rows of `(k, s, id)` in batches of `batch_size` rows, where `k` and `s` are pseudo-random
+/// with many duplicates and `NULL`s, and `id` is the position of the row.
+fn input(n: usize, batch_size: usize) -> Vec<Batch> {
+    let mut seed = 42u64;
+    let mut next = move || {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        (seed >> 33) as usize
+    };
+    let rows = (0..n)
+        .map(|id| {
+            let k = Some(next() % 8).filter(|k| *k != 0).map(|k| k as i32);
+            let s = Some(next() % 4).filter(|s| *s != 0).map(|s| "x".repeat(s));
+            (k, s, Some(id as i32))
+        })
+        .collect::<Vec<_>>();
+    rows.chunks(batch_size)
+        .map(|rows| {
+            let k = rows.iter().map(|row| row.0).collect::<Vec<_>>();
+            let s = rows.iter().map(|row| row.1.as_deref()).collect::<Vec<_>>();
+            let id = rows.iter().map(|row| row.2).collect::<Vec<_>>();
+            Batch::new(vec![ints(&k), strings(&s), ints(&id)]).unwrap()
+        })
+        .collect()
+}
+
+fn scan(batches: Vec<Batch>) -> BoxedOperator {
+    Box::new(Scan::new(TYPES.to_vec(), batches).unwrap())
+}
+
+fn collect(operator: &mut dyn PhysicalOperator) -> Vec<Batch> {
+    batches(operator).collect::<Result<Vec<_>>>().unwrap()
+}
+
+/// Rows of `batches` stably sorted by `orders`, one row at a time.
+fn expected(batches: &[Batch], orders: &[ColumnOrder]) -> Vec<String> {
+    let mut rows = batches
+        .iter()
+        .flat_map(|batch| (0..batch.num_rows()).map(move |row| (batch, row)))
+        .collect::<Vec<_>>();
+    rows.sort_by(|(a, ra), (b, rb)| {
+        let columns = [a.column(0), a.column(1)].map(|c| c.get(*ra));
+        let others = [b.column(0), b.column(1)].map(|c| c.get(*rb));
+        orders
+            .iter()
+            .map(|order| order.compare(columns[order.column], others[order.column]))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    rows.into_iter()
+        .map(|(batch, row)| {
+            let values = batch
+                .columns()
+                .iter()
+                .map(|column| column.get(row))
+                .collect::<Vec<_>>();
+            format!("{values:?}")
+        })
+        .collect()
+}
+
+fn orders() -> Vec<Vec<ColumnOrder>> {
+    vec![
+        vec![ColumnOrder::asc(0)],
+        vec![ColumnOrder::desc(0), ColumnOrder::asc(1).nulls_first(true)],
+        vec![ColumnOrder::asc(1), ColumnOrder::desc(0).nulls_first(false)],
+    ]
+}
+
+#[test]
+fn sort_in_memory() {
+    let input = input(200, 7);
+    for orders in orders() {
+        let mut sort = Sort::new(scan(input.clone()), orders.clone())
+            .unwrap()
+            .with_batch_size(16);
+        let output = collect(&mut sort);
+        assert!(output.iter().all(|batch| batch.num_rows() <= 16));
+        assert_eq!(rows(&output), expected(&input, &orders), "{orders:?}");
+    }
+
+    // `NULL`s are last in ascending order and first in descending order by default.
+    let batch = Batch::new(vec![
+        ints(&[Some(2), None, Some(1)]),
+        strings(&[Some("b"), Some("n"), Some("a")]),
+        ints(&[Some(0), Some(1), Some(2)]),
+    ])
+    .unwrap();
+    let sort = |orders| {
+        let mut sort = Sort::new(scan(vec![batch.clone()]), orders).unwrap();
+        rows(&collect(&mut sort))
+            .iter()
+            .map(|row| row.split(", ").last().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    let ids = |ids: [i32; 3]| ids.map(|id| format!("Some(Int32({id}))]"));
+    assert_eq!(sort(vec![ColumnOrder::asc(0)]), ids([2, 0, 1]));
+    assert_eq!(sort(vec![ColumnOrder::desc(0)]), ids([1, 0, 2]));
+    assert_eq!(
+        sort(vec![ColumnOrder::asc(0).nulls_first(true)]),
+        ids([1, 2, 0])
+    );
+    // Without any order, the input is returned as is.
+    assert_eq!(sort(vec![]), ids([0, 1, 2]));
+}
+
+#[test]
+fn sort_with_spilling() {
+    let dir = spill_dir("sort");
+    let input = input(500, 10);
+    let size = input[0].estimated_size();
+    // Spill each batch as a run, a few batches as a run, or spill nothing.
+    for budget in [0, size * 5, usize::MAX] {
+        for orders in orders() {
+            let config = SpillConfig {
+                dir: dir.clone(),
+                memory_budget: budget,
+            };
+            let mut sort = Sort::new(scan(input.clone()), orders.clone())
+                .unwrap()
+                .with_spill_config(config)
+                .with_batch_size(32);
+            let first = sort.next_batch().unwrap().unwrap();
+            let runs = num_files(&dir);
+            match budget {
+                0 => assert_eq!(runs, 50),
+                usize::MAX => assert_eq!(runs, 0),
+                _ => assert!(runs > 1 && runs < 50),
+            }
+            let mut output = vec![first];
+            output.extend(collect(&mut sort));
+            assert!(output.iter().all(|batch| batch.num_rows() <= 32));
+            assert_eq!(rows(&output), expected(&input, &orders), "{orders:?}");
+            // Spill files are removed with the operator.
+            drop(sort);
+            assert_eq!(num_files(&dir), 0);
+        }
+    }
+
+    // With at most 4 runs merged at once, 50 runs are merged into 13, 4 and then 1 before the
+    // final merge with the rows in memory.
+    for orders in orders() {
+        let config = SpillConfig {
+            dir: dir.clone(),
+            memory_budget: 0,
+        };
+        let mut sort = Sort::new(scan(input.clone()), orders.clone())
+            .unwrap()
+            .with_spill_config(config)
+            .with_fan_in(4)
+            .unwrap();
+        let first = sort.next_batch().unwrap().unwrap();
+        assert_eq!(num_files(&dir), 1);
+        let mut output = vec![first];
+        output.extend(collect(&mut sort));
+        assert_eq!(rows(&output), expected(&input, &orders), "{orders:?}");
+        drop(sort);
+        assert_eq!(num_files(&dir), 0);
+    }
+    assert!(
+        Sort::new(scan(vec![]), vec![])
+            .unwrap()
+            .with_fan_in(1)
+            .is_err()
+    );
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn top_n() {
+    let input = input(300, 9);
+    for orders in orders() {
+        for (limit, offset) in [(0, 0), (1, 0), (10, 0), (10, 25), (50, 280), (1000, 0)] {
+            let sort = Sort::new(scan(input.clone()), orders.clone()).unwrap();
+            let mut limit_sort = Limit::new(Box::new(sort), Some(limit), offset);
+            let expected = rows(&collect(&mut limit_sort));
+
+            let mut top_n = TopN::new(scan(input.clone()), orders.clone(), limit, offset)
+                .unwrap()
+                .with_batch_size(4);
+            let output = collect(&mut top_n);
+            assert!(output.iter().all(|batch| batch.num_rows() <= 4));
+            // Rows of equal order keys are taken in the order of the input.
+            assert_eq!(rows(&output), expected, "{orders:?} {limit} {offset}");
+        }
+    }
+}
+
+#[test]
+fn order_errors() {
+    assert_eq!(
+        error(Sort::new(scan(vec![]), vec![ColumnOrder::asc(3)])),
+        "column index 3 out of range, there are 3 input columns"
+    );
+    assert!(TopN::new(scan(vec![]), vec![ColumnOrder::desc(5)], 1, 0).is_err());
+}
+
+#[test]
+fn spill_files() {
+    let dir = spill_dir("spill");
+    // Values are read back exactly, including `-0.0` and `NaN`.
+    let batch = Batch::new(vec![
+        array::<I16Array>(&[Some(-1), None, Some(i16::MAX)]),
+        ints(&[Some(i32::MIN), Some(0), None]),
+        array::<I64Array>(&[None, Some(i64::MAX), Some(-7)]),
+        array::<F32Array>(&[Some(-0.0), Some(f32::NAN), None]),
+        array::<F64Array>(&[Some(f64::INFINITY), None, Some(-0.0)]),
+        array::<BoolArray>(&[Some(true), Some(false), None]),
+        strings(&[Some(""), None, Some("héllo")]),
+    ])
+    .unwrap();
+    let types = [
+        DataType::SmallInt,
+        DataType::Integer,
+        DataType::BigInt,
+        DataType::Real,
+        DataType::Double,
+        DataType::Boolean,
+        DataType::Varchar,
+    ];
+    let mut file = SpillFile::create(&dir).unwrap();
+    file.write(&batch).unwrap();
+    file.write(&batch.filter(&[false, true, false])).unwrap();
+    file.write(&batch.filter(&[false; 3])).unwrap();
+    let read = file
+        .into_reader(types.to_vec())
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(read.len(), 3);
+    assert_eq!(read[2].num_rows(), 0);
+    let mut expected = rows(std::slice::from_ref(&batch));
+    expected.push(expected[1].clone());
+    assert_eq!(rows(&read), expected);
+    assert_eq!(num_files(&dir), 0);
+
+    // Batches without columns keep their number of rows.
+    let mut file = SpillFile::create(&dir).unwrap();
+    file.write(&Batch::no_columns(5)).unwrap();
+    let mut reader = file.into_reader(vec![]).unwrap();
+    assert_eq!(reader.next_batch().unwrap().unwrap().num_rows(), 5);
+    assert!(reader.next_batch().unwrap().is_none());
+    drop(reader);
+
+    // The number of rows of a batch is written as a `u32`.
+    let mut file = SpillFile::create(&dir).unwrap();
+    assert_eq!(
+        error(file.write(&Batch::no_columns(1 << 32))),
+        "cannot spill a batch of 4294967296 rows"
+    );
+    drop(file);
+    std::fs::remove_dir(&dir).unwrap();
+}
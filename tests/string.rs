@@ -0,0 +1,255 @@
+//! Tests string functions registered in [`FunctionRegistry`].
+
+mod common;
+
+use anyhow::Result;
+use type_rust::array::*;
+use type_rust::expr::FunctionRegistry;
+
+use common::{error, ints, strings, values};
+
+/// Call function `name` on `args`, with the types of the arrays.
+fn call(name: &str, args: &[ArrayImpl]) -> Result<ArrayImpl> {
+    let types = args.iter().map(|arg| arg.data_type()).collect::<Vec<_>>();
+    let func = FunctionRegistry::with_builtins().build(name, &types)?;
+    func.eval_expr(&args.iter().collect::<Vec<_>>())
+}
+
+/// Call function `name` returning strings on `args`.
+fn call_str(name: &str, args: &[ArrayImpl]) -> Vec<Option<String>> {
+    values::<StringArray>(&call(name, args).unwrap())
+}
+
+fn owned(values: &[Option<&str>]) -> Vec<Option<String>> {
+    values.iter().map(|v| v.map(str::to_string)).collect()
+}
+
+#[test]
+fn length() {
+    let input = strings(&[Some("héllo"), Some(""), None]);
+    let result = call("length", std::slice::from_ref(&input)).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(5), Some(0), None]);
+    let result = call("char_length", std::slice::from_ref(&input)).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(5), Some(0), None]);
+    let result = call("octet_length", &[input]).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(6), Some(0), None]);
+}
+
+#[test]
+fn case_conversion() {
+    let input = strings(&[Some("Straße Ǆ"), None]);
+    assert_eq!(
+        call_str("upper", std::slice::from_ref(&input)),
+        owned(&[Some("STRASSE Ǆ"), None])
+    );
+    assert_eq!(
+        call_str("lower", &[input]),
+        owned(&[Some("straße ǆ"), None])
+    );
+}
+
+#[test]
+fn trim() {
+    let input = strings(&[Some("  a b  "), Some("xxaxx"), Some("")]);
+    assert_eq!(
+        call_str("trim", std::slice::from_ref(&input)),
+        owned(&[Some("a b"), Some("xxaxx"), Some("")])
+    );
+    assert_eq!(
+        call_str("ltrim", std::slice::from_ref(&input)),
+        owned(&[Some("a b  "), Some("xxaxx"), Some("")])
+    );
+    assert_eq!(
+        call_str("rtrim", std::slice::from_ref(&input)),
+        owned(&[Some("  a b"), Some("xxaxx"), Some("")])
+    );
+    let chars = strings(&[Some(" b"), Some("x"), None]);
+    assert_eq!(
+        call_str("trim", &[input.clone(), chars.clone()]),
+        owned(&[Some("a"), Some("a"), None])
+    );
+    assert_eq!(
+        call_str("ltrim", &[input.clone(), chars.clone()]),
+        owned(&[Some("a b  "), Some("axx"), None])
+    );
+    assert_eq!(
+        call_str("rtrim", &[input, chars]),
+        owned(&[Some("  a"), Some("xxa"), None])
+    );
+}
+
+#[test]
+fn substr() {
+    let input = strings(&[Some("héllo"); 5]);
+    let start = ints(&[Some(2), Some(0), Some(-2), Some(4), Some(10)]);
+    let count = ints(&[Some(3), Some(2), Some(4), Some(10), Some(1)]);
+    // Positions before the first character are counted, but produce nothing.
+    assert_eq!(
+        call_str("substr", &[input.clone(), start.clone(), count]),
+        owned(&[Some("éll"), Some("h"), Some("h"), Some("lo"), Some("")])
+    );
+    assert_eq!(
+        call_str("substr", &[input.clone(), start]),
+        owned(&[
+            Some("éllo"),
+            Some("héllo"),
+            Some("héllo"),
+            Some("lo"),
+            Some("")
+        ])
+    );
+    let err = error(call(
+        "substr",
+        &[strings(&[Some("a")]), ints(&[Some(1)]), ints(&[Some(-1)])],
+    ));
+    assert_eq!(err, "negative substring length not allowed");
+}
+
+#[test]
+fn replace() {
+    let input = strings(&[Some("abcabc"), Some("aaa"), Some("abc"), None]);
+    let from = strings(&[Some("bc"), Some("aa"), Some(""), Some("a")]);
+    let to = strings(&[Some("X"), Some("b"), Some("X"), Some("b")]);
+    assert_eq!(
+        call_str("replace", &[input, from, to]),
+        owned(&[Some("aXaX"), Some("ba"), Some("abc"), None])
+    );
+}
+
+#[test]
+fn concat() {
+    let a = strings(&[Some("a"), None, None]);
+    let b = strings(&[Some("b"), Some("b"), None]);
+    let c = strings(&[None, Some("c"), None]);
+    // `NULL` arguments are ignored.
+    assert_eq!(
+        call_str("concat", std::slice::from_ref(&a)),
+        owned(&[Some("a"), Some(""), Some("")])
+    );
+    assert_eq!(
+        call_str("concat", &[a.clone(), b.clone()]),
+        owned(&[Some("ab"), Some("b"), Some("")])
+    );
+    assert_eq!(
+        call_str(
+            "concat",
+            &[a.clone(), b.clone(), c.clone(), a.clone(), b.clone()]
+        ),
+        owned(&[Some("abab"), Some("bcb"), Some("")])
+    );
+    // Unlike `concat`, `||` produces `NULL` on `NULL` inputs.
+    assert_eq!(
+        call_str("concat_op", &[a, b]),
+        owned(&[Some("ab"), None, None])
+    );
+    assert!(call("concat", &[]).is_err());
+    assert!(call("concat", &[c, ints(&[Some(1); 3])]).is_err());
+}
+
+#[test]
+fn pad() {
+    let input = strings(&[Some("hi"), Some("hello"), Some("hé")]);
+    let len = ints(&[Some(5), Some(3), Some(4)]);
+    assert_eq!(
+        call_str("lpad", &[input.clone(), len.clone()]),
+        owned(&[Some("   hi"), Some("hel"), Some("  hé")])
+    );
+    assert_eq!(
+        call_str("rpad", &[input.clone(), len.clone()]),
+        owned(&[Some("hi   "), Some("hel"), Some("hé  ")])
+    );
+    let fill = strings(&[Some("xy"), Some("xy"), Some("")]);
+    // An empty fill leaves the string as it is, only truncated.
+    assert_eq!(
+        call_str("lpad", &[input.clone(), len.clone(), fill.clone()]),
+        owned(&[Some("xyxhi"), Some("hel"), Some("hé")])
+    );
+    assert_eq!(
+        call_str("rpad", &[input, len, fill]),
+        owned(&[Some("hixyx"), Some("hel"), Some("hé")])
+    );
+    let err = error(call(
+        "lpad",
+        &[strings(&[Some("a")]), ints(&[Some(i32::MAX)])],
+    ));
+    assert_eq!(err, "requested length too large");
+}
+
+#[test]
+fn search() {
+    let input = strings(&[Some("héllo"), Some("héllo"), None]);
+    let pattern = strings(&[Some("hé"), Some("lo"), Some("")]);
+    let result = call("starts_with", &[input.clone(), pattern.clone()]).unwrap();
+    assert_eq!(
+        values::<BoolArray>(&result),
+        vec![Some(true), Some(false), None]
+    );
+    let result = call("ends_with", &[input.clone(), pattern.clone()]).unwrap();
+    assert_eq!(
+        values::<BoolArray>(&result),
+        vec![Some(false), Some(true), None]
+    );
+    let result = call("contains", &[input.clone(), pattern.clone()]).unwrap();
+    assert_eq!(
+        values::<BoolArray>(&result),
+        vec![Some(true), Some(true), None]
+    );
+    // Positions are counted in characters.
+    let result = call("position", &[pattern, input]).unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(1), Some(4), None]);
+    let result = call(
+        "position",
+        &[strings(&[Some("x")]), strings(&[Some("abc")])],
+    )
+    .unwrap();
+    assert_eq!(values::<I32Array>(&result), vec![Some(0)]);
+}
+
+#[test]
+fn split_part() {
+    let input = strings(&[Some("a,b,,c"); 5]);
+    let delimiter = strings(&[Some(","); 5]);
+    let n = ints(&[Some(1), Some(3), Some(-1), Some(-4), Some(5)]);
+    assert_eq!(
+        call_str("split_part", &[input, delimiter, n]),
+        owned(&[Some("a"), Some(""), Some("c"), Some("a"), Some("")])
+    );
+    let input = strings(&[Some("abc"); 3]);
+    let delimiter = strings(&[Some(""); 3]);
+    let n = ints(&[Some(1), Some(-1), Some(2)]);
+    assert_eq!(
+        call_str("split_part", &[input, delimiter, n]),
+        owned(&[Some("abc"), Some("abc"), Some("")])
+    );
+    let err = error(call(
+        "split_part",
+        &[
+            strings(&[Some("a")]),
+            strings(&[Some(",")]),
+            ints(&[Some(0)]),
+        ],
+    ));
+    assert_eq!(err, "field position must not be zero");
+}
+
+#[test]
+fn reverse_and_repeat() {
+    let input = strings(&[Some("héllo"), Some(""), None]);
+    assert_eq!(
+        call_str("reverse", std::slice::from_ref(&input)),
+        owned(&[Some("olléh"), Some(""), None])
+    );
+    assert_eq!(
+        call_str("repeat", &[input, ints(&[Some(2), Some(3), Some(1)])]),
+        owned(&[Some("héllohéllo"), Some(""), None])
+    );
+    assert_eq!(
+        call_str("repeat", &[strings(&[Some("ab")]), ints(&[Some(-1)])]),
+        owned(&[Some("")])
+    );
+    let err = error(call(
+        "repeat",
+        &[strings(&[Some("ab")]), ints(&[Some(i32::MAX)])],
+    ));
+    assert_eq!(err, "requested length too large");
+}
@@ -0,0 +1,395 @@
+//! Tests window functions computed by [`Window`].
+
+mod common;
+
+use type_rust::aggregate::build_aggregate;
+use type_rust::array::*;
+use type_rust::dataType::DataType;
+use type_rust::executor::{
+    AggCall, ColumnOrder, Frame, FrameBound, Window, WindowCall, WindowFunction,
+};
+use type_rust::scalar::ScalarImpl;
+
+use common::{error, ints, strings, values};
+
+/// Rows of `(p, o, v)`, where `p` is the partition key and `o` is the order key.
+fn input() -> Batch {
+    Batch::new(vec![
+        strings(&[
+            Some("a"),
+            Some("b"),
+            Some("a"),
+            Some("a"),
+            Some("a"),
+            Some("b"),
+            None,
+        ]),
+        ints(&[
+            Some(3),
+            Some(1),
+            Some(1),
+            Some(2),
+            Some(2),
+            Some(2),
+            Some(1),
+        ]),
+        ints(&[
+            Some(30),
+            Some(100),
+            Some(10),
+            Some(20),
+            Some(21),
+            Some(200),
+            Some(7),
+        ]),
+    ])
+    .unwrap()
+}
+
+const INPUT_TYPES: [DataType; 3] = [DataType::Varchar, DataType::Integer, DataType::Integer];
+
+/// `sum(v)` of column `v`.
+fn sum(v: usize) -> WindowFunction {
+    let func = build_aggregate("sum", &[DataType::Integer]).unwrap();
+    WindowFunction::Aggregate(AggCall::new(func, vec![v]))
+}
+
+/// Compute `calls` over [`input`] partitioned by `p` and ordered by `o`, and return the columns
+/// of the calls.
+fn window(calls: Vec<WindowCall>) -> Vec<ArrayImpl> {
+    let window = Window::new(
+        INPUT_TYPES.to_vec(),
+        vec![0],
+        vec![ColumnOrder::asc(1)],
+        calls,
+    )
+    .unwrap();
+    let output = window.execute([Ok(input())]).unwrap();
+    // Rows are sorted by the partition and then the order, and peers keep the input order.
+    assert_eq!(
+        values::<I32Array>(output.column(2)),
+        [
+            Some(10),
+            Some(20),
+            Some(21),
+            Some(30),
+            Some(100),
+            Some(200),
+            Some(7)
+        ]
+    );
+    output.columns()[3..].to_vec()
+}
+
+#[test]
+fn ranking_functions() {
+    let output = window(vec![
+        WindowCall::new(WindowFunction::RowNumber),
+        WindowCall::new(WindowFunction::Rank),
+        WindowCall::new(WindowFunction::DenseRank),
+        WindowCall::new(WindowFunction::Ntile(3)),
+    ]);
+    let ranks = |column| {
+        values::<I64Array>(column)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ranks(&output[0]), [1, 2, 3, 4, 1, 2, 1]);
+    assert_eq!(ranks(&output[1]), [1, 2, 2, 4, 1, 2, 1]);
+    assert_eq!(ranks(&output[2]), [1, 2, 2, 3, 1, 2, 1]);
+    // Partitions with fewer rows than buckets have one row in each bucket.
+    assert_eq!(
+        values::<I32Array>(&output[3]),
+        [
+            Some(1),
+            Some(1),
+            Some(2),
+            Some(3),
+            Some(1),
+            Some(2),
+            Some(1)
+        ]
+    );
+}
+
+#[test]
+fn lag_and_lead() {
+    let output = window(vec![
+        WindowCall::new(WindowFunction::Lag {
+            arg: 2,
+            offset: 1,
+            default: Some(ScalarImpl::Int32(-1)),
+        }),
+        WindowCall::new(WindowFunction::Lead {
+            arg: 2,
+            offset: 2,
+            default: None,
+        }),
+        WindowCall::new(WindowFunction::Lag {
+            arg: 2,
+            offset: 0,
+            default: None,
+        }),
+    ]);
+    // Rows are not shifted across partitions.
+    assert_eq!(
+        values::<I32Array>(&output[0]),
+        [
+            Some(-1),
+            Some(10),
+            Some(20),
+            Some(21),
+            Some(-1),
+            Some(100),
+            Some(-1)
+        ]
+    );
+    assert_eq!(
+        values::<I32Array>(&output[1]),
+        [Some(21), Some(30), None, None, None, None, None]
+    );
+    assert_eq!(
+        values::<I32Array>(&output[2]),
+        [
+            Some(10),
+            Some(20),
+            Some(21),
+            Some(30),
+            Some(100),
+            Some(200),
+            Some(7)
+        ]
+    );
+}
+
+#[test]
+fn framed_values() {
+    use FrameBound::*;
+
+    let output = window(vec![
+        WindowCall::new(WindowFunction::FirstValue(2)),
+        WindowCall::new(WindowFunction::LastValue(2)),
+        WindowCall::new(WindowFunction::FirstValue(2))
+            .with_frame(Frame::rows(Preceding(1), CurrentRow)),
+        WindowCall::new(WindowFunction::LastValue(2))
+            .with_frame(Frame::rows(CurrentRow, UnboundedFollowing)),
+        WindowCall::new(WindowFunction::FirstValue(2))
+            .with_frame(Frame::rows(Following(2), Following(3))),
+    ]);
+    assert_eq!(
+        values::<I32Array>(&output[0]),
+        [
+            Some(10),
+            Some(10),
+            Some(10),
+            Some(10),
+            Some(100),
+            Some(100),
+            Some(7)
+        ]
+    );
+    // The default frame ends at the last peer of the current row.
+    assert_eq!(
+        values::<I32Array>(&output[1]),
+        [
+            Some(10),
+            Some(21),
+            Some(21),
+            Some(30),
+            Some(100),
+            Some(200),
+            Some(7)
+        ]
+    );
+    assert_eq!(
+        values::<I32Array>(&output[2]),
+        [
+            Some(10),
+            Some(10),
+            Some(20),
+            Some(21),
+            Some(100),
+            Some(100),
+            Some(7)
+        ]
+    );
+    assert_eq!(
+        values::<I32Array>(&output[3]),
+        [
+            Some(30),
+            Some(30),
+            Some(30),
+            Some(30),
+            Some(200),
+            Some(200),
+            Some(7)
+        ]
+    );
+    // Values of empty frames are `NULL`.
+    assert_eq!(
+        values::<I32Array>(&output[4]),
+        [Some(21), Some(30), None, None, None, None, None]
+    );
+}
+
+#[test]
+fn framed_aggregates() {
+    use FrameBound::*;
+
+    let output = window(vec![
+        WindowCall::new(sum(2)),
+        WindowCall::new(sum(2)).with_frame(Frame::rows(UnboundedPreceding, CurrentRow)),
+        WindowCall::new(sum(2)).with_frame(Frame::rows(Preceding(1), Following(1))),
+        WindowCall::new(sum(2)).with_frame(Frame::range(CurrentRow, UnboundedFollowing)),
+        WindowCall::new(sum(2)).with_frame(Frame::rows(Preceding(3), Preceding(2))),
+    ]);
+    assert_eq!(
+        values::<I64Array>(&output[0]),
+        [
+            Some(10),
+            Some(51),
+            Some(51),
+            Some(81),
+            Some(100),
+            Some(300),
+            Some(7)
+        ]
+    );
+    assert_eq!(
+        values::<I64Array>(&output[1]),
+        [
+            Some(10),
+            Some(30),
+            Some(51),
+            Some(81),
+            Some(100),
+            Some(300),
+            Some(7)
+        ]
+    );
+    assert_eq!(
+        values::<I64Array>(&output[2]),
+        [
+            Some(30),
+            Some(51),
+            Some(71),
+            Some(51),
+            Some(300),
+            Some(300),
+            Some(7)
+        ]
+    );
+    // `RANGE` frames starting at the current row include its preceding peers.
+    assert_eq!(
+        values::<I64Array>(&output[3]),
+        [
+            Some(81),
+            Some(71),
+            Some(71),
+            Some(30),
+            Some(300),
+            Some(200),
+            Some(7)
+        ]
+    );
+    // The sum of an empty frame is `NULL`.
+    assert_eq!(
+        values::<I64Array>(&output[4]),
+        [None, None, Some(10), Some(30), None, None, None]
+    );
+}
+
+#[test]
+fn sliding_frames_match_brute_force() {
+    use FrameBound::*;
+
+    // Enough rows for a deep segment tree, and values with `NULL`s.
+    let v = (0..1000)
+        .map(|i| (i % 7 != 0).then_some(i * 37 % 101))
+        .collect::<Vec<_>>();
+    let input = Batch::new(vec![ints(&v)]).unwrap();
+    let frames = [(3, 2), (0, 50), (10, 0)];
+    let calls = frames
+        .iter()
+        .map(|(preceding, following)| {
+            WindowCall::new(sum(0))
+                .with_frame(Frame::rows(Preceding(*preceding), Following(*following)))
+        })
+        .collect();
+    let window = Window::new(vec![DataType::Integer], vec![], vec![], calls).unwrap();
+    let output = window.execute([Ok(input)]).unwrap();
+    for (i, (preceding, following)) in frames.iter().enumerate() {
+        let expected = (0..v.len())
+            .map(|row| {
+                let frame = row.saturating_sub(*preceding)..(row + following + 1).min(v.len());
+                let values = v[frame].iter().flatten().collect::<Vec<_>>();
+                (!values.is_empty()).then(|| values.into_iter().map(|v| *v as i64).sum())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values::<I64Array>(output.column(i + 1)), expected);
+    }
+}
+
+#[test]
+fn sliding_frames_keep_order() {
+    use FrameBound::*;
+
+    // `string_agg` depends on the order in which states are merged.
+    let s = (0..20).map(|i| i.to_string()).collect::<Vec<_>>();
+    let s = s.iter().map(|s| Some(s.as_str())).collect::<Vec<_>>();
+    let input = Batch::new(vec![strings(&s), strings(&vec![Some(","); 20])]).unwrap();
+    let func = build_aggregate("string_agg", &[DataType::Varchar, DataType::Varchar]).unwrap();
+    let call = WindowCall::new(WindowFunction::Aggregate(AggCall::new(func, vec![0, 1])))
+        .with_frame(Frame::rows(Following(1), Following(4)));
+    let types = vec![DataType::Varchar, DataType::Varchar];
+    let window = Window::new(types, vec![], vec![], vec![call]).unwrap();
+    let output = window.execute([Ok(input)]).unwrap();
+    let result = values::<StringArray>(output.column(2));
+    assert_eq!(result[0].as_deref(), Some("1,2,3,4"));
+    assert_eq!(result[7].as_deref(), Some("8,9,10,11"));
+    assert_eq!(result[17].as_deref(), Some("18,19"));
+    assert_eq!(result[19], None);
+}
+
+#[test]
+fn window_errors() {
+    use FrameBound::*;
+
+    let new = |calls| Window::new(INPUT_TYPES.to_vec(), vec![0], vec![], calls);
+    let frame = |start, end| vec![WindowCall::new(sum(2)).with_frame(Frame::rows(start, end))];
+    assert_eq!(
+        error(new(frame(UnboundedFollowing, UnboundedFollowing))),
+        "frame start cannot be UNBOUNDED FOLLOWING"
+    );
+    assert_eq!(
+        error(new(frame(CurrentRow, Preceding(1)))),
+        "frame starting from current row cannot have preceding rows"
+    );
+    assert!(new(frame(Following(1), CurrentRow)).is_err());
+    assert!(new(frame(Preceding(1), Following(1))).is_ok());
+    let range = vec![WindowCall::new(sum(2)).with_frame(Frame::range(Preceding(1), CurrentRow))];
+    assert_eq!(
+        error(new(range)),
+        "RANGE with offset PRECEDING/FOLLOWING is not supported"
+    );
+    assert_eq!(
+        error(new(vec![WindowCall::new(WindowFunction::Ntile(0))])),
+        "argument of ntile must be greater than zero"
+    );
+    let lag = WindowFunction::Lag {
+        arg: 2,
+        offset: 1,
+        default: Some(ScalarImpl::String("x".to_string())),
+    };
+    assert!(new(vec![WindowCall::new(lag)]).is_err());
+    assert_eq!(
+        error(new(vec![WindowCall::new(WindowFunction::FirstValue(3))])),
+        "column index 3 out of range, there are 3 input columns"
+    );
+
+    // Input batches must have columns of the input types.
+    let window = new(vec![WindowCall::new(WindowFunction::RowNumber)]).unwrap();
+    let input = Batch::new(vec![ints(&[Some(1)]), ints(&[Some(1)]), ints(&[Some(1)])]);
+    assert!(window.execute([input]).is_err());
+}